@@ -9,15 +9,15 @@ import { useCatEarsStore } from '@/store/catears-store';
 import { AudioMode, CHIPTUNES, ChiptuneName, Note, ChiptuneSequence } from '@/types/catears';
 
 export function AudioControl() {
-  const { state, setAudioMode, setVolume } = useCatEarsStore();
+  const { state, earSelection, setAudioMode, setVolume } = useCatEarsStore();
   
   const getCurrentMode = (): string => {
-    const mode = state.speakers.mode;
+    const mode = earSelection === 'right' ? state.speakers.right : state.speakers.left;
     return Object.keys(mode)[0];
   };
   
   const getCurrentModeData = () => {
-    const mode = state.speakers.mode;
+    const mode = earSelection === 'right' ? state.speakers.right : state.speakers.left;
     return Object.values(mode)[0];
   };
 
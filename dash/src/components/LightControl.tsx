@@ -7,7 +7,7 @@ import { Label } from '@radix-ui/react-label';
 import { Lightbulb, ChevronDown, Sparkles } from 'lucide-react';
 import { useCatEarsStore } from '@/store/catears-store';
 import { ColorPicker } from './ColorPicker';
-import { LightMode, RGB8, LIGHT_PATTERNS, LightPatternName, ChasePattern, PulsePattern, RainbowPattern, LedPattern } from '@/types/catears';
+import { LightMode, RGB8, LIGHT_PATTERNS, LightPatternName, ChasePattern, GradientPattern, PulsePattern, RainbowPattern, LedPattern } from '@/types/catears';
 
 export function LightControl() {
   const { state, earSelection, setLightMode, setBrightness } = useCatEarsStore();
@@ -117,7 +117,8 @@ export function LightControl() {
             color: { r: 255, g: 0, b: 0 },
             min_brightness: 0,
             max_brightness: 255,
-            period_ms: 500
+            period_ms: 500,
+            shape: { Square: 50 }
           }
         };
         break;
@@ -133,11 +134,11 @@ export function LightControl() {
         break;
       case 'loading':
         newMode = { 
-          Chase: {
+          Comet: {
             color: { r: 0, g: 100, b: 255 },
             background: { r: 0, g: 0, b: 0 },
-            length: 3,
-            speed_ms: 150,
+            tail: 160,
+            speed_ms: 100,
             clockwise: true
           }
         };
@@ -200,18 +201,18 @@ export function LightControl() {
         );
       
       case 'Gradient':
-        const gradient = data as [RGB8, RGB8];
+        const gradient = data as GradientPattern;
         return (
           <div className="space-y-4">
             <ColorPicker
               label="Start Color"
               color={gradient[0]}
-              onChange={(color) => setLightMode({ Gradient: [color, gradient[1]] })}
+              onChange={(color) => setLightMode({ Gradient: [color, ...gradient.slice(1)] as GradientPattern })}
             />
             <ColorPicker
               label="End Color"
               color={gradient[1]}
-              onChange={(color) => setLightMode({ Gradient: [gradient[0], color] })}
+              onChange={(color) => setLightMode({ Gradient: [gradient[0], color, ...gradient.slice(2)] as GradientPattern })}
             />
           </div>
         );
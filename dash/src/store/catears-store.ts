@@ -79,6 +79,7 @@ export const useCatEarsStore = create<CatEarsStore>((set, get) => ({
     const current = get();
     const activeServo = current.earSelection === 'right' ? current.state.servos.right : current.state.servos.left;
     const activeLight = current.earSelection === 'right' ? current.state.lights.right : current.state.lights.left;
+    const activeAudio = current.earSelection === 'right' ? current.state.speakers.right : current.state.speakers.left;
     
     set({
       state: {
@@ -92,6 +93,11 @@ export const useCatEarsStore = create<CatEarsStore>((set, get) => ({
           left: activeLight,
           right: activeLight,
         },
+        speakers: {
+          ...current.state.speakers,
+          left: activeAudio,
+          right: activeAudio,
+        },
       },
     });
   },
@@ -187,15 +193,24 @@ export const useCatEarsStore = create<CatEarsStore>((set, get) => ({
   })),
   
   // Audio controls
-  setAudioMode: (mode: AudioMode) => set((state) => ({
-    state: {
-      ...state.state,
-      speakers: {
-        ...state.state.speakers,
-        mode,
+  setAudioMode: (mode: AudioMode) => set((state) => {
+    const { earSelection } = state;
+    const newSpeakers = { ...state.state.speakers };
+
+    if (earSelection === 'left' || earSelection === 'both') {
+      newSpeakers.left = mode;
+    }
+    if (earSelection === 'right' || earSelection === 'both') {
+      newSpeakers.right = mode;
+    }
+
+    return {
+      state: {
+        ...state.state,
+        speakers: newSpeakers,
       },
-    },
-  })),
+    };
+  }),
   
   setVolume: (volume) => set((state) => ({
     state: {
@@ -212,19 +227,24 @@ export const useCatEarsStore = create<CatEarsStore>((set, get) => ({
   
   loadState: (newState) => {
     // Handle the Chiptune array padding if needed
+    const trimChiptune = (mode: AudioMode): AudioMode =>
+      'Chiptune' in mode && mode.Chiptune
+        ? {
+            Chiptune: {
+              ...mode.Chiptune,
+              // Only use valid notes based on length
+              notes: mode.Chiptune.notes.slice(0, mode.Chiptune.length)
+            }
+          }
+        : mode;
+    // States from before the speakers were split play a single mode on both sides
+    const legacyMode = (newState.speakers as { mode?: AudioMode }).mode;
     const transformedState = {
       ...newState,
       speakers: {
-        ...newState.speakers,
-        mode: 'Chiptune' in newState.speakers.mode && newState.speakers.mode.Chiptune
-          ? {
-              Chiptune: {
-                ...newState.speakers.mode.Chiptune,
-                // Only use valid notes based on length
-                notes: newState.speakers.mode.Chiptune.notes.slice(0, newState.speakers.mode.Chiptune.length)
-              }
-            }
-          : newState.speakers.mode
+        left: trimChiptune(newState.speakers.left ?? legacyMode ?? { Silent: null }),
+        right: trimChiptune(newState.speakers.right ?? legacyMode ?? { Silent: null }),
+        volume: newState.speakers.volume
       }
     };
     
@@ -235,22 +255,25 @@ export const useCatEarsStore = create<CatEarsStore>((set, get) => ({
     const state = get().state;
     
     // Transform the state to match Rust's expected JSON format
+    const padChiptune = (mode: AudioMode): AudioMode =>
+      'Chiptune' in mode && mode.Chiptune
+        ? {
+            Chiptune: {
+              ...mode.Chiptune,
+              // Rust expects exactly 64 notes in the array
+              notes: [
+                ...mode.Chiptune.notes,
+                ...Array(64 - mode.Chiptune.notes.length).fill({ frequency: 0, duration_ms: 0 })
+              ].slice(0, 64)
+            }
+          }
+        : mode;
     const transformedState = {
       servos: state.servos,
       lights: state.lights,
       speakers: {
-        mode: 'Chiptune' in state.speakers.mode && state.speakers.mode.Chiptune
-          ? {
-              Chiptune: {
-                ...state.speakers.mode.Chiptune,
-                // Rust expects exactly 64 notes in the array
-                notes: [
-                  ...state.speakers.mode.Chiptune.notes,
-                  ...Array(64 - state.speakers.mode.Chiptune.notes.length).fill({ frequency: 0, duration_ms: 0 })
-                ].slice(0, 64)
-              }
-            }
-          : state.speakers.mode,
+        left: padChiptune(state.speakers.left),
+        right: padChiptune(state.speakers.right),
         volume: state.speakers.volume
       }
     };
@@ -5,40 +5,163 @@ export interface RGB8 {
 }
 
 export interface State {
+  power?: boolean; // Global power switch, defaults to on
   servos: Servos;
   lights: Lights;
   speakers: Speakers;
+  sleep?: SleepConfig; // Defaults to never sleeping on its own
+  status_led?: boolean; // On-board status LED, defaults to on
+  polling?: PollingConfig; // Defaults to fast polling for a minute after changes, backing off to every 30 s
+  touch?: TouchConfig; // Petting detection, only on builds with touch pads
+  uart_cli?: boolean; // Command line on the UART for an external controller, defaults to on
 }
 
 export type ServoMode = 
   | { Static: number } // 0-255, center at 125
   | { Sweep: { min: number; max: number; speed_ms: number } }
-  | { Twitch: { center: number; amplitude: number; interval_ms: number } };
+  | { Twitch: { center: number; amplitude: number; interval_ms: number } }
+  | { Reactive: { rest: number; perk: number; threshold: number; release: number } }; // Follows the microphone level
 
 export interface Servos {
   left: ServoMode;
   right: ServoMode;
+  aux0?: ServoMode | null; // Auxiliary servos, only on builds with aux-servos, left alone when unset
+  aux1?: ServoMode | null;
 }
 
 export interface Lights {
   left: LightMode;
   right: LightMode;
   brightness: number; // 0-255
+  auto_brightness?: boolean; // Dim with the ambient light, brightness is the ceiling, defaults to off
+  transition_ms?: number; // Crossfade time into a new mode, 0 switches at once, defaults to 300
+  rotation_offset_left?: number; // Index of the LED at the top of the ring (0-11), defaults to 0
+  rotation_offset_right?: number;
+  reversed_left?: boolean; // Ring wired counter-clockwise, defaults to false
+  reversed_right?: boolean;
+  white_balance?: ColorCorrection; // Scales of each channel out of 255, defaults to no correction
+  dither?: boolean; // Dither the brightness over time for smooth low-brightness fades, defaults to true
+  playlist?: PlaylistEntry[]; // Up to 8 presets shown on both rings in turn, over their own modes, defaults to empty
+}
+
+export type LightPreset =
+  | "Police"
+  | "Breathing"
+  | "Party"
+  | "Alert"
+  | "Success"
+  | "Loading"
+  | "CatEyes"
+  | "Notification"
+  | "Fire"
+  | "Ocean"
+  | "Stardust"
+  | "Marquee"
+  | "CameraFlash";
+
+export interface PlaylistEntry {
+  preset: LightPreset;
+  duration_s: number;
+}
+
+export interface ColorCorrection {
+  r: number; // 0-255
+  g: number;
+  b: number;
 }
 
 export interface Speakers {
-  mode: AudioMode;
-  volume: number; // 0-255
+  left: AudioMode;
+  right: AudioMode;
+  volume: number; // 0-255, master volume of both speakers
+  event?: AudioEvent | null; // Played once over both modes
+  muted?: boolean; // Plays on in silence
+  paused?: boolean; // Holds its place, continuing when resumed
+  balance?: number; // -127 (left only) to 127 (right only), defaults to 0 for both equally
+}
+
+export interface SleepConfig {
+  idle_timeout_s?: number; // Seconds without changes before sleeping, 0 to never sleep on its own
+  wake_after_s?: number; // Seconds asleep before waking up, 0 to only wake on the button
+}
+
+export interface PollingConfig {
+  fast_ms?: number; // Poll interval right after a change, defaults to 500
+  slow_ms?: number; // Longest poll interval, at most 60000, defaults to 30000
+  fast_window_s?: number; // Seconds of fast polling after a change, defaults to 60
+  decay_pct?: number; // Percentage the interval grows to per unchanged poll, defaults to 150
+}
+
+export interface TouchConfig {
+  threshold_pct?: number; // Percent change from the baseline that counts as a touch, lower is more sensitive, defaults to 3
+  tap?: Reaction; // Defaults to perking up with a pink blink
+  stroke?: Reaction; // Defaults to a wiggle with a longer pink glow
+}
+
+export type Gesture = "Perk" | "Flatten" | "Wiggle" | "Droop" | "Curious";
+
+export interface Flash {
+  color: RGB8;
+  duration_ms: number;
+}
+
+export interface Reaction {
+  gesture: Gesture | null;
+  flash: Flash | null;
 }
 
 export type LightMode = 
   | { Off: null }
   | { Solid: RGB8 }
-  | { Gradient: [RGB8, RGB8] }
+  | { Gradient: GradientPattern }
   | { Chase: ChasePattern }
   | { Pulse: PulsePattern }
   | { Rainbow: RainbowPattern }
-  | { Custom: LedPattern };
+  | { Custom: LedPattern }
+  | { Animation: Animation }
+  | { Vu: VuPattern }
+  | { BeatPulse: BeatPattern } // Flashes on the notes of the chiptune on its side
+  | { Fire: FirePattern }
+  | { Sparkle: SparklePattern }
+  | { Comet: CometPattern }
+  | { TheaterChase: TheaterChasePattern }
+  | { BatteryGauge: null } // Charge left, pulsing grey while unknown
+  | { Progress: ProgressPattern }
+  | { Segments: Segment[] } // Up to 4 arcs, later ones drawn over earlier ones
+  | { PaletteCycle: PaletteCyclePattern }
+  | { Strobe: StrobePattern }
+  | { MirrorOther: null }; // Flipped copy of the other ring, on one side only
+
+// Start and end colors, then the time in ms to turn around the ring once (0 holds still) and whether it ramps up
+// one half of the ring and down the other. The last two are left out of still, unmirrored gradients.
+export type GradientPattern = [RGB8, RGB8] | [RGB8, RGB8, number, boolean];
+
+export interface ProgressPattern {
+  value: number;        // 0-255, filling the ring clockwise from the top
+  color: RGB8;
+  background?: RGB8;    // Defaults to off
+}
+
+export interface StrobePattern {
+  color: RGB8;
+  on_ms: number;
+  off_ms: number;       // At least 30, shorter times are stretched
+  count?: number | null; // Flashes before staying dark, null to keep flashing
+}
+
+export type PaletteId = "Heat" | "Ocean" | "Party" | "Forest";
+
+export interface PaletteCyclePattern {
+  palette: PaletteId;
+  speed_ms: number;     // Time for the palette to go by once
+  spread: boolean;      // Spread around the ring, or one color on every LED
+}
+
+export interface Segment {
+  start: number;        // First LED of the arc (0-11)
+  length: number;       // LEDs going clockwise, wrapping past the last (0-12)
+  color: RGB8;
+}
 
 export interface ChasePattern {
   color: RGB8;
@@ -46,6 +169,22 @@ export interface ChasePattern {
   length: number;      // 1-12
   speed_ms: number;
   clockwise: boolean;
+  stepped?: boolean;   // Jump whole LEDs instead of gliding, defaults to false
+}
+
+export interface CometPattern {
+  color: RGB8;
+  background: RGB8;
+  tail: number;        // Brightness each tail LED keeps of the one ahead, in 256ths
+  speed_ms: number;
+  clockwise: boolean;
+}
+
+export interface TheaterChasePattern {
+  color: RGB8;
+  background: RGB8;
+  spacing: number;     // LEDs from one lit LED to the next, 2-4 wrap evenly on 12 LEDs
+  speed_ms: number;
 }
 
 export interface PulsePattern {
@@ -53,36 +192,173 @@ export interface PulsePattern {
   min_brightness: number; // 0-255
   max_brightness: number; // 0-255
   period_ms: number;
+  shape?: PulseShape; // Defaults to 'Sine'
 }
 
+export type PulseShape =
+  | 'Sine'
+  | 'Triangle'
+  | 'SawtoothUp'
+  | 'SawtoothDown'
+  | { Square: number }; // Percentage of each cycle at the maximum
+
 export interface RainbowPattern {
-  speed_ms: number;
+  speed_ms: number;    // Time to go once around the color wheel
   spread: boolean;
   brightness: number; // 0-255
+  reversed?: boolean; // Turn the other way, defaults to false
+  saturation?: number; // 0-255, lower for pastels, defaults to 255
 }
 
 export interface LedPattern {
-  leds: RGB8[]; // Array of 12 RGB values
+  leds: RGB8[]; // One RGB value per LED (12 on the usual rings), extras dropped and missing ones off
   looping: boolean;
 }
 
+export interface AnimationFrame {
+  leds: RGB8[]; // One RGB value per LED (12 on the usual rings), extras dropped and missing ones off
+  hold_ms: number; // Rounded up to whole 10 ms frames
+}
+
+export interface Animation {
+  frames: AnimationFrame[]; // At most 8
+  looping?: boolean; // Defaults to false, holding the last frame
+}
+
+export interface FirePattern {
+  cooling: number; // 0-255, higher gives shorter flames
+  sparking: number; // Chance of a new spark every 10 ms, in 256ths
+}
+
+export interface SparklePattern {
+  color?: RGB8 | null; // Null for a random hue per sparkle
+  background: RGB8;
+  chance: number; // Chance of a new sparkle every 10 ms, in 256ths
+  decay_ms: number;
+}
+
+export type Band = 'Full' | 'Low' | 'High';
+
+export interface VuPattern {
+  low: RGB8;
+  high: RGB8;
+  band: Band;
+  gain: number; // Sixteenths, 16 = unity
+}
+
+export interface BeatPattern {
+  color: RGB8;
+  background: RGB8;
+  decay_ms: number; // Cut short at one beat of a chiptune with a tempo
+  period_ms: number; // Between flashes while no chiptune plays
+}
+
 export type AudioMode =
   | { Silent: null }
   | { Tone: Note }
   | { Chiptune: ChiptuneSequence }
-  | { Audio: AudioClip };
+  | { NamedChiptune: ChiptunePreset } // Matched regardless of case
+  | { Playlist: Playlist }
+  | { Audio: AudioClip }
+  | { RemoteAudio: RemoteClip }
+  | { Noise: Noise }
+  | { Sweep: Sweep }
+  | { Dtmf: Dial };
+
+// Table picks a single-cycle wavetable by index: 0 sine, 1 organ, 2 soft square, 3 vocal, then any the firmware adds.
+export type Waveform = 'Sine' | 'Square' | 'Triangle' | 'Sawtooth' | { Table: number };
 
 export interface Note {
   frequency: number;
   duration_ms: number;
   volume?: number; // 0-255
+  waveform?: Waveform; // Defaults to Sine
+  duty?: number; // Square waves only: 256ths of each cycle spent high, defaults to 128 (50%)
+  envelope?: Envelope; // Defaults to a 5 ms fade in and out
+  vibrato?: Vibrato; // Defaults to a steady pitch
+  tremolo?: Tremolo; // Defaults to a steady volume
+  arpeggio?: Arpeggio; // Defaults to a single pitch
+  kind?: NoteKind; // Defaults to 'Tone'
+  repeat?: boolean; // Tones only: play over and over instead of once, defaults to false
+}
+
+export type Percussion = 'Kick' | 'Snare' | 'Hat';
+
+// Percussion notes ignore frequency, waveform, and envelope
+export type NoteKind = 'Tone' | { Percussion: Percussion };
+
+export interface Envelope {
+  attack_ms?: number; // Rise from silence to full volume, defaults to 5
+  decay_ms?: number; // Fall from full volume to the sustain level, defaults to 0
+  sustain?: number; // 0-255, level held after the decay, defaults to 255
+  release_ms?: number; // Fade to silence at the end of the note, defaults to 5
+}
+
+export interface Vibrato {
+  depth_cents?: number; // Swing above and below the frequency, defaults to 0 (off)
+  rate_hz?: number; // Swings per second, defaults to 0 (off)
+}
+
+export interface Tremolo {
+  depth?: number; // 0-255, how far the volume dips in 255ths, defaults to 0 (off)
+  rate_hz?: number; // Dips per second, defaults to 0 (off)
+}
+
+export interface Arpeggio {
+  semitones?: [number, number]; // Pitches above the frequency to cycle through, 0 for none, defaults to [0, 0] (off)
+  step_ms?: number; // Time on each pitch, defaults to 20
+}
+
+export interface Noise {
+  amplitude?: number; // 0-255, defaults to 128
+  smoothing?: number; // 0-255, from a hiss at 0 to a rumble near 255, defaults to 0
+  wobble_hz?: number; // Volume swells per second, defaults to 0 (steady)
+}
+
+export interface Sweep {
+  start_hz: number;
+  end_hz: number;
+  duration_ms: number;
+  repeat?: boolean; // Sweep back and forth instead of once, defaults to false
+}
+
+export interface Dial {
+  digits: string; // Up to 16 of 0-9, *, #, and A-D, anything else is skipped
+  tone_ms?: number; // Time each key sounds for, defaults to 100
+  gap_ms?: number; // Silence between keys, defaults to 100
 }
 
 export interface ChiptuneSequence {
-  notes: Note[];
-  length: number;
+  notes: Note[]; // Up to 64, padded with rests by the firmware
+  length?: number; // Defaults to the number of notes
   default_volume: number;
   looping: boolean;
+  bpm?: number | null; // Tempo the durations were written at, if they were written as note lengths
+  second_voice?: number; // Index of the first note of a second voice playing alongside the first, if there is one
+}
+
+export type ChiptunePreset =
+  | 'Coin'
+  | 'PowerUp'
+  | 'LevelComplete'
+  | 'GameOver'
+  | 'MenuSelect'
+  | 'Alert'
+  | 'Happy'
+  | 'Sad'
+  | 'Startup'
+  | 'Shutdown'
+  | 'DrumLoop'
+  | 'Curious'
+  | 'Disappointed'
+  | 'Doorbell'
+  | 'Purr'
+  | 'Error';
+
+export interface Playlist {
+  tunes: ChiptunePreset[]; // Always 8 entries, only the first `length` are played
+  length: number;
+  looping: boolean;
 }
 
 export interface AudioClip {
@@ -90,6 +366,17 @@ export interface AudioClip {
   bits_per_sample: number;
   is_stereo: boolean;
   looping: boolean;
+  loop_start?: number; // Sample to loop back to, 0 if left out
+}
+
+export interface RemoteClip {
+  id: number; // Streams clips/<id>.wav
+  looping?: boolean;
+}
+
+export interface AudioEvent {
+  id: number; // One more than the last event, each plays once
+  sound: { Tone: Note } | { Chiptune: ChiptunePreset };
 }
 
 // Predefined chiptune types
@@ -126,6 +413,7 @@ export type LightPatternName = keyof typeof LIGHT_PATTERNS;
 
 // Helper to create default state
 export const createDefaultState = (): State => ({
+  power: true,
   servos: {
     left: { Static: 125 },
     right: { Static: 125 },
@@ -146,7 +434,8 @@ export const createDefaultState = (): State => ({
     brightness: 255,
   },
   speakers: {
-    mode: { Silent: null },
+    left: { Silent: null },
+    right: { Silent: null },
     volume: 128,
   },
 });
@@ -1,4 +1,10 @@
 fn main() {
+    // Host builds, like the simulator, link as usual. The linker runs this script without cargo's environment when it
+    // hits an error, which `linker_be_nice` still has to handle.
+    if std::env::var("CARGO_CFG_TARGET_ARCH").is_ok_and(|arch| arch != "xtensa") {
+        return;
+    }
+
     linker_be_nice();
     // println!("cargo:rustc-link-arg-tests=-Tembedded-test.x");
     println!("cargo:rustc-link-arg=-Tdefmt.x");
@@ -0,0 +1,503 @@
+//! Terminal preview of a state, without flashing the device.
+//!
+//! Reads a state file in the same JSON format the device fetches from the remote, and parses it the same way. Then plays
+//! it back in the terminal: both LED rings are drawn as colored blocks at the firmware's frame rate, and every tone or
+//! chiptune note is printed as it would start playing.
+//!
+//! ```bash
+//! cargo +stable run --example simulate --features std --target x86_64-unknown-linux-gnu -- state.json [seconds]
+//! ```
+//!
+//! The simulation runs for 10 seconds unless told otherwise. There is no microphone, so the VU meter stays dark and the
+//! ears stay at rest. A beat pulse keeps time with the chiptune on its side, just as on the device. There is no battery
+//! either, so a battery gauge pulses as it does while the charge is unknown.
+
+use std::io::Write as _;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+use catears::audio::beat::Beat;
+use catears::audio::chiptunes::Preset;
+use catears::audio::dsp::Levels;
+use catears::audio::{
+    ChiptuneSequence, Mode as AudioMode, Noise, Note, NoteKind, RemoteClip, Side, Sweep, Waveform,
+};
+use catears::lights::render::{render, PatternState};
+use catears::lights::LED_COUNT;
+use catears::state::State;
+use smart_leds::RGB8;
+
+/// Time between frames, matching the LED task.
+const FRAME: Duration = Duration::from_millis(10);
+
+/// Default length of the simulation in seconds.
+const DEFAULT_SECONDS: u64 = 10;
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let Some(path) = args.next() else {
+        eprintln!("usage: simulate <state.json> [seconds]");
+        return ExitCode::FAILURE;
+    };
+    let seconds = match args.next().map(|arg| arg.parse::<u64>()) {
+        None => DEFAULT_SECONDS,
+        Some(Ok(seconds)) => seconds,
+        Some(Err(err)) => {
+            eprintln!("Invalid number of seconds: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let json = match std::fs::read_to_string(&path) {
+        Ok(json) => json,
+        Err(err) => {
+            eprintln!("Failed to read {path}: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let state = match serde_json_core::from_str::<State>(&json) {
+        Ok((state, _)) => state,
+        Err(err) => {
+            eprintln!("Failed to parse {path}: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    simulate(&state, Duration::from_secs(seconds));
+    ExitCode::SUCCESS
+}
+
+/// Plays back `state` in the terminal for `length`.
+fn simulate(state: &State, length: Duration) {
+    let brightness = if state.power {
+        state.lights.brightness
+    } else {
+        0
+    };
+    let levels = Levels::default();
+    // Seeded apart like the firmware's, so that fire does not mirror across the ears.
+    let mut left = PatternState::with_seed(0x9e37_79b9);
+    let mut right = PatternState::with_seed(0x85eb_ca6b);
+    let mut speakers = Side::ALL.map(|side| {
+        (
+            side,
+            Speakers::new(state.speakers.mode(side), state.speakers.volume),
+        )
+    });
+
+    let start = Instant::now();
+    let mut next_frame = start;
+    let mut stdout = std::io::stdout().lock();
+    while next_frame.duration_since(start) < length {
+        let elapsed = next_frame.duration_since(start);
+        for (side, speakers) in &mut speakers {
+            while let Some(event) = speakers.advance(elapsed) {
+                // Clear the ring line, print the event above it, and draw the rings again below.
+                let _ = writeln!(
+                    stdout,
+                    "\r\x1b[2K[{:>7.2} s] {:<5} {event}",
+                    elapsed.as_secs_f32(),
+                    side.name()
+                );
+            }
+        }
+
+        let frame_ms = FRAME.as_millis() as u32;
+        let [left_beat, right_beat] = speakers
+            .each_ref()
+            .map(|(_, speakers)| speakers.beat(elapsed));
+        let left_colors: [RGB8; LED_COUNT] = render(
+            &state.lights.left,
+            &mut left,
+            brightness,
+            &levels,
+            left_beat,
+            None,
+            frame_ms,
+        );
+        let right_colors: [RGB8; LED_COUNT] = render(
+            &state.lights.right,
+            &mut right,
+            brightness,
+            &levels,
+            right_beat,
+            None,
+            frame_ms,
+        );
+        let _ = write!(
+            stdout,
+            "\rL {}  R {}\x1b[0m",
+            Ring(&left_colors),
+            Ring(&right_colors)
+        );
+        let _ = stdout.flush();
+
+        next_frame += FRAME;
+        std::thread::sleep(next_frame.saturating_duration_since(Instant::now()));
+    }
+    let _ = writeln!(stdout);
+}
+
+/// A ring drawn as one colored block per LED.
+struct Ring<'a>(&'a [RGB8]);
+
+impl core::fmt::Display for Ring<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for color in self.0 {
+            write!(f, "\x1b[38;2;{};{};{}m\u{2588}", color.r, color.g, color.b)?;
+        }
+        Ok(())
+    }
+}
+
+/// Something the speakers start playing.
+enum Event {
+    Note {
+        /// Name of the chiptune the note is from, when playing a playlist.
+        tune: Option<&'static str>,
+        /// Voice the note is on, when its chiptune has two.
+        voice: Option<usize>,
+        index: usize,
+        count: usize,
+        note: Note,
+        volume: u8,
+    },
+    /// Clip data is compiled into the firmware and never part of a state, so a clip from a state file is empty.
+    Clip {
+        sample_rate: u32,
+    },
+    /// The simulator does not download anything, so it only names the clip that would stream.
+    RemoteClip {
+        clip: RemoteClip,
+    },
+    Noise {
+        noise: Noise,
+        volume: u8,
+    },
+    /// A repeating sweep reports each glide, every other one back down.
+    Sweep {
+        index: usize,
+        sweep: Sweep,
+        volume: u8,
+    },
+}
+
+impl core::fmt::Display for Event {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Note {
+                tune,
+                voice,
+                index,
+                count,
+                note,
+                volume,
+            } => {
+                if let Some(tune) = tune {
+                    write!(f, "{tune} ")?;
+                }
+                if let Some(voice) = voice {
+                    write!(f, "voice {} ", voice + 1)?;
+                }
+                write!(f, "note {}/{count}: ", index + 1)?;
+                if let NoteKind::Percussion(drum) = note.kind {
+                    write!(
+                        f,
+                        "{} for {} ms at volume {volume}",
+                        drum.name(),
+                        note.duration_ms
+                    )
+                } else if note.frequency > 0.0 {
+                    write!(
+                        f,
+                        "{:.1} Hz {} for {} ms at volume {volume}",
+                        note.frequency,
+                        note.waveform.name(),
+                        note.duration_ms
+                    )?;
+                    if note.waveform == Waveform::Square && note.duty != Note::EVEN_DUTY {
+                        write!(f, " at {:.1}% duty", f32::from(note.duty) / 2.56)?;
+                    }
+                    if note.vibrato.is_on() {
+                        write!(
+                            f,
+                            " with {} cent vibrato at {:.1} Hz",
+                            note.vibrato.depth_cents, note.vibrato.rate_hz
+                        )?;
+                    }
+                    if note.tremolo.is_on() {
+                        write!(
+                            f,
+                            " with {}/255 tremolo at {:.1} Hz",
+                            note.tremolo.depth, note.tremolo.rate_hz
+                        )?;
+                    }
+                    if note.arpeggio.is_on() {
+                        let [first, second] = note.arpeggio.semitones;
+                        write!(
+                            f,
+                            " arpeggiating +{first}/+{second} every {} ms",
+                            note.arpeggio.step_ms
+                        )?;
+                    }
+                    Ok(())
+                } else {
+                    write!(f, "rest for {} ms", note.duration_ms)
+                }
+            }
+            Self::Clip { sample_rate } => write!(f, "empty audio clip at {sample_rate} Hz"),
+            Self::RemoteClip { clip } => {
+                write!(
+                    f,
+                    "audio clip streamed from {}",
+                    catears::audio::stream::url(clip.id)
+                )?;
+                if clip.looping {
+                    write!(f, ", looping")?;
+                }
+                Ok(())
+            }
+            Self::Noise { noise, volume } => {
+                write!(
+                    f,
+                    "noise at amplitude {} and volume {volume}, smoothed by {}",
+                    noise.amplitude, noise.smoothing
+                )?;
+                if noise.wobble_hz > 0.0 {
+                    write!(f, " with a {:.1} Hz wobble", noise.wobble_hz)?;
+                }
+                Ok(())
+            }
+            Self::Sweep {
+                index,
+                sweep,
+                volume,
+            } => {
+                let (from, to) = if index % 2 == 0 {
+                    (sweep.start_hz, sweep.end_hz)
+                } else {
+                    (sweep.end_hz, sweep.start_hz)
+                };
+                write!(
+                    f,
+                    "sweep {}: {from:.1} Hz to {to:.1} Hz over {} ms at volume {volume}",
+                    index + 1,
+                    sweep.duration_ms
+                )
+            }
+        }
+    }
+}
+
+/// Follows a speaker task through an audio mode, reporting each note as it would start.
+struct Speakers {
+    mode: AudioMode,
+    /// Master volume, which tones without a volume of their own play at.
+    volume: u8,
+    /// Index of the next note of each voice to start, and when it starts. Only chiptunes have a second voice.
+    next: [Option<(usize, Duration)>; 2],
+    /// When the chiptune playing started, which both of its voices move on from together once it ends.
+    tune_start: Duration,
+    /// Number of notes other than rests the first voice has started, when the latest of them started, and when the
+    /// chiptune it is from ends. Playlists keep no beat, just like on the device.
+    beats: (u32, Duration, Duration),
+}
+
+impl Speakers {
+    fn new(mode: AudioMode, volume: u8) -> Self {
+        let mut speakers = Self {
+            mode,
+            volume,
+            next: [None; 2],
+            tune_start: Duration::ZERO,
+            beats: (0, Duration::ZERO, Duration::ZERO),
+        };
+        speakers.next = match mode {
+            AudioMode::Silent => [None; 2],
+            AudioMode::Chiptune(_)
+            | AudioMode::NamedChiptune(_)
+            | AudioMode::Playlist(_)
+            | AudioMode::Dtmf(_) => {
+                [0, 1].map(|voice| speakers.first_note(voice, 0, Duration::ZERO))
+            }
+            AudioMode::Tone(_)
+            | AudioMode::Audio(_)
+            | AudioMode::RemoteAudio(_)
+            | AudioMode::Noise(_)
+            | AudioMode::Sweep(_) => [Some((0, Duration::ZERO)), None],
+        };
+        speakers
+    }
+
+    /// Returns what starts playing by `elapsed`, if anything.
+    fn advance(&mut self, elapsed: Duration) -> Option<Event> {
+        let (voice, (index, at)) = (0..2)
+            .filter_map(|voice| Some((voice, self.next[voice]?)))
+            .min_by_key(|&(_, (_, at))| at)?;
+        if elapsed < at {
+            return None;
+        }
+        match self.mode {
+            AudioMode::Silent => None,
+            // A tone plays once and then goes silent, unless it repeats.
+            AudioMode::Tone(note) => {
+                self.next[0] =
+                    (note.repeat && note.duration_ms > 0).then(|| (0, at + duration_of(&note)));
+                Some(Event::Note {
+                    tune: None,
+                    voice: None,
+                    index: 0,
+                    count: 1,
+                    note,
+                    volume: note.volume.unwrap_or(self.volume),
+                })
+            }
+            AudioMode::Chiptune(_)
+            | AudioMode::NamedChiptune(_)
+            | AudioMode::Playlist(_)
+            | AudioMode::Dtmf(_) => self.note(voice, index, at),
+            AudioMode::Audio(clip) => {
+                self.next[0] = None;
+                Some(Event::Clip {
+                    sample_rate: clip.sample_rate,
+                })
+            }
+            AudioMode::RemoteAudio(clip) => {
+                self.next[0] = None;
+                Some(Event::RemoteClip { clip })
+            }
+            AudioMode::Noise(noise) => {
+                self.next[0] = None;
+                Some(Event::Noise {
+                    noise,
+                    volume: self.volume,
+                })
+            }
+            // Like a tone, a sweep plays once and then goes silent, unless it repeats.
+            AudioMode::Sweep(sweep) => {
+                self.next[0] = (sweep.repeat && sweep.duration_ms > 0).then(|| {
+                    let duration = Duration::from_millis(u64::from(sweep.duration_ms));
+                    (index + 1, at + duration)
+                });
+                Some(Event::Sweep {
+                    index,
+                    sweep,
+                    volume: self.volume,
+                })
+            }
+        }
+    }
+
+    /// Starts the note at `index` of `voice` at `at`, and schedules the one after it.
+    ///
+    /// The index counts the notes of all the chiptunes before as if each held the most a chiptune can.
+    fn note(&mut self, voice: usize, index: usize, at: Duration) -> Option<Event> {
+        let (tune, index) = (index / CAPACITY, index % CAPACITY);
+        let (name, sequence) = self.tune(tune)?;
+        let voices = sequence.voices();
+        let note = *voices[voice].get(index)?;
+        if index == 0 {
+            self.tune_start = at;
+        }
+        if voice == 0 && !note.is_rest() && !matches!(self.mode, AudioMode::Playlist(_)) {
+            self.beats = (self.beats.0 + 1, at, self.tune_start + length_of(&sequence));
+        }
+        self.next[voice] = if index + 1 < voices[voice].len() {
+            Some((tune * CAPACITY + index + 1, at + duration_of(&note)))
+        } else {
+            self.first_note(voice, tune + 1, self.tune_start + length_of(&sequence))
+        };
+        Some(Event::Note {
+            tune: name,
+            voice: (!voices[1].is_empty()).then_some(voice),
+            index,
+            count: voices[voice].len(),
+            note,
+            volume: note.volume.unwrap_or(sequence.default_volume),
+        })
+    }
+
+    /// Returns the latest beat of the chiptune playing at `elapsed`, as the speaker task would publish it.
+    fn beat(&self, elapsed: Duration) -> Option<Beat> {
+        let (count, at, end) = self.beats;
+        let bpm = self.tune(0).and_then(|(_, sequence)| sequence.bpm);
+        (count > 0 && elapsed < end).then(|| Beat {
+            count,
+            since_ms: (elapsed - at).as_millis() as u32,
+            bpm,
+        })
+    }
+
+    /// Returns the first note `voice` has in the chiptunes from `tune` on, which starts at `at`, and when it starts. Goes
+    /// around again from the first chiptune if what is playing loops.
+    fn first_note(
+        &self,
+        voice: usize,
+        mut tune: usize,
+        mut at: Duration,
+    ) -> Option<(usize, Duration)> {
+        let (count, looping) = match self.mode {
+            AudioMode::Chiptune(sequence) => (1, sequence.looping),
+            // A name the firmware does not know plays nothing.
+            AudioMode::NamedChiptune(name) => (1, name.sequence()?.looping),
+            AudioMode::Dtmf(_) => (1, false),
+            AudioMode::Playlist(playlist) => (playlist.tunes().len(), playlist.looping),
+            _ => return None,
+        };
+        // Going around more than once would find nothing new, and going around a silent loop would never end.
+        let silent = (0..count).all(|tune| {
+            self.tune(tune)
+                .is_none_or(|(_, sequence)| length_of(&sequence).is_zero())
+        });
+        let mut wrapped = false;
+        loop {
+            if tune >= count {
+                if !looping || silent || wrapped {
+                    return None;
+                }
+                tune = 0;
+                wrapped = true;
+            }
+            let (_, sequence) = self.tune(tune)?;
+            if !sequence.voices()[voice].is_empty() {
+                return Some((tune * CAPACITY, at));
+            }
+            at += length_of(&sequence);
+            tune += 1;
+        }
+    }
+
+    /// Returns the chiptune at `tune` in what is playing, along with its name if it was picked by one.
+    fn tune(&self, tune: usize) -> Option<(Option<&'static str>, ChiptuneSequence)> {
+        match self.mode {
+            AudioMode::Chiptune(sequence) => (tune == 0).then_some((None, sequence)),
+            AudioMode::Dtmf(dial) => (tune == 0).then(|| (Some("dial"), dial.sequence())),
+            AudioMode::NamedChiptune(name) => {
+                let sequence = name.sequence().filter(|_| tune == 0)?;
+                Some((name.preset().map(Preset::name), sequence))
+            }
+            AudioMode::Playlist(playlist) => {
+                let preset = *playlist.tunes().get(tune)?;
+                Some((Some(preset.name()), preset.sequence()))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Most notes a chiptune can hold.
+const CAPACITY: usize = ChiptuneSequence::new().notes.len();
+
+/// Returns how long a chiptune lasts, which is as long as its longer voice.
+fn length_of(sequence: &ChiptuneSequence) -> Duration {
+    sequence
+        .voices()
+        .map(|notes| notes.iter().map(duration_of).sum())
+        .into_iter()
+        .max()
+        .unwrap_or_default()
+}
+
+fn duration_of(note: &Note) -> Duration {
+    Duration::from_millis(u64::from(note.duration_ms))
+}
@@ -0,0 +1,224 @@
+//! Automatic LED brightness from ambient light.
+//!
+//! A photoresistor divider on an ADC pin reads brighter as the room gets brighter. The ambient light task samples it
+//! every few seconds and feeds the readings into a [`Dimmer`], which smooths them and maps them to a brightness scale
+//! along the [`Config::curve`]. The result is published into [`Readings`] in the runtime status, and the LED task
+//! multiplies the manual brightness by it while [`crate::state::Lights::auto_brightness`] is on, so the manual setting
+//! stays the ceiling.
+
+use core::sync::atomic::{AtomicBool, AtomicU16, AtomicU8, Ordering};
+
+/// A point on the brightness curve.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Point {
+    /// Smoothed ADC reading.
+    pub level: u16,
+    /// Brightness scale (0-255) at that reading.
+    pub scale: u8,
+}
+
+impl Point {
+    /// Creates a new point.
+    #[must_use]
+    pub const fn new(level: u16, scale: u8) -> Self {
+        Self { level, scale }
+    }
+}
+
+/// Ambient light configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    /// Time in milliseconds between readings.
+    pub sample_interval_ms: u64,
+    /// How slowly the smoothed reading follows the raw readings, as a power of two in samples.
+    pub smoothing_shift: u8,
+    /// Brightness curve by rising reading, interpolated linearly in between and held flat beyond the ends.
+    pub curve: [Point; 4],
+    /// Change in brightness scale needed before the published scale follows, so the LEDs do not visibly hunt.
+    pub hysteresis: u8,
+}
+
+impl Config {
+    /// Default configuration for a 12-bit reading, dim in a dark room and at full brightness in daylight.
+    pub const DEFAULT: Self = Self {
+        sample_interval_ms: 2000,
+        smoothing_shift: 2,
+        curve: [
+            Point::new(100, 40),
+            Point::new(800, 100),
+            Point::new(2000, 180),
+            Point::new(3500, 255),
+        ],
+        hysteresis: 16,
+    };
+
+    /// Returns the brightness scale for a smoothed reading, ignoring hysteresis.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use catears::ambient::Config;
+    ///
+    /// let config = Config::DEFAULT;
+    /// assert_eq!(config.scale(0), 40);
+    /// assert_eq!(config.scale(450), 70);
+    /// assert_eq!(config.scale(4095), 255);
+    /// ```
+    #[must_use]
+    pub fn scale(&self, level: u16) -> u8 {
+        let first = self.curve[0];
+        if level <= first.level {
+            return first.scale;
+        }
+        let mut from = first;
+        for to in self.curve.into_iter().skip(1) {
+            if level <= to.level {
+                let span = u32::from(to.level.saturating_sub(from.level)).max(1);
+                let t = u32::from(level - from.level);
+                let from_scale = u32::from(from.scale);
+                let to_scale = u32::from(to.scale);
+                let scale = if to_scale >= from_scale {
+                    from_scale + (to_scale - from_scale) * t / span
+                } else {
+                    from_scale - (from_scale - to_scale) * t / span
+                };
+                return u8::try_from(scale).unwrap_or(u8::MAX);
+            }
+            from = to;
+        }
+        from.scale
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Smooths ambient light readings and turns them into a steady brightness scale.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::ambient::{Config, Dimmer};
+///
+/// let mut dimmer = Dimmer::new(Config::DEFAULT);
+///
+/// // The first reading is taken as is.
+/// assert_eq!(dimmer.update(3500), 255);
+///
+/// // A passing shadow is smoothed away, and small wobbles do not move the scale.
+/// assert_eq!(dimmer.update(3000), 255);
+/// assert_eq!(dimmer.update(3500), 255);
+/// assert_eq!(dimmer.update(3300), 255);
+///
+/// // Walking indoors dims the LEDs over a few readings.
+/// for _ in 0..20 {
+///     dimmer.update(100);
+/// }
+/// assert_eq!(dimmer.scale(), 40);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dimmer {
+    config: Config,
+    /// Smoothed reading, scaled by 2 to the power of the smoothing shift.
+    level: Option<u32>,
+    scale: u8,
+}
+
+impl Dimmer {
+    /// Creates a new dimmer at full brightness that takes its first reading as is.
+    #[must_use]
+    pub const fn new(config: Config) -> Self {
+        Self {
+            config,
+            level: None,
+            scale: u8::MAX,
+        }
+    }
+
+    /// Returns the smoothed reading.
+    #[must_use]
+    pub fn level(&self) -> u16 {
+        self.level.map_or(0, |level| {
+            u16::try_from(level >> self.config.smoothing_shift).unwrap_or(u16::MAX)
+        })
+    }
+
+    /// Returns the current brightness scale.
+    #[must_use]
+    pub const fn scale(&self) -> u8 {
+        self.scale
+    }
+
+    /// Processes a raw reading and returns the brightness scale to apply.
+    pub fn update(&mut self, raw: u16) -> u8 {
+        let shift = self.config.smoothing_shift;
+        let level = self.level.get_or_insert(u32::from(raw) << shift);
+        *level = *level - (*level >> shift) + u32::from(raw);
+
+        let target = self.config.scale(self.level());
+        // The ends of the curve are always reached, so the LEDs never get stuck just short of full or lowest.
+        let at_end = target == self.config.curve[0].scale || target == self.config.curve[3].scale;
+        if target.abs_diff(self.scale) >= self.config.hysteresis || (at_end && target != self.scale)
+        {
+            self.scale = target;
+        }
+        self.scale
+    }
+}
+
+/// Latest ambient light readings, published by the ambient light task for the rest of the firmware.
+pub struct Readings {
+    present: AtomicBool,
+    level: AtomicU16,
+    scale: AtomicU8,
+}
+
+impl Readings {
+    /// Creates new readings for an absent sensor, at full brightness.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            present: AtomicBool::new(false),
+            level: AtomicU16::new(0),
+            scale: AtomicU8::new(u8::MAX),
+        }
+    }
+
+    /// Marks the sensor as set up.
+    pub fn set_present(&self) {
+        self.present.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether the sensor was set up at boot.
+    #[must_use]
+    pub fn is_present(&self) -> bool {
+        self.present.load(Ordering::Relaxed)
+    }
+
+    /// Publishes the latest state of a dimmer.
+    pub fn publish(&self, dimmer: &Dimmer) {
+        self.level.store(dimmer.level(), Ordering::Relaxed);
+        self.scale.store(dimmer.scale(), Ordering::Relaxed);
+    }
+
+    /// Returns the latest smoothed reading.
+    #[must_use]
+    pub fn level(&self) -> u16 {
+        self.level.load(Ordering::Relaxed)
+    }
+
+    /// Returns the brightness scale to apply when automatic brightness is on.
+    #[must_use]
+    pub fn scale(&self) -> u8 {
+        self.scale.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for Readings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -6,7 +6,13 @@
 //! - **Silent**: No audio output (default state)
 //! - **Tone**: Simple single-frequency tone generation for basic beeps and alerts
 //! - **Chiptune**: Retro-style music sequences composed of multiple notes, perfect for game sounds
+//! - **Named chiptune**: A predefined chiptune by name, which keeps the state small
+//! - **Playlist**: Predefined chiptunes chained one after another, for melodies longer than a single chiptune holds
 //! - **Audio**: Raw PCM audio playback for pre-recorded sound effects and speech
+//! - **Remote audio**: Audio clips streamed from the server, for recordings too long to embed in the firmware
+//! - **Noise**: Continuous noise, smoothed and wobbled into wind or a purr
+//! - **Sweep**: A tone gliding between two pitches, once or back and forth like a siren
+//! - **DTMF**: Keys dialed as the dual tones of a touch-tone phone
 //!
 //! # Features
 //!
@@ -15,6 +21,22 @@
 //! - Support for 8-bit and 16-bit PCM audio in mono or stereo
 //! - Looping support for both chiptunes and audio clips
 //! - Volume control at both note and sequence levels
+//! - Sine, square, triangle, and sawtooth waveforms, single-cycle wavetables, and an ADSR envelope per note
+//!
+//! # Playback Behavior
+//!
+//! The speaker task re-reads the shared state continuously and reacts to mode changes while playing:
+//!
+//! - A `Tone` plays once for its `duration_ms` and the speakers then stay silent for as long as the mode is unchanged.
+//!   To replay the same tone, switch to a different mode first.
+//! - A `Chiptune` plays its notes in order, restarting from the first note if `looping` is set.
+//! - A `NamedChiptune` plays like a chiptune, or switches back to `Silent` with a warning if it names none.
+//! - An `Audio` clip plays once and then holds silence like a tone, or over and over if `looping` is set, without a gap
+//!   at the seam and from `loop_start` on after the first time.
+//! - A `RemoteAudio` clip does the same once enough of it has downloaded, and switches back to `Silent` if the download
+//!   fails.
+//! - Any change of mode interrupts the current tone or chiptune note within roughly 50 ms, rather than waiting for the
+//!   note to finish, and a clip within about 100 ms.
 //!
 //! # Examples
 //!
@@ -39,18 +61,53 @@
 //!
 //! # Audio File Preparation
 //!
-//! For raw audio clips, convert your audio files to uncompressed PCM format:
+//! Audio clips are played from uncompressed PCM. The easiest way to get it is a WAV file, which carries its own sample
+//! rate and format for [`Clip::from_wav`] to read, so they cannot be mixed up:
 //!
 //! ```bash
 //! # Convert to 8-bit mono at 8kHz (recommended for embedded systems)
-//! ffmpeg -i input.mp3 -f u8 -ar 8000 -ac 1 output.raw
+//! ffmpeg -i input.mp3 -acodec pcm_u8 -ar 8000 -ac 1 output.wav
 //!
 //! # Convert to 16-bit mono at 16kHz (higher quality)
-//! ffmpeg -i input.mp3 -f s16le -ar 16000 -ac 1 output.raw
+//! ffmpeg -i input.mp3 -acodec pcm_s16le -ar 16000 -ac 1 output.wav
 //! ```
+//!
+//! Headerless raw PCM works too, with `-f u8` or `-f s16le` and a `.raw` output, as long as the clip is created with
+//! the same sample rate and format it was converted to.
+
+pub mod beat;
+pub mod dsp;
+pub mod dtmf;
+pub mod rtttl;
+pub mod stream;
+pub mod synth;
+pub mod wav;
 
 use serde::{Deserialize, Serialize};
 
+/// One of the two speakers, each driven by an I2S peripheral of its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Side {
+    /// Speaker by the left ear.
+    Left,
+    /// Speaker by the right ear.
+    Right,
+}
+
+impl Side {
+    /// Both speakers, left first.
+    pub const ALL: [Self; 2] = [Self::Left, Self::Right];
+
+    /// Returns the human-readable name of the side.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Left => "left",
+            Self::Right => "right",
+        }
+    }
+}
+
 /// Audio playback modes for the speakers.
 ///
 /// Defines the various audio output options available, from simple tone generation to complex chiptune melodies
@@ -64,7 +121,8 @@ pub enum Mode {
 
     /// Simple tone generation with configurable parameters.
     ///
-    /// Generates a single note, either continuous or for a specified duration.
+    /// Plays a single note once, after which the speaker switches itself back to [`Mode::Silent`], or over and over if
+    /// the note repeats.
     Tone(Note),
 
     /// Chiptune sequence composed of multiple notes.
@@ -72,17 +130,52 @@ pub enum Mode {
     /// Plays a sequence of notes, either custom or from predefined melodies.
     Chiptune(ChiptuneSequence),
 
+    /// Predefined chiptune asked for by name.
+    ///
+    /// Plays like [`Mode::Chiptune`], without spelling out every note in the state. A name that matches no chiptune
+    /// switches the speaker back to [`Mode::Silent`].
+    NamedChiptune(chiptunes::PresetName),
+
+    /// Predefined chiptunes played one after another.
+    ///
+    /// Plays melodies too long for a single chiptune as a chain of shorter ones.
+    Playlist(Playlist),
+
     /// Raw audio playback from embedded audio data.
     ///
     /// Plays pre-recorded audio samples embedded in the binary.
     Audio(Clip),
+
+    /// Audio clip streamed from the server.
+    ///
+    /// Plays a WAV file as it downloads, after which the speaker holds silence like it does for [`Mode::Audio`], or
+    /// switches itself back to [`Mode::Silent`] if the download fails.
+    RemoteAudio(RemoteClip),
+
+    /// Continuous noise, for purring, wind, and the like.
+    ///
+    /// Plays for as long as the speaker stays on it.
+    Noise(Noise),
+
+    /// Tone gliding from one pitch to another, such as a siren.
+    ///
+    /// Sweeps once, after which the speaker switches itself back to [`Mode::Silent`], or back and forth if the sweep
+    /// repeats.
+    Sweep(Sweep),
+
+    /// Keys dialed as DTMF tones, like a touch-tone phone.
+    ///
+    /// Dials once, after which the speaker switches itself back to [`Mode::Silent`].
+    Dtmf(dtmf::Dial),
 }
 
 /// Reference to embedded audio data.
 ///
 /// Points to audio data that has been compiled into the binary using `include_bytes!`. For embedded systems, we use
 /// raw PCM data for simplicity and performance.
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+///
+/// Two clips are equal when they point at the same audio data in the same format, without comparing the data itself.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Clip {
     /// Pointer to the start of the audio data.
     #[serde(skip)]
@@ -95,6 +188,10 @@ pub struct Clip {
     pub is_stereo: bool,
     /// Whether to loop the audio after completion.
     pub looping: bool,
+    /// Sample a looping clip loops back to, so that an attack plays once and only what follows it repeats. A loop start
+    /// past the end loops the whole clip.
+    #[serde(default)]
+    pub loop_start: u32,
 }
 
 impl Clip {
@@ -112,6 +209,7 @@ impl Clip {
             bits_per_sample,
             is_stereo,
             looping: false,
+            loop_start: 0,
         }
     }
 
@@ -134,6 +232,99 @@ impl Clip {
         self
     }
 
+    /// Enables looping for the audio clip, playing it from the start once and then looping back to `sample`.
+    #[must_use]
+    pub const fn with_loop_from(mut self, sample: u32) -> Self {
+        self.looping = true;
+        self.loop_start = sample;
+        self
+    }
+
+    /// Returns the clip relabeled as recorded at [`synth::SAMPLE_RATE`], for speakers running at its actual rate.
+    ///
+    /// [`synth::fill_clip`] then renders it a sample per frame, with nothing to resample, see [`synth::output_rate`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use catears::audio::synth::{clip_frames, output_rate, SAMPLE_RATE};
+    /// use catears::audio::{Clip, Mode};
+    ///
+    /// static DATA: [u8; 8000] = [128; 8000];
+    /// let clip = Clip::mono_8bit(&DATA, 8000);
+    /// assert_eq!(output_rate(&Mode::Audio(clip)), 8000);
+    /// assert_eq!(clip.native().sample_rate, SAMPLE_RATE);
+    /// assert_eq!(clip_frames(&clip.native()), 8000);
+    /// ```
+    #[must_use]
+    pub const fn native(mut self) -> Self {
+        self.sample_rate = synth::SAMPLE_RATE;
+        self
+    }
+
+    /// Returns the part of the clip from `start_ms` to `end_ms`, without copying any of its data, so that one recording
+    /// can hold several sounds.
+    ///
+    /// Both ends are rounded down to whole frames and kept within the clip, so a slice past the end is empty. The slice
+    /// plays like a clip of its own, and a loop start counts from its beginning.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use catears::audio::Clip;
+    ///
+    /// static MEOWS: [u8; 22_050] = [0; 22_050];
+    /// let meows = Clip::mono_8bit(&MEOWS, 22_050);
+    ///
+    /// // 3 ms at 22.05 kHz is 66.15 samples, which rounds down to 66.
+    /// let meow = meows.slice(3, 500);
+    /// assert_eq!(meow.sample_count(), 11_025 - 66);
+    /// assert_eq!(meow.data.as_ptr(), MEOWS[66..].as_ptr());
+    /// assert_eq!(meow.duration_ms(), 497);
+    ///
+    /// // A stereo 16-bit frame is four bytes, and slices never split one.
+    /// static STEREO: [u8; 4 * 8000] = [0; 4 * 8000];
+    /// let stereo = Clip::new(&STEREO, 8000, 16, true);
+    /// let slice = stereo.slice(250, 750);
+    /// assert_eq!(slice.data.len(), 4 * 4000);
+    /// assert_eq!(slice.data.as_ptr(), STEREO[4 * 2000..].as_ptr());
+    ///
+    /// // Out of range, a slice stops at the end of the clip.
+    /// assert_eq!(stereo.slice(750, 5000).sample_count(), 2000);
+    /// assert_eq!(stereo.slice(5000, 6000).sample_count(), 0);
+    /// assert_eq!(stereo.slice(600, 200).sample_count(), 0);
+    /// ```
+    #[must_use]
+    pub const fn slice(mut self, start_ms: u32, end_ms: u32) -> Self {
+        let frame_bytes = (self.bits_per_sample / 8) as usize * if self.is_stereo { 2 } else { 1 };
+        if frame_bytes == 0 {
+            return self;
+        }
+        let frames = self.data.len() / frame_bytes;
+        let start = self.frames_in(start_ms, frames);
+        let mut end = self.frames_in(end_ms, frames);
+        if end < start {
+            end = start;
+        }
+        let (_, rest) = self.data.split_at(start * frame_bytes);
+        let (data, _) = rest.split_at((end - start) * frame_bytes);
+        self.data = data;
+        self
+    }
+
+    /// Returns how many frames of the clip play in `ms` milliseconds, at most `frames`.
+    const fn frames_in(&self, ms: u32, frames: usize) -> usize {
+        let count = ms as u64 * self.sample_rate as u64 / 1000;
+        if count < frames as u64 {
+            #[allow(clippy::cast_possible_truncation)]
+            {
+                count as usize
+            }
+        } else {
+            frames
+        }
+    }
+
     /// Returns the number of samples in the audio clip.
     #[must_use]
     pub const fn sample_count(&self) -> u32 {
@@ -154,9 +345,342 @@ impl Clip {
     }
 }
 
+impl PartialEq for Clip {
+    fn eq(&self, other: &Self) -> bool {
+        core::ptr::eq(self.data, other.data)
+            && self.sample_rate == other.sample_rate
+            && self.bits_per_sample == other.bits_per_sample
+            && self.is_stereo == other.is_stereo
+            && self.looping == other.looping
+            && self.loop_start == other.loop_start
+    }
+}
+
+/// Audio clip streamed from the server rather than embedded in the firmware.
+///
+/// Clips are WAV files, in any of the formats [`Clip::from_wav`] accepts, named by their id under
+/// [`stream::URL_PREFIX`], so `{"RemoteAudio":{"id":7}}` plays `clips/7.wav`. Since they download as they play, they
+/// can be as long as they like, but they are best kept to 8-bit mono at a low sample rate so that the connection keeps
+/// up, see [`stream`].
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::{Mode, RemoteClip};
+///
+/// let (mode, _) = serde_json_core::from_str::<Mode>(r#"{"RemoteAudio":{"id":7}}"#).unwrap();
+/// assert_eq!(mode, Mode::RemoteAudio(RemoteClip::new(7)));
+///
+/// let (mode, _) = serde_json_core::from_str::<Mode>(r#"{"RemoteAudio":{"id":7,"looping":true}}"#).unwrap();
+/// assert_eq!(mode, Mode::RemoteAudio(RemoteClip::new(7).with_loop()));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoteClip {
+    /// Id of the clip, which names the file it is downloaded from.
+    pub id: u16,
+    /// Whether to download and play the clip again after completion.
+    #[serde(default)]
+    pub looping: bool,
+}
+
+impl RemoteClip {
+    /// Creates a reference to the remote clip `id`, played once.
+    #[must_use]
+    pub const fn new(id: u16) -> Self {
+        Self { id, looping: false }
+    }
+
+    /// Enables looping for the remote clip.
+    #[must_use]
+    pub const fn with_loop(mut self) -> Self {
+        self.looping = true;
+        self
+    }
+}
+
+/// Sound played once over whatever a speaker is playing, such as a chirp when the ears are booped.
+///
+/// Events are sent in [`crate::state::Speakers::event`]. Each speaker plays an event once when it sees an id it has not
+/// played yet, and then goes back to its own mode, from the start. So a new event needs an id of its own, one more than
+/// the last, while the same event sent again with the rest of the state plays only the once.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::chiptunes::{Preset, PresetName};
+/// use catears::audio::{Event, Mode, Note, Sound};
+///
+/// let (event, _) = serde_json_core::from_str::<Event>(r#"{"id":3,"sound":{"Chiptune":"coin"}}"#).unwrap();
+/// assert_eq!(event, Event::new(3, Sound::Chiptune(PresetName::from(Preset::Coin))));
+///
+/// // Events play once, even where the sound on its own would go on and on.
+/// let chirp = Sound::Tone(Note::new(2000.0, 80).with_repeat(true));
+/// assert_eq!(Event::new(4, chirp).mode(), Mode::Tone(Note::new(2000.0, 80)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Event {
+    /// Id of the event, counting up from 1 with each new event.
+    pub id: u32,
+    /// What the event sounds like.
+    pub sound: Sound,
+}
+
+impl Event {
+    /// Creates an event `id` that plays `sound`.
+    #[must_use]
+    pub const fn new(id: u32, sound: Sound) -> Self {
+        Self { id, sound }
+    }
+
+    /// Returns the mode that plays the event once, or [`Mode::Silent`] for a chiptune name that matches no chiptune.
+    #[must_use]
+    pub fn mode(&self) -> Mode {
+        match self.sound {
+            Sound::Tone(mut note) => {
+                note.repeat = false;
+                Mode::Tone(note)
+            }
+            Sound::Chiptune(name) => name.sequence().map_or(Mode::Silent, |mut sequence| {
+                sequence.looping = false;
+                Mode::Chiptune(sequence)
+            }),
+        }
+    }
+}
+
+/// What a sound [`Event`] plays.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Sound {
+    /// Single note.
+    Tone(Note),
+    /// Predefined chiptune, by name like [`Mode::NamedChiptune`].
+    Chiptune(chiptunes::PresetName),
+}
+
+/// Noise, shaped into anything from a hiss to a purr.
+///
+/// White noise hisses. Smoothing it lets each sample follow on from the last, which takes the highs out and leaves a
+/// rumble, and a wobble swells its volume up and down that many times a second. Smoothed noise wobbling at about 25 Hz
+/// sounds remarkably like a purring cat, see [`Noise::PURR`].
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::{Mode, Noise};
+///
+/// let wind = Noise::new(96).with_smoothing(200);
+/// assert_eq!((wind.amplitude, wind.smoothing, wind.wobble_hz), (96, 200, 0.0));
+///
+/// // Anything left out is white noise at a steady volume.
+/// let (mode, _) = serde_json_core::from_str::<Mode>(r#"{"Noise":{"amplitude":64}}"#).unwrap();
+/// assert_eq!(mode, Mode::Noise(Noise::new(64)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Noise {
+    /// Volume level (0-255), scaled by the master volume.
+    pub amplitude: u8,
+    /// How closely each sample follows the last (0-255), from white noise at 0 to a deep rumble near 255.
+    pub smoothing: u8,
+    /// How many times per second the volume swells and fades, or 0.0 for a steady volume.
+    pub wobble_hz: f32,
+}
+
+impl Noise {
+    /// White noise at half volume.
+    pub const DEFAULT: Self = Self::new(128);
+
+    /// A low, rumbling purr.
+    pub const PURR: Self = Self::new(192).with_smoothing(240).with_wobble(25.0);
+
+    /// Creates new white noise at a steady volume of `amplitude`.
+    #[must_use]
+    pub const fn new(amplitude: u8) -> Self {
+        Self {
+            amplitude,
+            smoothing: 0,
+            wobble_hz: 0.0,
+        }
+    }
+
+    /// Sets how closely each sample of the noise follows the last.
+    #[must_use]
+    pub const fn with_smoothing(mut self, smoothing: u8) -> Self {
+        self.smoothing = smoothing;
+        self
+    }
+
+    /// Sets how many times per second the volume of the noise swells and fades.
+    #[must_use]
+    pub const fn with_wobble(mut self, wobble_hz: f32) -> Self {
+        self.wobble_hz = wobble_hz;
+        self
+    }
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Sweep of a tone from one frequency to another, see [`Mode::Sweep`].
+///
+/// The frequency moves in a straight line from the start to the end over the duration. A repeating sweep then glides
+/// back to the start, and keeps going back and forth until the mode changes.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::{Mode, Sweep};
+///
+/// let siren = Sweep::new(600.0, 1200.0, 500).with_repeat(true);
+/// let mut json = [0u8; 128];
+/// let len = serde_json_core::to_slice(&Mode::Sweep(siren), &mut json).unwrap();
+/// assert_eq!(
+///     core::str::from_utf8(&json[..len]).unwrap(),
+///     r#"{"Sweep":{"start_hz":600.0,"end_hz":1200.0,"duration_ms":500,"repeat":true}}"#
+/// );
+///
+/// // Sweeps play once unless told otherwise.
+/// let (mode, _) =
+///     serde_json_core::from_str::<Mode>(r#"{"Sweep":{"start_hz":600.0,"end_hz":1200.0,"duration_ms":500}}"#).unwrap();
+/// assert_eq!(mode, Mode::Sweep(Sweep::new(600.0, 1200.0, 500)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Sweep {
+    /// Frequency in Hz the sweep starts at.
+    pub start_hz: f32,
+    /// Frequency in Hz the sweep ends at.
+    pub end_hz: f32,
+    /// Time in milliseconds to glide from the start to the end.
+    pub duration_ms: u16,
+    /// Whether the sweep goes back and forth for as long as the speaker stays on it, instead of sweeping once.
+    #[serde(default)]
+    pub repeat: bool,
+}
+
+impl Sweep {
+    /// Creates a new sweep from `start_hz` to `end_hz` over `duration_ms`, played once.
+    #[must_use]
+    pub const fn new(start_hz: f32, end_hz: f32, duration_ms: u16) -> Self {
+        Self {
+            start_hz,
+            end_hz,
+            duration_ms,
+            repeat: false,
+        }
+    }
+
+    /// Sets whether the sweep goes back and forth until the mode changes instead of sweeping once.
+    #[must_use]
+    pub const fn with_repeat(mut self, repeat: bool) -> Self {
+        self.repeat = repeat;
+        self
+    }
+}
+
+/// Shape of the wave a note is synthesized with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Waveform {
+    /// Pure sine wave, soft and round.
+    #[default]
+    Sine,
+    /// Square wave, the classic hollow chiptune sound.
+    Square,
+    /// Triangle wave, between a sine and a square, like old consoles' bass channels.
+    Triangle,
+    /// Sawtooth wave, bright and buzzy.
+    Sawtooth,
+    /// One of the single-cycle [`wavetables`], by index. An index with no table plays as a sine.
+    Table(u8),
+}
+
+impl Waveform {
+    /// Returns the human-readable name of the waveform, which for a wavetable is the table's name.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Sine => "sine",
+            Self::Square => "square",
+            Self::Triangle => "triangle",
+            Self::Sawtooth => "sawtooth",
+            Self::Table(index) => wavetables::get(index).map_or("table", |table| table.name),
+        }
+    }
+}
+
+/// What a note plays: a pitched tone, or a drum hit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum NoteKind {
+    /// A tone at the note's frequency, on its waveform.
+    #[default]
+    Tone,
+    /// A drum hit, which ignores the note's frequency, waveform, and envelope and dies away on its own.
+    Percussion(Percussion),
+}
+
+/// A drum played by a percussion note.
+///
+/// Each hit starts at full volume and dies away within about a quarter of a second, or sooner if the note is shorter.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::synth::{amplitude, fill_tone, frames_for};
+/// use catears::audio::{Note, Percussion};
+///
+/// let loudest = amplitude(255, 255);
+/// let peak = |buffer: &[i16]| buffer.iter().map(|&sample| f32::from(sample).abs()).fold(0.0, f32::max);
+/// let crossings = |buffer: &[i16]| buffer.windows(2).filter(|pair| (pair[0] < 0) != (pair[1] < 0)).count();
+///
+/// let mut hits = Vec::new();
+/// for drum in [Percussion::Kick, Percussion::Snare, Percussion::Hat] {
+///     let mut stereo = vec![0i16; 2 * frames_for(250)];
+///     assert_eq!(fill_tone(&mut stereo, &Note::percussion(drum, 250), loudest), frames_for(250));
+///     let buffer: Vec<i16> = stereo.iter().step_by(2).copied().collect();
+///
+///     // Loud right away, nearly gone by the end, and never out of range.
+///     assert!(peak(&buffer[..frames_for(10)]) > loudest * 0.5);
+///     assert!(peak(&buffer[frames_for(200)..]) < loudest * 0.1);
+///     assert!(peak(&buffer) <= loudest);
+///     hits.push(buffer);
+/// }
+///
+/// // The kick is a low thump, the hat a bright hiss, and the snare in between.
+/// let [kick, snare, hat] = [&hits[0], &hits[1], &hits[2]].map(|hit| crossings(&hit[..frames_for(20)]));
+/// assert!(kick < 20 && kick < snare && snare < hat);
+///
+/// // Every hit of a drum sounds the same.
+/// let mut again = vec![0i16; 2 * frames_for(250)];
+/// fill_tone(&mut again, &Note::percussion(Percussion::Snare, 250), loudest);
+/// assert!(again.iter().step_by(2).eq(hits[1].iter()));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Percussion {
+    /// Bass drum: a low sine thump that falls in pitch as it dies away.
+    Kick,
+    /// Snare drum: a burst of noise over a short tonal body.
+    Snare,
+    /// Closed hi-hat: a short tick of bright noise.
+    Hat,
+}
+
+impl Percussion {
+    /// Returns the human-readable name of the drum.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Kick => "kick",
+            Self::Snare => "snare",
+            Self::Hat => "hat",
+        }
+    }
+}
+
 /// A single note in a chiptune sequence.
 ///
-/// Represents one note with its frequency, duration, and optional volume control.
+/// Represents one note with its frequency, duration, optional volume control, and waveform.
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Note {
     /// Frequency of the note in Hz (0.0 for rest/silence).
@@ -165,26 +689,70 @@ pub struct Note {
     pub duration_ms: u16,
     /// Volume level (0-255), or None to use the sequence's default volume.
     pub volume: Option<u8>,
+    /// Shape of the wave, a sine unless set.
+    #[serde(default)]
+    pub waveform: Waveform,
+    /// Share of each cycle a square wave spends high, in 256ths, an even 128 unless set. Other waveforms ignore this.
+    #[serde(default = "default_duty")]
+    pub duty: u8,
+    /// How the note swells in and dies away, a short click-free fade at both ends unless set.
+    #[serde(default)]
+    pub envelope: Envelope,
+    /// How the pitch of the note wobbles, steady unless set.
+    #[serde(default)]
+    pub vibrato: Vibrato,
+    /// How the volume of the note wobbles, steady unless set.
+    #[serde(default)]
+    pub tremolo: Tremolo,
+    /// Pitches the note cycles through to fake a chord, a single pitch unless set.
+    #[serde(default)]
+    pub arpeggio: Arpeggio,
+    /// Whether the note is a tone or a drum hit, a tone unless set.
+    #[serde(default)]
+    pub kind: NoteKind,
+    /// Whether a tone plays over and over for as long as the speaker stays on it. Unless set, a tone plays once and the
+    /// speaker goes back to silence. Notes in a chiptune ignore this.
+    #[serde(default)]
+    pub repeat: bool,
 }
 
 impl Note {
-    /// Creates a new note with the specified frequency and duration, using default volume.
+    /// Duty cycle of an even square wave, high for half of each cycle.
+    pub const EVEN_DUTY: u8 = 128;
+
+    /// Creates a new sine note with the specified frequency and duration, using default volume.
     #[must_use]
     pub const fn new(frequency: f32, duration_ms: u16) -> Self {
         Self {
             frequency,
             duration_ms,
             volume: None,
+            waveform: Waveform::Sine,
+            duty: Self::EVEN_DUTY,
+            envelope: Envelope::DEFAULT,
+            vibrato: Vibrato::OFF,
+            tremolo: Tremolo::OFF,
+            arpeggio: Arpeggio::OFF,
+            kind: NoteKind::Tone,
+            repeat: false,
         }
     }
 
-    /// Creates a new note with specified frequency, duration, and volume.
+    /// Creates a new sine note with specified frequency, duration, and volume.
     #[must_use]
     pub const fn with_volume(frequency: f32, duration_ms: u16, volume: u8) -> Self {
         Self {
             frequency,
             duration_ms,
             volume: Some(volume),
+            waveform: Waveform::Sine,
+            duty: Self::EVEN_DUTY,
+            envelope: Envelope::DEFAULT,
+            vibrato: Vibrato::OFF,
+            tremolo: Tremolo::OFF,
+            arpeggio: Arpeggio::OFF,
+            kind: NoteKind::Tone,
+            repeat: false,
         }
     }
 
@@ -195,118 +763,1217 @@ impl Note {
             frequency: 0.0,
             duration_ms,
             volume: None,
+            waveform: Waveform::Sine,
+            duty: Self::EVEN_DUTY,
+            envelope: Envelope::DEFAULT,
+            vibrato: Vibrato::OFF,
+            tremolo: Tremolo::OFF,
+            arpeggio: Arpeggio::OFF,
+            kind: NoteKind::Tone,
+            repeat: false,
         }
     }
-}
 
-/// A sequence of notes forming a chiptune melody.
-///
-/// Can store up to 64 notes in a fixed-size array for embedded systems compatibility.
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
-pub struct ChiptuneSequence {
-    /// Array of notes in the sequence.
-    #[serde(with = "serde_arrays")]
-    pub notes: [Note; 64],
-    /// Number of valid notes in the sequence (0-64).
-    pub length: u8,
-    /// Default volume for notes that don't specify their own volume.
-    pub default_volume: u8,
-    /// Whether to loop the sequence after completion.
-    pub looping: bool,
-}
+    /// Creates a hit of `drum` lasting `duration_ms`, using default volume.
+    #[must_use]
+    pub const fn percussion(drum: Percussion, duration_ms: u16) -> Self {
+        let mut note = Self::rest(duration_ms);
+        note.kind = NoteKind::Percussion(drum);
+        note
+    }
 
-impl ChiptuneSequence {
-    /// Creates a new empty chiptune sequence.
+    /// Returns whether the note is a rest, a tone without a frequency.
     #[must_use]
-    pub const fn new() -> Self {
-        Self {
-            notes: [Note::rest(0); 64],
-            length: 0,
-            default_volume: 128,
-            looping: false,
-        }
+    pub const fn is_rest(&self) -> bool {
+        self.frequency <= 0.0 && matches!(self.kind, NoteKind::Tone)
     }
 
-    /// Creates a new chiptune sequence from a slice of notes.
+    /// Creates a new sine note at the pitch of MIDI note `note`, tuned to A4 (MIDI 69) at 440 Hz.
     ///
-    /// # Panics
+    /// # Examples
     ///
-    /// Panics if the slice contains more than 64 notes.
+    /// ```rust
+    /// use catears::audio::Note;
+    ///
+    /// assert_eq!(Note::from_midi(69, 100).frequency, 440.0);
+    /// assert!((Note::from_midi(60, 100).frequency - 261.63).abs() < 0.5);
+    /// assert!((Note::from_midi(80, 100).frequency - 830.61).abs() < 0.5);
+    /// ```
     #[must_use]
-    pub fn from_notes(notes: &[Note]) -> Self {
-        assert!(
-            notes.len() <= 64,
-            "ChiptuneSequence can hold at most 64 notes"
-        );
-        let mut sequence = Self::new();
-        for (i, note) in notes.iter().enumerate() {
-            sequence.notes[i] = *note;
+    pub fn from_midi(note: u8, duration_ms: u16) -> Self {
+        let semitones = f32::from(note) - 69.0;
+        Self::new(440.0 * libm::powf(2.0, semitones / 12.0), duration_ms)
+    }
+
+    /// Creates a new sine note at a pitch written in scientific pitch notation: a letter, an optional sharp (`#`) or flat
+    /// (`b`), and an octave from 0 to 8. Returns `None` if `name` is not such a pitch.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use catears::audio::Note;
+    ///
+    /// assert_eq!(Note::named("A4", 100), Some(Note::new(440.0, 100)));
+    /// assert!((Note::named("C4", 100).unwrap().frequency - 261.63).abs() < 0.5);
+    /// assert!((Note::named("G#5", 100).unwrap().frequency - 830.61).abs() < 0.5);
+    /// assert_eq!(Note::named("Ab5", 100), Note::named("G#5", 100));
+    /// assert_eq!(Note::named("c#5", 100), Note::named("C#5", 100));
+    ///
+    /// assert_eq!(Note::named("H4", 100), None);
+    /// assert_eq!(Note::named("C9", 100), None);
+    /// assert_eq!(Note::named("C#", 100), None);
+    /// assert_eq!(Note::named("C4x", 100), None);
+    /// ```
+    #[must_use]
+    pub fn named(name: &str, duration_ms: u16) -> Option<Self> {
+        let mut chars = name.chars();
+        let semitone: i8 = match chars.next()?.to_ascii_uppercase() {
+            'C' => 0,
+            'D' => 2,
+            'E' => 4,
+            'F' => 5,
+            'G' => 7,
+            'A' => 9,
+            'B' => 11,
+            _ => return None,
+        };
+        let mut next = chars.next()?;
+        let accidental = match next {
+            '#' => 1,
+            'b' => -1,
+            _ => 0,
+        };
+        if accidental != 0 {
+            next = chars.next()?;
         }
-        sequence.length = u8::try_from(notes.len()).expect("notes.len() should be <= 64");
-        sequence
+        let octave = next.to_digit(10).filter(|&octave| octave <= 8)?;
+        if chars.next().is_some() {
+            return None;
+        }
+        // C0 is MIDI note 12, and the octave is at most 8, so this always lands between 11 and 120.
+        let midi = (i8::try_from(octave).ok()? + 1) * 12 + semitone + accidental;
+        Some(Self::from_midi(u8::try_from(midi).ok()?, duration_ms))
     }
 
-    /// Sets the default volume for the sequence.
+    /// Sets the waveform of the note.
     #[must_use]
-    pub const fn with_volume(mut self, volume: u8) -> Self {
-        self.default_volume = volume;
+    pub const fn with_waveform(mut self, waveform: Waveform) -> Self {
+        self.waveform = waveform;
         self
     }
 
-    /// Enables looping for the sequence.
+    /// Sets the duty cycle of the note, how much of each cycle in 256ths a square wave spends high. NES-style pulse
+    /// waves use 32 (12.5%), 64 (25%), or the even 128 (50%).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use catears::audio::{Note, Waveform};
+    ///
+    /// let pulse = Note::new(440.0, 100).with_waveform(Waveform::Square).with_duty(64);
+    /// assert_eq!(pulse.duty, 64);
+    ///
+    /// // Notes from before duty cycles play an even square wave.
+    /// let (old, _) = serde_json_core::from_str::<Note>(r#"{"frequency":440.0,"duration_ms":100,"volume":null}"#).unwrap();
+    /// assert_eq!(old.duty, Note::EVEN_DUTY);
+    /// ```
     #[must_use]
-    pub const fn with_loop(mut self) -> Self {
-        self.looping = true;
+    pub const fn with_duty(mut self, duty: u8) -> Self {
+        self.duty = duty;
         self
     }
-}
 
-impl Default for ChiptuneSequence {
-    fn default() -> Self {
-        Self::new()
+    /// Sets the envelope of the note.
+    #[must_use]
+    pub const fn with_envelope(mut self, envelope: Envelope) -> Self {
+        self.envelope = envelope;
+        self
     }
-}
 
-/// Predefined chiptune melodies for common game events and UI feedback.
-pub mod chiptunes {
-    use super::{ChiptuneSequence, Note};
-
-    /// Classic Mario-style coin collection sound.
+    /// Sets the vibrato of the note.
     #[must_use]
-    pub fn coin_collect() -> ChiptuneSequence {
-        ChiptuneSequence::from_notes(&[
-            Note::new(988.0, 100),  // B5
-            Note::new(1319.0, 400), // E6
-        ])
+    pub const fn with_vibrato(mut self, vibrato: Vibrato) -> Self {
+        self.vibrato = vibrato;
+        self
     }
 
-    /// Power-up acquisition jingle.
+    /// Sets the tremolo of the note.
     #[must_use]
-    pub fn power_up() -> ChiptuneSequence {
-        ChiptuneSequence::from_notes(&[
-            Note::new(523.0, 100),  // C5
-            Note::new(659.0, 100),  // E5
-            Note::new(784.0, 100),  // G5
-            Note::new(1047.0, 200), // C6
-        ])
+    pub const fn with_tremolo(mut self, tremolo: Tremolo) -> Self {
+        self.tremolo = tremolo;
+        self
     }
 
-    /// Level completion fanfare.
+    /// Sets the arpeggio of the note.
     #[must_use]
-    pub fn level_complete() -> ChiptuneSequence {
-        ChiptuneSequence::from_notes(&[
-            Note::new(523.0, 150),  // C5
-            Note::new(659.0, 150),  // E5
-            Note::new(784.0, 150),  // G5
-            Note::new(1047.0, 150), // C6
-            Note::new(784.0, 150),  // G5
-            Note::new(1047.0, 400), // C6
-        ])
+    pub const fn with_arpeggio(mut self, arpeggio: Arpeggio) -> Self {
+        self.arpeggio = arpeggio;
+        self
     }
 
-    /// Game over melody.
+    /// Sets whether the note, played as a tone, repeats until the mode changes instead of playing once.
     #[must_use]
-    pub fn game_over() -> ChiptuneSequence {
+    pub const fn with_repeat(mut self, repeat: bool) -> Self {
+        self.repeat = repeat;
+        self
+    }
+}
+
+const fn default_duty() -> u8 {
+    Note::EVEN_DUTY
+}
+
+/// Volume envelope of a note: attack, decay, sustain, and release.
+///
+/// The note rises from silence to full volume over the attack, falls to the sustain level over the decay, holds it, and
+/// fades to silence over the release at its very end. A note too short for its attack and release gets both shortened
+/// in proportion, see [`synth::envelope`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Envelope {
+    /// Time in milliseconds to rise from silence to full volume.
+    pub attack_ms: u16,
+    /// Time in milliseconds to fall from full volume to the sustain level.
+    pub decay_ms: u16,
+    /// Level (0-255) held after the decay, as a fraction of full volume.
+    pub sustain: u8,
+    /// Time in milliseconds to fade to silence at the end of the note.
+    pub release_ms: u16,
+}
+
+impl Envelope {
+    /// Default envelope, just long enough at both ends for the speakers not to pop.
+    pub const DEFAULT: Self = Self::new(5, 0, u8::MAX, 5);
+
+    /// Creates a new envelope.
+    #[must_use]
+    pub const fn new(attack_ms: u16, decay_ms: u16, sustain: u8, release_ms: u16) -> Self {
+        Self {
+            attack_ms,
+            decay_ms,
+            sustain,
+            release_ms,
+        }
+    }
+
+    /// Creates an envelope that only fades in over `fade_in_ms` and out over `fade_out_ms`, holding full volume in
+    /// between.
+    ///
+    /// The default fades of 5 ms at either end keep the speakers from popping. Short blips sound crisper with less, and
+    /// low notes need more not to thump.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use catears::audio::{Envelope, Note};
+    ///
+    /// assert_eq!(Envelope::fades(5, 5), Envelope::DEFAULT);
+    ///
+    /// let (blip, _) = serde_json_core::from_str::<Note>(
+    ///     r#"{"frequency":1200.0,"duration_ms":30,"volume":null,"envelope":{"attack_ms":1,"release_ms":2}}"#,
+    /// )
+    /// .unwrap();
+    /// assert_eq!(blip.envelope, Envelope::fades(1, 2));
+    /// ```
+    #[must_use]
+    pub const fn fades(fade_in_ms: u16, fade_out_ms: u16) -> Self {
+        Self::new(fade_in_ms, 0, u8::MAX, fade_out_ms)
+    }
+}
+
+impl Default for Envelope {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Vibrato of a note: a slow sinusoidal wobble of its pitch around the note's frequency.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Vibrato {
+    /// How far the pitch swings above and below the note's frequency, in cents (hundredths of a semitone).
+    pub depth_cents: u16,
+    /// How many times per second the pitch swings back and forth.
+    pub rate_hz: f32,
+}
+
+impl Vibrato {
+    /// No vibrato, a steady pitch.
+    pub const OFF: Self = Self::new(0, 0.0);
+
+    /// Creates a new vibrato.
+    #[must_use]
+    pub const fn new(depth_cents: u16, rate_hz: f32) -> Self {
+        Self {
+            depth_cents,
+            rate_hz,
+        }
+    }
+
+    /// Returns whether the vibrato changes the pitch at all.
+    #[must_use]
+    pub fn is_on(&self) -> bool {
+        self.depth_cents > 0 && self.rate_hz > 0.0
+    }
+}
+
+impl Default for Vibrato {
+    fn default() -> Self {
+        Self::OFF
+    }
+}
+
+/// Tremolo of a note: a slow sinusoidal wobble of its volume, dipping below the note's envelope and back.
+///
+/// The volume starts each note at the envelope and dips by up to `depth` 255ths of it, so the tremolo only ever takes
+/// away and never pushes a note past its full volume, see [`synth::tremolo`].
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::synth::{amplitude, fill_tone, frames_for};
+/// use catears::audio::{Note, Tremolo};
+///
+/// // A second of a note pulsing four times, all the way down to silence between pulses.
+/// let loudest = amplitude(255, 255);
+/// let note = Note::new(441.0, 1000).with_tremolo(Tremolo::new(255, 4.0));
+/// let mut second = vec![0i16; 2 * frames_for(1000)];
+/// fill_tone(&mut second, &note, loudest);
+/// assert!(second.iter().all(|&sample| f32::from(sample).abs() <= loudest));
+///
+/// // The loudness of successive cycles of the tone rises and falls at the rate of the tremolo.
+/// let rms: Vec<f32> = second
+///     .chunks(2 * 100)
+///     .map(|window| (window.iter().map(|&sample| f32::from(sample).powi(2)).sum::<f32>() / 200.0).sqrt())
+///     .collect();
+/// let loud = |rms: f32| rms > loudest / core::f32::consts::SQRT_2 / 2.0;
+/// let swells = rms[10..].windows(2).filter(|pair| !loud(pair[0]) && loud(pair[1])).count();
+/// assert_eq!(swells, 4);
+/// assert!(rms[110] > loudest * 0.69 && rms[55] < loudest * 0.01);
+///
+/// // Notes from before tremolo keep a steady volume.
+/// let (old, _) = serde_json_core::from_str::<Note>(r#"{"frequency":440.0,"duration_ms":100,"volume":null}"#).unwrap();
+/// assert_eq!(old.tremolo, Tremolo::OFF);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Tremolo {
+    /// How far the volume dips at the bottom of each wobble, in 255ths of the note's volume.
+    pub depth: u8,
+    /// How many times per second the volume dips and comes back.
+    pub rate_hz: f32,
+}
+
+impl Tremolo {
+    /// No tremolo, a steady volume.
+    pub const OFF: Self = Self::new(0, 0.0);
+
+    /// Creates a new tremolo.
+    #[must_use]
+    pub const fn new(depth: u8, rate_hz: f32) -> Self {
+        Self { depth, rate_hz }
+    }
+
+    /// Returns whether the tremolo changes the volume at all.
+    #[must_use]
+    pub fn is_on(&self) -> bool {
+        self.depth > 0 && self.rate_hz > 0.0
+    }
+}
+
+impl Default for Tremolo {
+    fn default() -> Self {
+        Self::OFF
+    }
+}
+
+/// Arpeggio of a note, the chiptune way of playing a chord on a single voice.
+///
+/// The note cycles through its own frequency and each pitch above it in turn, switching every step. An offset of zero
+/// leaves its slot out, so a single offset alternates between two pitches.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::{Arpeggio, Note};
+///
+/// // A major chord, switching at the default pace.
+/// let note = Note::new(523.0, 200).with_arpeggio(Arpeggio::chord([4, 7]));
+/// assert_eq!(note.arpeggio.step_ms, 20);
+///
+/// let mut json = [0u8; 512];
+/// let len = serde_json_core::to_slice(&note, &mut json).unwrap();
+/// let (parsed, _) = serde_json_core::from_slice::<Note>(&json[..len]).unwrap();
+/// assert_eq!(parsed, note);
+///
+/// // Notes from before arpeggios keep a single pitch.
+/// let (old, _) = serde_json_core::from_str::<Note>(r#"{"frequency":440.0,"duration_ms":100,"volume":null}"#).unwrap();
+/// assert_eq!(old.arpeggio, Arpeggio::OFF);
+/// assert!(!old.arpeggio.is_on());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Arpeggio {
+    /// Pitches above the note's frequency to cycle through, in semitones, or zero for none.
+    pub semitones: [u8; 2],
+    /// Time in milliseconds spent on each pitch before switching to the next.
+    pub step_ms: u16,
+}
+
+impl Arpeggio {
+    /// No arpeggio, a single pitch.
+    pub const OFF: Self = Self::new([0, 0], 20);
+
+    /// Creates a new arpeggio.
+    #[must_use]
+    pub const fn new(semitones: [u8; 2], step_ms: u16) -> Self {
+        Self { semitones, step_ms }
+    }
+
+    /// Creates a new arpeggio through `semitones` above the note, switching every 20 ms like a tracker at 50 Hz.
+    #[must_use]
+    pub const fn chord(semitones: [u8; 2]) -> Self {
+        Self::new(semitones, Self::OFF.step_ms)
+    }
+
+    /// Returns whether the arpeggio changes the pitch at all.
+    #[must_use]
+    pub const fn is_on(&self) -> bool {
+        (self.semitones[0] > 0 || self.semitones[1] > 0) && self.step_ms > 0
+    }
+}
+
+impl Default for Arpeggio {
+    fn default() -> Self {
+        Self::OFF
+    }
+}
+
+/// Length of a note in a score, as a fraction of a whole note, see [`ChiptuneSequence::from_score`].
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::NoteLength;
+///
+/// assert_eq!(NoteLength::QUARTER.duration_ms(120), 500);
+/// assert_eq!(NoteLength::WHOLE.duration_ms(120), 2000);
+/// assert_eq!(NoteLength::SIXTEENTH.duration_ms(120), 125);
+///
+/// // A dot makes a note half again as long.
+/// assert_eq!(NoteLength::QUARTER.dotted().duration_ms(120), 750);
+/// assert_eq!(NoteLength::EIGHTH.dotted().duration_ms(100), 450);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoteLength {
+    /// How many of the note make up a whole note, such as 4 for a quarter note.
+    pub division: u8,
+    /// Whether the note is dotted, half again as long.
+    pub dotted: bool,
+}
+
+impl NoteLength {
+    /// Whole note, four beats.
+    pub const WHOLE: Self = Self::new(1);
+    /// Half note, two beats.
+    pub const HALF: Self = Self::new(2);
+    /// Quarter note, a single beat.
+    pub const QUARTER: Self = Self::new(4);
+    /// Eighth note, half a beat.
+    pub const EIGHTH: Self = Self::new(8);
+    /// Sixteenth note, a quarter of a beat.
+    pub const SIXTEENTH: Self = Self::new(16);
+
+    /// Creates a new undotted note length of a whole note divided by `division`.
+    #[must_use]
+    pub const fn new(division: u8) -> Self {
+        Self {
+            division,
+            dotted: false,
+        }
+    }
+
+    /// Returns the dotted version of the length, half again as long.
+    #[must_use]
+    pub const fn dotted(mut self) -> Self {
+        self.dotted = true;
+        self
+    }
+
+    /// Returns how many milliseconds a note of this length lasts at `bpm` quarter notes per minute.
+    #[must_use]
+    pub fn duration_ms(self, bpm: u16) -> u16 {
+        // A whole note lasts four beats, of 60,000 / bpm milliseconds each.
+        let beats = u32::from(bpm) * u32::from(self.division);
+        let mut duration_ms = 240_000 / beats.max(1);
+        if self.dotted {
+            duration_ms += duration_ms / 2;
+        }
+        u16::try_from(duration_ms).unwrap_or(u16::MAX)
+    }
+}
+
+/// A sequence of notes forming a chiptune melody.
+///
+/// Can store up to 64 notes in a fixed-size array for embedded systems compatibility. The notes can be split into two
+/// voices that play at the same time, such as a melody over a bass line, each keeping its own timing.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::{ChiptuneSequence, Note};
+///
+/// let melody = [Note::new(523.0, 150), Note::new(659.0, 150)];
+/// let bass = [Note::new(131.0, 300)];
+/// let tune = ChiptuneSequence::from_voices(&melody, &bass);
+/// assert_eq!((tune.length, tune.second_voice), (3, Some(2)));
+/// assert_eq!(tune.voices(), [&melody[..], &bass[..]]);
+///
+/// // A single voice serializes without any mention of a second one.
+/// let mut json = vec![0u8; 4096];
+/// let len = serde_json_core::to_slice(&ChiptuneSequence::from_notes(&melody), &mut json).unwrap();
+/// assert!(!core::str::from_utf8(&json[..len]).unwrap().contains("second_voice"));
+/// let len = serde_json_core::to_slice(&tune, &mut json).unwrap();
+/// let (parsed, _) = serde_json_core::from_slice::<ChiptuneSequence>(&json[..len]).unwrap();
+/// assert_eq!(parsed, tune);
+/// ```
+///
+/// Only the notes up to `length` are serialized, and `length` can be left out when parsing, so a short chiptune makes a
+/// short document:
+///
+/// ```rust
+/// use catears::audio::{ChiptuneSequence, Mode, Note};
+/// use catears::state::State;
+///
+/// let notes = [262.0, 330.0, 392.0, 523.0, 659.0].map(|frequency| Note::new(frequency, 100));
+/// let tune = ChiptuneSequence::from_notes(&notes).with_loop();
+/// let mut json = [0u8; 4096];
+/// let len = serde_json_core::to_slice(&tune, &mut json).unwrap();
+/// let text = core::str::from_utf8(&json[..len]).unwrap();
+/// assert_eq!(text.matches("frequency").count(), 5);
+/// let (parsed, _) = serde_json_core::from_str::<ChiptuneSequence>(text).unwrap();
+/// assert_eq!(parsed, tune);
+///
+/// // The rest of the notes are rests, and documents that spell out all 64 notes still parse.
+/// assert_eq!(parsed.notes[5..], [Note::rest(0); 59]);
+/// let mut padded = tune;
+/// padded.length = 64;
+/// let mut long_json = vec![0u8; 32_768];
+/// let len = serde_json_core::to_slice(&padded, &mut long_json).unwrap();
+/// let text = core::str::from_utf8(&long_json[..len]).unwrap().replace(r#""length":64"#, r#""length":5"#);
+/// assert_eq!(serde_json_core::from_str::<ChiptuneSequence>(&text).unwrap().0, tune);
+///
+/// // More than 64 notes, or a length past the notes given, is an error rather than a panic.
+/// let note = r#"{"frequency":440.0,"duration_ms":100,"volume":null}"#;
+/// let long = format!(r#"{{"notes":[{}],"default_volume":128,"looping":false}}"#, vec![note; 65].join(","));
+/// assert!(serde_json_core::from_str::<ChiptuneSequence>(&long).is_err());
+/// let short = format!(r#"{{"notes":[{note}],"length":2,"default_volume":128,"looping":false}}"#);
+/// assert!(serde_json_core::from_str::<ChiptuneSequence>(&short).is_err());
+/// let (parsed, _) = serde_json_core::from_str::<ChiptuneSequence>(&short.replace(r#""length":2,"#, "")).unwrap();
+/// assert_eq!(parsed.length, 1);
+///
+/// // A whole state with a chiptune in it fits easily in the buffer the remote state is read into.
+/// let mut state = State::default();
+/// state.speakers.left = Mode::Chiptune(tune);
+/// let mut json = [0u8; 8192];
+/// let len = serde_json_core::to_slice(&state, &mut json).unwrap();
+/// assert!(len < 4096, "{len} bytes");
+/// let (parsed, _) = serde_json_core::from_slice::<State>(&json[..len]).unwrap();
+/// assert_eq!(parsed, state);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "CompactSequence", into = "CompactSequence")]
+pub struct ChiptuneSequence {
+    /// Array of notes in the sequence.
+    pub notes: [Note; 64],
+    /// Number of valid notes in the sequence (0-64).
+    pub length: u8,
+    /// Default volume for notes that don't specify their own volume.
+    pub default_volume: u8,
+    /// Whether to loop the sequence after completion.
+    pub looping: bool,
+    /// Tempo in beats per minute the note durations were written at, if they were written as lengths.
+    #[serde(default)]
+    pub bpm: Option<u16>,
+    /// Index of the first note of the second voice, if there is one. The notes before it are the first voice, and the
+    /// rest up to `length` the second.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub second_voice: Option<u8>,
+}
+
+impl ChiptuneSequence {
+    /// Creates a new empty chiptune sequence.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            notes: [Note::rest(0); 64],
+            length: 0,
+            default_volume: 128,
+            looping: false,
+            bpm: None,
+            second_voice: None,
+        }
+    }
+
+    /// Creates a new chiptune sequence from a slice of notes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the slice contains more than 64 notes.
+    #[must_use]
+    pub fn from_notes(notes: &[Note]) -> Self {
+        assert!(
+            notes.len() <= 64,
+            "ChiptuneSequence can hold at most 64 notes"
+        );
+        let mut sequence = Self::new();
+        for (i, note) in notes.iter().enumerate() {
+            sequence.notes[i] = *note;
+        }
+        sequence.length = u8::try_from(notes.len()).expect("notes.len() should be <= 64");
+        sequence
+    }
+
+    /// Creates a new chiptune sequence of two voices that play at the same time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the voices contain more than 64 notes between them.
+    #[must_use]
+    pub fn from_voices(first: &[Note], second: &[Note]) -> Self {
+        assert!(
+            first.len() + second.len() <= 64,
+            "ChiptuneSequence can hold at most 64 notes"
+        );
+        let mut sequence = Self::new();
+        for (i, note) in first.iter().chain(second).enumerate() {
+            sequence.notes[i] = *note;
+        }
+        sequence.length =
+            u8::try_from(first.len() + second.len()).expect("notes.len() should be <= 64");
+        sequence.second_voice =
+            Some(u8::try_from(first.len()).expect("first.len() should be <= 64"));
+        sequence
+    }
+
+    /// Returns the notes of the first and second voices. The second is empty if the sequence has a single voice.
+    #[must_use]
+    pub fn voices(&self) -> [&[Note]; 2] {
+        let length = usize::from(self.length).min(self.notes.len());
+        let split = self
+            .second_voice
+            .map_or(length, |split| usize::from(split).min(length));
+        [&self.notes[..split], &self.notes[split..length]]
+    }
+
+    /// Creates a new chiptune sequence at `bpm` from a score of pitches in Hz (0.0 for a rest) and their lengths.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the score contains more than 64 notes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use catears::audio::{ChiptuneSequence, NoteLength};
+    ///
+    /// let tune = ChiptuneSequence::from_score(
+    ///     120,
+    ///     &[(440.0, NoteLength::QUARTER), (0.0, NoteLength::EIGHTH), (660.0, NoteLength::HALF.dotted())],
+    /// );
+    /// assert_eq!(tune.bpm, Some(120));
+    /// let notes = &tune.notes[..usize::from(tune.length)];
+    /// assert_eq!(notes.iter().map(|note| note.duration_ms).collect::<Vec<_>>(), [500, 250, 1500]);
+    /// assert_eq!(notes[1].frequency, 0.0);
+    ///
+    /// // Changing the tempo rescales every note.
+    /// let faster = tune.with_bpm(150);
+    /// let notes = &faster.notes[..usize::from(faster.length)];
+    /// assert_eq!(notes.iter().map(|note| note.duration_ms).collect::<Vec<_>>(), [400, 200, 1200]);
+    /// ```
+    #[must_use]
+    pub fn from_score(bpm: u16, score: &[(f32, NoteLength)]) -> Self {
+        assert!(
+            score.len() <= 64,
+            "ChiptuneSequence can hold at most 64 notes"
+        );
+        let mut sequence = Self::new();
+        for (note, &(frequency, length)) in sequence.notes.iter_mut().zip(score) {
+            *note = Note::new(frequency, length.duration_ms(bpm));
+        }
+        sequence.length = u8::try_from(score.len()).expect("score.len() should be <= 64");
+        sequence.bpm = Some(bpm);
+        sequence
+    }
+
+    /// Sets the tempo of the sequence. If it already had one, every note is stretched or squeezed to the new tempo.
+    ///
+    /// A sequence written in milliseconds has no tempo to start from, so its notes stay as they are.
+    #[must_use]
+    pub fn with_bpm(mut self, bpm: u16) -> Self {
+        if let Some(old) = self.bpm.filter(|&old| old > 0 && bpm > 0) {
+            for note in &mut self.notes {
+                let duration_ms = u32::from(note.duration_ms) * u32::from(old) / u32::from(bpm);
+                note.duration_ms = u16::try_from(duration_ms).unwrap_or(u16::MAX);
+            }
+        }
+        self.bpm = Some(bpm);
+        self
+    }
+
+    /// Sets the default volume for the sequence.
+    #[must_use]
+    pub const fn with_volume(mut self, volume: u8) -> Self {
+        self.default_volume = volume;
+        self
+    }
+
+    /// Enables looping for the sequence.
+    #[must_use]
+    pub const fn with_loop(mut self) -> Self {
+        self.looping = true;
+        self
+    }
+}
+
+/// The notes of a chiptune up to its length, which is all that is serialized of them.
+#[derive(Debug, Clone, Copy)]
+struct Notes {
+    notes: [Note; 64],
+    length: usize,
+}
+
+impl Serialize for Notes {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(&self.notes[..self.length])
+    }
+}
+
+impl<'de> Deserialize<'de> for Notes {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        /// Reads up to 64 notes, padding the rest with rests.
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = Notes;
+
+            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                formatter.write_str("at most 64 notes")
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<Notes, A::Error> {
+                let mut notes = Notes {
+                    notes: ChiptuneSequence::new().notes,
+                    length: 0,
+                };
+                while let Some(note) = seq.next_element()? {
+                    let Some(slot) = notes.notes.get_mut(notes.length) else {
+                        return Err(serde::de::Error::invalid_length(notes.length + 1, &self));
+                    };
+                    *slot = note;
+                    notes.length += 1;
+                }
+                Ok(notes)
+            }
+        }
+
+        deserializer.deserialize_seq(Visitor)
+    }
+}
+
+/// A chiptune as it is serialized, see [`ChiptuneSequence`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct CompactSequence {
+    notes: Notes,
+    #[serde(default)]
+    length: Option<u8>,
+    default_volume: u8,
+    looping: bool,
+    #[serde(default)]
+    bpm: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    second_voice: Option<u8>,
+}
+
+impl From<ChiptuneSequence> for CompactSequence {
+    fn from(sequence: ChiptuneSequence) -> Self {
+        let length = usize::from(sequence.length).min(sequence.notes.len());
+        Self {
+            notes: Notes {
+                notes: sequence.notes,
+                length,
+            },
+            length: Some(sequence.length),
+            default_volume: sequence.default_volume,
+            looping: sequence.looping,
+            bpm: sequence.bpm,
+            second_voice: sequence.second_voice,
+        }
+    }
+}
+
+impl TryFrom<CompactSequence> for ChiptuneSequence {
+    type Error = &'static str;
+
+    fn try_from(compact: CompactSequence) -> Result<Self, Self::Error> {
+        // Fewer than 64 notes, so the count fits.
+        #[allow(clippy::cast_possible_truncation)]
+        let given = compact.notes.length as u8;
+        let length = compact.length.unwrap_or(given);
+        if length > given {
+            return Err("chiptune length is more than the notes given");
+        }
+        Ok(Self {
+            notes: compact.notes.notes,
+            length,
+            default_volume: compact.default_volume,
+            looping: compact.looping,
+            bpm: compact.bpm,
+            second_voice: compact.second_voice,
+        })
+    }
+}
+
+impl Default for ChiptuneSequence {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Predefined chiptunes played one after another, see [`Mode::Playlist`].
+///
+/// A chiptune holds at most 64 notes, and making room for more would grow every state, so longer melodies are split
+/// into predefined chiptunes that a playlist chains together by name.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::chiptunes::Preset;
+/// use catears::audio::{Mode, Playlist};
+///
+/// let playlist = Playlist::from_presets(&[Preset::Startup, Preset::Happy]).with_loop();
+/// assert_eq!(playlist.tunes(), [Preset::Startup, Preset::Happy]);
+///
+/// let mut json = [0u8; 256];
+/// let len = serde_json_core::to_slice(&Mode::Playlist(playlist), &mut json).unwrap();
+/// let (parsed, _) = serde_json_core::from_slice::<Mode>(&json[..len]).unwrap();
+/// assert_eq!(parsed, Mode::Playlist(playlist));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Playlist {
+    /// Chiptunes to play in order.
+    pub tunes: [chiptunes::Preset; 8],
+    /// Number of chiptunes in the playlist (0-8).
+    pub length: u8,
+    /// Whether to start over from the first chiptune after the last.
+    pub looping: bool,
+}
+
+impl Playlist {
+    /// Creates a new playlist of `presets`, played once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are more than 8 presets.
+    #[must_use]
+    pub fn from_presets(presets: &[chiptunes::Preset]) -> Self {
+        assert!(presets.len() <= 8, "Playlist can hold at most 8 chiptunes");
+        let mut tunes = [chiptunes::Preset::default(); 8];
+        tunes[..presets.len()].copy_from_slice(presets);
+        Self {
+            tunes,
+            length: u8::try_from(presets.len()).expect("presets.len() should be <= 8"),
+            looping: false,
+        }
+    }
+
+    /// Enables looping for the playlist.
+    #[must_use]
+    pub const fn with_loop(mut self) -> Self {
+        self.looping = true;
+        self
+    }
+
+    /// Returns the chiptunes in the playlist.
+    #[must_use]
+    pub fn tunes(&self) -> &[chiptunes::Preset] {
+        &self.tunes[..usize::from(self.length).min(self.tunes.len())]
+    }
+}
+
+/// Predefined chiptune melodies for common game events and UI feedback.
+pub mod chiptunes {
+    use core::cell::Cell;
+
+    use critical_section::Mutex;
+    use serde::{Deserialize, Serialize};
+
+    use super::{Arpeggio, ChiptuneSequence, Envelope, Note, Percussion, Vibrato, Waveform};
+
+    /// One of the predefined chiptunes, to pick it by name.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use catears::audio::chiptunes::{self, Preset};
+    ///
+    /// assert_eq!(Preset::from_name("powerup"), Some(Preset::PowerUp));
+    /// assert_eq!(Preset::from_name("kazoo"), None);
+    /// assert_eq!(Preset::PowerUp.sequence(), chiptunes::power_up());
+    /// assert!(Preset::ALL.iter().all(|&preset| Preset::from_name(preset.name()) == Some(preset)));
+    ///
+    /// // Every chiptune has something to play, and is over within a few seconds.
+    /// for preset in Preset::ALL {
+    ///     let sequence = preset.sequence();
+    ///     assert!(sequence.length >= 1, "{} is empty", preset.name());
+    ///     let longest = sequence.voices().map(|notes| notes.iter().map(|note| u32::from(note.duration_ms)).sum::<u32>());
+    ///     assert!(longest.into_iter().max().unwrap() <= 3000, "{} is too long", preset.name());
+    /// }
+    /// ```
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+    pub enum Preset {
+        /// See [`coin_collect`].
+        #[default]
+        Coin,
+        /// See [`power_up`].
+        PowerUp,
+        /// See [`level_complete`].
+        LevelComplete,
+        /// See [`game_over`].
+        GameOver,
+        /// See [`menu_select`].
+        MenuSelect,
+        /// See [`alert`].
+        Alert,
+        /// See [`happy`].
+        Happy,
+        /// See [`sad`].
+        Sad,
+        /// See [`startup`].
+        Startup,
+        /// See [`shutdown`].
+        Shutdown,
+        /// See [`drum_loop`].
+        DrumLoop,
+        /// See [`curious`].
+        Curious,
+        /// See [`disappointed`].
+        Disappointed,
+        /// See [`doorbell`].
+        Doorbell,
+        /// See [`purr`].
+        Purr,
+        /// See [`error`].
+        Error,
+    }
+
+    impl Preset {
+        /// Every predefined chiptune.
+        pub const ALL: [Self; 16] = [
+            Self::Coin,
+            Self::PowerUp,
+            Self::LevelComplete,
+            Self::GameOver,
+            Self::MenuSelect,
+            Self::Alert,
+            Self::Happy,
+            Self::Sad,
+            Self::Startup,
+            Self::Shutdown,
+            Self::DrumLoop,
+            Self::Curious,
+            Self::Disappointed,
+            Self::Doorbell,
+            Self::Purr,
+            Self::Error,
+        ];
+
+        /// Returns the name of the chiptune, all lowercase without spaces.
+        #[must_use]
+        pub const fn name(self) -> &'static str {
+            match self {
+                Self::Coin => "coin",
+                Self::PowerUp => "powerup",
+                Self::LevelComplete => "levelcomplete",
+                Self::GameOver => "gameover",
+                Self::MenuSelect => "menuselect",
+                Self::Alert => "alert",
+                Self::Happy => "happy",
+                Self::Sad => "sad",
+                Self::Startup => "startup",
+                Self::Shutdown => "shutdown",
+                Self::DrumLoop => "drumloop",
+                Self::Curious => "curious",
+                Self::Disappointed => "disappointed",
+                Self::Doorbell => "doorbell",
+                Self::Purr => "purr",
+                Self::Error => "error",
+            }
+        }
+
+        /// Returns the chiptune with `name`, as returned by [`Preset::name`], if there is one.
+        #[must_use]
+        pub fn from_name(name: &str) -> Option<Self> {
+            Self::ALL.into_iter().find(|preset| preset.name() == name)
+        }
+
+        /// Returns the notes of the chiptune.
+        #[must_use]
+        pub fn sequence(self) -> ChiptuneSequence {
+            match self {
+                Self::Coin => coin_collect(),
+                Self::PowerUp => power_up(),
+                Self::LevelComplete => level_complete(),
+                Self::GameOver => game_over(),
+                Self::MenuSelect => menu_select(),
+                Self::Alert => alert(),
+                Self::Happy => happy(),
+                Self::Sad => sad(),
+                Self::Startup => startup(),
+                Self::Shutdown => shutdown(),
+                Self::DrumLoop => drum_loop(),
+                Self::Curious => curious(),
+                Self::Disappointed => disappointed(),
+                Self::Doorbell => doorbell(),
+                Self::Purr => purr(),
+                Self::Error => error(),
+            }
+        }
+    }
+
+    /// Chiptune the firmware adds to the predefined ones, see [`register`].
+    #[derive(Debug, Clone, Copy)]
+    pub struct Custom {
+        /// Name to ask for the chiptune by, at most [`PresetName::LEN`] bytes long to fit in a state.
+        pub name: &'static str,
+        /// Returns the notes of the chiptune.
+        pub sequence: fn() -> ChiptuneSequence,
+    }
+
+    /// Chiptunes registered by the firmware.
+    static REGISTERED: Mutex<Cell<&'static [Custom]>> = Mutex::new(Cell::new(&[]));
+
+    /// Adds `chiptunes` to the ones that can be asked for by name, in place of any registered before.
+    ///
+    /// This is how a firmware adds melodies of its own without changing the predefined ones, by registering a table of
+    /// them once at startup. A registered chiptune plays wherever a name does, as a [`super::Mode::NamedChiptune`] or a
+    /// sound event, but not in a playlist, which only holds predefined chiptunes. A predefined chiptune wins over a
+    /// registered one of the same name.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use catears::audio::chiptunes::{self, Custom};
+    /// use catears::audio::{ChiptuneSequence, Note};
+    ///
+    /// fn meow() -> ChiptuneSequence {
+    ///     ChiptuneSequence::from_notes(&[Note::new(700.0, 80), Note::new(500.0, 200)])
+    /// }
+    ///
+    /// static CUSTOM: [Custom; 1] = [Custom { name: "meow", sequence: meow }];
+    /// chiptunes::register(&CUSTOM);
+    /// assert_eq!(chiptunes::by_name("Meow"), Some(meow()));
+    /// assert_eq!(chiptunes::by_name("coin"), Some(chiptunes::coin_collect()));
+    /// assert_eq!(chiptunes::by_name("kazoo"), None);
+    ///
+    /// // Registered chiptunes have something to play and fit in a state too.
+    /// for custom in chiptunes::registered() {
+    ///     assert!((custom.sequence)().length >= 1 && custom.name.len() <= chiptunes::PresetName::LEN);
+    /// }
+    /// ```
+    pub fn register(chiptunes: &'static [Custom]) {
+        critical_section::with(|cs| REGISTERED.borrow(cs).set(chiptunes));
+    }
+
+    /// Returns the chiptunes registered with [`register`].
+    #[must_use]
+    pub fn registered() -> &'static [Custom] {
+        critical_section::with(|cs| REGISTERED.borrow(cs).get())
+    }
+
+    /// Returns the chiptune called `name`, predefined or registered, regardless of case.
+    #[must_use]
+    pub fn by_name(name: &str) -> Option<ChiptuneSequence> {
+        if let Some(preset) = Preset::ALL
+            .into_iter()
+            .find(|preset| preset.name().eq_ignore_ascii_case(name))
+        {
+            return Some(preset.sequence());
+        }
+        registered()
+            .iter()
+            .find(|custom| custom.name.eq_ignore_ascii_case(name))
+            .map(|custom| (custom.sequence)())
+    }
+
+    /// Name of a predefined chiptune, see [`super::Mode::NamedChiptune`].
+    ///
+    /// The name is kept as it was given, up to [`PresetName::LEN`] bytes, even when it names no chiptune. A name the
+    /// firmware does not know, say one added to the remote state before the firmware caught up, then only silences the
+    /// speaker it is for, rather than failing the whole state. Names are matched regardless of case, so both `powerup`
+    /// and `PowerUp` work.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use catears::audio::chiptunes::{Preset, PresetName};
+    /// use catears::audio::Mode;
+    ///
+    /// let (mode, _) = serde_json_core::from_str::<Mode>(r#"{"NamedChiptune":"LevelComplete"}"#).unwrap();
+    /// let Mode::NamedChiptune(name) = mode else { panic!() };
+    /// assert_eq!(name.preset(), Some(Preset::LevelComplete));
+    ///
+    /// let (mode, _) = serde_json_core::from_str::<Mode>(r#"{"NamedChiptune":"kazoo"}"#).unwrap();
+    /// assert_eq!(mode, Mode::NamedChiptune(PresetName::new("kazoo")));
+    /// let Mode::NamedChiptune(name) = mode else { panic!() };
+    /// assert_eq!((name.as_str(), name.preset()), ("kazoo", None));
+    ///
+    /// let mut json = [0u8; 64];
+    /// let len = serde_json_core::to_slice(&Mode::NamedChiptune(Preset::Happy.into()), &mut json).unwrap();
+    /// assert_eq!(&json[..len], br#"{"NamedChiptune":"happy"}"#);
+    ///
+    /// // Longer names are cut short, and never name a chiptune.
+    /// assert_eq!(PresetName::new("a very long name indeed").as_str(), "a very long name");
+    /// ```
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct PresetName {
+        bytes: [u8; PresetName::LEN],
+        len: u8,
+    }
+
+    impl PresetName {
+        /// Longest name kept, in bytes.
+        pub const LEN: usize = 16;
+
+        /// Creates a name, cut short at [`PresetName::LEN`] bytes.
+        #[must_use]
+        pub fn new(name: &str) -> Self {
+            let mut len = name.len().min(Self::LEN);
+            while !name.is_char_boundary(len) {
+                len -= 1;
+            }
+            let mut bytes = [0; Self::LEN];
+            bytes[..len].copy_from_slice(&name.as_bytes()[..len]);
+            Self {
+                bytes,
+                len: u8::try_from(len).unwrap_or_default(),
+            }
+        }
+
+        /// Returns the name as it was given.
+        #[must_use]
+        pub fn as_str(&self) -> &str {
+            core::str::from_utf8(&self.bytes[..usize::from(self.len)]).unwrap_or_default()
+        }
+
+        /// Returns the predefined chiptune with this name, if there is one.
+        #[must_use]
+        pub fn preset(&self) -> Option<Preset> {
+            Preset::ALL
+                .into_iter()
+                .find(|preset| preset.name().eq_ignore_ascii_case(self.as_str()))
+        }
+
+        /// Returns the notes of the chiptune with this name, predefined or registered, if there is one, see
+        /// [`by_name`].
+        #[must_use]
+        pub fn sequence(&self) -> Option<ChiptuneSequence> {
+            by_name(self.as_str())
+        }
+    }
+
+    impl From<Preset> for PresetName {
+        fn from(preset: Preset) -> Self {
+            Self::new(preset.name())
+        }
+    }
+
+    impl Serialize for PresetName {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(self.as_str())
+        }
+    }
+
+    impl<'de> Deserialize<'de> for PresetName {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            /// Takes any string as a name.
+            struct Visitor;
+
+            impl serde::de::Visitor<'_> for Visitor {
+                type Value = PresetName;
+
+                fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                    formatter.write_str("the name of a chiptune")
+                }
+
+                fn visit_str<E: serde::de::Error>(self, name: &str) -> Result<PresetName, E> {
+                    Ok(PresetName::new(name))
+                }
+            }
+
+            deserializer.deserialize_str(Visitor)
+        }
+    }
+
+    /// Returns a note on the 25% pulse wave that gives the game jingles their reedy lead.
+    const fn pulse(frequency: f32, duration_ms: u16) -> Note {
+        Note::new(frequency, duration_ms)
+            .with_waveform(Waveform::Square)
+            .with_duty(64)
+    }
+
+    /// Returns a note on the triangle wave that carries the bass lines under the melodies.
+    const fn bass(frequency: f32, duration_ms: u16) -> Note {
+        Note::new(frequency, duration_ms).with_waveform(Waveform::Triangle)
+    }
+
+    /// Classic Mario-style coin collection sound.
+    #[must_use]
+    pub fn coin_collect() -> ChiptuneSequence {
+        ChiptuneSequence::from_notes(&[
+            pulse(988.0, 100),  // B5
+            pulse(1319.0, 400), // E6
+        ])
+    }
+
+    /// Power-up acquisition jingle.
+    #[must_use]
+    pub fn power_up() -> ChiptuneSequence {
+        ChiptuneSequence::from_notes(&[
+            pulse(523.0, 100),                                         // C5
+            pulse(659.0, 100),                                         // E5
+            pulse(784.0, 100),                                         // G5
+            pulse(1047.0, 200).with_arpeggio(Arpeggio::chord([4, 7])), // C6 major
+        ])
+    }
+
+    /// Level completion fanfare.
+    #[must_use]
+    pub fn level_complete() -> ChiptuneSequence {
+        ChiptuneSequence::from_voices(
+            &[
+                pulse(523.0, 150),                                         // C5
+                pulse(659.0, 150),                                         // E5
+                pulse(784.0, 150),                                         // G5
+                pulse(1047.0, 150),                                        // C6
+                pulse(784.0, 150),                                         // G5
+                pulse(1047.0, 400).with_arpeggio(Arpeggio::chord([4, 7])), // C6 major
+            ],
+            &[
+                bass(131.0, 300), // C3
+                bass(165.0, 300), // E3
+                bass(196.0, 150), // G3
+                bass(131.0, 400), // C3
+            ],
+        )
+    }
+
+    /// Game over melody.
+    #[must_use]
+    pub fn game_over() -> ChiptuneSequence {
         ChiptuneSequence::from_notes(&[
             Note::new(523.0, 200), // C5
             Note::new(494.0, 200), // B4
@@ -337,13 +2004,20 @@ pub mod chiptunes {
     /// Happy/cheerful melody for positive events.
     #[must_use]
     pub fn happy() -> ChiptuneSequence {
-        ChiptuneSequence::from_notes(&[
-            Note::new(523.0, 150),  // C5
-            Note::new(659.0, 150),  // E5
-            Note::new(784.0, 150),  // G5
-            Note::new(659.0, 150),  // E5
-            Note::new(1047.0, 300), // C6
-        ])
+        ChiptuneSequence::from_voices(
+            &[
+                Note::new(523.0, 150),  // C5
+                Note::new(659.0, 150),  // E5
+                Note::new(784.0, 150),  // G5
+                Note::new(659.0, 150),  // E5
+                Note::new(1047.0, 300), // C6
+            ],
+            &[
+                bass(131.0, 300), // C3
+                bass(196.0, 300), // G3
+                bass(131.0, 300), // C3
+            ],
+        )
     }
 
     /// Sad/minor key melody for negative events.
@@ -380,18 +2054,294 @@ pub mod chiptunes {
             Note::new(262.0, 200), // C4
         ])
     }
+
+    /// One bar of a rock beat at 120 beats per minute, looping: kicks and snares with a hi-hat on every eighth note.
+    #[must_use]
+    pub fn drum_loop() -> ChiptuneSequence {
+        use Percussion::{Hat, Kick, Snare};
+
+        ChiptuneSequence::from_notes(&[
+            Note::percussion(Kick, 250),
+            Note::percussion(Hat, 250),
+            Note::percussion(Snare, 250),
+            Note::percussion(Hat, 250),
+            Note::percussion(Kick, 250),
+            Note::percussion(Kick, 250),
+            Note::percussion(Snare, 250),
+            Note::percussion(Hat, 250),
+        ])
+        .with_bpm(120)
+        .with_loop()
+    }
+
+    /// Rising trill, for when something catches the ears' attention.
+    #[must_use]
+    pub fn curious() -> ChiptuneSequence {
+        ChiptuneSequence::from_notes(&[
+            pulse(784.0, 60),                                       // G5
+            pulse(880.0, 60),                                       // A5
+            pulse(784.0, 60),                                       // G5
+            pulse(880.0, 60),                                       // A5
+            pulse(988.0, 60),                                       // B5
+            pulse(1047.0, 60),                                      // C6
+            pulse(1175.0, 250).with_vibrato(Vibrato::new(30, 6.0)), // D6
+        ])
+    }
+
+    /// Slide down to a low, wobbling note, like a deflated "aww".
+    #[must_use]
+    pub fn disappointed() -> ChiptuneSequence {
+        ChiptuneSequence::from_notes(&[
+            bass(494.0, 80),                                      // B4
+            bass(466.0, 80),                                      // Bb4
+            bass(440.0, 80),                                      // A4
+            bass(415.0, 80),                                      // Ab4
+            bass(392.0, 600).with_vibrato(Vibrato::new(40, 4.0)), // G4
+        ])
+    }
+
+    /// Two-tone "ding-dong", each tone ringing out like a bell.
+    #[must_use]
+    pub fn doorbell() -> ChiptuneSequence {
+        let ring = Envelope::new(2, 600, 40, 100);
+        ChiptuneSequence::from_notes(&[
+            Note::new(659.0, 500).with_envelope(ring), // E5
+            Note::new(523.0, 900).with_envelope(ring), // C5
+        ])
+    }
+
+    /// Short purr, rumbling in and out twice.
+    #[must_use]
+    pub fn purr() -> ChiptuneSequence {
+        let breath = Envelope::new(250, 0, u8::MAX, 250);
+        let rumble = |frequency, duration_ms| {
+            Note::new(frequency, duration_ms)
+                .with_waveform(Waveform::Sawtooth)
+                .with_vibrato(Vibrato::new(50, 25.0))
+                .with_envelope(breath)
+        };
+        ChiptuneSequence::from_notes(&[
+            rumble(90.0, 700),
+            Note::rest(100),
+            rumble(80.0, 800),
+            Note::rest(100),
+            rumble(90.0, 700),
+        ])
+    }
+
+    /// Low buzz twice over, for something that went wrong.
+    #[must_use]
+    pub fn error() -> ChiptuneSequence {
+        let buzz = |frequency, duration_ms| {
+            Note::new(frequency, duration_ms).with_waveform(Waveform::Square)
+        };
+        ChiptuneSequence::from_notes(&[buzz(150.0, 150), Note::rest(60), buzz(110.0, 350)])
+    }
+}
+
+/// Single-cycle wavetables for [`Waveform::Table`], for timbres richer than the basic waveforms.
+///
+/// Each table holds one cycle of a wave in [`LEN`] samples, starting at zero and rising like the basic waveforms. A
+/// note on one steps through it with the same phase as any other note, and reads between two entries by linear
+/// interpolation, which costs about as much as a sine.
+///
+/// The predefined tables come first, from [`SINE`] at index 0, followed by any the firmware adds with [`register`].
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::wavetables::{self, LEN};
+/// use catears::audio::synth::oscillator;
+/// use catears::audio::Waveform;
+///
+/// // Table 0 is a sine, close enough to the real thing to sound the same.
+/// let step = 440.0 / 44_100.0;
+/// for i in 0..1000 {
+///     let phase = i as f32 / 1000.0;
+///     let sine = oscillator(Waveform::Sine, phase, step, 128);
+///     assert!((oscillator(Waveform::Table(0), phase, step, 128) - sine).abs() < 0.002);
+/// }
+///
+/// // Every table starts at zero, rises, and stays in range.
+/// for index in 0..wavetables::BUILT_IN.len() as u8 {
+///     let table = wavetables::get(index).unwrap();
+///     assert_eq!(table.samples[0], 0);
+///     assert!(table.samples[1] > 0);
+///     assert!((0..LEN * 10).all(|i| table.sample(i as f32 / (LEN * 10) as f32).abs() <= 1.0));
+/// }
+///
+/// // A table that does not exist plays as a sine rather than failing.
+/// assert_eq!(wavetables::get(200), None);
+/// assert_eq!(oscillator(Waveform::Table(200), 0.3, step, 128), oscillator(Waveform::Sine, 0.3, step, 128));
+/// assert_eq!(Waveform::Table(200).name(), "table");
+/// assert_eq!(Waveform::Table(1).name(), "organ");
+/// ```
+pub mod wavetables {
+    use core::cell::Cell;
+
+    use critical_section::Mutex;
+
+    /// Number of samples in one cycle of a wavetable.
+    pub const LEN: usize = 64;
+
+    /// One cycle of a wave, see [`super::Waveform::Table`].
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Wavetable {
+        /// Name to ask for the table by.
+        pub name: &'static str,
+        /// One cycle of the wave, starting at zero, with full scale at `i16::MAX`.
+        pub samples: [i16; LEN],
+    }
+
+    impl Wavetable {
+        /// Returns the value (-1.0 to 1.0) of the wave at `phase` through its cycle (0.0 to 1.0), interpolating linearly
+        /// between the two nearest samples.
+        #[must_use]
+        #[allow(
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss,
+            clippy::cast_precision_loss,
+            reason = "the position is floored and wrapped into the table before it becomes an index"
+        )]
+        pub fn sample(&self, phase: f32) -> f32 {
+            let position = (phase - libm::floorf(phase)) * LEN as f32;
+            let index = (position as usize).min(LEN - 1);
+            let fraction = position - index as f32;
+            let from = f32::from(self.samples[index]);
+            let to = f32::from(self.samples[(index + 1) % LEN]);
+            (from + (to - from) * fraction) / f32::from(i16::MAX)
+        }
+    }
+
+    /// A pure sine, the same as [`super::Waveform::Sine`].
+    pub const SINE: Wavetable = Wavetable {
+        name: "sine",
+        samples: SINE_SAMPLES,
+    };
+
+    /// Drawbar organ, the fundamental with the next three harmonics pulled out.
+    pub const ORGAN: Wavetable = Wavetable {
+        name: "organ",
+        samples: ORGAN_SAMPLES,
+    };
+
+    /// The first three odd harmonics of a square wave, hollow like a square but without its buzz.
+    pub const SOFT_SQUARE: Wavetable = Wavetable {
+        name: "softsquare",
+        samples: SOFT_SQUARE_SAMPLES,
+    };
+
+    /// Harmonics bunched around the third, for a nasal, vowel-like sound.
+    pub const VOCAL: Wavetable = Wavetable {
+        name: "vocal",
+        samples: VOCAL_SAMPLES,
+    };
+
+    /// The predefined wavetables, by index.
+    pub const BUILT_IN: [Wavetable; 4] = [SINE, ORGAN, SOFT_SQUARE, VOCAL];
+
+    const SINE_SAMPLES: [i16; LEN] = [
+        0, 3212, 6393, 9512, 12539, 15446, 18204, 20787, 23170, 25329, 27245, 28898, 30273, 31356,
+        32137, 32609, 32767, 32609, 32137, 31356, 30273, 28898, 27245, 25329, 23170, 20787, 18204,
+        15446, 12539, 9512, 6393, 3212, 0, -3212, -6393, -9512, -12539, -15446, -18204, -20787,
+        -23170, -25329, -27245, -28898, -30273, -31356, -32137, -32609, -32767, -32609, -32137,
+        -31356, -30273, -28898, -27245, -25329, -23170, -20787, -18204, -15446, -12539, -9512,
+        -6393, -3212,
+    ];
+
+    const ORGAN_SAMPLES: [i16; LEN] = [
+        0, 8488, 16357, 23059, 28167, 31425, 32767, 32315, 30355, 27297, 23616, 19794, 16262,
+        13354, 11274, 10087, 9725, 10014, 10707, 11532, 12234, 12606, 12523, 11942, 10905, 9517,
+        7921, 6268, 4689, 3268, 2038, 971, 0, -971, -2038, -3268, -4689, -6268, -7921, -9517,
+        -10905, -11942, -12523, -12606, -12234, -11532, -10707, -10014, -9725, -10087, -11274,
+        -13354, -16262, -19794, -23616, -27297, -30355, -32315, -32767, -31425, -28167, -23059,
+        -16357, -8488,
+    ];
+
+    const SOFT_SQUARE_SAMPLES: [i16; LEN] = [
+        0, 10184, 19258, 26319, 30844, 32767, 32468, 30664, 28235, 26029, 24675, 24475, 25360,
+        26956, 28706, 30039, 30535, 30039, 28706, 26956, 25360, 24475, 24675, 26029, 28235, 30664,
+        32468, 32767, 30844, 26319, 19258, 10184, 0, -10184, -19258, -26319, -30844, -32767,
+        -32468, -30664, -28235, -26029, -24675, -24475, -25360, -26956, -28706, -30039, -30535,
+        -30039, -28706, -26956, -25360, -24475, -24675, -26029, -28235, -30664, -32468, -32767,
+        -30844, -26319, -19258, -10184,
+    ];
+
+    const VOCAL_SAMPLES: [i16; LEN] = [
+        0, 12531, 23034, 29996, 32767, 31622, 27545, 21840, 15722, 10042, 5217, 1336, -1628, -3654,
+        -4618, -4375, -2917, -500, 2348, 4960, 6770, 7536, 7445, 7041, 6970, 7674, 9145, 10869,
+        11992, 11657, 9370, 5242, 0, -5242, -9370, -11657, -11992, -10869, -9145, -7674, -6970,
+        -7041, -7445, -7536, -6770, -4960, -2348, 500, 2917, 4375, 4618, 3654, 1628, -1336, -5217,
+        -10042, -15722, -21840, -27545, -31622, -32767, -29996, -23034, -12531,
+    ];
+
+    /// Wavetables registered by the firmware.
+    static REGISTERED: Mutex<Cell<&'static [Wavetable]>> = Mutex::new(Cell::new(&[]));
+
+    /// Adds `wavetables` after the predefined ones, in place of any registered before.
+    ///
+    /// This is how a firmware adds timbres of its own, by registering a table of them once at startup, the same way it
+    /// adds chiptunes. The first one registered gets the index right after the last predefined one.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use catears::audio::wavetables::{self, Wavetable, LEN, SINE};
+    /// use catears::audio::Waveform;
+    ///
+    /// static CUSTOM: [Wavetable; 1] = [Wavetable { name: "flat", samples: [0; LEN] }];
+    /// wavetables::register(&CUSTOM);
+    /// let index = wavetables::BUILT_IN.len() as u8;
+    /// assert_eq!(wavetables::get(index), Some(&CUSTOM[0]));
+    /// assert_eq!(wavetables::by_name("Flat"), Some(Waveform::Table(index)));
+    /// assert_eq!(wavetables::by_name("sine"), Some(Waveform::Table(0)));
+    /// assert_eq!(wavetables::get(0), Some(&SINE));
+    /// assert_eq!(wavetables::by_name("kazoo"), None);
+    /// ```
+    pub fn register(wavetables: &'static [Wavetable]) {
+        critical_section::with(|cs| REGISTERED.borrow(cs).set(wavetables));
+    }
+
+    /// Returns the wavetables registered with [`register`].
+    #[must_use]
+    pub fn registered() -> &'static [Wavetable] {
+        critical_section::with(|cs| REGISTERED.borrow(cs).get())
+    }
+
+    /// Returns the wavetable at `index`, predefined or registered, or `None` if there is none.
+    #[must_use]
+    pub fn get(index: u8) -> Option<&'static Wavetable> {
+        let index = usize::from(index);
+        BUILT_IN
+            .get(index)
+            .or_else(|| registered().get(index - BUILT_IN.len()))
+    }
+
+    /// Returns the waveform of the wavetable called `name`, predefined or registered, regardless of case.
+    #[must_use]
+    pub fn by_name(name: &str) -> Option<super::Waveform> {
+        BUILT_IN
+            .iter()
+            .chain(registered())
+            .position(|table| table.name.eq_ignore_ascii_case(name))
+            .and_then(|index| u8::try_from(index).ok())
+            .map(super::Waveform::Table)
+    }
 }
 
 /// Predefined audio clips embedded in the binary.
 ///
-/// These audio clips are included at compile time using `include_bytes!` macro.For embedded systems, we use raw PCM format (uncompressed) for simplicity.
-/// Convert audio files to raw PCM using tools like ffmpeg:
-/// `ffmpeg -i input.mp3 -f u8 -ar 8000 -ac 1 output.raw`
+/// These audio clips are included at compile time using `include_bytes!` macro. For embedded systems, we use PCM format
+/// (uncompressed) for simplicity, preferably in a WAV file that [`Clip::from_wav`] reads the format from. Convert audio
+/// files to it using tools like ffmpeg: `ffmpeg -i input.mp3 -acodec pcm_u8 -ar 8000 -ac 1 output.wav`
 pub mod clips {
     use super::Clip;
 
-    // Example of how to embed audio files:
-    // const MEOW_DATA: &[u8] = include_bytes!("../assets/meow.raw");
+    // Example of how to embed audio files, checked when the firmware is built:
+    // const MEOW: Clip = match Clip::from_wav(include_bytes!("../assets/meow.wav")) {
+    //     Ok(clip) => clip,
+    //     Err(_) => panic!("meow.wav is not a WAV file the speakers can play"),
+    // };
     // const PURR_DATA: &[u8] = include_bytes!("../assets/purr.raw");
 
     // Example audio clip functions:
@@ -399,7 +2349,7 @@ pub mod clips {
     /// Cat meow sound effect.
     #[must_use]
     pub fn meow() -> Clip {
-        Clip::mono_8bit(MEOW_DATA, 8000)
+        MEOW
     }
 
     /// Cat purr sound effect (looped).
@@ -0,0 +1,104 @@
+//! Beats of the chiptunes the speakers play, for lights that keep time with them.
+//!
+//! Each speaker task publishes into a [`Clock`] of its own whenever a note starts in the first voice of the chiptune
+//! it plays, and clears it when the chiptune stops. The LED task reads the clock of the matching side each frame, so
+//! neither task waits on the other, and a light mode that follows the beat falls back to a steady pace of its own when
+//! there is nothing to follow.
+//!
+//! Notes are published as they are rendered, which is about one audio buffer before they are heard, so the lights run
+//! that far ahead of the speakers at most.
+
+use core::sync::atomic::{AtomicBool, AtomicU16, AtomicU32, Ordering};
+
+/// The latest beat of a chiptune, as seen at some moment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Beat {
+    /// Number of notes that have started so far, counting on through loops.
+    pub count: u32,
+    /// Time in milliseconds since the latest note started.
+    pub since_ms: u32,
+    /// Tempo of the chiptune in beats per minute, if it was written with one.
+    pub bpm: Option<u16>,
+}
+
+impl Beat {
+    /// Returns the length of a beat in milliseconds at the chiptune's tempo, if it has one.
+    #[must_use]
+    pub fn period_ms(&self) -> Option<u32> {
+        self.bpm
+            .filter(|&bpm| bpm > 0)
+            .map(|bpm| 60_000 / u32::from(bpm))
+    }
+}
+
+/// Lock-free cell holding the latest [`Beat`] of a speaker.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::beat::{Beat, Clock};
+///
+/// let clock = Clock::new();
+/// assert_eq!(clock.get(1000), None);
+///
+/// clock.publish(1, 1000, Some(120));
+/// let beat = clock.get(1100).unwrap();
+/// assert_eq!(beat, Beat { count: 1, since_ms: 100, bpm: Some(120) });
+/// assert_eq!(beat.period_ms(), Some(500));
+///
+/// clock.stop();
+/// assert_eq!(clock.get(1200), None);
+/// ```
+pub struct Clock {
+    playing: AtomicBool,
+    count: AtomicU32,
+    at_ms: AtomicU32,
+    /// Tempo in beats per minute, zero without one.
+    bpm: AtomicU16,
+}
+
+impl Clock {
+    /// Creates a new clock with nothing playing.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            playing: AtomicBool::new(false),
+            count: AtomicU32::new(0),
+            at_ms: AtomicU32::new(0),
+            bpm: AtomicU16::new(0),
+        }
+    }
+
+    /// Publishes that note number `count` started at `at_ms`, on a clock in milliseconds that may wrap.
+    pub fn publish(&self, count: u32, at_ms: u32, bpm: Option<u16>) {
+        self.count.store(count, Ordering::Relaxed);
+        self.at_ms.store(at_ms, Ordering::Relaxed);
+        self.bpm.store(bpm.unwrap_or_default(), Ordering::Relaxed);
+        self.playing.store(true, Ordering::Relaxed);
+    }
+
+    /// Publishes that the chiptune stopped.
+    pub fn stop(&self) {
+        self.playing.store(false, Ordering::Relaxed);
+    }
+
+    /// Returns the latest beat as seen at `now_ms`, or `None` if no chiptune is playing.
+    #[must_use]
+    pub fn get(&self, now_ms: u32) -> Option<Beat> {
+        if !self.playing.load(Ordering::Relaxed) {
+            return None;
+        }
+        let bpm = self.bpm.load(Ordering::Relaxed);
+        Some(Beat {
+            count: self.count.load(Ordering::Relaxed),
+            since_ms: now_ms.wrapping_sub(self.at_ms.load(Ordering::Relaxed)),
+            bpm: (bpm > 0).then_some(bpm),
+        })
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
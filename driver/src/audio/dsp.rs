@@ -0,0 +1,270 @@
+//! Allocation-free signal processing for sound-reactive effects.
+//!
+//! The microphone task feeds raw samples into a [`LevelMeter`], which tracks a rolling RMS and peak envelope plus a
+//! crude low/high band split, and publishes the result into a shared [`AudioLevel`] that the light and servo tasks
+//! read. Everything is integer-only so it is cheap to run on every DMA buffer.
+
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+/// Shift of the one-pole low-pass filter used to split the bands.
+///
+/// The cutoff frequency is roughly `sample_rate / (2π · 2^shift)`, i.e. about 440 Hz at 44.1 kHz.
+const BAND_SPLIT_SHIFT: u32 = 4;
+
+/// Shift controlling how quickly the RMS envelope falls when the input gets quieter (larger is slower).
+const RELEASE_SHIFT: u32 = 3;
+
+/// Amount the peak envelope falls per processed buffer.
+const PEAK_DECAY: u8 = 4;
+
+/// Audio levels on a 0-255 scale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Levels {
+    /// Smoothed RMS level of the full signal.
+    pub rms: u8,
+    /// Decaying peak level of the full signal.
+    pub peak: u8,
+    /// Smoothed RMS level of the low band.
+    pub low: u8,
+    /// Smoothed RMS level of the high band.
+    pub high: u8,
+}
+
+impl Levels {
+    /// Returns the smoothed RMS level of the given band.
+    #[must_use]
+    pub const fn band(&self, band: Band) -> u8 {
+        match band {
+            Band::Full => self.rms,
+            Band::Low => self.low,
+            Band::High => self.high,
+        }
+    }
+}
+
+/// Frequency band a sound-reactive effect follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Band {
+    /// The whole signal.
+    #[default]
+    Full,
+    /// Bass, below roughly 440 Hz.
+    Low,
+    /// Everything above the low band.
+    High,
+}
+
+/// Lock-free cell holding the most recently measured [`Levels`].
+pub struct AudioLevel {
+    rms: AtomicU8,
+    peak: AtomicU8,
+    low: AtomicU8,
+    high: AtomicU8,
+}
+
+impl AudioLevel {
+    /// Creates a new cell reporting silence.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            rms: AtomicU8::new(0),
+            peak: AtomicU8::new(0),
+            low: AtomicU8::new(0),
+            high: AtomicU8::new(0),
+        }
+    }
+
+    /// Publishes new levels.
+    pub fn publish(&self, levels: Levels) {
+        self.rms.store(levels.rms, Ordering::Relaxed);
+        self.peak.store(levels.peak, Ordering::Relaxed);
+        self.low.store(levels.low, Ordering::Relaxed);
+        self.high.store(levels.high, Ordering::Relaxed);
+    }
+
+    /// Returns the most recently published levels.
+    #[must_use]
+    pub fn get(&self) -> Levels {
+        Levels {
+            rms: self.rms.load(Ordering::Relaxed),
+            peak: self.peak.load(Ordering::Relaxed),
+            low: self.low.load(Ordering::Relaxed),
+            high: self.high.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Default for AudioLevel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Rolling RMS/peak envelope follower with a two-band split.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::dsp::LevelMeter;
+///
+/// let mut meter = LevelMeter::new();
+///
+/// // Silence stays silent.
+/// assert_eq!(meter.process([0i16; 256]).rms, 0);
+///
+/// // A full-scale square wave drives the envelope towards the top of the scale.
+/// let loud: [i16; 256] = core::array::from_fn(|i| if i % 2 == 0 { i16::MAX } else { -i16::MAX });
+/// let mut levels = meter.process(loud);
+/// for _ in 0..8 {
+///     levels = meter.process(loud);
+/// }
+/// assert!(levels.rms > 240);
+/// assert_eq!(levels.peak, 255);
+/// // A square wave at the Nyquist frequency is all treble.
+/// assert!(levels.high > levels.low);
+///
+/// // Once the input stops, the envelope decays instead of dropping instantly.
+/// let quieter = meter.process([0i16; 256]);
+/// assert!(quieter.rms > 0 && quieter.rms < levels.rms);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LevelMeter {
+    /// State of the band-splitting low-pass filter, in sample units scaled by `2^BAND_SPLIT_SHIFT`.
+    lowpass: i32,
+    /// Smoothed RMS of the full, low, and high signals in sample units.
+    rms: u32,
+    low: u32,
+    high: u32,
+    /// Decaying peak level on the 0-255 scale.
+    peak: u8,
+}
+
+impl LevelMeter {
+    /// Creates a new meter reporting silence.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            lowpass: 0,
+            rms: 0,
+            low: 0,
+            high: 0,
+            peak: 0,
+        }
+    }
+
+    /// Processes a buffer of mono samples and returns the updated levels.
+    pub fn process(&mut self, samples: impl IntoIterator<Item = i16>) -> Levels {
+        let mut count = 0u64;
+        let mut sum = 0u64;
+        let mut low_sum = 0u64;
+        let mut high_sum = 0u64;
+        let mut peak = 0u32;
+
+        for sample in samples {
+            let x = i32::from(sample);
+            self.lowpass += x - (self.lowpass >> BAND_SPLIT_SHIFT);
+            let low = self.lowpass >> BAND_SPLIT_SHIFT;
+            let high = x - low;
+
+            count += 1;
+            sum += square(x);
+            low_sum += square(low);
+            high_sum += square(high);
+            peak = peak.max(x.unsigned_abs());
+        }
+
+        if count > 0 {
+            self.rms = follow(self.rms, mean_root(sum, count));
+            self.low = follow(self.low, mean_root(low_sum, count));
+            self.high = follow(self.high, mean_root(high_sum, count));
+            self.peak = self.peak.saturating_sub(PEAK_DECAY).max(to_level(peak));
+        }
+
+        self.levels()
+    }
+
+    /// Returns the current levels without processing any samples.
+    #[must_use]
+    pub fn levels(&self) -> Levels {
+        Levels {
+            rms: to_level(self.rms),
+            peak: self.peak,
+            low: to_level(self.low),
+            high: to_level(self.high),
+        }
+    }
+}
+
+/// Level trigger with hysteresis.
+///
+/// The trigger turns on once the level reaches `on` and only turns off again once the level drops below `off`, so a
+/// level hovering around a single threshold does not make the output flap.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::dsp::Trigger;
+///
+/// let mut trigger = Trigger::new();
+/// assert!(!trigger.update(150, 160, 100));
+/// assert!(trigger.update(170, 160, 100));
+/// // Dipping just under the on threshold keeps the trigger active.
+/// assert!(trigger.update(140, 160, 100));
+/// assert!(!trigger.update(90, 160, 100));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Trigger {
+    active: bool,
+}
+
+impl Trigger {
+    /// Creates a new inactive trigger.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { active: false }
+    }
+
+    /// Updates the trigger with a new level and returns whether it is active.
+    ///
+    /// If `off` is above `on`, it is treated as equal to `on`.
+    pub fn update(&mut self, level: u8, on: u8, off: u8) -> bool {
+        if level >= on {
+            self.active = true;
+        } else if level < off.min(on) {
+            self.active = false;
+        }
+        self.active
+    }
+
+    /// Returns whether the trigger is active.
+    #[must_use]
+    pub const fn is_active(&self) -> bool {
+        self.active
+    }
+}
+
+fn square(x: i32) -> u64 {
+    let x = u64::from(x.unsigned_abs());
+    x * x
+}
+
+fn mean_root(sum: u64, count: u64) -> u32 {
+    // The root of a mean of squared i16 values always fits in a u32.
+    u32::try_from((sum / count).isqrt()).unwrap_or(u32::MAX)
+}
+
+/// Moves an envelope towards `target`, jumping up immediately and falling back slowly.
+fn follow(current: u32, target: u32) -> u32 {
+    if target >= current {
+        target
+    } else {
+        current - ((current - target) >> RELEASE_SHIFT).max(1)
+    }
+}
+
+/// Maps a sample magnitude (0-32768) onto the 0-255 scale.
+fn to_level(magnitude: u32) -> u8 {
+    u8::try_from(magnitude >> 7).unwrap_or(u8::MAX)
+}
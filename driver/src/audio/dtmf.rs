@@ -0,0 +1,222 @@
+//! Dialing of DTMF tones, the beeps of a touch-tone phone.
+//!
+//! DTMF (dual-tone multi-frequency) signaling gives each key of a phone keypad a pair of sine tones played together,
+//! one from a row of low frequencies and one from a column of high ones. Besides the digits there are `*`, `#`, and the
+//! rarely seen `A` to `D` keys of the fourth column.
+//!
+//! A [`Dial`] is played as a chiptune of two voices, the low tones in the first and the high ones in the second, so the
+//! speaker task mixes the pair the same way it mixes any two voices.
+
+use serde::{Deserialize, Serialize};
+
+use super::{ChiptuneSequence, Note};
+
+/// Low frequencies in Hz of the four rows of the keypad, top to bottom.
+const ROWS: [f32; 4] = [697.0, 770.0, 852.0, 941.0];
+
+/// High frequencies in Hz of the four columns of the keypad, left to right.
+const COLUMNS: [f32; 4] = [1209.0, 1336.0, 1477.0, 1633.0];
+
+/// Keys of the keypad, row by row.
+const KEYPAD: [[char; 4]; 4] = [
+    ['1', '2', '3', 'A'],
+    ['4', '5', '6', 'B'],
+    ['7', '8', '9', 'C'],
+    ['*', '0', '#', 'D'],
+];
+
+/// Returns the low and high frequencies in Hz of the key `symbol`, or `None` if it is not a key of the keypad.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::dtmf::tones;
+///
+/// assert_eq!(tones('1'), Some((697.0, 1209.0)));
+/// assert_eq!(tones('0'), Some((941.0, 1336.0)));
+/// assert_eq!(tones('#'), Some((941.0, 1477.0)));
+/// assert_eq!(tones('d'), tones('D'));
+/// assert_eq!(tones('x'), None);
+/// ```
+#[must_use]
+pub fn tones(symbol: char) -> Option<(f32, f32)> {
+    let symbol = symbol.to_ascii_uppercase();
+    KEYPAD.iter().zip(ROWS).find_map(|(keys, low)| {
+        let column = keys.iter().position(|&key| key == symbol)?;
+        Some((low, COLUMNS[column]))
+    })
+}
+
+/// Keys to dial, kept as they were given.
+///
+/// Holds up to [`Digits::LEN`] bytes, so that a [`Dial`] fits in a state without allocating.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Digits {
+    bytes: [u8; Digits::LEN],
+    len: u8,
+}
+
+impl Digits {
+    /// Most keys dialed in one go, in bytes.
+    pub const LEN: usize = 16;
+
+    /// Creates the keys to dial, cut short at [`Digits::LEN`] bytes.
+    #[must_use]
+    pub fn new(digits: &str) -> Self {
+        let mut len = digits.len().min(Self::LEN);
+        while !digits.is_char_boundary(len) {
+            len -= 1;
+        }
+        let mut bytes = [0; Self::LEN];
+        bytes[..len].copy_from_slice(&digits.as_bytes()[..len]);
+        Self {
+            bytes,
+            len: u8::try_from(len).unwrap_or_default(),
+        }
+    }
+
+    /// Returns the keys as they were given.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.bytes[..usize::from(self.len)]).unwrap_or_default()
+    }
+
+    /// Returns the characters that are not keys of the keypad, which are skipped when dialing.
+    pub fn invalid(&self) -> impl Iterator<Item = char> + '_ {
+        self.as_str()
+            .chars()
+            .filter(|&symbol| tones(symbol).is_none())
+    }
+}
+
+impl Serialize for Digits {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Digits {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        /// Takes any string as keys, skipping what is not one when dialing.
+        struct Visitor;
+
+        impl serde::de::Visitor<'_> for Visitor {
+            type Value = Digits;
+
+            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                formatter.write_str("keys to dial")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, digits: &str) -> Result<Digits, E> {
+                Ok(Digits::new(digits))
+            }
+        }
+
+        deserializer.deserialize_str(Visitor)
+    }
+}
+
+/// Keys dialed as DTMF tones, see [`super::Mode::Dtmf`].
+///
+/// Each key sounds for `tone_ms` with `gap_ms` of silence before the next one. Characters that are not keys of the
+/// keypad are skipped.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::dtmf::Dial;
+/// use catears::audio::synth::{amplitude, frames_for, ChiptuneGenerator};
+/// use catears::audio::Mode;
+///
+/// let dial = Dial::new("5x5");
+/// assert_eq!(dial.digits.invalid().collect::<Vec<_>>(), ['x']);
+///
+/// // Each key is a pair of tones played at once, so there are two voices with a gap between the keys.
+/// let sequence = dial.sequence();
+/// let [low, high] = sequence.voices();
+/// assert_eq!(low.iter().map(|note| note.frequency).collect::<Vec<_>>(), [770.0, 0.0, 770.0]);
+/// assert_eq!(high.iter().map(|note| note.frequency).collect::<Vec<_>>(), [1336.0, 0.0, 1336.0]);
+/// assert!(!sequence.looping);
+///
+/// // Mixed together, the pair never overflows, and the gap is silent.
+/// let mut buffer = vec![0i16; 2 * frames_for(300)];
+/// assert_eq!(ChiptuneGenerator::new(&sequence, 255).fill(&mut buffer), frames_for(300));
+/// assert!(buffer.iter().all(|&sample| f32::from(sample).abs() <= amplitude(255, 255)));
+/// assert!(buffer[2 * frames_for(110)..2 * frames_for(190)].iter().all(|&sample| sample == 0));
+///
+/// // Timings are optional, and the keys come through as a plain string.
+/// let (mode, _) = serde_json_core::from_str::<Mode>(r#"{"Dtmf":{"digits":"555-0123"}}"#).unwrap();
+/// assert_eq!(mode, Mode::Dtmf(Dial::new("555-0123")));
+/// let mut json = [0u8; 128];
+/// let len = serde_json_core::to_slice(&Mode::Dtmf(Dial::new("*69").with_timing(80, 40)), &mut json).unwrap();
+/// assert_eq!(&json[..len], br#"{"Dtmf":{"digits":"*69","tone_ms":80,"gap_ms":40}}"#);
+///
+/// // Even the longest dial fits in a chiptune.
+/// assert_eq!(Dial::new("0123456789ABCD*#").sequence().length, 62);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Dial {
+    /// Keys to dial, in order.
+    pub digits: Digits,
+    /// Time in milliseconds each key sounds for.
+    #[serde(default = "default_tone_ms")]
+    pub tone_ms: u16,
+    /// Time in milliseconds of silence between keys.
+    #[serde(default = "default_gap_ms")]
+    pub gap_ms: u16,
+}
+
+impl Dial {
+    /// Default time each key sounds for, about how long a quick press of a key lasts.
+    pub const TONE_MS: u16 = 100;
+
+    /// Default time between keys.
+    pub const GAP_MS: u16 = 100;
+
+    /// Creates a dial of `digits` at the default timing.
+    #[must_use]
+    pub fn new(digits: &str) -> Self {
+        Self {
+            digits: Digits::new(digits),
+            tone_ms: Self::TONE_MS,
+            gap_ms: Self::GAP_MS,
+        }
+    }
+
+    /// Sets how long each key sounds for and how long the silence between keys lasts.
+    #[must_use]
+    pub const fn with_timing(mut self, tone_ms: u16, gap_ms: u16) -> Self {
+        self.tone_ms = tone_ms;
+        self.gap_ms = gap_ms;
+        self
+    }
+
+    /// Returns the chiptune that dials the keys once, the low tones in the first voice and the high ones in the second.
+    #[must_use]
+    pub fn sequence(&self) -> ChiptuneSequence {
+        let mut low = [Note::rest(0); 2 * Digits::LEN];
+        let mut high = low;
+        let mut length = 0;
+        for (row, column) in self.digits.as_str().chars().filter_map(tones) {
+            if length > 0 {
+                low[length] = Note::rest(self.gap_ms);
+                high[length] = Note::rest(self.gap_ms);
+                length += 1;
+            }
+            low[length] = Note::new(row, self.tone_ms);
+            high[length] = Note::new(column, self.tone_ms);
+            length += 1;
+        }
+        let mut sequence = ChiptuneSequence::from_voices(&low[..length], &high[..length]);
+        sequence.default_volume = u8::MAX;
+        sequence
+    }
+}
+
+const fn default_tone_ms() -> u16 {
+    Dial::TONE_MS
+}
+
+const fn default_gap_ms() -> u16 {
+    Dial::GAP_MS
+}
@@ -0,0 +1,202 @@
+//! Parsing of RTTTL ringtones into chiptunes.
+//!
+//! RTTTL (Ring Tone Text Transfer Language) is the plain-text format old phones shared their ringtones in, so there is a
+//! large supply of tunes written in it. A ringtone has three sections separated by colons: a name, the defaults, and the
+//! notes, such as `Beep:d=4,o=5,b=120:8c6,p,4.g#,16a.7`.
+//!
+//! The defaults set the duration (`d`) and octave (`o`) of notes that leave them out, and the tempo in beats per minute
+//! (`b`). Each note is an optional duration as a fraction of a whole note (1, 2, 4, 8, 16, or 32), a pitch from `a` to
+//! `g` (`h` is taken for `b`) or `p` for a rest, an optional sharp (`#`), an optional octave, and an optional dot that
+//! makes it half again as long. The dot is accepted both before and after the octave, since both are common.
+
+use super::{ChiptuneSequence, Note, NoteLength};
+
+/// Why a ringtone could not be parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Error {
+    /// The ringtone does not have a name, defaults, and notes separated by colons.
+    Sections,
+    /// A default is unknown or out of range.
+    Default,
+    /// The note at this index, counting from zero, is not a valid note.
+    Note(usize),
+    /// The ringtone has more notes than a chiptune can hold.
+    TooLong,
+}
+
+/// Default duration, octave, and tempo of a ringtone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Defaults {
+    duration: u8,
+    octave: u8,
+    bpm: u16,
+}
+
+impl Defaults {
+    /// Defaults that apply when the ringtone does not set them, as in the RTTTL specification.
+    const SPEC: Self = Self {
+        duration: 4,
+        octave: 6,
+        bpm: 63,
+    };
+
+    fn parse(section: &str) -> Result<Self, Error> {
+        let mut defaults = Self::SPEC;
+        for setting in section.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (key, value) = setting.split_once('=').ok_or(Error::Default)?;
+            let value = value.trim().parse::<u32>().map_err(|_| Error::Default)?;
+            match key.trim() {
+                "d" => {
+                    defaults.duration = u8::try_from(value)
+                        .ok()
+                        .filter(|&duration| is_duration(duration))
+                        .ok_or(Error::Default)?;
+                }
+                "o" => {
+                    defaults.octave = u8::try_from(value)
+                        .ok()
+                        .filter(|&octave| octave <= 8)
+                        .ok_or(Error::Default)?;
+                }
+                "b" => {
+                    defaults.bpm = u16::try_from(value)
+                        .ok()
+                        .filter(|bpm| (1..=900).contains(bpm))
+                        .ok_or(Error::Default)?;
+                }
+                _ => return Err(Error::Default),
+            }
+        }
+        Ok(defaults)
+    }
+}
+
+/// Returns whether `value` is a note duration RTTTL allows.
+const fn is_duration(value: u8) -> bool {
+    matches!(value, 1 | 2 | 4 | 8 | 16 | 32)
+}
+
+/// Parses a single note, or returns `None` if it is not one.
+fn parse_note(token: &str, defaults: Defaults) -> Option<Note> {
+    let bytes = token.as_bytes();
+    let mut i = 0;
+
+    let digits = bytes.iter().take_while(|b| b.is_ascii_digit()).count();
+    let duration = if digits == 0 {
+        defaults.duration
+    } else {
+        token[..digits]
+            .parse::<u8>()
+            .ok()
+            .filter(|&d| is_duration(d))?
+    };
+    i += digits;
+
+    let semitone: Option<u8> = match bytes.get(i)?.to_ascii_lowercase() {
+        b'c' => Some(0),
+        b'd' => Some(2),
+        b'e' => Some(4),
+        b'f' => Some(5),
+        b'g' => Some(7),
+        b'a' => Some(9),
+        b'b' | b'h' => Some(11),
+        b'p' => None,
+        _ => return None,
+    };
+    i += 1;
+
+    let sharp = bytes.get(i) == Some(&b'#');
+    if sharp {
+        i += 1;
+    }
+    let mut dotted = bytes.get(i) == Some(&b'.');
+    if dotted {
+        i += 1;
+    }
+    let octave = match bytes.get(i) {
+        Some(&digit @ b'0'..=b'8') => {
+            i += 1;
+            digit - b'0'
+        }
+        _ => defaults.octave,
+    };
+    if !dotted && bytes.get(i) == Some(&b'.') {
+        dotted = true;
+        i += 1;
+    }
+    if i != bytes.len() {
+        return None;
+    }
+
+    let length = NoteLength {
+        division: duration,
+        dotted,
+    };
+    let duration_ms = length.duration_ms(defaults.bpm);
+    Some(match semitone {
+        Some(semitone) => {
+            Note::from_midi((octave + 1) * 12 + semitone + u8::from(sharp), duration_ms)
+        }
+        None => Note::rest(duration_ms),
+    })
+}
+
+impl ChiptuneSequence {
+    /// Parses an RTTTL ringtone into a chiptune, see [`crate::audio::rtttl`] for the format.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the ringtone is malformed, or if it has more than 64 notes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use catears::audio::rtttl::Error;
+    /// use catears::audio::ChiptuneSequence;
+    ///
+    /// let close = |a: f32, b: f32| (a - b).abs() < 0.5;
+    ///
+    /// let nokia = "Nokia:d=4,o=5,b=225:8e6,8d6,f#,g#,8c#6,8b,d,e,8b,8a,c#,e,2a";
+    /// let tune = ChiptuneSequence::from_rtttl(nokia).unwrap();
+    /// let notes = &tune.notes[..usize::from(tune.length)];
+    /// assert_eq!(notes.len(), 13);
+    /// assert!(close(notes[0].frequency, 1318.51) && notes[0].duration_ms == 133);
+    /// assert!(close(notes[2].frequency, 739.99) && notes[2].duration_ms == 266);
+    /// assert!(close(notes[12].frequency, 880.0) && notes[12].duration_ms == 533);
+    ///
+    /// let simpsons = "The Simpsons:d=4,o=5,b=160:c.6,e6,f#6,8a6,g.6,e6,c6,8a,8f#,8f#,8f#,2g,8p,8p,8f#,8f#,8f#,\
+    ///                 8g,a#.,8c6,8c6,8c6,c6";
+    /// let tune = ChiptuneSequence::from_rtttl(simpsons).unwrap();
+    /// let notes = &tune.notes[..usize::from(tune.length)];
+    /// assert_eq!(notes.len(), 23);
+    /// assert!(close(notes[0].frequency, 1046.5) && notes[0].duration_ms == 562);
+    /// assert!(close(notes[11].frequency, 783.99) && notes[11].duration_ms == 750);
+    /// assert_eq!((notes[12].frequency, notes[12].duration_ms), (0.0, 187));
+    /// assert!(close(notes[18].frequency, 932.33) && notes[18].duration_ms == 562);
+    ///
+    /// assert_eq!(ChiptuneSequence::from_rtttl("no sections"), Err(Error::Sections));
+    /// assert_eq!(ChiptuneSequence::from_rtttl("x:d=3:c"), Err(Error::Default));
+    /// assert_eq!(ChiptuneSequence::from_rtttl("x:d=4:c,k,e"), Err(Error::Note(1)));
+    /// let long = "x::".to_string() + &["c"; 65].join(",");
+    /// assert_eq!(ChiptuneSequence::from_rtttl(&long), Err(Error::TooLong));
+    /// ```
+    pub fn from_rtttl(tune: &str) -> Result<Self, Error> {
+        let mut sections = tune.splitn(3, ':');
+        let (Some(_name), Some(defaults), Some(notes)) =
+            (sections.next(), sections.next(), sections.next())
+        else {
+            return Err(Error::Sections);
+        };
+        let defaults = Defaults::parse(defaults)?;
+
+        let mut sequence = Self::new();
+        sequence.bpm = Some(defaults.bpm);
+        let tokens = notes.split(',').map(str::trim).filter(|s| !s.is_empty());
+        for (index, token) in tokens.enumerate() {
+            let note = parse_note(token, defaults).ok_or(Error::Note(index))?;
+            *sequence.notes.get_mut(index).ok_or(Error::TooLong)? = note;
+            sequence.length = u8::try_from(index + 1).map_err(|_| Error::TooLong)?;
+        }
+        Ok(sequence)
+    }
+}
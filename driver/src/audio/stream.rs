@@ -0,0 +1,449 @@
+//! Streaming of audio clips from the network.
+//!
+//! A clip too long to embed in the firmware can be played from a WAV file on the server instead, see
+//! [`super::RemoteClip`]. Each speaker has a fetch task of its own, and the two talk through a [`Link`]: the speaker
+//! asks for a clip, and the fetch task downloads it in chunks into a ring buffer that the speaker plays from. The
+//! speaker never touches the network, so a slow or dropped connection only ever starves it of samples, and the fetch
+//! task never touches the speaker.
+//!
+//! The [`Decoder`] turns the bytes into frames at [`SAMPLE_RATE`] as they arrive, interpolating between samples just
+//! like [`super::synth::fill_clip`] does for embedded clips.
+//!
+//! The ring buffer holds [`BUFFER_LEN`] bytes, which is about two seconds of 8-bit mono audio at 8 kHz but only a tenth
+//! of a second of 16-bit stereo audio at 44.1 kHz, so low sample rates survive a hiccup in the connection far better.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, pipe::Pipe, signal::Signal};
+use embassy_time::WithTimeout as _;
+
+use super::synth::{widen, Sample, SAMPLE_RATE};
+use super::wav;
+
+/// Length in bytes of the ring buffer between the fetch task and the speaker.
+pub const BUFFER_LEN: usize = 16 * 1024;
+
+/// How much of a clip has to be buffered before it starts playing, unless it is shorter than that.
+pub const PREFILL_LEN: usize = BUFFER_LEN * 3 / 4;
+
+/// Longest WAV header, including any metadata chunks before the samples, that a streamed clip can have.
+pub const HEADER_LEN: usize = 1024;
+
+/// Where streamed clips are downloaded from, followed by their id and `.wav`.
+pub const URL_PREFIX: &str = "https://storage.googleapis.com/ziyadedher/catears/clips/";
+
+/// Longest URL of a streamed clip.
+pub const URL_LEN: usize = URL_PREFIX.len() + 16;
+
+/// How long a blocked write waits before checking whether the clip is still wanted.
+const WRITE_POLL_INTERVAL: embassy_time::Duration = embassy_time::Duration::from_millis(100);
+
+/// Returns the URL the clip `id` is downloaded from.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::stream::url;
+///
+/// assert_eq!(url(7), "https://storage.googleapis.com/ziyadedher/catears/clips/7.wav");
+/// ```
+#[must_use]
+pub fn url(id: u16) -> heapless::String<URL_LEN> {
+    use core::fmt::Write as _;
+
+    let mut url = heapless::String::new();
+    // The longest id and the extension fit in the room left after the prefix.
+    let _ = write!(url, "{URL_PREFIX}{id}.wav");
+    url
+}
+
+/// A clip asked for by the speaker, handed to the fetch task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct Request {
+    /// Id of the clip to download.
+    pub id: u16,
+    /// Which request this is, so that the fetch task notices once it is no longer wanted.
+    generation: u32,
+}
+
+/// How a request is getting on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Progress {
+    /// The fetch task has not picked the request up yet.
+    Pending,
+    /// The clip is downloading into the ring buffer.
+    Streaming,
+    /// The whole clip has been downloaded, though the ring buffer may still hold some of it.
+    Finished,
+    /// The download failed, or the request was replaced or stopped.
+    Failed,
+}
+
+/// What came of reading from a [`Link`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Read {
+    /// This many bytes of the clip were read.
+    Bytes(usize),
+    /// Nothing has arrived yet, but more is on its way.
+    Waiting,
+    /// The whole clip has been read.
+    Finished,
+    /// The download failed, or the request was replaced or stopped.
+    Failed,
+}
+
+const PENDING: u32 = 0;
+const STREAMING: u32 = 1;
+const FINISHED: u32 = 2;
+const FAILED: u32 = 3;
+
+/// Connection between a speaker and the task that fetches its clips.
+///
+/// The speaker [`start`](Self::start)s a request and [`read`](Self::read)s the clip as it downloads. The fetch task
+/// waits for requests with [`requested`](Self::requested), [`write`](Self::write)s what it downloads, and reports how
+/// the download ended with [`finish`](Self::finish). Starting another request or [`stop`](Self::stop)ping replaces the
+/// current one, and the fetch task finds out the next time it writes.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::stream::{Link, Progress, Read};
+/// use embassy_futures::block_on;
+///
+/// static LINK: Link = Link::new();
+///
+/// let request = LINK.start(7);
+/// assert_eq!(LINK.progress(request), Progress::Pending);
+/// let mut buffer = [0u8; 8];
+/// assert_eq!(LINK.read(request, &mut buffer), Read::Waiting);
+///
+/// // The fetch task picks the request up and downloads the clip.
+/// let fetching = block_on(LINK.requested());
+/// assert_eq!(fetching.id, 7);
+/// assert!(block_on(LINK.write(fetching, b"RIFF")));
+/// LINK.finish(fetching, true);
+///
+/// assert_eq!(LINK.progress(request), Progress::Finished);
+/// assert_eq!(LINK.read(request, &mut buffer), Read::Bytes(4));
+/// assert_eq!(&buffer[..4], b"RIFF");
+/// assert_eq!(LINK.read(request, &mut buffer), Read::Finished);
+///
+/// // Once the speaker moves on, the fetch task stops writing.
+/// let request = LINK.start(8);
+/// let fetching = block_on(LINK.requested());
+/// LINK.stop();
+/// assert!(!block_on(LINK.write(fetching, b"RIFF")));
+/// assert_eq!(LINK.read(request, &mut buffer), Read::Failed);
+/// ```
+pub struct Link {
+    /// The latest request, waiting for the fetch task.
+    request: Signal<CriticalSectionRawMutex, Request>,
+    /// Bytes of the clip on their way from the fetch task to the speaker.
+    pipe: Pipe<CriticalSectionRawMutex, BUFFER_LEN>,
+    /// Generation of the current request, shifted left by two, and its progress in the low two bits.
+    state: AtomicU32,
+}
+
+impl Default for Link {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Link {
+    /// Creates a link with no request.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            request: Signal::new(),
+            pipe: Pipe::new(),
+            state: AtomicU32::new(FAILED),
+        }
+    }
+
+    /// Asks for the clip `id`, replacing any earlier request.
+    pub fn start(&self, id: u16) -> Request {
+        let request = Request {
+            id,
+            generation: self.next_generation(PENDING),
+        };
+        self.request.signal(request);
+        request
+    }
+
+    /// Gives up on the current request.
+    pub fn stop(&self) {
+        self.next_generation(FAILED);
+    }
+
+    /// Returns how `request` is getting on.
+    pub fn progress(&self, request: Request) -> Progress {
+        let state = self.state.load(Ordering::Acquire);
+        if state >> 2 != request.generation {
+            return Progress::Failed;
+        }
+        match state & 0b11 {
+            PENDING => Progress::Pending,
+            STREAMING => Progress::Streaming,
+            FINISHED => Progress::Finished,
+            _ => Progress::Failed,
+        }
+    }
+
+    /// Returns how many bytes are waiting in the ring buffer.
+    pub fn buffered(&self) -> usize {
+        self.pipe.len()
+    }
+
+    /// Reads as much of the clip asked for by `request` into `buffer` as has arrived.
+    pub fn read(&self, request: Request, buffer: &mut [u8]) -> Read {
+        // The progress is checked before reading, so the last bytes are read before the clip counts as finished.
+        match self.progress(request) {
+            Progress::Pending => Read::Waiting,
+            Progress::Failed => Read::Failed,
+            progress => match self.pipe.try_read(buffer) {
+                Ok(len) => Read::Bytes(len),
+                Err(_) if progress == Progress::Finished => Read::Finished,
+                Err(_) => Read::Waiting,
+            },
+        }
+    }
+
+    /// Waits for the next request, and empties the ring buffer of anything left over from the last one.
+    pub async fn requested(&self) -> Request {
+        loop {
+            let request = self.request.wait().await;
+            // Nothing writes to the ring buffer but the fetch task, so once it is cleared it only holds this request.
+            self.pipe.clear();
+            if self.advance(request, PENDING, STREAMING) {
+                return request;
+            }
+        }
+    }
+
+    /// Writes downloaded bytes of the clip asked for by `request`, waiting for the speaker to make room. Returns
+    /// `false` without writing the rest if the request is no longer wanted.
+    pub async fn write(&self, request: Request, mut bytes: &[u8]) -> bool {
+        while !bytes.is_empty() {
+            if self.progress(request) != Progress::Streaming {
+                return false;
+            }
+            if let Ok(written) = self
+                .pipe
+                .write(bytes)
+                .with_timeout(WRITE_POLL_INTERVAL)
+                .await
+            {
+                bytes = &bytes[written..];
+            }
+        }
+        true
+    }
+
+    /// Reports that the download asked for by `request` ended, completely or not.
+    pub fn finish(&self, request: Request, complete: bool) {
+        self.advance(request, STREAMING, if complete { FINISHED } else { FAILED });
+    }
+
+    /// Moves on to a new request in the `progress` state, returning its generation.
+    fn next_generation(&self, progress: u32) -> u32 {
+        // Only the speaker moves the generation on, so nothing else can do so in between.
+        let generation =
+            (self.state.load(Ordering::Acquire) >> 2).wrapping_add(1) & (u32::MAX >> 2);
+        self.state
+            .store(generation << 2 | progress, Ordering::Release);
+        generation
+    }
+
+    /// Moves `request` from the `from` state to the `to` state, returning whether it was still there to move.
+    fn advance(&self, request: Request, from: u32, to: u32) -> bool {
+        self.state
+            .compare_exchange(
+                request.generation << 2 | from,
+                request.generation << 2 | to,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            )
+            .is_ok()
+    }
+}
+
+/// What came of decoding some of a streamed clip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decoded {
+    /// How many bytes of the input were used up.
+    pub consumed: usize,
+    /// How many frames were produced.
+    pub frames: usize,
+}
+
+/// Decodes the samples of a WAV file into interleaved stereo frames at [`SAMPLE_RATE`], a few bytes at a time.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::stream::Decoder;
+///
+/// // 8-bit mono at 4410 Hz, so every sample stretches across ten frames.
+/// let mut file = b"RIFF\0\0\0\0WAVEfmt \x10\0\0\0\x01\0\x01\0\x3A\x11\0\0\x3A\x11\0\0\x01\0\x08\0".to_vec();
+/// file.extend_from_slice(b"data\x03\0\0\0");
+/// file.extend_from_slice(&[128, 255, 0]);
+/// let (mut decoder, start) = Decoder::new(&file).unwrap();
+/// assert_eq!(start, 44);
+///
+/// // The first sample alone is not enough to interpolate towards the next.
+/// let mut buffer = [1i16; 2 * 32];
+/// let decoded = decoder.decode(&file[44..45], &mut buffer, false);
+/// assert_eq!((decoded.consumed, decoded.frames), (1, 0));
+///
+/// // With the next sample, the frames between the two come out.
+/// let decoded = decoder.decode(&file[45..46], &mut buffer, false);
+/// assert_eq!((decoded.consumed, decoded.frames), (1, 10));
+/// assert_eq!(buffer[..2], [0, 0]);
+/// assert_eq!(buffer[2 * 5..2 * 6], [(127 << 8) / 2, (127 << 8) / 2]);
+///
+/// // The last sample is held until the end, and then the clip is over.
+/// let decoded = decoder.decode(&file[46..], &mut buffer, true);
+/// assert_eq!((decoded.consumed, decoded.frames), (1, 20));
+/// assert_eq!(buffer[..2], [127 << 8, 127 << 8]);
+/// assert_eq!(buffer[2 * 19..2 * 20], [-128 << 8, -128 << 8]);
+/// assert_eq!(decoder.decode(&[], &mut buffer, true).frames, 0);
+/// assert!(decoder.is_over());
+///
+/// // Samples split between two reads are put back together.
+/// let mut file = b"RIFF\0\0\0\0WAVEfmt \x10\0\0\0\x01\0\x02\0\x44\xAC\0\0\x10\xB1\x02\0\x04\0\x10\0data".to_vec();
+/// file.extend_from_slice(&8u32.to_le_bytes());
+/// file.extend_from_slice(&[0x00, 0x40, 0x00, 0xC0, 0x00, 0x20, 0x00, 0xE0]);
+/// let (mut decoder, start) = Decoder::new(&file).unwrap();
+/// let (first, second) = file[start..].split_at(3);
+/// assert_eq!(decoder.decode(first, &mut buffer, false).frames, 0);
+/// assert_eq!(decoder.decode(second, &mut buffer, true).frames, 2);
+/// assert_eq!(buffer[..4], [0x4000, -0x4000, 0x2000, -0x2000]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Decoder {
+    /// Number of channels, 1 or 2.
+    channels: usize,
+    /// Bytes per sample, 1 or 2.
+    bytes_per_sample: usize,
+    /// Sample rate of the clip in Hz.
+    sample_rate: u64,
+    /// Bytes of samples left, as the `data` chunk declares it.
+    remaining: usize,
+    /// Start of a frame split between two reads.
+    partial: [u8; 4],
+    /// How many bytes of `partial` have arrived.
+    partial_len: usize,
+    /// The last two frames of the clip read, the older first.
+    window: [[Sample; 2]; 2],
+    /// How many frames of the clip have been read.
+    read: u64,
+    /// How many frames have been produced.
+    produced: u64,
+}
+
+impl Decoder {
+    /// Starts decoding the WAV file whose start is `header`, returning the decoder and the offset of the first sample
+    /// in `header`. The header has to be complete, which is the case once [`HEADER_LEN`] bytes or the whole file have
+    /// arrived.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file is not a WAV file, or if its samples are in a format the speakers cannot play.
+    pub fn new(header: &[u8]) -> Result<(Self, usize), wav::Error> {
+        let header = wav::parse_header(header)?;
+        let decoder = Self {
+            channels: usize::from(header.channels),
+            bytes_per_sample: usize::from(header.bits_per_sample / 8),
+            sample_rate: u64::from(header.sample_rate),
+            remaining: header.data_len,
+            partial: [0; 4],
+            partial_len: 0,
+            window: [[0; 2]; 2],
+            read: 0,
+            produced: 0,
+        };
+        Ok((decoder, header.data_start))
+    }
+
+    /// Decodes the next bytes of the clip in `input` into `output`, as interleaved stereo frames at full volume.
+    ///
+    /// `finished` says that nothing follows `input`, so the last sample is held rather than waited on. Once the clip is
+    /// over, no more frames come out.
+    pub fn decode(&mut self, input: &[u8], output: &mut [Sample], finished: bool) -> Decoded {
+        let mut consumed = 0;
+        let mut frames = 0;
+        let rate = u64::from(SAMPLE_RATE);
+        for frame in output.chunks_exact_mut(2) {
+            // The output frame falls `remainder / rate` of the way from sample `source` to the next.
+            let position = self.produced * self.sample_rate;
+            let source = position / rate;
+            let remainder = i64::try_from(position % rate).unwrap_or(0);
+            while self.read < source + 2 {
+                let (next, used) = self.next_frame(&input[consumed..]);
+                consumed += used;
+                let Some(next) = next else {
+                    break;
+                };
+                self.window = [self.window[1], next];
+                self.read += 1;
+            }
+
+            let [from, to] = if self.read == source + 2 {
+                self.window
+            } else if self.read == source + 1 && (finished || self.remaining == 0) {
+                [self.window[1]; 2]
+            } else {
+                // Either the next sample has not arrived yet, or the clip is over.
+                break;
+            };
+            for ((output, from), to) in frame.iter_mut().zip(from).zip(to) {
+                let interpolated = i64::from(from)
+                    + (i64::from(to) - i64::from(from)) * remainder / i64::from(SAMPLE_RATE);
+                // Between two samples, this never leaves the range of a sample.
+                #[allow(clippy::cast_possible_truncation)]
+                {
+                    *output = interpolated as Sample;
+                }
+            }
+            self.produced += 1;
+            frames += 1;
+        }
+        Decoded { consumed, frames }
+    }
+
+    /// Returns whether every sample the `data` chunk declares has been played, so that whatever follows the samples in
+    /// the file can be ignored.
+    #[must_use]
+    pub fn is_over(&self) -> bool {
+        self.remaining == 0
+            && self.produced * self.sample_rate / u64::from(SAMPLE_RATE) >= self.read
+    }
+
+    /// Reads the next frame of the clip from `input`, returning it, if it is complete, and how many bytes were used.
+    fn next_frame(&mut self, input: &[u8]) -> (Option<[Sample; 2]>, usize) {
+        let frame_len = self.channels * self.bytes_per_sample;
+        let used = (frame_len - self.partial_len)
+            .min(input.len())
+            .min(self.remaining);
+        self.partial[self.partial_len..self.partial_len + used].copy_from_slice(&input[..used]);
+        self.partial_len += used;
+        self.remaining -= used;
+        if self.partial_len < frame_len {
+            return (None, used);
+        }
+
+        self.partial_len = 0;
+        let sample = |channel: usize| -> Sample {
+            let offset = channel * self.bytes_per_sample;
+            match self.bytes_per_sample {
+                1 => widen((i16::from(self.partial[offset]) - 128) << 8),
+                _ => widen(i16::from_le_bytes([
+                    self.partial[offset],
+                    self.partial[offset + 1],
+                ])),
+            }
+        };
+        (Some([sample(0), sample(self.channels - 1)]), used)
+    }
+}
@@ -0,0 +1,1527 @@
+//! Tone synthesis for the speakers.
+//!
+//! The speaker task renders each tone, drum hit, or chiptune note into interleaved stereo buffers with a
+//! [`ToneGenerator`], sweeps with a [`SweepGenerator`], noise with a [`NoiseGenerator`], and each stretch of an audio
+//! clip with [`fill_clip`], and hands them to the I2S DMA one at a time. Everything in here is pure, so the synthesis can be checked on the host without any hardware.
+
+use super::chiptunes::Preset;
+use super::wavetables::{self, Wavetable};
+use super::{
+    Arpeggio, ChiptuneSequence, Clip, Envelope, Mode, Noise, Note, NoteKind, Percussion, Playlist,
+    Side, Sweep, Tremolo, Vibrato, Waveform,
+};
+
+/// Output sample rate of the I2S peripherals in Hz.
+pub const SAMPLE_RATE: u32 = 44_100;
+
+/// One sample of the interleaved stereo buffers handed to the speakers.
+///
+/// 16 bits by default. With the `dac-32bit` feature, samples are 32 bits wide and carry 24 bits of resolution in their
+/// top bits, the left-justified layout 24-bit DACs expect in a 32-bit slot.
+#[cfg(not(feature = "dac-32bit"))]
+pub type Sample = i16;
+
+/// One sample of the interleaved stereo buffers handed to the speakers.
+///
+/// 16 bits by default. With the `dac-32bit` feature, samples are 32 bits wide and carry 24 bits of resolution in their
+/// top bits, the left-justified layout 24-bit DACs expect in a 32-bit slot.
+#[cfg(feature = "dac-32bit")]
+pub type Sample = i32;
+
+/// Full scale of a [`Sample`].
+#[cfg(not(feature = "dac-32bit"))]
+const FULL_SCALE: f32 = 32_767.0;
+
+/// Full scale of a [`Sample`], the largest 24-bit value shifted into the top of 32 bits.
+#[cfg(feature = "dac-32bit")]
+const FULL_SCALE: f32 = 8_388_607.0 * 256.0;
+
+/// Returns a sample of 16-bit material, such as a clip, as a [`Sample`] at the same level.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::synth::{narrow, widen};
+///
+/// assert_eq!(narrow(widen(-12_345)), -12_345);
+/// assert_eq!(narrow(widen(i16::MAX)), i16::MAX);
+/// ```
+#[must_use]
+pub fn widen(sample: i16) -> Sample {
+    Sample::from(sample) << (Sample::BITS - i16::BITS)
+}
+
+/// Returns `sample` cut down to its top 16 bits, the inverse of [`widen`].
+#[must_use]
+pub fn narrow(sample: Sample) -> i16 {
+    let [.., low, high] = sample.to_le_bytes();
+    i16::from_le_bytes([low, high])
+}
+
+/// Returns `sample` as a float, exactly for anything the synthesis or [`widen`] produces.
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::cast_lossless,
+    reason = "samples hold at most 24 bits of resolution, which a float keeps exactly"
+)]
+fn to_float(sample: Sample) -> f32 {
+    sample as f32
+}
+
+/// Returns the number of frames a note of `duration_ms` lasts.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::synth::frames_for;
+///
+/// assert_eq!(frames_for(0), 0);
+/// assert_eq!(frames_for(10), 441);
+/// assert_eq!(frames_for(250), 11_025);
+/// assert_eq!(frames_for(1000), 44_100);
+/// ```
+#[must_use]
+pub fn frames_for(duration_ms: u16) -> usize {
+    usize::from(duration_ms) * SAMPLE_RATE as usize / 1000
+}
+
+/// Returns the peak amplitude of a note at `volume`, played at `master_volume`.
+///
+/// Full volume on both is half of full scale, which leaves headroom for the speaker amplifiers.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::synth::amplitude;
+///
+/// assert_eq!(amplitude(0, 255), 0.0);
+/// assert_eq!(amplitude(255, 255), f32::from(i16::MAX) / 2.0);
+/// assert_eq!(amplitude(255, 0), 0.0);
+/// ```
+#[must_use]
+pub fn amplitude(volume: u8, master_volume: u8) -> f32 {
+    (FULL_SCALE * f32::from(volume) / 255.0) * (f32::from(master_volume) / 255.0) * 0.5
+}
+
+/// Returns `volume` for the speaker on `side` at `balance`, from -127 for only the left speaker through 0 for both
+/// equally to 127 for only the right speaker.
+///
+/// The speaker the balance leans away from is turned down linearly, the other plays at `volume`. It only ever turns a
+/// volume down, so it scales whatever volume it is given without leaving the range of a `u8`.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::synth::balanced;
+/// use catears::audio::Side;
+///
+/// assert_eq!(balanced(200, 0, Side::Left), 200);
+/// assert_eq!(balanced(200, 0, Side::Right), 200);
+///
+/// // Leaning right turns the left speaker down, and leaves the right one alone.
+/// assert_eq!(balanced(254, 64, Side::Left), 126);
+/// assert_eq!(balanced(254, 64, Side::Right), 254);
+/// assert_eq!(balanced(255, 127, Side::Left), 0);
+/// assert_eq!(balanced(255, -127, Side::Right), 0);
+/// assert_eq!(balanced(255, i8::MIN, Side::Right), 0);
+/// ```
+#[must_use]
+pub const fn balanced(volume: u8, balance: i8, side: Side) -> u8 {
+    let away = match side {
+        Side::Left => balance,
+        Side::Right => balance.saturating_neg(),
+    };
+    let weight = if away > 0 {
+        127 - away.unsigned_abs()
+    } else {
+        127
+    } as u16;
+    // Never above `volume`, so always fits.
+    #[allow(clippy::cast_possible_truncation)]
+    {
+        (volume as u16 * weight / 127) as u8
+    }
+}
+
+/// Returns the gain (0.0 to 1.0) of the frame at `index` in a note of `total` frames shaped by `envelope`.
+///
+/// If the attack and release together are longer than the note, both are shortened in proportion so that the note still
+/// rises and falls all the way. The decay is simply cut short by the release.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::synth::{envelope, frames_for};
+/// use catears::audio::Envelope;
+///
+/// // The default envelope fades in and out over 5 ms, 220 frames, and holds full volume in between.
+/// let default = Envelope::DEFAULT;
+/// assert_eq!(envelope(0, 1000, &default), 0.0);
+/// assert_eq!(envelope(110, 1000, &default), 0.5);
+/// assert_eq!(envelope(220, 1000, &default), 1.0);
+/// assert_eq!(envelope(780, 1000, &default), 1.0);
+/// assert_eq!(envelope(890, 1000, &default), 0.5);
+/// assert_eq!(envelope(999, 1000, &default), 1.0 / 220.0);
+///
+/// // Decaying from full volume to the sustain level after the attack, and releasing from there.
+/// let pluck = Envelope::new(10, 20, 51, 10);
+/// let total = frames_for(100);
+/// assert_eq!(envelope(frames_for(10), total, &pluck), 1.0);
+/// assert!((envelope(frames_for(20), total, &pluck) - 0.6).abs() < 1e-6);
+/// assert!((envelope(frames_for(50), total, &pluck) - 0.2).abs() < 1e-6);
+/// assert!((envelope(total - frames_for(5), total, &pluck) - 0.1).abs() < 1e-3);
+///
+/// // A note shorter than its attack and release still peaks, at the same point of the way through.
+/// let swell = Envelope::new(30, 0, 255, 10);
+/// let total = frames_for(20);
+/// assert_eq!(envelope(frames_for(15), total, &swell), 1.0);
+/// assert!((envelope(frames_for(5), total, &swell) - 1.0 / 3.0).abs() < 1e-2);
+/// assert!(envelope(total - 1, total, &swell) < 0.01);
+///
+/// // Without fades a note is at full volume from its first frame to its last.
+/// let hard = Envelope::fades(0, 0);
+/// assert_eq!(envelope(0, 1000, &hard), 1.0);
+/// assert_eq!(envelope(999, 1000, &hard), 1.0);
+///
+/// // Fades of different lengths at either end, a quick 1 ms in and a slow 20 ms out.
+/// let blip = Envelope::fades(1, 20);
+/// let total = frames_for(30);
+/// assert_eq!(envelope(frames_for(1), total, &blip), 1.0);
+/// assert_eq!(envelope(total - frames_for(20), total, &blip), 1.0);
+/// assert_eq!(envelope(total - frames_for(10), total, &blip), 0.5);
+///
+/// // Too long for the note, the fades keep their 1:3 proportion and meet in the middle without going negative.
+/// let thump = Envelope::fades(10, 30);
+/// let total = frames_for(20);
+/// assert_eq!(envelope(frames_for(5), total, &thump), 1.0);
+/// assert!((0..total).all(|index| (0.0..=1.0).contains(&envelope(index, total, &thump))));
+/// ```
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn envelope(index: usize, total: usize, envelope: &Envelope) -> f32 {
+    let mut attack = frames_for(envelope.attack_ms);
+    let mut release = frames_for(envelope.release_ms);
+    if attack + release > total {
+        let scaled = u64::try_from(attack).unwrap_or(u64::MAX)
+            * u64::try_from(total).unwrap_or(u64::MAX)
+            / u64::try_from(attack + release).unwrap_or(u64::MAX);
+        attack = usize::try_from(scaled).unwrap_or(total);
+        release = total - attack;
+    }
+    let decay = frames_for(envelope.decay_ms);
+    let sustain = f32::from(envelope.sustain) / 255.0;
+
+    let level = if index < attack {
+        index as f32 / attack as f32
+    } else if index - attack < decay {
+        1.0 - (1.0 - sustain) * (index - attack) as f32 / decay as f32
+    } else {
+        sustain
+    };
+    let remaining = total.saturating_sub(index);
+    if remaining < release {
+        level * remaining as f32 / release as f32
+    } else {
+        level
+    }
+}
+
+/// Returns the value (-1.0 to 1.0) of `waveform` at `phase` through its cycle (0.0 to 1.0), for a wave that advances
+/// by `step` of a cycle per frame. A square wave spends `duty` 256ths of each cycle high, see [`Note::duty`].
+///
+/// Every waveform starts at zero and rises, and a wavetable that does not exist plays as a sine, see
+/// [`super::wavetables`]. The jumps in the square and sawtooth waves are smoothed over about a frame
+/// on either side with polynomial band-limited steps, so they do not alias into a harsh buzz at high notes. An uneven
+/// square wave is shifted by its average so that it still swings around zero, and scaled back down into range.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::synth::{fill_tone, oscillator};
+/// use catears::audio::{Note, Waveform};
+///
+/// let step = 440.0 / 44_100.0;
+/// for waveform in [Waveform::Sine, Waveform::Triangle] {
+///     assert!(oscillator(waveform, 0.0, step, 128).abs() < 1e-6);
+///     assert!((oscillator(waveform, 0.25, step, 128) - 1.0).abs() < 1e-6);
+///     assert!((oscillator(waveform, 0.75, step, 128) + 1.0).abs() < 1e-6);
+/// }
+/// assert_eq!(oscillator(Waveform::Square, 0.25, step, 128), 1.0);
+/// assert_eq!(oscillator(Waveform::Square, 0.75, step, 128), -1.0);
+/// assert_eq!(oscillator(Waveform::Sawtooth, 0.5, step, 128), 0.0);
+///
+/// // The jumps are smoothed: right at them the square and sawtooth waves sit halfway.
+/// assert!(oscillator(Waveform::Square, 0.0, step, 128).abs() < 1e-6);
+/// assert!(oscillator(Waveform::Square, 0.5, step, 128).abs() < 1e-6);
+/// assert!(oscillator(Waveform::Sawtooth, 0.0, step, 128).abs() < 1e-6);
+///
+/// // A 25% pulse wave is high a quarter of the time, and averages to zero.
+/// let samples: Vec<f32> = (0..1000).map(|i| oscillator(Waveform::Square, i as f32 / 1000.0, 0.001, 64)).collect();
+/// let high = samples.iter().filter(|&&sample| sample > 0.0).count();
+/// assert!((248..=252).contains(&high));
+/// assert!(samples.iter().sum::<f32>().abs() < 1.0);
+/// assert!(samples.iter().all(|sample| sample.abs() <= 1.0));
+///
+/// // So does a note played on one.
+/// for (duty, share) in [(32, 0.125), (64, 0.25), (128, 0.5)] {
+///     let note = Note::new(441.0, 1000).with_waveform(Waveform::Square).with_duty(duty);
+///     let mut buffer = vec![0i16; 2 * 44_100];
+///     fill_tone(&mut buffer, &note, 10_000.0);
+///     let high = buffer.iter().step_by(2).filter(|&&sample| sample > 0).count();
+///     assert!((high as f32 / 44_100.0 - share).abs() < 0.02);
+/// }
+/// ```
+#[must_use]
+pub fn oscillator(waveform: Waveform, phase: f32, step: f32, duty: u8) -> f32 {
+    match waveform {
+        Waveform::Sine => libm::sinf(2.0 * core::f32::consts::PI * phase),
+        Waveform::Square => {
+            // A duty of zero would never go high, and sit at a constant offset instead of sounding.
+            let duty = f32::from(duty.max(1)) / 256.0;
+            let naive = if phase < duty { 1.0 } else { -1.0 };
+            let wave = naive + poly_blep(phase, step) - poly_blep(wrap(phase + 1.0 - duty), step);
+            (wave - (2.0 * duty - 1.0)) / (2.0 * duty.max(1.0 - duty))
+        }
+        Waveform::Triangle => 1.0 - 4.0 * (wrap(phase + 0.25) - 0.5).abs(),
+        Waveform::Sawtooth => {
+            // Shifted by half a cycle, so it starts at zero like the others and jumps in the middle.
+            let phase = wrap(phase + 0.5);
+            2.0 * phase - 1.0 - poly_blep(phase, step)
+        }
+        Waveform::Table(index) => wavetables::get(index).map_or_else(
+            || oscillator(Waveform::Sine, phase, step, duty),
+            |table| table.sample(phase),
+        ),
+    }
+}
+
+/// Returns the correction that smooths a jump of two at the start of a cycle, for a wave advancing by `step` per frame.
+fn poly_blep(phase: f32, step: f32) -> f32 {
+    if phase < step {
+        let t = phase / step;
+        2.0 * t - t * t - 1.0
+    } else if phase > 1.0 - step {
+        let t = (phase - 1.0) / step;
+        t * t + 2.0 * t + 1.0
+    } else {
+        0.0
+    }
+}
+
+/// Returns how much faster than its base frequency a note with `vibrato` runs at frame `index`.
+fn vibrato_ratio(index: usize, vibrato: Vibrato) -> f32 {
+    if !vibrato.is_on() {
+        return 1.0;
+    }
+    #[allow(clippy::cast_precision_loss)]
+    let wobble = libm::sinf(
+        2.0 * core::f32::consts::PI * wrap(vibrato.rate_hz * index as f32 / SAMPLE_RATE as f32),
+    );
+    libm::powf(2.0, f32::from(vibrato.depth_cents) / 1200.0 * wobble)
+}
+
+/// Returns the gain (0.0 to 1.0) of `tremolo` at frame `index` of a note, which multiplies its envelope.
+///
+/// The gain starts every note at full and dips sinusoidally by up to the depth, so it never goes above 1.0.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::synth::{frames_for, tremolo};
+/// use catears::audio::Tremolo;
+///
+/// assert_eq!(tremolo(1234, Tremolo::OFF), 1.0);
+///
+/// let half = Tremolo::new(128, 10.0);
+/// assert_eq!(tremolo(0, half), 1.0);
+/// assert!((tremolo(frames_for(50), half) - 127.0 / 255.0).abs() < 1e-3);
+/// assert!((tremolo(frames_for(100), half) - 1.0).abs() < 1e-3);
+/// ```
+#[must_use]
+pub fn tremolo(index: usize, tremolo: Tremolo) -> f32 {
+    if !tremolo.is_on() {
+        return 1.0;
+    }
+    #[allow(clippy::cast_precision_loss)]
+    let dip =
+        (1.0 - libm::cosf(
+            2.0 * core::f32::consts::PI * wrap(tremolo.rate_hz * index as f32 / SAMPLE_RATE as f32),
+        )) / 2.0;
+    1.0 - f32::from(tremolo.depth) / 255.0 * dip
+}
+
+/// Returns how much higher than its base frequency a note with `arpeggio` plays at frame `index`.
+fn arpeggio_ratio(index: usize, arpeggio: Arpeggio) -> f32 {
+    if !arpeggio.is_on() {
+        return 1.0;
+    }
+    let mut pitches = [0u8; 3];
+    let mut count = 1;
+    for semitones in arpeggio
+        .semitones
+        .into_iter()
+        .filter(|&semitones| semitones > 0)
+    {
+        pitches[count] = semitones;
+        count += 1;
+    }
+    let step = frames_for(arpeggio.step_ms).max(1);
+    let semitones = pitches[(index / step) % count];
+    libm::powf(2.0, f32::from(semitones) / 12.0)
+}
+
+/// Wraps a phase into 0.0 to 1.0.
+fn wrap(phase: f32) -> f32 {
+    phase - libm::floorf(phase)
+}
+
+/// Advances the xorshift generator in `random`, which must not be zero, and returns its next value (-1.0 to 1.0).
+fn white_noise(random: &mut u32) -> f32 {
+    *random ^= *random << 13;
+    *random ^= *random >> 17;
+    *random ^= *random << 5;
+    #[allow(clippy::cast_possible_wrap, clippy::cast_precision_loss)]
+    let white = *random as i32 as f32 / 2_147_483_648.0;
+    white
+}
+
+/// Seed of the noise in drum hits, the same every time so that every hit of a drum sounds alike.
+const PERCUSSION_SEED: u32 = 0x9E37_79B9;
+
+/// Envelope of a drum hit, which starts at full volume and only needs a release in case the note cuts it short.
+const PERCUSSION_ENVELOPE: Envelope = Envelope::new(0, 0, u8::MAX, 5);
+
+/// Renders a note into `buffer` as interleaved stereo frames at `amplitude`, returning how many frames were produced.
+///
+/// A `frequency` of zero renders a rest. A note longer than the buffer is cut short, releasing at the end of the buffer
+/// instead. With vibrato, the phase advances by a step that swings sinusoidally around the note's frequency, and with an
+/// arpeggio the step jumps between pitches. The phase carries on across every change, so neither clicks. A tremolo
+/// scales the envelope down and back up, see [`tremolo`].
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::synth::{amplitude, fill_tone, frames_for};
+/// use catears::audio::{Arpeggio, Envelope, Note, Vibrato, Waveform};
+///
+/// let mut buffer = [1i16; 2 * 1000];
+///
+/// // A 10 ms note fits, a second of it is cut short at the end of the buffer.
+/// let loudest = amplitude(255, 255);
+/// assert_eq!(fill_tone(&mut buffer, &Note::new(440.0, 10), loudest), frames_for(10));
+/// assert_eq!(fill_tone(&mut buffer, &Note::new(440.0, 1000), loudest), 1000);
+///
+/// // Both channels carry the same signal, which fades in from silence and out again, and never clips at full volume.
+/// assert!(buffer.chunks(2).all(|frame| frame[0] == frame[1]));
+/// assert_eq!(buffer[0], 0);
+/// assert!(f32::from(buffer[1998]).abs() < loudest / 200.0);
+/// assert!(buffer.iter().all(|&sample| f32::from(sample).abs() <= loudest));
+/// assert!(buffer.iter().any(|&sample| f32::from(sample) > loudest * 0.99));
+///
+/// // The phase advances smoothly from frame to frame, with no jumps for the speaker to click on.
+/// let max_step = loudest * 2.0 * core::f32::consts::PI * 440.0 / 44_100.0;
+/// assert!(buffer
+///     .chunks(2)
+///     .zip(buffer.chunks(2).skip(1))
+///     .all(|(a, b)| f32::from(b[0] - a[0]).abs() <= max_step + 1.0));
+///
+/// // The other waveforms fade in and out and stay in range just the same.
+/// for waveform in [Waveform::Square, Waveform::Triangle, Waveform::Sawtooth] {
+///     fill_tone(&mut buffer, &Note::new(440.0, 1000).with_waveform(waveform), loudest);
+///     assert_eq!(buffer[0], 0);
+///     assert!(buffer.iter().all(|&sample| f32::from(sample).abs() <= loudest));
+/// }
+/// // A square wave spends most of its time at full swing.
+/// fill_tone(&mut buffer, &Note::new(440.0, 1000).with_waveform(Waveform::Square), loudest);
+/// let full = buffer[2 * 220..2 * 780].iter().filter(|&&sample| f32::from(sample).abs() > loudest * 0.99);
+/// assert!(full.count() > 2 * 500);
+///
+/// // The envelope shapes the note, here down to a quiet sustain.
+/// let quiet = Note::new(440.0, 1000).with_envelope(Envelope::new(1, 1, 26, 1));
+/// fill_tone(&mut buffer, &quiet, loudest);
+/// assert!(buffer[2 * 100..].iter().all(|&sample| f32::from(sample).abs() <= loudest * 0.11));
+///
+/// // A rest is silence.
+/// assert_eq!(fill_tone(&mut buffer, &Note::rest(10), loudest), frames_for(10));
+/// assert!(buffer[..2 * frames_for(10)].iter().all(|&sample| sample == 0));
+///
+/// // A steady note crosses zero at an even pace, vibrato speeds it up and slows it down.
+/// let spacings = |buffer: &[i16]| {
+///     let rising: Vec<usize> = (1..buffer.len() / 2)
+///         .filter(|&i| buffer[2 * (i - 1)] < 0 && buffer[2 * i] >= 0)
+///         .collect();
+///     let spacings: Vec<usize> = rising.windows(2).map(|pair| pair[1] - pair[0]).collect();
+///     (*spacings.iter().min().unwrap(), *spacings.iter().max().unwrap())
+/// };
+/// let mut second = vec![0i16; 2 * 44_100];
+/// fill_tone(&mut second, &Note::new(441.0, 1000), loudest);
+/// let (shortest, longest) = spacings(&second);
+/// assert!(longest - shortest <= 1);
+/// let vibrato = Vibrato::new(100, 5.0);
+/// fill_tone(&mut second, &Note::new(441.0, 1000).with_vibrato(vibrato), loudest);
+/// let (shortest, longest) = spacings(&second);
+/// assert!(shortest <= 96 && longest >= 104);
+///
+/// // An arpeggio an octave up switches between the two pitches every 20 ms, without jumping.
+/// let octaves = Note::new(441.0, 1000).with_arpeggio(Arpeggio::chord([12, 0]));
+/// fill_tone(&mut second, &octaves, loudest);
+/// assert_eq!(spacings(&second[2 * 441..2 * 882]), (100, 100));
+/// assert_eq!(spacings(&second[2 * 882..2 * 1323]), (50, 50));
+/// let max_step = loudest * 2.0 * core::f32::consts::PI * 882.0 / 44_100.0;
+/// assert!(second
+///     .chunks(2)
+///     .zip(second.chunks(2).skip(1))
+///     .all(|(a, b)| (f32::from(b[0]) - f32::from(a[0])).abs() <= max_step + 1.0));
+/// ```
+#[must_use]
+pub fn fill_tone(buffer: &mut [Sample], note: &Note, amplitude: f32) -> usize {
+    let mut tone = ToneGenerator::new(note, amplitude);
+    tone.frames = tone.frames.min(buffer.len() / 2);
+    tone.fill(buffer)
+}
+
+/// Renders a note chunk by chunk, for notes longer than a single buffer.
+///
+/// Each call to [`ToneGenerator::fill`] carries on where the last one stopped, so the phase runs on across the seams and
+/// the envelope spans the whole note, releasing only in the final chunk.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::synth::{amplitude, fill_tone, frames_for, ToneGenerator};
+/// use catears::audio::Note;
+///
+/// let note = Note::new(440.0, 1000);
+/// let loudest = amplitude(255, 255);
+/// let mut tone = ToneGenerator::new(&note, loudest);
+/// assert_eq!(tone.remaining(), frames_for(1000));
+///
+/// // A second of audio takes 11 buffers of 4096 frames, the last one partly filled.
+/// let mut buffer = [0i16; 2 * 4096];
+/// let mut whole = Vec::new();
+/// let mut chunks = 0;
+/// loop {
+///     let frames = tone.fill(&mut buffer);
+///     if frames == 0 {
+///         break;
+///     }
+///     whole.extend_from_slice(&buffer[..2 * frames]);
+///     chunks += 1;
+/// }
+/// assert_eq!(chunks, 11);
+/// assert_eq!(whole.len(), 2 * frames_for(1000));
+///
+/// // The seams are seamless, the chunks match the note rendered in one go.
+/// let mut reference = vec![0i16; 2 * frames_for(1000)];
+/// assert_eq!(fill_tone(&mut reference, &note, loudest), frames_for(1000));
+/// assert_eq!(whole, reference);
+///
+/// // Only the final chunk fades out.
+/// assert!(whole[2 * 4095].abs() > 1000);
+/// assert!(whole.last().unwrap().abs() < 200);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ToneGenerator {
+    note: Note,
+    amplitude: f32,
+    /// Length of the note in frames.
+    frames: usize,
+    /// Frames rendered so far.
+    position: usize,
+    /// Phase of the oscillator, from 0.0 to 1.0.
+    phase: f32,
+    /// State of the xorshift generator behind the noise in drum hits, never zero.
+    random: u32,
+    /// Last noise value of a hi-hat, which each new one is taken from to keep only the brightest part.
+    previous: f32,
+    /// Wavetable the note plays on, looked up once rather than every frame.
+    table: Option<&'static Wavetable>,
+}
+
+impl ToneGenerator {
+    /// Creates a new generator for `note` at `amplitude`, starting at the beginning of the note.
+    #[must_use]
+    pub fn new(note: &Note, amplitude: f32) -> Self {
+        let mut note = *note;
+        let table = match note.waveform {
+            Waveform::Table(index) => wavetables::get(index),
+            _ => None,
+        };
+        if table.is_none() && matches!(note.waveform, Waveform::Table(_)) {
+            note.waveform = Waveform::Sine;
+        }
+        Self {
+            note,
+            amplitude,
+            frames: frames_for(note.duration_ms),
+            position: 0,
+            phase: 0.0,
+            random: PERCUSSION_SEED,
+            previous: 0.0,
+            table,
+        }
+    }
+
+    /// Returns how many frames of the note are left to render.
+    #[must_use]
+    pub const fn remaining(&self) -> usize {
+        self.frames - self.position
+    }
+
+    /// Renders the next chunk of the note into `buffer` as interleaved stereo frames, returning how many frames were
+    /// produced. Zero means the note is over.
+    pub fn fill(&mut self, buffer: &mut [Sample]) -> usize {
+        let frames = self.remaining().min(buffer.len() / 2);
+        let output = buffer.chunks_exact_mut(2).take(frames);
+        let note = self.note;
+
+        match note.kind {
+            NoteKind::Percussion(drum) => {
+                for (i, frame) in (self.position..).zip(output) {
+                    let gain = envelope(i, self.frames, &PERCUSSION_ENVELOPE);
+                    #[allow(clippy::cast_possible_truncation)]
+                    let sample = (self.hit(drum, i) * self.amplitude * gain) as Sample;
+                    frame.fill(sample);
+                }
+            }
+            NoteKind::Tone if note.frequency > 0.0 => {
+                #[allow(clippy::cast_precision_loss)]
+                let base_step = note.frequency / SAMPLE_RATE as f32;
+                for (i, frame) in (self.position..).zip(output) {
+                    let step = base_step
+                        * vibrato_ratio(i, note.vibrato)
+                        * arpeggio_ratio(i, note.arpeggio);
+                    let gain = envelope(i, self.frames, &note.envelope) * tremolo(i, note.tremolo);
+                    let wave = self.table.map_or_else(
+                        || oscillator(note.waveform, self.phase, step, note.duty),
+                        |table| table.sample(self.phase),
+                    );
+                    #[allow(clippy::cast_possible_truncation)]
+                    let sample = (wave * self.amplitude * gain) as Sample;
+                    frame.fill(sample);
+                    self.phase = wrap(self.phase + step);
+                }
+            }
+            NoteKind::Tone => output.for_each(|frame| frame.fill(0)),
+        }
+
+        self.position += frames;
+        frames
+    }
+
+    /// Returns the value (-1.0 to 1.0) of a hit of `drum` at frame `index`, advancing the oscillator and noise.
+    fn hit(&mut self, drum: Percussion, index: usize) -> f32 {
+        #[allow(clippy::cast_precision_loss)]
+        let (seconds, sample_rate) = (index as f32 / SAMPLE_RATE as f32, SAMPLE_RATE as f32);
+        let decay = |time_constant: f32| libm::expf(-seconds / time_constant);
+        match drum {
+            Percussion::Kick => {
+                // The pitch drops from 150 Hz toward 50 Hz over the first few tens of milliseconds, which is the punch.
+                let frequency = 50.0 + 100.0 * decay(0.03);
+                let sample = libm::sinf(2.0 * core::f32::consts::PI * self.phase);
+                self.phase = wrap(self.phase + frequency / sample_rate);
+                sample * decay(0.06)
+            }
+            Percussion::Snare => {
+                let noise = white_noise(&mut self.random);
+                let body = libm::sinf(2.0 * core::f32::consts::PI * self.phase);
+                self.phase = wrap(self.phase + 180.0 / sample_rate);
+                0.7 * noise * decay(0.05) + 0.3 * body * decay(0.03)
+            }
+            Percussion::Hat => {
+                // The difference between successive noise values keeps the highs and drops the lows.
+                let white = white_noise(&mut self.random);
+                let bright = 0.5 * (white - self.previous);
+                self.previous = white;
+                bright * decay(0.02)
+            }
+        }
+    }
+}
+
+/// Renders a chiptune chunk by chunk, running each note straight into the next so there is no gap between them.
+///
+/// A chunk can span several notes, and a looping chiptune starts over for as long as it is asked for more. Each note
+/// plays at its own volume, or the chiptune's default one, scaled by the master volume. A chiptune with a second voice
+/// mixes the two at half level each so they never overflow, and only ends or starts over once both voices are done.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::synth::{amplitude, fill_tone, frames_for, ChiptuneGenerator};
+/// use catears::audio::{ChiptuneSequence, Note};
+///
+/// let notes = [Note::new(440.0, 10), Note::with_volume(660.0, 10, 255)];
+/// let sequence = ChiptuneSequence::from_notes(&notes);
+/// let mut chiptune = ChiptuneGenerator::new(&sequence, 200);
+///
+/// // Both notes fit in one buffer, back to back, just as if each were rendered on its own.
+/// let mut buffer = [0i16; 2 * 1000];
+/// assert_eq!(chiptune.fill(&mut buffer), 2 * frames_for(10));
+/// let mut expected = [0i16; 2 * 882];
+/// fill_tone(&mut expected, &notes[0], amplitude(sequence.default_volume, 200));
+/// fill_tone(&mut expected[2 * 441..], &notes[1], amplitude(255, 200));
+/// assert_eq!(buffer[..2 * 882], expected);
+/// assert_eq!(chiptune.fill(&mut buffer), 0);
+/// assert_eq!(chiptune.notes_started(), 2);
+///
+/// // A looping chiptune never runs out, unless it has nothing to play.
+/// let mut looping = sequence;
+/// looping.looping = true;
+/// let mut chiptune = ChiptuneGenerator::new(&looping, 200);
+/// for _ in 0..10 {
+///     assert_eq!(chiptune.fill(&mut buffer), 1000);
+/// }
+/// // Notes keep counting through the loops, 441 frames apiece.
+/// assert_eq!(chiptune.notes_started(), 23);
+/// let mut empty = ChiptuneSequence::from_notes(&[Note::rest(0)]);
+/// empty.looping = true;
+/// assert_eq!(ChiptuneGenerator::new(&empty, 200).fill(&mut buffer), 0);
+///
+/// // Two voices play on top of each other, each at half level, for as long as the longer one lasts.
+/// let bass = [Note::with_volume(110.0, 30, 255)];
+/// let duet = ChiptuneSequence::from_voices(&notes, &bass);
+/// let mut mixed = vec![0i16; 2 * 2000];
+/// assert_eq!(ChiptuneGenerator::new(&duet, 200).fill(&mut mixed), frames_for(30));
+/// let (mut high, mut low) = (vec![0i16; 2 * 2000], vec![0i16; 2 * 2000]);
+/// ChiptuneGenerator::new(&sequence, 200).fill(&mut high);
+/// fill_tone(&mut low, &bass[0], amplitude(255, 200));
+/// assert!((0..2 * frames_for(30)).all(|i| i32::from(mixed[i]) == (i32::from(high[i]) + i32::from(low[i])) / 2));
+///
+/// // Even two voices at full volume stay in range.
+/// let mut loud = ChiptuneSequence::from_voices(&[Note::with_volume(440.0, 500, 255)], &[Note::with_volume(440.0, 500, 255)]);
+/// loud.looping = true;
+/// let mut chiptune = ChiptuneGenerator::new(&loud, 255);
+/// for _ in 0..30 {
+///     assert_eq!(chiptune.fill(&mut buffer), 1000);
+///     assert!(buffer.iter().all(|&sample| f32::from(sample).abs() <= amplitude(255, 255)));
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChiptuneGenerator {
+    sequence: ChiptuneSequence,
+    master_volume: u8,
+    /// Progress through the first and second voices.
+    voices: [Voice; 2],
+}
+
+/// Progress through one voice of a chiptune.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Voice {
+    /// Index of the next note to start, within the voice.
+    next: usize,
+    /// Note currently playing.
+    tone: Option<ToneGenerator>,
+    /// Number of notes other than rests started so far, counting on through loops.
+    sounded: u32,
+}
+
+impl Voice {
+    const START: Self = Self {
+        next: 0,
+        tone: None,
+        sounded: 0,
+    };
+
+    /// Renders the voice into `buffer` until it runs out of notes, returning how many frames were produced.
+    fn fill(
+        &mut self,
+        buffer: &mut [Sample],
+        notes: &[Note],
+        default_volume: u8,
+        master_volume: u8,
+    ) -> usize {
+        let capacity = buffer.len() / 2;
+        let mut filled = 0;
+        while filled < capacity {
+            if let Some(tone) = &mut self.tone {
+                let frames = tone.fill(&mut buffer[2 * filled..]);
+                if frames > 0 {
+                    filled += frames;
+                    continue;
+                }
+            }
+            let Some(note) = notes.get(self.next) else {
+                self.tone = None;
+                break;
+            };
+            self.next += 1;
+            if !note.is_rest() {
+                self.sounded = self.sounded.wrapping_add(1);
+            }
+            let volume = note.volume.unwrap_or(default_volume);
+            self.tone = Some(ToneGenerator::new(note, amplitude(volume, master_volume)));
+        }
+        filled
+    }
+}
+
+/// Frames of the second voice rendered at a time before mixing it into the first.
+const MIX_FRAMES: usize = 256;
+
+impl ChiptuneGenerator {
+    /// Creates a new generator for `sequence` at `master_volume`, starting at its first note.
+    #[must_use]
+    pub const fn new(sequence: &ChiptuneSequence, master_volume: u8) -> Self {
+        Self {
+            sequence: *sequence,
+            master_volume,
+            voices: [Voice::START; 2],
+        }
+    }
+
+    /// Renders the next chunk of the chiptune into `buffer` as interleaved stereo frames, returning how many frames were
+    /// produced. Anything short of a full buffer means the chiptune is over.
+    pub fn fill(&mut self, buffer: &mut [Sample]) -> usize {
+        let capacity = buffer.len() / 2;
+        let mut filled = 0;
+        while filled < capacity {
+            filled += self.fill_voices(&mut buffer[2 * filled..]);
+            if filled < capacity && !self.restart() {
+                break;
+            }
+        }
+        filled
+    }
+
+    /// Renders both voices into `buffer` until they both run out of notes, returning how many frames were produced.
+    fn fill_voices(&mut self, buffer: &mut [Sample]) -> usize {
+        let [first, second] = self.sequence.voices();
+        let (default_volume, master_volume) = (self.sequence.default_volume, self.master_volume);
+        let [first_voice, second_voice] = &mut self.voices;
+        let mut filled = first_voice.fill(buffer, first, default_volume, master_volume);
+        if second.is_empty() {
+            return filled;
+        }
+
+        // Past the end of the first voice, the second plays over silence.
+        buffer[2 * filled..].fill(0);
+        let mut scratch = [0; 2 * MIX_FRAMES];
+        for (chunk, offset) in buffer
+            .chunks_mut(2 * MIX_FRAMES)
+            .zip((0..).step_by(MIX_FRAMES))
+        {
+            let scratch = &mut scratch[..chunk.len()];
+            let frames = second_voice.fill(scratch, second, default_volume, master_volume);
+            scratch[2 * frames..].fill(0);
+            for (sample, &other) in chunk.iter_mut().zip(scratch.iter()) {
+                // Two samples in range halve back into range, so the mix can never overflow.
+                #[allow(clippy::cast_possible_truncation)]
+                let mixed = ((i64::from(*sample) + i64::from(other)) / 2) as Sample;
+                *sample = mixed;
+            }
+            if frames > 0 {
+                filled = filled.max(offset + frames);
+            }
+        }
+        filled
+    }
+
+    /// Starts both voices from the top again if the chiptune loops. Returns `false` if there is nothing left to play.
+    fn restart(&mut self) -> bool {
+        // Looping a chiptune without a single frame in it would never return.
+        let [first, second] = self.sequence.voices();
+        let silent = first
+            .iter()
+            .chain(second)
+            .all(|note| frames_for(note.duration_ms) == 0);
+        if !self.sequence.looping || silent {
+            return false;
+        }
+        for voice in &mut self.voices {
+            *voice = Voice {
+                sounded: voice.sounded,
+                ..Voice::START
+            };
+        }
+        true
+    }
+
+    /// Returns how many notes other than rests the first voice has started so far, counting on through loops. A note
+    /// counts as started as soon as any of it is rendered.
+    #[must_use]
+    pub const fn notes_started(&self) -> u32 {
+        self.voices[0].sounded
+    }
+}
+
+/// Renders a playlist chunk by chunk, running the last note of each chiptune straight into the first of the next.
+///
+/// Each chiptune in the playlist plays once, whether or not it loops on its own, and a looping playlist starts over
+/// from its first chiptune.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::chiptunes::Preset;
+/// use catears::audio::synth::{frames_for, ChiptuneGenerator, PlaylistGenerator};
+/// use catears::audio::Playlist;
+///
+/// let length = |preset: Preset| {
+///     let sequence = preset.sequence();
+///     sequence.notes[..usize::from(sequence.length)].iter().map(|note| frames_for(note.duration_ms)).sum::<usize>()
+/// };
+///
+/// // The second chiptune picks up right where the first left off, in the middle of a buffer.
+/// let playlist = Playlist::from_presets(&[Preset::Coin, Preset::Alert]);
+/// let mut generator = PlaylistGenerator::new(&playlist, 255);
+/// let total = length(Preset::Coin) + length(Preset::Alert);
+/// let mut whole = vec![0i16; 2 * total];
+/// assert_eq!(generator.fill(&mut whole), total);
+/// assert_eq!(generator.fill(&mut whole), 0);
+///
+/// let mut expected = vec![0i16; 2 * total];
+/// let coin = ChiptuneGenerator::new(&Preset::Coin.sequence(), 255).fill(&mut expected);
+/// ChiptuneGenerator::new(&Preset::Alert.sequence(), 255).fill(&mut expected[2 * coin..]);
+/// assert_eq!(whole, expected);
+///
+/// // A looping playlist goes around again.
+/// let mut generator = PlaylistGenerator::new(&playlist.with_loop(), 255);
+/// for _ in 0..10 {
+///     assert_eq!(generator.fill(&mut whole), total);
+/// }
+/// assert_eq!(whole, expected);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaylistGenerator {
+    playlist: Playlist,
+    master_volume: u8,
+    /// Index of the chiptune playing.
+    index: usize,
+    /// Chiptune playing, or `None` once the playlist is over.
+    chiptune: Option<ChiptuneGenerator>,
+}
+
+impl PlaylistGenerator {
+    /// Creates a new generator for `playlist` at `master_volume`, starting at its first chiptune.
+    #[must_use]
+    pub fn new(playlist: &Playlist, master_volume: u8) -> Self {
+        let chiptune = playlist
+            .tunes()
+            .first()
+            .map(|&preset| Self::chiptune(preset, master_volume));
+        Self {
+            playlist: *playlist,
+            master_volume,
+            index: 0,
+            chiptune,
+        }
+    }
+
+    /// Renders the next chunk of the playlist into `buffer` as interleaved stereo frames, returning how many frames
+    /// were produced. Anything short of a full buffer means the playlist is over.
+    pub fn fill(&mut self, buffer: &mut [Sample]) -> usize {
+        let capacity = buffer.len() / 2;
+        let mut filled = 0;
+        // Going all the way around a looping playlist without a single frame would never return.
+        let mut silent = 0;
+        while let Some(chiptune) = &mut self.chiptune {
+            let frames = chiptune.fill(&mut buffer[2 * filled..]);
+            filled += frames;
+            if filled == capacity {
+                break;
+            }
+            silent = if frames == 0 { silent + 1 } else { 0 };
+
+            let tunes = self.playlist.tunes();
+            self.index += 1;
+            if self.index == tunes.len() && self.playlist.looping && silent < tunes.len() {
+                self.index = 0;
+            }
+            self.chiptune = tunes
+                .get(self.index)
+                .map(|&preset| Self::chiptune(preset, self.master_volume));
+        }
+        filled
+    }
+
+    /// Returns a generator that plays the chiptune of `preset` once.
+    fn chiptune(preset: Preset, master_volume: u8) -> ChiptuneGenerator {
+        let mut sequence = preset.sequence();
+        sequence.looping = false;
+        ChiptuneGenerator::new(&sequence, master_volume)
+    }
+}
+
+/// Renders a sweep chunk by chunk, see [`Sweep`].
+///
+/// The frequency moves a little every frame while the phase runs on, so the pitch glides instead of stepping and
+/// chirping. A single sweep fades in and out with the default envelope of a note. A repeating one only fades in, and
+/// then turns around at each end for as long as it is asked for more.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::synth::{amplitude, SweepGenerator};
+/// use catears::audio::Sweep;
+///
+/// let spacings = |buffer: &[i16]| {
+///     let rising: Vec<usize> = (1..buffer.len() / 2)
+///         .filter(|&i| buffer[2 * (i - 1)] < 0 && buffer[2 * i] >= 0)
+///         .collect();
+///     let spacings: Vec<usize> = rising.windows(2).map(|pair| pair[1] - pair[0]).collect();
+///     (*spacings.iter().min().unwrap(), *spacings.iter().max().unwrap())
+/// };
+/// let loudest = amplitude(255, 255);
+///
+/// // An octave up over a second, starting and ending at the right pitch.
+/// let mut sweep = SweepGenerator::new(&Sweep::new(441.0, 882.0, 1000), loudest);
+/// let mut second = vec![0i16; 2 * 50_000];
+/// assert_eq!(sweep.fill(&mut second), 44_100);
+/// assert_eq!(sweep.fill(&mut second[..2 * 4096]), 0);
+/// let (_, longest) = spacings(&second[..2 * 2205]);
+/// assert!((99..=101).contains(&longest));
+/// let (shortest, _) = spacings(&second[2 * 41_895..2 * 44_100]);
+/// assert!((49..=51).contains(&shortest));
+///
+/// // The phase runs on as the pitch moves, so the wave never jumps.
+/// let max_step = loudest * 2.0 * core::f32::consts::PI * 882.0 / 44_100.0;
+/// assert!(second[..2 * 44_100]
+///     .chunks(2)
+///     .zip(second.chunks(2).skip(1))
+///     .all(|(a, b)| (f32::from(b[0]) - f32::from(a[0])).abs() <= max_step + 1.0));
+///
+/// // A repeating sweep never runs out, and is back down to where it started after going up and down once.
+/// let mut siren = SweepGenerator::new(&Sweep::new(441.0, 882.0, 1000).with_repeat(true), loudest);
+/// assert_eq!(siren.fill(&mut second[..2 * 44_100]), 44_100);
+/// assert_eq!(siren.fill(&mut second[..2 * 44_100]), 44_100);
+/// let (_, longest) = spacings(&second[2 * 41_895..2 * 44_100]);
+/// assert!((99..=101).contains(&longest));
+/// for _ in 0..10 {
+///     assert_eq!(siren.fill(&mut second), 50_000);
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SweepGenerator {
+    sweep: Sweep,
+    amplitude: f32,
+    /// Length of a single sweep in frames.
+    frames: usize,
+    /// Frames into the current sweep up and back down, or into the only sweep if it does not repeat.
+    position: usize,
+    /// Frames rendered so far, up to the end of the fade in.
+    elapsed: usize,
+    /// Phase of the oscillator, from 0.0 to 1.0.
+    phase: f32,
+}
+
+impl SweepGenerator {
+    /// Creates a new generator for `sweep` at `amplitude`, starting at its start frequency.
+    #[must_use]
+    pub fn new(sweep: &Sweep, amplitude: f32) -> Self {
+        Self {
+            sweep: *sweep,
+            amplitude,
+            frames: frames_for(sweep.duration_ms),
+            position: 0,
+            elapsed: 0,
+            phase: 0.0,
+        }
+    }
+
+    /// Renders the next chunk of the sweep into `buffer` as interleaved stereo frames, returning how many frames were
+    /// produced. Zero means the sweep is over.
+    pub fn fill(&mut self, buffer: &mut [Sample]) -> usize {
+        let capacity = buffer.len() / 2;
+        let frames = if self.sweep.repeat && self.frames > 0 {
+            capacity
+        } else {
+            self.frames.saturating_sub(self.position).min(capacity)
+        };
+        let fade_in = frames_for(Envelope::DEFAULT.attack_ms);
+
+        for frame in buffer.chunks_exact_mut(2).take(frames) {
+            // Each sweep back down mirrors the one up before it.
+            let into = self.position % self.frames;
+            let into = if self.position / self.frames % 2 == 1 {
+                self.frames - into
+            } else {
+                into
+            };
+            #[allow(clippy::cast_precision_loss)]
+            let frequency = self.sweep.start_hz
+                + (self.sweep.end_hz - self.sweep.start_hz) * into as f32 / self.frames as f32;
+            let gain = if self.sweep.repeat {
+                envelope(self.elapsed, usize::MAX, &Envelope::DEFAULT)
+            } else {
+                envelope(self.position, self.frames, &Envelope::DEFAULT)
+            };
+            #[allow(clippy::cast_possible_truncation)]
+            let sample = (libm::sinf(2.0 * core::f32::consts::PI * self.phase)
+                * self.amplitude
+                * gain) as Sample;
+            frame.fill(sample);
+
+            #[allow(clippy::cast_precision_loss)]
+            let step = frequency / SAMPLE_RATE as f32;
+            self.phase = wrap(self.phase + step);
+            self.position += 1;
+            if self.sweep.repeat && self.position == 2 * self.frames {
+                self.position = 0;
+            }
+            self.elapsed = (self.elapsed + 1).min(fade_in);
+        }
+        frames
+    }
+}
+
+/// Renders noise chunk by chunk, for as long as it is asked for more.
+///
+/// The noise comes from a xorshift generator, which is plenty random to the ear, and is smoothed by a one-pole low-pass
+/// filter. Smoothing takes energy out along with the highs, so smoothed noise is boosted back up to about as loud as
+/// white noise at the same amplitude.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::synth::{amplitude, NoiseGenerator};
+/// use catears::audio::Noise;
+///
+/// let rms = |samples: &[i16]| {
+///     (samples.iter().map(|&s| f64::from(s).powi(2)).sum::<f64>() / samples.len() as f64).sqrt()
+/// };
+/// let roughness = |samples: &[i16]| {
+///     samples.windows(2).map(|pair| (f64::from(pair[1]) - f64::from(pair[0])).abs()).sum::<f64>()
+/// };
+/// let loudest = amplitude(255, 255);
+/// let mut buffer = [0i16; 2 * 4096];
+///
+/// // White noise never runs out, stays within its amplitude, and plays the same on both channels.
+/// let mut white = NoiseGenerator::new(&Noise::new(255), 255, 1);
+/// for _ in 0..10 {
+///     assert_eq!(white.fill(&mut buffer), 4096);
+/// }
+/// assert!(buffer.iter().all(|&sample| f32::from(sample).abs() <= loudest));
+/// assert!(buffer.chunks(2).all(|frame| frame[0] == frame[1]));
+/// let left: Vec<i16> = buffer.iter().step_by(2).copied().collect();
+/// let white_rms = rms(&left);
+/// assert!((white_rms / (f64::from(loudest) / 3f64.sqrt()) - 1.0).abs() < 0.1);
+///
+/// // Smoothed noise is about as loud, but far less jagged.
+/// let mut rumble = NoiseGenerator::new(&Noise::new(255).with_smoothing(240), 255, 1);
+/// rumble.fill(&mut buffer);
+/// rumble.fill(&mut buffer);
+/// let smooth: Vec<i16> = buffer.iter().step_by(2).copied().collect();
+/// assert!((rms(&smooth) / white_rms - 1.0).abs() < 0.3);
+/// assert!(roughness(&smooth) * 4.0 < roughness(&left));
+///
+/// // A wobble at 25 Hz starts silent and peaks 20 ms in.
+/// let mut purr = NoiseGenerator::new(&Noise::new(255).with_wobble(25.0), 255, 1);
+/// purr.fill(&mut buffer);
+/// let purr: Vec<i16> = buffer.iter().step_by(2).copied().collect();
+/// assert!(rms(&purr[..100]) * 10.0 < rms(&purr[832..932]));
+///
+/// // Different seeds give different noise, and the master volume scales it.
+/// let mut other = [0i16; 2 * 4096];
+/// NoiseGenerator::new(&Noise::new(255), 255, 2).fill(&mut other);
+/// NoiseGenerator::new(&Noise::new(255), 255, 1).fill(&mut buffer);
+/// assert_ne!(buffer, other);
+/// NoiseGenerator::new(&Noise::new(255), 0, 1).fill(&mut buffer);
+/// assert!(buffer.iter().all(|&sample| sample == 0));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoiseGenerator {
+    noise: Noise,
+    amplitude: f32,
+    /// State of the xorshift generator, never zero.
+    random: u32,
+    /// Output of the low-pass filter.
+    filtered: f32,
+    /// Phase of the wobble, from 0.0 to 1.0.
+    phase: f32,
+}
+
+impl NoiseGenerator {
+    /// Creates a new generator for `noise` at `master_volume`, with its randomness seeded from `seed`.
+    #[must_use]
+    pub fn new(noise: &Noise, master_volume: u8, seed: u32) -> Self {
+        Self {
+            noise: *noise,
+            amplitude: amplitude(noise.amplitude, master_volume),
+            random: seed.max(1),
+            filtered: 0.0,
+            phase: 0.0,
+        }
+    }
+
+    /// Renders the next chunk of noise into `buffer` as interleaved stereo frames, returning how many frames were
+    /// produced, which is always all of them.
+    pub fn fill(&mut self, buffer: &mut [Sample]) -> usize {
+        // Each output sample moves this fraction of the way toward the new random one.
+        let follow = 1.0 - f32::from(self.noise.smoothing) / 256.0;
+        // Filtering white noise like this divides its power by (2 - follow) / follow.
+        let boost = libm::sqrtf((2.0 - follow) / follow);
+        #[allow(clippy::cast_precision_loss)]
+        let step = self.noise.wobble_hz / SAMPLE_RATE as f32;
+
+        let mut frames = 0;
+        for frame in buffer.chunks_exact_mut(2) {
+            let white = white_noise(&mut self.random);
+            self.filtered += follow * (white - self.filtered);
+
+            let gain = if step > 0.0 {
+                let gain = 0.5 - 0.5 * libm::cosf(2.0 * core::f32::consts::PI * self.phase);
+                self.phase = wrap(self.phase + step);
+                gain
+            } else {
+                1.0
+            };
+            #[allow(clippy::cast_possible_truncation)]
+            let sample =
+                ((self.filtered * boost).clamp(-1.0, 1.0) * self.amplitude * gain) as Sample;
+            frame.fill(sample);
+            frames += 1;
+        }
+        frames
+    }
+}
+
+/// Time in milliseconds the speakers take to ramp the master volume all the way from silence to full, or back.
+pub const VOLUME_RAMP_MS: u16 = 100;
+
+/// Master volume as the speakers play it, which slews toward the volume they are set to instead of jumping there.
+///
+/// A step in volume in the middle of a wave clicks, and so does cutting a wave off. Audio is rendered at full volume
+/// and then scaled frame by frame as the ramp moves toward its target, which takes [`VOLUME_RAMP_MS`] from silence to
+/// full volume and proportionally less for smaller changes.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::synth::{frames_for, VolumeRamp};
+///
+/// let mut ramp = VolumeRamp::new(255, 100);
+/// let mut buffer = [10_000i16; 2 * 8192];
+/// ramp.apply(&mut buffer, 255);
+/// assert!(buffer.iter().all(|&sample| sample == 10_000));
+///
+/// // Turning it down does not happen at once, it is halfway there after half of the ramp.
+/// ramp.apply(&mut buffer[..2 * frames_for(50)], 0);
+/// assert!((ramp.volume() - 127.5).abs() < 0.5);
+/// let left: Vec<i16> = buffer[..2 * frames_for(50)].iter().step_by(2).copied().collect();
+/// assert!(left.windows(2).all(|pair| pair[1] <= pair[0] && pair[0] - pair[1] <= 3));
+/// assert!((left[frames_for(50) - 1] - 5_000).abs() < 10);
+///
+/// // Then it stays silent once it gets there.
+/// buffer.fill(10_000);
+/// ramp.apply(&mut buffer, 0);
+/// assert_eq!(ramp.volume(), 0.0);
+/// assert!(buffer[2 * frames_for(50)..].iter().all(|&sample| sample == 0));
+///
+/// // Smaller changes take proportionally less time, and both channels follow the same ramp.
+/// buffer.fill(-10_000);
+/// ramp.apply(&mut buffer[..2 * frames_for(20)], 51);
+/// assert!((ramp.volume() - 51.0).abs() < 0.5);
+/// assert!(buffer.chunks(2).all(|frame| frame[0] == frame[1]));
+/// buffer.fill(-10_000);
+/// ramp.apply(&mut buffer, 51);
+/// assert_eq!(ramp.volume(), 51.0);
+/// assert!(buffer.iter().all(|&sample| (sample + 2_000).abs() <= 1));
+///
+/// // Without a ramp at all, the volume jumps straight to its target.
+/// let mut instant = VolumeRamp::new(255, 0);
+/// instant.apply(&mut buffer[..2], 0);
+/// assert_eq!(instant.volume(), 0.0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VolumeRamp {
+    /// Current volume, from 0.0 to 255.0.
+    volume: f32,
+    /// Most the volume moves in a frame.
+    step: f32,
+}
+
+impl VolumeRamp {
+    /// Creates a new ramp that starts at `volume` and takes `full_scale_ms` to go from silence to full volume.
+    #[must_use]
+    pub fn new(volume: u8, full_scale_ms: u16) -> Self {
+        #[allow(clippy::cast_precision_loss)]
+        let step = 255.0 / frames_for(full_scale_ms).max(1) as f32;
+        Self {
+            volume: f32::from(volume),
+            step,
+        }
+    }
+
+    /// Returns the current volume, from 0.0 to 255.0.
+    #[must_use]
+    pub const fn volume(&self) -> f32 {
+        self.volume
+    }
+
+    /// Scales the interleaved stereo frames in `buffer` by the volume, moving it a step toward `target` every frame.
+    pub fn apply(&mut self, buffer: &mut [Sample], target: u8) {
+        let target = f32::from(target);
+        for frame in buffer.chunks_exact_mut(2) {
+            self.volume = if self.volume < target {
+                (self.volume + self.step).min(target)
+            } else {
+                (self.volume - self.step).max(target)
+            };
+            let gain = self.volume / 255.0;
+            for sample in frame {
+                // Scaling down by at most one never leaves the range of a sample.
+                #[allow(clippy::cast_possible_truncation)]
+                {
+                    *sample = (to_float(*sample) * gain) as Sample;
+                }
+            }
+        }
+    }
+}
+
+/// Sample rates in Hz the speakers can be switched to for a clip, see [`output_rate`].
+pub const OUTPUT_RATES: core::ops::RangeInclusive<u32> = 8_000..=48_000;
+
+/// Returns the sample rate in Hz to run the speakers at while playing `mode`.
+///
+/// A clip plays at its own rate, as long as the speakers can run at it and its format is supported, which saves
+/// resampling it. Everything else is synthesized at [`SAMPLE_RATE`]. A clip rendered at its own rate is rendered as if
+/// it were at [`SAMPLE_RATE`], which is a sample per frame, see [`Clip::native`].
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::synth::{output_rate, SAMPLE_RATE};
+/// use catears::audio::{Clip, Mode, Note};
+///
+/// static DATA: [u8; 8] = [128; 8];
+/// assert_eq!(output_rate(&Mode::Audio(Clip::mono_8bit(&DATA, 8000))), 8000);
+/// assert_eq!(output_rate(&Mode::Audio(Clip::mono_16bit(&DATA, 22_050))), 22_050);
+/// assert_eq!(output_rate(&Mode::Tone(Note::new(440.0, 100))), SAMPLE_RATE);
+///
+/// // Rates the speakers cannot run at, and clips that cannot play, stay at the synthesis rate.
+/// assert_eq!(output_rate(&Mode::Audio(Clip::mono_8bit(&DATA, 4000))), SAMPLE_RATE);
+/// assert_eq!(output_rate(&Mode::Audio(Clip::new(&DATA, 8000, 12, false))), SAMPLE_RATE);
+/// ```
+#[must_use]
+pub fn output_rate(mode: &Mode) -> u32 {
+    match mode {
+        Mode::Audio(clip) if OUTPUT_RATES.contains(&clip.sample_rate) && clip_frames(clip) > 0 => {
+            clip.sample_rate
+        }
+        _ => SAMPLE_RATE,
+    }
+}
+
+/// Returns the number of output frames a clip lasts at [`SAMPLE_RATE`], or zero if its format is not supported.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::synth::clip_frames;
+/// use catears::audio::Clip;
+///
+/// static DATA: [u8; 8000] = [128; 8000];
+/// assert_eq!(clip_frames(&Clip::mono_8bit(&DATA, 8000)), 44_100);
+/// assert_eq!(clip_frames(&Clip::mono_16bit(&DATA, 8000)), 22_050);
+/// assert_eq!(clip_frames(&Clip::new(&DATA, 8000, 12, false)), 0);
+/// ```
+#[must_use]
+pub fn clip_frames(clip: &Clip) -> usize {
+    if !matches!(clip.bits_per_sample, 8 | 16) || clip.sample_rate == 0 {
+        return 0;
+    }
+    let frames =
+        u64::from(clip.sample_count()) * u64::from(SAMPLE_RATE) / u64::from(clip.sample_rate);
+    usize::try_from(frames).unwrap_or(usize::MAX)
+}
+
+/// Renders a clip into `buffer` as interleaved stereo frames, starting `start` output frames into the clip, and returns
+/// how many frames were produced. Zero means the clip is over, which a looping clip never is.
+///
+/// The clip is resampled to [`SAMPLE_RATE`] by interpolating linearly between its samples. Each output frame is placed
+/// in the clip from its own index, so there is no fraction to carry over from one buffer to the next and no drift. Past
+/// the last sample, a looping clip glides into its [`Clip::loop_start`] and any other clip holds its last. A looping
+/// clip wraps around in the middle of the buffer and always fills it, so there is no gap at the seam. 8-bit samples are taken
+/// as unsigned and 16-bit samples as signed little-endian, and a mono clip plays on both channels. The samples are
+/// scaled by `volume`.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::synth::{clip_frames, fill_clip};
+/// use catears::audio::Clip;
+///
+/// // An 8-bit mono clip at a tenth of the output rate, from silence to the extremes.
+/// static DATA: [u8; 3] = [128, 255, 0];
+/// let clip = Clip::mono_8bit(&DATA, 4410);
+/// let mut buffer = [1i16; 2 * 16];
+/// assert_eq!(fill_clip(&mut buffer, &clip, 0, 255), 16);
+/// assert_eq!(buffer[..2], [0, 0]);
+/// assert_eq!(buffer[2 * 10..2 * 11], [127 << 8, 127 << 8]);
+///
+/// // In between its samples, the clip moves evenly from one to the next.
+/// assert_eq!(buffer[2 * 5..2 * 6], [(127 << 8) / 2, (127 << 8) / 2]);
+/// assert!(buffer.chunks(2).take(10).zip(buffer.chunks(2).skip(1)).all(|(a, b)| b[0] > a[0]));
+/// assert!(buffer.chunks(2).skip(10).zip(buffer.chunks(2).skip(11)).all(|(a, b)| b[0] < a[0]));
+///
+/// // The rest of the clip comes in the next call, and then it is over.
+/// assert_eq!(fill_clip(&mut buffer, &clip, 16, 255), clip_frames(&clip) - 16);
+/// assert_eq!(buffer[2 * 4..2 * 5], [-128 << 8, -128 << 8]);
+/// assert_eq!(fill_clip(&mut buffer, &clip, clip_frames(&clip), 255), 0);
+///
+/// // After the last sample, the clip holds it, unless it loops back to the first.
+/// assert_eq!(buffer[2 * 13..2 * 14], [-128 << 8, -128 << 8]);
+/// fill_clip(&mut buffer, &clip.with_loop(), 16, 255);
+/// assert_eq!(buffer[2 * 9..2 * 10], [-128 << 7, -128 << 7]);
+///
+/// // Whatever the size of the buffers, the clip comes out the same.
+/// let mut whole = [0i16; 2 * 30];
+/// fill_clip(&mut whole, &clip, 0, 255);
+/// let mut pieces = [0i16; 2 * 30];
+/// for start in (0..30).step_by(7) {
+///     fill_clip(&mut pieces[2 * start..], &clip, start, 255);
+/// }
+/// assert_eq!(whole, pieces);
+///
+/// // A looping clip comes around seamlessly, even when its length is not a whole number of frames, and whatever the
+/// // size of the buffers. 8000 Hz puts five samples 27.5625 frames apart.
+/// static LOOP: [u8; 5] = [0, 64, 128, 192, 255];
+/// let looping = Clip::mono_8bit(&LOOP, 8000).with_loop();
+/// let mut whole = [0i16; 2 * 200];
+/// assert_eq!(fill_clip(&mut whole, &looping, 0, 255), 200);
+/// for size in [1, 7, 27, 28, 64] {
+///     let mut pieces = [0i16; 2 * 200];
+///     for start in (0..200).step_by(size) {
+///         let end = (start + size).min(200);
+///         assert_eq!(fill_clip(&mut pieces[2 * start..2 * end], &looping, start, 255), end - start);
+///     }
+///     assert_eq!(whole, pieces);
+/// }
+/// // Frame 441 lands exactly on the 80th sample, which is the first one again.
+/// assert_eq!(whole[..2], [-128 << 8, -128 << 8]);
+/// let mut later = [0i16; 2];
+/// fill_clip(&mut later, &looping, 441, 255);
+/// assert_eq!(later, whole[..2]);
+///
+/// // With a loop start, the first samples play once and then the rest repeats.
+/// let sustained = Clip::mono_8bit(&DATA, 4410).with_loop_from(1);
+/// let mut buffer = [0i16; 2 * 60];
+/// fill_clip(&mut buffer, &sustained, 0, 255);
+/// assert_eq!(buffer[2 * 20..2 * 21], [-128 << 8, -128 << 8]);
+/// assert_eq!(buffer[2 * 30..2 * 31], [127 << 8, 127 << 8]);
+/// assert_eq!(buffer[2 * 25..2 * 26], buffer[2 * 45..2 * 46]);
+/// assert!(buffer[..2 * 10].chunks(2).all(|frame| frame[0] < 127 << 8));
+/// assert!(buffer[2 * 30..].chunks(2).all(|frame| frame[0] != 0 || frame[1] != 0));
+///
+/// // A 16-bit stereo clip keeps its channels apart, scaled by the volume.
+/// static STEREO: [u8; 4] = [0x00, 0x40, 0x00, 0xC0];
+/// let clip = Clip::new(&STEREO, 44_100, 16, true);
+/// assert_eq!(fill_clip(&mut buffer, &clip, 0, 255), 1);
+/// assert_eq!(buffer[..2], [0x4000, -0x4000]);
+/// fill_clip(&mut buffer, &clip, 0, 0);
+/// assert_eq!(buffer[..2], [0, 0]);
+/// ```
+#[must_use]
+pub fn fill_clip(buffer: &mut [Sample], clip: &Clip, start: usize, volume: u8) -> usize {
+    let total = clip_frames(clip);
+    let count = usize::try_from(clip.sample_count()).unwrap_or(usize::MAX);
+    let looping = clip.looping && total > 0;
+    let frames = if looping {
+        buffer.len() / 2
+    } else {
+        total.saturating_sub(start).min(buffer.len() / 2)
+    };
+    let bytes_per_sample = usize::from(clip.bits_per_sample / 8);
+    let channels = if clip.is_stereo { 2 } else { 1 };
+    let loop_start = usize::try_from(clip.loop_start)
+        .ok()
+        .filter(|&loop_start| loop_start < count)
+        .unwrap_or(0);
+    let sample = |source: usize, channel: usize| -> i64 {
+        let offset = (source * channels + channel) * bytes_per_sample;
+        let raw = match bytes_per_sample {
+            1 => clip
+                .data
+                .get(offset)
+                .map_or(0, |&byte| (i16::from(byte) - 128) << 8),
+            _ => clip
+                .data
+                .get(offset..offset + 2)
+                .map_or(0, |bytes| i16::from_le_bytes([bytes[0], bytes[1]])),
+        };
+        i64::from(widen(raw))
+    };
+
+    // Positions count in steps of 1 / rate of a sample.
+    let rate = u64::from(SAMPLE_RATE);
+    let end = u64::try_from(count).unwrap_or(u64::MAX) * rate;
+    let loop_from = u64::try_from(loop_start).unwrap_or(0) * rate;
+    for (i, frame) in buffer.chunks_exact_mut(2).take(frames).enumerate() {
+        let mut position =
+            u64::try_from(start + i).unwrap_or(u64::MAX) * u64::from(clip.sample_rate);
+        if looping && position >= end {
+            position = loop_from + (position - end) % (end - loop_from);
+        }
+        // The output frame falls `remainder / rate` of the way from sample `source` to the next.
+        let source = usize::try_from(position / rate).unwrap_or(usize::MAX);
+        let remainder = i64::try_from(position % rate).unwrap_or(0);
+        let next = if source + 1 < count {
+            source + 1
+        } else if looping {
+            loop_start
+        } else {
+            source
+        };
+        for (output, channel) in frame.iter_mut().zip([0, channels - 1]) {
+            let (from, to) = (sample(source, channel), sample(next, channel));
+            let interpolated = from + (to - from) * remainder / i64::from(SAMPLE_RATE);
+            // Between two samples and scaled down by at most one, this never leaves the range of a sample.
+            #[allow(clippy::cast_possible_truncation)]
+            {
+                *output = (interpolated * i64::from(volume) / 255) as Sample;
+            }
+        }
+    }
+
+    frames
+}
+
+/// Returns after how many output frames a looping clip comes back around to exactly the same point, counting from once
+/// it has looped for the first time, or zero if it does not loop.
+///
+/// A position in a looping clip can be moved back by this many frames without changing what [`fill_clip`] renders
+/// there, which keeps it from growing without end.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::synth::{clip_frames, clip_loop_frames, fill_clip};
+/// use catears::audio::Clip;
+///
+/// static DATA: [u8; 5] = [0, 64, 128, 192, 255];
+/// let clip = Clip::mono_8bit(&DATA, 8000);
+/// assert_eq!(clip_loop_frames(&clip), 0);
+///
+/// // Five samples at 8000 Hz last 27.5625 frames, so the frames only line up with the samples again every 441.
+/// assert_eq!(clip_loop_frames(&clip.with_loop()), 441);
+/// assert_eq!(clip_loop_frames(&clip.with_loop_from(3)), 441);
+/// assert_eq!(clip_loop_frames(&Clip::mono_8bit(&DATA, 4410).with_loop()), 50);
+///
+/// let clip = clip.with_loop_from(3);
+/// let position = clip_frames(&clip) + 1000;
+/// let (mut here, mut there) = ([0i16; 2 * 64], [0i16; 2 * 64]);
+/// fill_clip(&mut here, &clip, position, 255);
+/// fill_clip(&mut there, &clip, position - clip_loop_frames(&clip), 255);
+/// assert_eq!(here, there);
+/// ```
+#[must_use]
+pub fn clip_loop_frames(clip: &Clip) -> usize {
+    if !clip.looping || clip_frames(clip) == 0 {
+        return 0;
+    }
+    let count = u64::from(clip.sample_count());
+    let loop_start = if u64::from(clip.loop_start) < count {
+        u64::from(clip.loop_start)
+    } else {
+        0
+    };
+    // The loop comes around once the frames have stepped through a whole number of loops.
+    let length = (count - loop_start) * u64::from(SAMPLE_RATE);
+    let (mut a, mut b) = (length, u64::from(clip.sample_rate));
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    usize::try_from(length / a).unwrap_or(0)
+}
@@ -0,0 +1,260 @@
+//! Parsing of WAV files into audio clips.
+//!
+//! A WAV file is a RIFF container: the magic `RIFF`, the size of the rest of the file, and the form type `WAVE`,
+//! followed by chunks that each start with a four-letter id and the size of their contents, padded to an even length.
+//! The `fmt ` chunk describes the samples and the `data` chunk holds them, and any other chunk, such as the metadata
+//! audio editors like to add, is skipped.
+//!
+//! Only uncompressed PCM can be played, in 8-bit or 16-bit samples and one or two channels. The parser runs in a
+//! `const` context, so a clip embedded with `include_bytes!` can be checked when the firmware is built:
+//!
+//! ```rust,ignore
+//! const MEOW: Clip = match Clip::from_wav(include_bytes!("../assets/meow.wav")) {
+//!     Ok(clip) => clip,
+//!     Err(_) => panic!("meow.wav is not a WAV file the speakers can play"),
+//! };
+//! ```
+
+use super::Clip;
+
+/// Why a WAV file could not be parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Error {
+    /// The file does not start with a RIFF header of the `WAVE` form.
+    Riff,
+    /// A chunk runs past the end of the file.
+    Truncated,
+    /// The file has no `fmt ` chunk, or it is too short.
+    Format,
+    /// The samples are compressed, or in a format other than integer PCM.
+    Compressed,
+    /// The samples are not 8-bit or 16-bit, there are more than two channels, or the sample rate is zero.
+    Unsupported,
+    /// The file has no `data` chunk.
+    NoData,
+}
+
+/// Format tag of integer PCM samples.
+const PCM: u16 = 1;
+
+/// Format tag of a `fmt ` chunk that names its actual format further in, in the first two bytes of a GUID.
+const EXTENSIBLE: u16 = 0xFFFE;
+
+/// Reads a little-endian `u16` at `offset`, which must leave room for it.
+const fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+/// Reads a little-endian `u32` at `offset`, which must leave room for it.
+const fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+/// Returns the four-letter chunk id `id` as it reads from a file.
+const fn fourcc(id: [u8; 4]) -> u32 {
+    u32::from_le_bytes(id)
+}
+
+/// Format and whereabouts of the samples in a WAV file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    /// Number of channels, 1 or 2.
+    pub channels: u16,
+    /// Sample rate in Hz.
+    pub sample_rate: u32,
+    /// Number of bits per sample, 8 or 16.
+    pub bits_per_sample: u16,
+    /// Offset of the first sample from the start of the file.
+    pub data_start: usize,
+    /// Length of the samples in bytes as the `data` chunk declares it, which may run past the end of the file.
+    pub data_len: usize,
+}
+
+/// Parses the header of a WAV file, everything up to its samples. See [`crate::audio::wav`] for the formats it
+/// accepts.
+///
+/// Only the start of the file is needed, as long as it reaches past the `data` chunk header, so the header of a file
+/// that is still downloading can be parsed from what has arrived. Any chunk before the samples has to be complete.
+///
+/// # Errors
+///
+/// Returns an error if the file is not a WAV file, or if its samples are in a format the speakers cannot play.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::wav::{parse_header, Error};
+///
+/// let start = b"RIFF\xFF\xFF\xFF\xFFWAVEfmt \x10\0\0\0\x01\0\x01\0\x40\x1F\0\0\x40\x1F\0\0\x01\0\x08\0\
+///               data\0\0\x01\0\x80\x80";
+/// let header = parse_header(start).unwrap();
+/// assert_eq!((header.channels, header.sample_rate, header.bits_per_sample), (1, 8000, 8));
+/// assert_eq!((header.data_start, header.data_len), (44, 0x10000));
+///
+/// // Until the `data` chunk header has arrived, there is nothing to play.
+/// assert_eq!(parse_header(&start[..40]), Err(Error::NoData));
+/// ```
+pub const fn parse_header(data: &[u8]) -> Result<Header, Error> {
+    if data.len() < 12
+        || read_u32(data, 0) != fourcc(*b"RIFF")
+        || read_u32(data, 8) != fourcc(*b"WAVE")
+    {
+        return Err(Error::Riff);
+    }
+
+    // Channels, sample rate, and bits per sample, once the `fmt ` chunk turns up.
+    let mut format: Option<(u16, u32, u16)> = None;
+    // Offset and declared size of the samples, once the `data` chunk turns up.
+    let mut samples: Option<(usize, usize)> = None;
+    let mut offset = 12;
+    while offset + 8 <= data.len() {
+        let id = read_u32(data, offset);
+        let size = read_u32(data, offset + 4) as usize;
+        let start = offset + 8;
+        let available = data.len() - start;
+
+        if id == fourcc(*b"data") {
+            samples = Some((start, size));
+        } else if size > available {
+            return Err(Error::Truncated);
+        } else if id == fourcc(*b"fmt ") {
+            if size < 16 {
+                return Err(Error::Format);
+            }
+            let mut tag = read_u16(data, start);
+            if tag == EXTENSIBLE {
+                // The actual format starts the GUID after the size of the extension, valid bits, and channel mask.
+                if size < 26 {
+                    return Err(Error::Format);
+                }
+                tag = read_u16(data, start + 24);
+            }
+            if tag != PCM {
+                return Err(Error::Compressed);
+            }
+            format = Some((
+                read_u16(data, start + 2),
+                read_u32(data, start + 4),
+                read_u16(data, start + 14),
+            ));
+        }
+
+        // Chunks are padded to an even length. A truncated data chunk is the last one, so this ends the loop.
+        offset = start.saturating_add(size).saturating_add(size % 2);
+    }
+
+    let Some((channels, sample_rate, bits_per_sample)) = format else {
+        return Err(Error::Format);
+    };
+    let Some((data_start, data_len)) = samples else {
+        return Err(Error::NoData);
+    };
+    if !matches!(bits_per_sample, 8 | 16) || !matches!(channels, 1 | 2) || sample_rate == 0 {
+        return Err(Error::Unsupported);
+    }
+    Ok(Header {
+        channels,
+        sample_rate,
+        bits_per_sample,
+        data_start,
+        data_len,
+    })
+}
+
+impl Clip {
+    /// Parses a WAV file into a clip that plays its samples straight out of `data`, without copying them. See
+    /// [`crate::audio::wav`] for the formats it accepts.
+    ///
+    /// A `data` chunk that claims to run past the end of the file, as in a recording that was cut short, is played up
+    /// to the end of the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file is not a WAV file, or if its samples are in a format the speakers cannot play.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use catears::audio::wav::Error;
+    /// use catears::audio::Clip;
+    ///
+    /// /// Builds a WAV file with a PCM `fmt ` chunk, any `extra` chunks after it, and `samples` in its `data` chunk.
+    /// fn wav(channels: u16, sample_rate: u32, bits: u16, extra: &[u8], samples: &[u8]) -> &'static [u8] {
+    ///     let block_align = channels * bits / 8;
+    ///     let mut file = b"RIFF\0\0\0\0WAVEfmt \x10\0\0\0\x01\0".to_vec();
+    ///     file.extend_from_slice(&channels.to_le_bytes());
+    ///     file.extend_from_slice(&sample_rate.to_le_bytes());
+    ///     file.extend_from_slice(&(sample_rate * u32::from(block_align)).to_le_bytes());
+    ///     file.extend_from_slice(&block_align.to_le_bytes());
+    ///     file.extend_from_slice(&bits.to_le_bytes());
+    ///     file.extend_from_slice(extra);
+    ///     file.extend_from_slice(b"data");
+    ///     file.extend_from_slice(&(samples.len() as u32).to_le_bytes());
+    ///     file.extend_from_slice(samples);
+    ///     let size = (file.len() - 8) as u32;
+    ///     file[4..8].copy_from_slice(&size.to_le_bytes());
+    ///     Box::leak(file.into_boxed_slice())
+    /// }
+    ///
+    /// // 8-bit mono, with a metadata chunk of odd length, padded, in the way.
+    /// let samples = [128, 255, 0, 128];
+    /// let file = wav(1, 8000, 8, b"LIST\x03\0\0\0abc\0", &samples);
+    /// let clip = Clip::from_wav(file).unwrap();
+    /// assert_eq!(clip, Clip::mono_8bit(&file[file.len() - 4..], 8000));
+    /// assert_eq!(clip.data, samples);
+    ///
+    /// // 16-bit stereo, pointing into the original file.
+    /// let samples = [0x00, 0x10, 0x00, 0xF0, 0xFF, 0x7F, 0x00, 0x80];
+    /// let file = wav(2, 44_100, 16, &[], &samples);
+    /// let clip = Clip::from_wav(file).unwrap();
+    /// assert_eq!((clip.sample_rate, clip.bits_per_sample, clip.is_stereo), (44_100, 16, true));
+    /// assert_eq!(clip.sample_count(), 2);
+    /// assert!(core::ptr::eq(clip.data, &file[file.len() - 8..]));
+    ///
+    /// // A file cut short plays what there is of it.
+    /// let file = wav(1, 8000, 8, &[], &[128; 100]);
+    /// assert_eq!(Clip::from_wav(&file[..file.len() - 50]).unwrap().data.len(), 50);
+    ///
+    /// // Anything else is rejected.
+    /// let mut bad_magic = wav(1, 8000, 8, &[], &[128; 4]).to_vec();
+    /// bad_magic[..4].copy_from_slice(b"RIFX");
+    /// assert_eq!(Clip::from_wav(Box::leak(bad_magic.into_boxed_slice())), Err(Error::Riff));
+    /// assert_eq!(Clip::from_wav(b"RIFF"), Err(Error::Riff));
+    /// let mut compressed = wav(1, 8000, 8, &[], &[128; 4]).to_vec();
+    /// compressed[20] = 2; // ADPCM
+    /// assert_eq!(Clip::from_wav(Box::leak(compressed.into_boxed_slice())), Err(Error::Compressed));
+    /// assert_eq!(Clip::from_wav(wav(1, 8000, 24, &[], &[0; 6])), Err(Error::Unsupported));
+    /// assert_eq!(Clip::from_wav(wav(6, 8000, 16, &[], &[0; 12])), Err(Error::Unsupported));
+    /// let file = wav(1, 8000, 8, &[], &[]);
+    /// assert_eq!(Clip::from_wav(&file[..file.len() - 8]), Err(Error::NoData));
+    /// assert_eq!(Clip::from_wav(b"RIFF\x04\0\0\0WAVE"), Err(Error::Format));
+    /// assert_eq!(Clip::from_wav(b"RIFF\x0C\0\0\0WAVEfmt \x10\0\0\0"), Err(Error::Truncated));
+    /// ```
+    pub const fn from_wav(data: &'static [u8]) -> Result<Self, Error> {
+        let header = match parse_header(data) {
+            Ok(header) => header,
+            Err(error) => return Err(error),
+        };
+        let available = data.len() - header.data_start;
+        let size = if header.data_len < available {
+            header.data_len
+        } else {
+            available
+        };
+        let samples = data.split_at(header.data_start).1.split_at(size).0;
+        // Checked to be 8 or 16 when parsing the header.
+        #[allow(clippy::cast_possible_truncation)]
+        let bits_per_sample = header.bits_per_sample as u8;
+        Ok(Self::new(
+            samples,
+            header.sample_rate,
+            bits_per_sample,
+            header.channels == 2,
+        ))
+    }
+}
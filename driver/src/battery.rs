@@ -0,0 +1,71 @@
+//! Battery level, for the lights to show how much charge is left.
+//!
+//! Nothing on the board measures the battery yet. [`Readings`] in the runtime status holds the latest charge as a
+//! percentage for whatever does, such as a future ADC task or the `system battery` command when trying out the
+//! thresholds, and the LED task reads it every frame for [`crate::lights::Mode::BatteryGauge`].
+
+use core::sync::atomic::{AtomicU8, Ordering};
+
+/// Charge in percent below which the battery counts as low.
+pub const LOW_PERCENT: u8 = 20;
+
+/// Charge in percent below which the battery counts as critically low.
+pub const CRITICAL_PERCENT: u8 = 10;
+
+/// Marks that there is no reading.
+const UNKNOWN: u8 = u8::MAX;
+
+/// Latest battery charge, published for the rest of the firmware.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::battery::Readings;
+///
+/// let battery = Readings::new();
+/// assert_eq!(battery.percent(), None);
+///
+/// battery.publish(42);
+/// assert_eq!(battery.percent(), Some(42));
+/// battery.publish(150);
+/// assert_eq!(battery.percent(), Some(100));
+///
+/// battery.clear();
+/// assert_eq!(battery.percent(), None);
+/// ```
+pub struct Readings {
+    percent: AtomicU8,
+}
+
+impl Readings {
+    /// Creates new readings with no charge known.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            percent: AtomicU8::new(UNKNOWN),
+        }
+    }
+
+    /// Publishes the charge in percent, capped at 100.
+    pub fn publish(&self, percent: u8) {
+        self.percent.store(percent.min(100), Ordering::Relaxed);
+    }
+
+    /// Forgets the charge, such as when the reading fails.
+    pub fn clear(&self) {
+        self.percent.store(UNKNOWN, Ordering::Relaxed);
+    }
+
+    /// Returns the latest charge in percent, or `None` if it is not known.
+    #[must_use]
+    pub fn percent(&self) -> Option<u8> {
+        let percent = self.percent.load(Ordering::Relaxed);
+        (percent != UNKNOWN).then_some(percent)
+    }
+}
+
+impl Default for Readings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
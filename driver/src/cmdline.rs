@@ -1,6 +1,9 @@
 use core::convert::Infallible;
 
+use crate::servo::gestures::{Gesture, GestureChannel, GestureRequest};
+
 use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, rwlock::RwLock};
+use embassy_time::{Duration, WithTimeout as _};
 use embedded_cli::{
     arguments::{FromArgument, FromArgumentError},
     cli::CliBuilder,
@@ -26,17 +29,44 @@ const COMMAND_BUFFER_SIZE: usize = 64;
 /// bytes of command history can be stored.
 const HISTORY_BUFFER_SIZE: usize = 128;
 
+/// Baud rate of the command line on the UART, set at build time through the `CLI_UART_BAUD` environment variable.
+#[cfg(feature = "uart")]
+pub const UART_BAUD: u32 = match option_env!("CLI_UART_BAUD") {
+    Some(baud) => parse_baud(baud),
+    None => 115_200,
+};
+
+/// Parses a baud rate at compile time, failing the build if it is not a number.
+#[cfg(feature = "uart")]
+#[allow(clippy::panic, reason = "only ever evaluated at compile time")]
+const fn parse_baud(baud: &str) -> u32 {
+    let bytes = baud.as_bytes();
+    let mut value: u32 = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        assert!(bytes[i].is_ascii_digit(), "CLI_UART_BAUD must be a number");
+        value = value * 10 + (bytes[i] - b'0') as u32;
+        i += 1;
+    }
+    value
+}
+
 /// Root command enumeration for the CLI.
 ///
 /// This enum defines all top-level commands available in the command-line interface. Each variant represents a
 /// different subsystem that can be controlled through the CLI.
 #[derive(Command)]
-enum Command {
+enum Command<'a> {
     /// System status commands
     Status {
         #[command(subcommand)]
         action: StatusCommand,
     },
+    /// System diagnostics commands
+    System {
+        #[command(subcommand)]
+        action: SystemCommand,
+    },
     /// Light control commands
     Light {
         #[command(subcommand)]
@@ -50,7 +80,22 @@ enum Command {
     /// Audio control commands
     Audio {
         #[command(subcommand)]
-        action: AudioCommand,
+        action: AudioCommand<'a>,
+    },
+    /// Motion sensor commands
+    Imu {
+        #[command(subcommand)]
+        action: ImuCommand,
+    },
+    /// Touch pad commands
+    Touch {
+        #[command(subcommand)]
+        action: TouchCommand,
+    },
+    /// Deep sleep commands
+    Sleep {
+        #[command(subcommand)]
+        action: SleepCommand,
     },
 }
 
@@ -63,6 +108,78 @@ enum StatusCommand {
     Get,
 }
 
+/// System diagnostics subcommands.
+///
+/// These commands report on the firmware itself rather than on what the ears are doing.
+#[derive(Command)]
+enum SystemCommand {
+    /// Get memory usage, boot and reset causes, uptime, and chip temperature
+    Info,
+    /// Get the outcome and timing of every startup stage
+    Boot,
+    /// Get the last panic, watchdog, or brownout reset, which is kept until cleared
+    Lastcrash {
+        /// Pass clear to forget the last crash
+        action: Option<CrashAction>,
+    },
+    /// Log buffer commands
+    Log {
+        #[command(subcommand)]
+        action: LogCommand,
+    },
+    /// Turn the on-board status LED on or off
+    Led {
+        /// Whether the status LED shows connectivity and errors (on or off)
+        switch: Switch,
+    },
+    /// Turn the command line on the UART on or off
+    Uart {
+        /// Whether an external controller on the UART can send commands (on or off)
+        switch: Switch,
+    },
+    /// Get the last and longest iteration time and the overrun count of every task
+    Timing {
+        /// Pass reset to clear the longest times and overrun counts
+        action: Option<TimingAction>,
+    },
+    /// Get the battery charge, or fake one for trying out the battery gauge
+    Battery {
+        /// Charge in percent (0-100) to fake, or clear to forget it
+        charge: Option<BatteryCharge>,
+    },
+}
+
+/// Log buffer subcommands.
+#[derive(Command)]
+enum LogCommand {
+    /// Turn shipping logs to the network collector on or off
+    Net {
+        /// Whether to ship logs (on or off)
+        switch: Switch,
+    },
+    /// Print and clear the buffered log frames as hex, one frame per line, for decoding with defmt-print
+    Dump,
+}
+
+/// Deep sleep subcommands.
+#[derive(Command)]
+enum SleepCommand {
+    /// Get sleep settings and the last wake cause
+    Get,
+    /// Go to sleep now
+    Now,
+    /// Set idle timeout before sleeping on its own
+    Idle {
+        /// Seconds without changes before sleeping (0 to never sleep on its own)
+        seconds: u32,
+    },
+    /// Set timer to wake up again after sleeping
+    Wake {
+        /// Seconds asleep before waking up (0 to only wake on the button)
+        seconds: u32,
+    },
+}
+
 /// Light control subcommands.
 ///
 /// These commands allow reading and modifying the LED light modes on either side of the device.
@@ -70,13 +187,13 @@ enum StatusCommand {
 enum LightCommand {
     /// Get light status
     Get {
-        /// Light side (left or right)
-        side: Side,
+        /// Light side (left, right, or both to mirror the left on the right)
+        side: LightSide,
     },
     /// Set light to solid color
     Solid {
-        /// Light side (left or right)
-        side: Side,
+        /// Light side (left, right, or both to mirror the left on the right)
+        side: LightSide,
         /// Red value (0-255)
         r: u8,
         /// Green value (0-255)
@@ -86,30 +203,219 @@ enum LightCommand {
     },
     /// Set light to off
     Off {
-        /// Light side (left or right)
-        side: Side,
+        /// Light side (left, right, or both to mirror the left on the right)
+        side: LightSide,
     },
     /// Set light to rainbow pattern
     Rainbow {
-        /// Light side (left or right)
-        side: Side,
+        /// Light side (left, right, or both to mirror the left on the right)
+        side: LightSide,
+        /// Saturation of the colors (0-255, default 255, lower for pastels)
+        saturation: Option<u8>,
+        /// Whether the colors turn the other way (on or off, default off)
+        reversed: Option<Switch>,
     },
     /// Set light to pulse pattern
     Pulse {
-        /// Light side (left or right)
-        side: Side,
+        /// Light side (left, right, or both to mirror the left on the right)
+        side: LightSide,
+        /// Red value (0-255)
+        r: u8,
+        /// Green value (0-255)
+        g: u8,
+        /// Blue value (0-255)
+        b: u8,
+        /// Pulse shape (sine, triangle, saw-up, saw-down, or square, default sine)
+        shape: Option<crate::lights::PulseShape>,
+    },
+    /// Set light to a gradient between two colors, optionally turning around the ring
+    Gradient {
+        /// Light side (left, right, or both to mirror the left on the right)
+        side: LightSide,
+        /// Start red value (0-255)
+        r1: u8,
+        /// Start green value (0-255)
+        g1: u8,
+        /// Start blue value (0-255)
+        b1: u8,
+        /// End red value (0-255)
+        r2: u8,
+        /// End green value (0-255)
+        g2: u8,
+        /// End blue value (0-255)
+        b2: u8,
+        /// Time in milliseconds to go around the ring once (default 0, holding still)
+        rotate: Option<u16>,
+        /// Whether to ramp up one half of the ring and down the other (on or off, default off)
+        mirrored: Option<Switch>,
+    },
+    /// Set light to flash on the notes of the chiptune on the same side
+    Beat {
+        /// Light side (left, right, or both to mirror the left on the right)
+        side: LightSide,
+        /// Red value (0-255)
+        r: u8,
+        /// Green value (0-255)
+        g: u8,
+        /// Blue value (0-255)
+        b: u8,
+    },
+    /// Set light to a comet circling the ring with a fading tail
+    Comet {
+        /// Light side (left, right, or both to mirror the left on the right)
+        side: LightSide,
+        /// Red value (0-255)
+        r: u8,
+        /// Green value (0-255)
+        g: u8,
+        /// Blue value (0-255)
+        b: u8,
+    },
+    /// Set light to a theater chase, every few LEDs lit and shifting along
+    Theater {
+        /// Light side (left, right, or both to mirror the left on the right)
+        side: LightSide,
         /// Red value (0-255)
         r: u8,
         /// Green value (0-255)
         g: u8,
         /// Blue value (0-255)
         b: u8,
+        /// LEDs from one lit LED to the next (default 3)
+        spacing: Option<u8>,
+    },
+    /// Set light to sparkle, in one color or in random hues if none is given
+    Sparkle {
+        /// Light side (left, right, or both to mirror the left on the right)
+        side: LightSide,
+        /// Red value (0-255)
+        r: Option<u8>,
+        /// Green value (0-255)
+        g: Option<u8>,
+        /// Blue value (0-255)
+        b: Option<u8>,
     },
     /// Set global brightness
     Brightness {
         /// Brightness value (0-255)
         value: u8,
     },
+    /// Turn automatic brightness from the ambient light sensor on or off
+    Auto {
+        /// Whether to dim with the ambient light (on or off)
+        switch: Switch,
+    },
+    /// Set how long the rings take to crossfade into a new mode
+    Transition {
+        /// Crossfade time in milliseconds (0 to switch at once)
+        ms: u16,
+    },
+    /// Turn dithering of the brightness on or off, off when filming to keep cameras from catching it
+    Dither {
+        /// Whether to dither the brightness (on or off)
+        switch: Switch,
+    },
+    /// Set light to a battery gauge, pulsing grey while the charge is unknown
+    Battery {
+        /// Light side (left, right, or both to mirror the left on the right)
+        side: LightSide,
+    },
+    /// Set light to a progress bar, filling clockwise from the top
+    Progress {
+        /// Light side (left, right, or both to mirror the left on the right)
+        side: LightSide,
+        /// How far along in percent (0-100)
+        percent: u8,
+    },
+    /// Light an arc of a ring, replacing an arc with the same start, or clear every arc
+    Segment {
+        /// Light side (left, right, or both to mirror the left on the right)
+        side: LightSide,
+        /// Index of the first LED of the arc (counting from 0), or clear
+        start: SegmentStart,
+        /// Number of LEDs in the arc, going clockwise (up to the whole ring)
+        len: Option<u8>,
+        /// Red value (0-255)
+        r: Option<u8>,
+        /// Green value (0-255)
+        g: Option<u8>,
+        /// Blue value (0-255)
+        b: Option<u8>,
+    },
+    /// Set light to cycle through a built-in palette
+    Palette {
+        /// Light side (left, right, or both to mirror the left on the right)
+        side: LightSide,
+        /// Palette name (see light palettes)
+        palette: crate::lights::PaletteId,
+        /// Time in milliseconds the palette takes to go by once (default 4000)
+        speed: Option<u16>,
+        /// Whether to spread the palette around the ring (on or off, default on)
+        spread: Option<Switch>,
+    },
+    /// List the built-in palettes
+    Palettes,
+    /// Set light to a strobe of hard flashes, optionally stopping after a number of them
+    Strobe {
+        /// Light side (left, right, or both to mirror the left on the right)
+        side: LightSide,
+        /// Red value (0-255)
+        r: u8,
+        /// Green value (0-255)
+        g: u8,
+        /// Blue value (0-255)
+        b: u8,
+        /// Time in milliseconds each flash lasts (default 50)
+        on: Option<u16>,
+        /// Time in milliseconds between flashes (at least 30, default 100)
+        off: Option<u16>,
+        /// Number of flashes before the light stays dark (default unlimited)
+        count: Option<u16>,
+    },
+    /// Set the white balance of both rings to a preset, or to scales of red, green, and blue
+    Whitebalance {
+        /// Preset (neutral, tungsten, or cool), or red scale (0-255)
+        white: WhitePoint,
+        /// Green scale (0-255), after a red scale
+        g: Option<u8>,
+        /// Blue scale (0-255), after a red scale
+        b: Option<u8>,
+    },
+    /// Set which LED of a ring is at the top, where the patterns start
+    Offset {
+        /// Light side (left or right)
+        side: Side,
+        /// Index of the LED at the top (counting from 0)
+        n: u8,
+    },
+    /// Set whether a ring is wired counter-clockwise, toggling it if not given
+    Reverse {
+        /// Light side (left or right)
+        side: Side,
+        /// Whether the ring is reversed (on or off)
+        switch: Option<Switch>,
+    },
+    /// Light playlist commands, showing presets on both rings in turn
+    Playlist {
+        #[command(subcommand)]
+        action: PlaylistCommand,
+    },
+}
+
+/// Light playlist subcommands.
+#[derive(Command)]
+enum PlaylistCommand {
+    /// Show the playlist and the presets it can hold
+    Show,
+    /// Add a preset to the end of the playlist
+    Add {
+        /// Preset name (see light playlist show)
+        preset: crate::lights::patterns::Preset,
+        /// Time in seconds to show it for
+        seconds: u16,
+    },
+    /// Clear the playlist, leaving the rings to their own modes
+    Clear,
 }
 
 /// Servo control subcommands.
@@ -119,36 +425,142 @@ enum LightCommand {
 enum ServoCommand {
     /// Get servo position
     Get {
-        /// Servo side (left or right)
-        side: Side,
+        /// Servo (left, right, aux0, or aux1)
+        servo: ServoName,
     },
     /// Set servo position
     Set {
-        /// Servo side (left or right)
-        side: Side,
+        /// Servo (left, right, aux0, or aux1)
+        servo: ServoName,
         /// Position value (0-255)
         value: u8,
     },
+    /// Play a one-shot gesture, or `servo gesture list` to list gestures
+    Gesture {
+        /// Servo side (left, right, or both), or list
+        side: GestureSide,
+        /// Gesture name
+        name: Option<GestureName>,
+    },
+}
+
+/// Motion sensor subcommands.
+///
+/// These commands report the accelerometer readings and calibrate its resting orientation.
+#[derive(Command)]
+enum ImuCommand {
+    /// Get current tilt and shake count
+    Get,
+    /// Capture the current orientation as level (hold still)
+    Calibrate,
+}
+
+/// Touch pad subcommands.
+///
+/// These commands report the touch pad readings, tune how easily petting is detected, and recapture the baselines.
+#[derive(Command)]
+enum TouchCommand {
+    /// Get current readings, baselines, and tap and stroke counts
+    Get,
+    /// Set the change from the baseline that counts as a touch
+    Threshold {
+        /// Percent change (1-100, lower is more sensitive)
+        value: u8,
+    },
+    /// Capture the current readings as untouched (keep your hands off)
+    Calibrate,
 }
 
 /// Audio control subcommands.
 ///
-/// These commands allow controlling the audio output including tones, chiptunes, and volume.
+/// These commands allow controlling the audio output on either side of the device, including tones, chiptunes, and the
+/// master volume.
 #[derive(Command)]
-enum AudioCommand {
+enum AudioCommand<'a> {
     /// Get current audio status
     Get,
     /// Set audio to silent
-    Silent,
-    /// Play a tone
+    Silent {
+        /// Speaker side (left or right)
+        side: Side,
+    },
+    /// Play a tone once
     Tone {
+        /// Speaker side (left or right)
+        side: Side,
         /// Frequency in Hz
         freq: u16,
         /// Duration in milliseconds
         duration: u16,
+        /// Waveform (sine, square, triangle, sawtooth, or a wavetable: organ, softsquare, vocal), sine if left out
+        waveform: Option<crate::audio::Waveform>,
     },
-    /// Play a predefined chiptune
+    /// Play a chiptune by name, or several predefined ones one after another
     Chiptune {
+        /// Speaker side (left or right)
+        side: Side,
+        /// Chiptune name, or up to 8 predefined ones separated by commas, such as "startup,happy"
+        names: ChiptuneNames,
+    },
+    /// Play an RTTTL ringtone, quoted if it has spaces
+    Rtttl {
+        /// Speaker side (left or right)
+        side: Side,
+        /// Ringtone, such as "Beep:d=4,o=5,b=120:8c6,p,g#"
+        tune: &'a str,
+    },
+    /// Play noise until the mode changes, such as "noise left 192 240 25" for a purr
+    Noise {
+        /// Speaker side (left or right)
+        side: Side,
+        /// Volume level (0-255)
+        amplitude: u8,
+        /// Smoothing (0-255) from a hiss to a rumble, none if left out
+        smoothing: Option<u8>,
+        /// Volume wobble in Hz, steady if left out
+        wobble: Option<u16>,
+    },
+    /// Glide a tone from one frequency to another, such as "sweep left 600 1200 500 on" for a siren
+    Sweep {
+        /// Speaker side (left or right)
+        side: Side,
+        /// Start frequency in Hz
+        start: u16,
+        /// End frequency in Hz
+        end: u16,
+        /// Duration of a sweep in milliseconds
+        duration: u16,
+        /// Sweep back and forth until stopped (on or off), once if left out
+        repeat: Option<Switch>,
+    },
+    /// Dial keys as touch-tone beeps on both speakers, such as "dial 555-0123"
+    Dial {
+        /// Keys to dial, up to 16 of 0-9, *, #, and A-D
+        digits: &'a str,
+        /// Time each key sounds for in milliseconds, 100 if left out
+        tone: Option<u16>,
+        /// Time between keys in milliseconds, 100 if left out
+        gap: Option<u16>,
+    },
+    /// Stream an audio clip from the server, such as "stream left 7" for clips/7.wav
+    Stream {
+        /// Speaker side (left or right)
+        side: Side,
+        /// Clip id
+        id: u16,
+        /// Play it over and over (on or off), once if left out
+        looping: Option<Switch>,
+    },
+    /// Silence both speakers without changing what they play
+    Mute,
+    /// Let both speakers be heard again
+    Unmute,
+    /// Hold both speakers where they are in whatever they play
+    Pause,
+    /// Carry on playing from where the speakers were paused
+    Resume,
+    /// Play a chiptune once on both speakers, over whatever they are playing
+    Play {
         /// Chiptune name
         name: ChiptuneName,
     },
@@ -157,6 +569,167 @@ enum AudioCommand {
         /// Volume level (0-255)
         value: u8,
     },
+    /// Balance the speakers against each other
+    Balance {
+        /// Balance from -127 (left only) through 0 (center) to 127 (right only), or L or R followed by how far
+        value: Balance,
+    },
+}
+
+/// Speaker balance argument, either a signed number or leaning left or right by how far, such as "L20".
+///
+/// A bare negative number reads like an option to the command line, hence the other spelling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Balance(i8);
+
+impl<'a> FromArgument<'a> for Balance {
+    fn from_arg(arg: &'a str) -> Result<Self, FromArgumentError<'a>> {
+        let error = FromArgumentError {
+            value: arg,
+            expected: "a balance from -127 to 127, center, or L or R followed by 0 to 127",
+        };
+        let parse = |value: &str| value.parse::<i8>().ok().filter(|&value| value >= 0);
+        let balance = match arg.as_bytes().first() {
+            _ if arg.eq_ignore_ascii_case("center") => Some(0),
+            Some(b'l' | b'L') => parse(&arg[1..]).map(|value| -value),
+            Some(b'r' | b'R') => parse(&arg[1..]),
+            _ => arg.parse::<i8>().ok().filter(|&value| value >= -127),
+        };
+        balance.map(Balance).ok_or(error)
+    }
+}
+
+/// An on/off argument.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Switch {
+    /// Turned on
+    On,
+    /// Turned off
+    Off,
+}
+
+impl<'a> FromArgument<'a> for Switch {
+    fn from_arg(arg: &'a str) -> Result<Self, FromArgumentError<'a>> {
+        match arg.to_lowercase().as_str() {
+            "on" => Ok(Switch::On),
+            "off" => Ok(Switch::Off),
+            _ => Err(FromArgumentError {
+                value: arg,
+                expected: "on or off",
+            }),
+        }
+    }
+}
+
+impl<'a> FromArgument<'a> for crate::lights::PulseShape {
+    fn from_arg(arg: &'a str) -> Result<Self, FromArgumentError<'a>> {
+        match arg.to_lowercase().as_str() {
+            "sine" => Ok(Self::Sine),
+            "triangle" => Ok(Self::Triangle),
+            "saw-up" | "sawtooth-up" => Ok(Self::SawtoothUp),
+            "saw-down" | "sawtooth-down" => Ok(Self::SawtoothDown),
+            "square" | "blink" => Ok(Self::Square(50)),
+            _ => Err(FromArgumentError {
+                value: arg,
+                expected: "sine, triangle, saw-up, saw-down, or square",
+            }),
+        }
+    }
+}
+
+impl<'a> FromArgument<'a> for crate::lights::PaletteId {
+    fn from_arg(arg: &'a str) -> Result<Self, FromArgumentError<'a>> {
+        Self::from_name(arg).ok_or(FromArgumentError {
+            value: arg,
+            expected: "heat, ocean, party, or forest",
+        })
+    }
+}
+
+impl<'a> FromArgument<'a> for crate::lights::patterns::Preset {
+    fn from_arg(arg: &'a str) -> Result<Self, FromArgumentError<'a>> {
+        Self::from_name(arg).ok_or(FromArgumentError {
+            value: arg,
+            expected: "a light preset (see light playlist show)",
+        })
+    }
+}
+
+impl<'a> FromArgument<'a> for crate::audio::Waveform {
+    fn from_arg(arg: &'a str) -> Result<Self, FromArgumentError<'a>> {
+        match arg.to_lowercase().as_str() {
+            "sine" => Ok(Self::Sine),
+            "square" => Ok(Self::Square),
+            "triangle" => Ok(Self::Triangle),
+            "sawtooth" | "saw" => Ok(Self::Sawtooth),
+            _ => crate::audio::wavetables::by_name(arg).ok_or(FromArgumentError {
+                value: arg,
+                expected: "sine, square, triangle, sawtooth, or a wavetable such as organ",
+            }),
+        }
+    }
+}
+
+/// What to do with the task timings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TimingAction {
+    /// Clear the longest times and overrun counts
+    Reset,
+}
+
+impl<'a> FromArgument<'a> for TimingAction {
+    fn from_arg(arg: &'a str) -> Result<Self, FromArgumentError<'a>> {
+        match arg.to_lowercase().as_str() {
+            "reset" => Ok(TimingAction::Reset),
+            _ => Err(FromArgumentError {
+                value: arg,
+                expected: "reset",
+            }),
+        }
+    }
+}
+
+/// Battery charge to fake with `system battery`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BatteryCharge {
+    /// Charge in percent
+    Percent(u8),
+    /// Forget the charge, as if nothing measured it
+    Clear,
+}
+
+impl<'a> FromArgument<'a> for BatteryCharge {
+    fn from_arg(arg: &'a str) -> Result<Self, FromArgumentError<'a>> {
+        if arg.eq_ignore_ascii_case("clear") {
+            return Ok(BatteryCharge::Clear);
+        }
+        match arg.parse() {
+            Ok(percent) if percent <= 100 => Ok(BatteryCharge::Percent(percent)),
+            _ => Err(FromArgumentError {
+                value: arg,
+                expected: "a charge in percent (0-100) or clear",
+            }),
+        }
+    }
+}
+
+/// What to do with the last crash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CrashAction {
+    /// Forget the last crash
+    Clear,
+}
+
+impl<'a> FromArgument<'a> for CrashAction {
+    fn from_arg(arg: &'a str) -> Result<Self, FromArgumentError<'a>> {
+        match arg.to_lowercase().as_str() {
+            "clear" => Ok(CrashAction::Clear),
+            _ => Err(FromArgumentError {
+                value: arg,
+                expected: "clear",
+            }),
+        }
+    }
 }
 
 /// Represents a side selection (left or right).
@@ -184,6 +757,15 @@ impl<'a> FromArgument<'a> for Side {
     }
 }
 
+impl From<Side> for crate::audio::Side {
+    fn from(side: Side) -> Self {
+        match side {
+            Side::Left => Self::Left,
+            Side::Right => Self::Right,
+        }
+    }
+}
+
 impl uDebug for Side {
     fn fmt<W>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error>
     where
@@ -196,63 +778,305 @@ impl uDebug for Side {
     }
 }
 
-/// Predefined chiptune names that can be played.
+/// Ring selection for the `light` commands that set or get a mode.
+///
+/// In addition to the usual sides, `both` sets the left ring and has the right one mirror it, so that directional
+/// patterns look mirrored across the head.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum ChiptuneName {
-    Coin,
-    PowerUp,
-    LevelComplete,
-    GameOver,
-    MenuSelect,
-    Alert,
-    Happy,
-    Sad,
-    Startup,
-    Shutdown,
+enum LightSide {
+    /// Left ring
+    Left,
+    /// Right ring
+    Right,
+    /// Left ring, mirrored on the right one
+    Both,
 }
 
-impl<'a> FromArgument<'a> for ChiptuneName {
+impl LightSide {
+    /// Returns the mode of the selected ring, the left one for `both`.
+    fn mode(self, lights: &crate::state::Lights) -> &crate::lights::Mode {
+        match self {
+            LightSide::Left | LightSide::Both => &lights.left,
+            LightSide::Right => &lights.right,
+        }
+    }
+
+    /// Returns whether the ring on `side` is selected.
+    fn includes(self, side: Side) -> bool {
+        matches!(
+            (self, side),
+            (LightSide::Both, _) | (LightSide::Left, Side::Left) | (LightSide::Right, Side::Right)
+        )
+    }
+
+    /// Sets the selected ring to `mode`, or with `both` sets the left ring and has the right one mirror it, and
+    /// returns what was set for messages.
+    fn set(self, lights: &mut crate::state::Lights, mode: crate::lights::Mode) -> &'static str {
+        match self {
+            LightSide::Left => {
+                lights.left = mode;
+                "left light"
+            }
+            LightSide::Right => {
+                lights.right = mode;
+                "right light"
+            }
+            LightSide::Both => {
+                lights.left = mode;
+                lights.right = crate::lights::Mode::MirrorOther;
+                "both lights"
+            }
+        }
+    }
+}
+
+impl<'a> FromArgument<'a> for LightSide {
     fn from_arg(arg: &'a str) -> Result<Self, FromArgumentError<'a>> {
         match arg.to_lowercase().as_str() {
-            "coin" => Ok(ChiptuneName::Coin),
-            "powerup" => Ok(ChiptuneName::PowerUp),
-            "levelcomplete" | "level" => Ok(ChiptuneName::LevelComplete),
-            "gameover" => Ok(ChiptuneName::GameOver),
-            "menuselect" | "menu" => Ok(ChiptuneName::MenuSelect),
-            "alert" => Ok(ChiptuneName::Alert),
-            "happy" => Ok(ChiptuneName::Happy),
-            "sad" => Ok(ChiptuneName::Sad),
-            "startup" => Ok(ChiptuneName::Startup),
-            "shutdown" => Ok(ChiptuneName::Shutdown),
+            "left" | "l" => Ok(LightSide::Left),
+            "right" | "r" => Ok(LightSide::Right),
+            "both" | "b" => Ok(LightSide::Both),
             _ => Err(FromArgumentError {
                 value: arg,
-                expected: "coin, powerup, levelcomplete, gameover, menuselect, alert, happy, sad, startup, or shutdown",
+                expected: "left (l), right (r), or both (b)",
             }),
         }
     }
 }
 
-/// Initializes the command-line interface for controlling the device.
-///
-/// This function sets up the CLI over USB serial JTAG, configuring it with appropriate buffers and spawning the
-/// handler task that processes incoming commands. The CLI provides an interactive interface for controlling servos,
-/// lights, audio, and querying system status.
-///
-/// # Parameters
-///
-/// * `state` - Shared state containing servo, light, and audio values that will be read and modified by CLI commands
-/// * `serial` - USB serial JTAG peripheral for communication with the host
-/// * `spawner` - Executor spawner for running the CLI handler task
-///
-/// # Panics
-///
-/// Panics if:
-/// - Failed to write the initial header or help message to the serial port
-/// - Failed to build the CLI instance
-/// - Failed to spawn the CLI handler task on the provided executor
-pub async fn init(
-    state: &'static RwLock<CriticalSectionRawMutex, crate::state::State>,
-    mut serial: UsbSerialJtag<'static, esp_hal::Async>,
+/// First argument of the `light segment` command, either where the arc starts or `clear` to remove every arc.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SegmentStart {
+    /// Index of the first LED of the arc
+    Led(u8),
+    /// Remove every arc
+    Clear,
+}
+
+impl<'a> FromArgument<'a> for SegmentStart {
+    fn from_arg(arg: &'a str) -> Result<Self, FromArgumentError<'a>> {
+        if arg.eq_ignore_ascii_case("clear") {
+            return Ok(SegmentStart::Clear);
+        }
+        match arg.parse() {
+            Ok(led) if usize::from(led) < crate::lights::LED_COUNT => Ok(SegmentStart::Led(led)),
+            _ => Err(FromArgumentError {
+                value: arg,
+                expected: "a start LED or clear",
+            }),
+        }
+    }
+}
+
+/// First argument of the `light whitebalance` command, either a preset or the scale of the red channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WhitePoint {
+    /// Named preset
+    Preset(crate::lights::ColorCorrection),
+    /// Red scale, followed by the green and blue ones
+    Red(u8),
+}
+
+impl<'a> FromArgument<'a> for WhitePoint {
+    fn from_arg(arg: &'a str) -> Result<Self, FromArgumentError<'a>> {
+        if let Some(correction) = crate::lights::ColorCorrection::from_name(arg) {
+            return Ok(WhitePoint::Preset(correction));
+        }
+        arg.parse()
+            .map(WhitePoint::Red)
+            .map_err(|_| FromArgumentError {
+                value: arg,
+                expected: "neutral, tungsten, cool, or a red scale (0-255)",
+            })
+    }
+}
+
+/// Servo selector for the `servo get` and `servo set` commands.
+///
+/// Besides the ears, this names the two auxiliary servos, which only move on builds with the `aux-servos` feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ServoName {
+    /// Left ear
+    Left,
+    /// Right ear
+    Right,
+    /// First auxiliary servo
+    Aux0,
+    /// Second auxiliary servo
+    Aux1,
+}
+
+impl<'a> FromArgument<'a> for ServoName {
+    fn from_arg(arg: &'a str) -> Result<Self, FromArgumentError<'a>> {
+        match arg.to_lowercase().as_str() {
+            "left" | "l" => Ok(ServoName::Left),
+            "right" | "r" => Ok(ServoName::Right),
+            "aux0" => Ok(ServoName::Aux0),
+            "aux1" => Ok(ServoName::Aux1),
+            _ => Err(FromArgumentError {
+                value: arg,
+                expected: "left (l), right (r), aux0, or aux1",
+            }),
+        }
+    }
+}
+
+impl uDebug for ServoName {
+    fn fmt<W>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        match self {
+            ServoName::Left => f.write_str("Left"),
+            ServoName::Right => f.write_str("Right"),
+            ServoName::Aux0 => f.write_str("Aux0"),
+            ServoName::Aux1 => f.write_str("Aux1"),
+        }
+    }
+}
+
+/// Target of a `servo gesture` command.
+///
+/// In addition to the usual sides, gestures can be played on both ears at once, and `list` enumerates the available
+/// gestures instead of playing one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GestureSide {
+    /// Play on the given ears
+    Target(crate::servo::gestures::Target),
+    /// List available gestures
+    List,
+}
+
+impl<'a> FromArgument<'a> for GestureSide {
+    fn from_arg(arg: &'a str) -> Result<Self, FromArgumentError<'a>> {
+        match arg.to_lowercase().as_str() {
+            "left" | "l" => Ok(GestureSide::Target(crate::servo::gestures::Target::Left)),
+            "right" | "r" => Ok(GestureSide::Target(crate::servo::gestures::Target::Right)),
+            "both" | "b" => Ok(GestureSide::Target(crate::servo::gestures::Target::Both)),
+            "list" => Ok(GestureSide::List),
+            _ => Err(FromArgumentError {
+                value: arg,
+                expected: "left (l), right (r), both (b), or list",
+            }),
+        }
+    }
+}
+
+/// Gesture name argument.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct GestureName(crate::servo::gestures::Gesture);
+
+impl<'a> FromArgument<'a> for GestureName {
+    fn from_arg(arg: &'a str) -> Result<Self, FromArgumentError<'a>> {
+        crate::servo::gestures::Gesture::from_name(arg)
+            .map(GestureName)
+            .ok_or(FromArgumentError {
+                value: arg,
+                expected: "perk, flatten, wiggle, droop, or curious",
+            })
+    }
+}
+
+/// Returns the predefined chiptune named `name`, allowing for the short names of the longer ones.
+fn chiptune_preset(name: &str) -> Option<crate::audio::chiptunes::Preset> {
+    let name = name.trim().to_lowercase();
+    let name = match name.as_str() {
+        "level" => "levelcomplete",
+        "menu" => "menuselect",
+        "drums" => "drumloop",
+        name => name,
+    };
+    crate::audio::chiptunes::Preset::from_name(name)
+}
+
+/// Returns the name of the chiptune, predefined or registered by the firmware, called `name`, allowing for the short
+/// names of the longer predefined ones.
+fn chiptune_name(name: &str) -> Option<crate::audio::chiptunes::PresetName> {
+    if let Some(preset) = chiptune_preset(name) {
+        return Some(preset.into());
+    }
+    let name = crate::audio::chiptunes::PresetName::new(name.trim());
+    name.sequence().map(|_| name)
+}
+
+/// Chiptune name argument, predefined or registered by the firmware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ChiptuneName(crate::audio::chiptunes::PresetName);
+
+impl<'a> FromArgument<'a> for ChiptuneName {
+    fn from_arg(arg: &'a str) -> Result<Self, FromArgumentError<'a>> {
+        chiptune_name(arg).map(ChiptuneName).ok_or(FromArgumentError {
+            value: arg,
+            expected: "coin, powerup, levelcomplete, gameover, menuselect, alert, happy, sad, startup, shutdown, \
+                drumloop, curious, disappointed, doorbell, purr, error, or a chiptune the firmware registered",
+        })
+    }
+}
+
+/// Chiptunes argument: a single chiptune by name, or a playlist of predefined ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChiptuneNames {
+    /// A single chiptune, predefined or registered by the firmware
+    One(crate::audio::chiptunes::PresetName),
+    /// Predefined chiptunes one after another
+    Several(crate::audio::Playlist),
+}
+
+impl<'a> FromArgument<'a> for ChiptuneNames {
+    fn from_arg(arg: &'a str) -> Result<Self, FromArgumentError<'a>> {
+        if arg.contains(',') {
+            return crate::audio::Playlist::from_arg(arg).map(ChiptuneNames::Several);
+        }
+        ChiptuneName::from_arg(arg).map(|ChiptuneName(name)| ChiptuneNames::One(name))
+    }
+}
+
+/// A playlist argument: predefined chiptune names separated by commas.
+impl<'a> FromArgument<'a> for crate::audio::Playlist {
+    fn from_arg(arg: &'a str) -> Result<Self, FromArgumentError<'a>> {
+        let error = || {
+            FromArgumentError {
+            value: arg,
+            expected: "up to 8 of coin, powerup, levelcomplete, gameover, menuselect, alert, happy, sad, startup, \
+                shutdown, drumloop, curious, disappointed, doorbell, purr, or error, separated by commas",
+        }
+        };
+        let mut presets = [crate::audio::chiptunes::Preset::default(); 8];
+        let mut length = 0;
+        for name in arg.split(',') {
+            let preset = chiptune_preset(name).ok_or_else(error)?;
+            *presets.get_mut(length).ok_or_else(error)? = preset;
+            length += 1;
+        }
+        Ok(crate::audio::Playlist::from_presets(&presets[..length]))
+    }
+}
+
+/// Initializes the command-line interface for controlling the device.
+///
+/// This function sets up the CLI over USB serial JTAG, configuring it with appropriate buffers and spawning the
+/// handler task that processes incoming commands. The CLI provides an interactive interface for controlling servos,
+/// lights, audio, and querying system status.
+///
+/// # Parameters
+///
+/// * `state` - Shared state containing servo, light, and audio values that will be read and modified by CLI commands
+/// * `status` - Runtime status reported by the `status get` command
+/// * `gestures` - Channel used to request one-shot servo gestures
+/// * `serial` - USB serial JTAG peripheral for communication with the host
+/// * `spawner` - Executor spawner for running the CLI handler task
+///
+/// # Panics
+///
+/// Panics if:
+/// - Failed to write the initial header or help message to the serial port
+/// - Failed to build the CLI instance
+/// - Failed to spawn the CLI handler task on the provided executor
+pub async fn init(
+    state: &'static RwLock<CriticalSectionRawMutex, crate::state::State>,
+    status: &'static crate::status::Status,
+    gestures: &'static GestureChannel,
+    mut serial: UsbSerialJtag<'static, esp_hal::Async>,
     spawner: &embassy_executor::Spawner,
 ) {
     serial
@@ -274,60 +1098,192 @@ pub async fn init(
         .expect("Failed to build CLI");
 
     spawner
-        .spawn(handler(state, serial_rx, cli))
+        .spawn(usb_handler(state, status, gestures, serial_rx, cli))
         .expect("Failed to spawn CLI handler");
 }
 
-/// CLI handler task that processes incoming commands.
+/// Initializes the command-line interface on a UART, for an external controller to drive the ears through.
 ///
-/// This task runs indefinitely, reading bytes from the serial port and processing them through the CLI. When complete
-/// commands are entered, they are parsed and executed, potentially modifying the shared system state. The handler
-/// implements a read-modify-write pattern to safely update state only when changes are made.
+/// The UART runs the same commands as the USB serial interface, and both can be used at the same time. Input on the UART
+/// is ignored while [`crate::state::State::uart_cli`] is off.
+///
+/// # Panics
+///
+/// Panics if:
+/// - Failed to build the CLI instance
+/// - Failed to spawn the CLI handler task on the provided executor
+#[cfg(feature = "uart")]
+pub fn init_uart(
+    state: &'static RwLock<CriticalSectionRawMutex, crate::state::State>,
+    status: &'static crate::status::Status,
+    gestures: &'static GestureChannel,
+    uart: esp_hal::uart::Uart<'static, Async>,
+    spawner: &embassy_executor::Spawner,
+) {
+    let (uart_rx, uart_tx) = uart.split();
+    let cli = CliBuilder::default()
+        .writer(uart_tx)
+        .command_buffer([0; COMMAND_BUFFER_SIZE])
+        .history_buffer([0; HISTORY_BUFFER_SIZE])
+        .prompt("> ")
+        .build()
+        .expect("Failed to build UART CLI");
+
+    spawner
+        .spawn(uart_handler(state, status, gestures, uart_rx, cli))
+        .expect("Failed to spawn UART CLI handler");
+}
+
+/// CLI handler task for the USB serial interface.
+#[embassy_executor::task]
+async fn usb_handler(
+    state: &'static RwLock<CriticalSectionRawMutex, crate::state::State>,
+    status: &'static crate::status::Status,
+    gestures: &'static GestureChannel,
+    serial_rx: UsbSerialJtagRx<'static, Async>,
+    cli: embedded_cli::cli::Cli<
+        UsbSerialJtagTx<'static, Async>,
+        Infallible,
+        [u8; COMMAND_BUFFER_SIZE],
+        [u8; HISTORY_BUFFER_SIZE],
+    >,
+) {
+    handle(
+        state,
+        status,
+        gestures,
+        serial_rx,
+        cli,
+        crate::watchdog::Task::Cli,
+        |_| true,
+    )
+    .await;
+}
+
+/// CLI handler task for the UART interface.
+#[cfg(feature = "uart")]
+#[embassy_executor::task]
+async fn uart_handler(
+    state: &'static RwLock<CriticalSectionRawMutex, crate::state::State>,
+    status: &'static crate::status::Status,
+    gestures: &'static GestureChannel,
+    uart_rx: esp_hal::uart::UartRx<'static, Async>,
+    cli: embedded_cli::cli::Cli<
+        esp_hal::uart::UartTx<'static, Async>,
+        esp_hal::uart::TxError,
+        [u8; COMMAND_BUFFER_SIZE],
+        [u8; HISTORY_BUFFER_SIZE],
+    >,
+) {
+    handle(
+        state,
+        status,
+        gestures,
+        uart_rx,
+        cli,
+        crate::watchdog::Task::UartCli,
+        |state| state.uart_cli,
+    )
+    .await;
+}
+
+/// Processes incoming commands from a serial transport.
+///
+/// This runs indefinitely, reading bytes from the transport and processing them through the CLI. When complete
+/// commands are entered, they are parsed and executed, potentially modifying the shared system state. Every byte is
+/// processed while holding the state's write lock, so commands from several transports are applied one at a time and
+/// never overwrite each other's changes. Input is dropped while `enabled` returns `false` for the current state.
 ///
 /// The task supports the following command categories:
 /// - Status queries for reading current servo, light, and audio values
+/// - System diagnostics such as memory usage, chip temperature, startup results, the last crash, task timing, the log
+///   buffer, and the status LED
 /// - Light control for setting various LED modes on left/right sides
-/// - Servo control for setting positions on left/right sides
+/// - Servo control for setting positions on left/right sides and playing gestures
 /// - Audio control for playing tones, chiptunes, and adjusting volume
+/// - Motion sensor readings and calibration
+/// - Touch pad readings, sensitivity, and calibration
+/// - Deep sleep and its idle timeout and wake timer
 ///
 /// # Parameters
 ///
 /// * `state` - Shared state containing servo, light, and audio values
-/// * `serial_rx` - Receive half of the USB serial connection
-/// * `cli` - Configured CLI instance for processing commands
+/// * `status` - Runtime status reported by the `status get` command
+/// * `gestures` - Channel used to request one-shot servo gestures
+/// * `serial_rx` - Receive half of the transport
+/// * `cli` - Configured CLI instance for processing commands, writing to the transmit half of the transport
+/// * `task` - Task the handler stamps its heartbeat and records its timing as
+/// * `enabled` - Whether the transport accepts commands in a given state
 #[allow(clippy::too_many_lines)]
-#[embassy_executor::task]
-async fn handler(
+async fn handle<R, W>(
     state: &'static RwLock<CriticalSectionRawMutex, crate::state::State>,
-    mut serial_rx: UsbSerialJtagRx<'static, Async>,
+    status: &'static crate::status::Status,
+    gestures: &'static GestureChannel,
+    mut serial_rx: R,
     mut cli: embedded_cli::cli::Cli<
-        UsbSerialJtagTx<'static, Async>,
-        Infallible,
+        W,
+        W::Error,
         [u8; COMMAND_BUFFER_SIZE],
         [u8; HISTORY_BUFFER_SIZE],
     >,
-) {
+    task: crate::watchdog::Task,
+    enabled: impl Fn(&crate::state::State) -> bool,
+) where
+    R: embedded_io_async::Read,
+    W: embedded_io::Write,
+{
     loop {
+        status.heartbeats.stamp(task);
+
         let mut buffer = [0u8; 1];
-        if serial_rx.read(&mut buffer).await.is_ok() {
-            // Read the current state once before processing commands
-            let mut state_copy = *state.read().await;
+        // Wake up at least once a second even without input so the watchdog knows the handler is alive.
+        let Ok(read) = serial_rx
+            .read(&mut buffer)
+            .with_timeout(Duration::from_secs(1))
+            .await
+        else {
+            continue;
+        };
+        if read.is_ok() {
+            let started = embassy_time::Instant::now();
+            // Hold the write lock until the command is applied, so that other transports wait their turn
+            let mut locked_state = state.write().await;
+            let mut state_copy = *locked_state;
+            if !enabled(&state_copy) {
+                continue;
+            }
 
-            let _ = cli.process_byte::<Command, _>(
+            let _ = cli.process_byte::<Command<'_>, _>(
                 buffer[0],
                 &mut Command::processor(|cli, command| {
                     match command {
                         Command::Status { action } => {
                             match action {
                                 StatusCommand::Get => {
-                                    // Display servo positions
                                     uwrite!(
                                         cli.writer(),
-                                        "System Status:\r\n  Servos - Left: {}, Right: {}\r\n",
-                                        state_copy.servos.left,
-                                        state_copy.servos.right,
+                                        "System Status:\r\n  Device: {} ({:?})\r\n  Power: {}\r\n",
+                                        crate::identity::device_name().as_str(),
+                                        MacAddress(crate::identity::mac()),
+                                        if state_copy.power { "on" } else { "off" }
                                     )?;
 
+                                    // Display servo positions
+                                    uwrite!(cli.writer(), "  Servos - Left: ")?;
+                                    display_servo_mode(cli.writer(), &state_copy.servos.left)?;
+                                    uwrite!(cli.writer(), ", Right: ")?;
+                                    display_servo_mode(cli.writer(), &state_copy.servos.right)?;
+                                    for (name, mode) in [
+                                        ("Aux0", &state_copy.servos.aux0),
+                                        ("Aux1", &state_copy.servos.aux1),
+                                    ] {
+                                        if let Some(mode) = mode {
+                                            uwrite!(cli.writer(), ", {}: ", name)?;
+                                            display_servo_mode(cli.writer(), mode)?;
+                                        }
+                                    }
+                                    uwrite!(cli.writer(), "\r\n")?;
+
                                     // Display light modes
                                     uwrite!(cli.writer(), "  Lights:\r\n")?;
                                     uwrite!(cli.writer(), "    Left: ")?;
@@ -336,202 +1292,1068 @@ async fn handler(
                                     display_light_mode(cli.writer(), &state_copy.lights.right)?;
                                     uwrite!(
                                         cli.writer(),
-                                        "\r\n    Brightness: {}\r\n",
-                                        state_copy.lights.brightness
+                                        "\r\n    Brightness: {}, auto {}, dither {}, transition {} ms\r\n",
+                                        state_copy.lights.brightness,
+                                        if state_copy.lights.auto_brightness { "on" } else { "off" },
+                                        if state_copy.lights.dither { "on" } else { "off" },
+                                        state_copy.lights.transition_ms
+                                    )?;
+                                    uwrite!(cli.writer(), "    White balance: ")?;
+                                    display_white_balance(cli.writer(), state_copy.lights.white_balance)?;
+                                    uwrite!(cli.writer(), "\r\n")?;
+                                    uwrite!(
+                                        cli.writer(),
+                                        "    Ring tops: left LED {}{}, right LED {}{}\r\n",
+                                        state_copy.lights.rotation_offset_left,
+                                        if state_copy.lights.reversed_left { " reversed" } else { "" },
+                                        state_copy.lights.rotation_offset_right,
+                                        if state_copy.lights.reversed_right { " reversed" } else { "" }
                                     )?;
+                                    let playlist = state_copy.lights.playlist.entries();
+                                    if !playlist.is_empty() {
+                                        uwrite!(cli.writer(), "    Playlist:")?;
+                                        for entry in playlist {
+                                            uwrite!(cli.writer(), " {} {} s", entry.preset.name(), entry.duration_s)?;
+                                        }
+                                        uwrite!(cli.writer(), "\r\n")?;
+                                    }
+                                    if status.ambient.is_present() {
+                                        uwrite!(
+                                            cli.writer(),
+                                            "    Ambient light: {}, brightness scale {}\r\n",
+                                            status.ambient.level(),
+                                            status.ambient.scale()
+                                        )?;
+                                    }
 
                                     // Display audio status
-                                    uwrite!(cli.writer(), "  Audio:\r\n    Mode: ")?;
-                                    display_audio_mode(cli.writer(), &state_copy.speakers.mode)?;
+                                    uwrite!(cli.writer(), "  Audio:\r\n    Left: ")?;
+                                    display_audio_mode(cli.writer(), &state_copy.speakers.left)?;
+                                    uwrite!(cli.writer(), "\r\n    Right: ")?;
+                                    display_audio_mode(cli.writer(), &state_copy.speakers.right)?;
                                     uwrite!(
                                         cli.writer(),
-                                        "\r\n    Volume: {}\r\n",
+                                        "\r\n    Volume: {}\r\n    Balance: ",
                                         state_copy.speakers.volume
                                     )?;
-                                }
-                            }
-                        }
-                        Command::Light { action } => match action {
-                            LightCommand::Get { side } => {
-                                let mode = match side {
-                                    Side::Left => &state_copy.lights.left,
-                                    Side::Right => &state_copy.lights.right,
-                                };
-                                uwrite!(cli.writer(), "Light {:?}: ", side)?;
-                                display_light_mode(cli.writer(), mode)?;
-                                uwrite!(cli.writer(), "\r\n")?;
-                            }
-                            LightCommand::Solid { side, r, g, b } => {
-                                let color = RGB8::new(r, g, b);
-                                match side {
-                                    Side::Left => {
-                                        state_copy.lights.left = crate::lights::Mode::Solid(color);
+                                    display_balance(cli.writer(), state_copy.speakers.balance)?;
+                                    uwrite!(cli.writer(), "\r\n")?;
+                                    for (name, underruns) in [
+                                        ("left", &status.underruns_left),
+                                        ("right", &status.underruns_right),
+                                    ] {
                                         uwrite!(
                                             cli.writer(),
-                                            "Set left light to solid RGB({},{},{})\r\n",
-                                            r,
-                                            g,
-                                            b
+                                            "    Underruns {}: {}, longest {} us\r\n",
+                                            name,
+                                            underruns.count(),
+                                            underruns.longest_us()
                                         )?;
                                     }
-                                    Side::Right => {
-                                        state_copy.lights.right = crate::lights::Mode::Solid(color);
+                                    for (name, output) in [
+                                        ("left", &status.output_left),
+                                        ("right", &status.output_right),
+                                    ] {
+                                        let levels = output.get();
                                         uwrite!(
                                             cli.writer(),
-                                            "Set right light to solid RGB({},{},{})\r\n",
-                                            r,
-                                            g,
-                                            b
+                                            "    Output level {}: {} RMS, {} peak\r\n",
+                                            name,
+                                            levels.rms,
+                                            levels.peak
                                         )?;
                                     }
+
+                                    // Display peripheral health
+                                    uwrite!(cli.writer(), "  Health:\r\n")?;
+                                    for (name, health) in [
+                                        ("LED left", &status.led_left),
+                                        ("LED right", &status.led_right),
+                                        ("Servo left", &status.servo_left),
+                                        ("Servo right", &status.servo_right),
+                                        #[cfg(feature = "aux-servos")]
+                                        ("Servo aux", &status.servo_aux),
+                                        ("Speaker left", &status.speaker_left),
+                                        ("Speaker right", &status.speaker_right),
+                                        ("Microphone", &status.microphone),
+                                        ("IMU", &status.imu),
+                                        ("Remote", &status.remote),
+                                    ] {
+                                        uwrite!(cli.writer(), "    {}: ", name)?;
+                                        display_health(cli.writer(), health)?;
+                                        uwrite!(cli.writer(), "\r\n")?;
+                                    }
+                                }
+                            }
+                        }
+                        Command::System { action } => match action {
+                            SystemCommand::Info => {
+                                let memory = &status.memory;
+                                uwrite!(
+                                    cli.writer(),
+                                    "System Info:\r\n  Heap: {} of {} bytes used, {} free, peak {}\r\n  Boot cause: {}\r\n",
+                                    memory.used(),
+                                    memory.size(),
+                                    memory.free(),
+                                    memory.peak(),
+                                    status.sleep.wake_cause().name()
+                                )?;
+                                match status.thermal.celsius() {
+                                    Some(celsius) => uwrite!(
+                                        cli.writer(),
+                                        "  Temperature: {}C ({})\r\n",
+                                        celsius,
+                                        status.thermal.level().name()
+                                    )?,
+                                    None => uwrite!(cli.writer(), "  Temperature: unknown\r\n")?,
+                                }
+                                uwrite!(
+                                    cli.writer(),
+                                    "  Boot: {}\r\n  Logs: {} bytes buffered, {} frames dropped, network {}\r\n",
+                                    status.boot.overall().name(),
+                                    status.logs.len(),
+                                    status.logs.dropped(),
+                                    if status.logs.network() { "on" } else { "off" }
+                                )?;
+                                let resets = &status.resets;
+                                uwrite!(
+                                    cli.writer(),
+                                    "  Uptime: {}s\r\n  Resets: {} since power on, last by {}, previous session up {}s\r\n",
+                                    embassy_time::Instant::now().as_secs(),
+                                    resets.reboots(),
+                                    resets.cause().name(),
+                                    resets.previous_uptime_s()
+                                )?;
+                                uwrite!(
+                                    cli.writer(),
+                                    "  Supply: {}, shed load {} times, {} brownouts in a row\r\n",
+                                    if status.supply.is_active() { "shedding load" } else { "normal" },
+                                    status.supply.events(),
+                                    resets.brownouts()
+                                )?;
+                                display_battery(cli.writer(), status.battery.percent())?;
+                                uwrite!(
+                                    cli.writer(),
+                                    "  Status LED: {}\r\n",
+                                    if state_copy.status_led { "on" } else { "off" }
+                                )?;
+                            }
+                            SystemCommand::Battery { charge } => {
+                                match charge {
+                                    Some(BatteryCharge::Percent(percent)) => {
+                                        status.battery.publish(percent);
+                                    }
+                                    Some(BatteryCharge::Clear) => status.battery.clear(),
+                                    None => {}
+                                }
+                                display_battery(cli.writer(), status.battery.percent())?;
+                            }
+                            SystemCommand::Led { switch } => {
+                                state_copy.status_led = switch == Switch::On;
+                                uwrite!(
+                                    cli.writer(),
+                                    "Status LED {}\r\n",
+                                    if switch == Switch::On { "on" } else { "off" }
+                                )?;
+                            }
+                            SystemCommand::Uart { switch } => {
+                                state_copy.uart_cli = switch == Switch::On;
+                                uwrite!(
+                                    cli.writer(),
+                                    "UART command line {}\r\n",
+                                    if state_copy.uart_cli { "on" } else { "off" }
+                                )?;
+                            }
+                            SystemCommand::Timing { action: None } => {
+                                uwrite!(
+                                    cli.writer(),
+                                    "Task Timing (last, longest, overruns of budget):\r\n"
+                                )?;
+                                for task in crate::watchdog::Task::ALL {
+                                    let stats = status.timing.get(task);
+                                    uwrite!(
+                                        cli.writer(),
+                                        "  {}: {}us, {}us, {} over {}us\r\n",
+                                        task.name(),
+                                        stats.last_us,
+                                        stats.max_us,
+                                        stats.overruns,
+                                        crate::timing::budget_us(task)
+                                    )?;
                                 }
                             }
-                            LightCommand::Off { side } => match side {
-                                Side::Left => {
-                                    state_copy.lights.left = crate::lights::Mode::Off;
-                                    uwrite!(cli.writer(), "Turned off left light\r\n")?;
+                            SystemCommand::Timing {
+                                action: Some(TimingAction::Reset),
+                            } => {
+                                status.timing.reset();
+                                uwrite!(cli.writer(), "Cleared task timings\r\n")?;
+                            }
+                            SystemCommand::Log { action } => match action {
+                                LogCommand::Net { switch } => {
+                                    status.logs.set_network(switch == Switch::On);
+                                    uwrite!(
+                                        cli.writer(),
+                                        "Network logging {}\r\n",
+                                        if switch == Switch::On { "on" } else { "off" }
+                                    )?;
                                 }
-                                Side::Right => {
-                                    state_copy.lights.right = crate::lights::Mode::Off;
-                                    uwrite!(cli.writer(), "Turned off right light\r\n")?;
+                                LogCommand::Dump => {
+                                    let mut chunk = [0u8; crate::logging::MAX_FRAME_LEN];
+                                    loop {
+                                        let len = status.logs.take_frames(&mut chunk);
+                                        if len == 0 {
+                                            break;
+                                        }
+                                        write_frames_hex(cli.writer(), &chunk[..len])?;
+                                    }
+                                    uwrite!(
+                                        cli.writer(),
+                                        "{} frames dropped since boot\r\n",
+                                        status.logs.dropped()
+                                    )?;
                                 }
                             },
-                            LightCommand::Rainbow { side } => {
-                                let pattern = crate::lights::RainbowPattern::new(500);
-                                match side {
-                                    Side::Left => {
-                                        state_copy.lights.left =
-                                            crate::lights::Mode::Rainbow(pattern);
+                            SystemCommand::Boot => {
+                                uwrite!(
+                                    cli.writer(),
+                                    "Boot: {}\r\n",
+                                    status.boot.overall().name()
+                                )?;
+                                for stage in crate::startup::Stage::ALL {
+                                    uwrite!(
+                                        cli.writer(),
+                                        "  {}: {} ({}ms)\r\n",
+                                        stage.name(),
+                                        status.boot.outcome(stage).name(),
+                                        status.boot.duration_ms(stage)
+                                    )?;
+                                }
+                            }
+                            SystemCommand::Lastcrash { action: None } => {
+                                match status.last_crash.get() {
+                                    crate::crash::Stored::Empty => {
+                                        uwrite!(cli.writer(), "No crash recorded\r\n")?;
+                                    }
+                                    crate::crash::Stored::Corrupted => {
                                         uwrite!(
                                             cli.writer(),
-                                            "Set left light to rainbow pattern\r\n"
+                                            "Last crash: record corrupted, clear it to record the next one cleanly\r\n"
                                         )?;
                                     }
-                                    Side::Right => {
-                                        state_copy.lights.right =
-                                            crate::lights::Mode::Rainbow(pattern);
+                                    crate::crash::Stored::Crash(record) => {
                                         uwrite!(
                                             cli.writer(),
-                                            "Set right light to rainbow pattern\r\n"
+                                            "Last crash: {} after {}s up, firmware {}\r\n",
+                                            record.cause.name(),
+                                            record.uptime_s,
+                                            record.version.as_str()
                                         )?;
+                                        if record.has_location() {
+                                            uwrite!(
+                                                cli.writer(),
+                                                "  At {}:{}:{}: {}\r\n",
+                                                record.file.as_str(),
+                                                record.line,
+                                                record.column,
+                                                record.message.as_str()
+                                            )?;
+                                        }
                                     }
                                 }
                             }
-                            LightCommand::Pulse { side, r, g, b } => {
+                            SystemCommand::Lastcrash {
+                                action: Some(CrashAction::Clear),
+                            } => {
+                                status.last_crash.clear();
+                                uwrite!(cli.writer(), "Cleared last crash\r\n")?;
+                            }
+                        },
+                        Command::Light { action } => match action {
+                            LightCommand::Get { side } => {
+                                for (ring, mode) in [
+                                    (Side::Left, &state_copy.lights.left),
+                                    (Side::Right, &state_copy.lights.right),
+                                ] {
+                                    if side.includes(ring) {
+                                        uwrite!(cli.writer(), "Light {:?}: ", ring)?;
+                                        display_light_mode(cli.writer(), mode)?;
+                                        uwrite!(cli.writer(), "\r\n")?;
+                                    }
+                                }
+                            }
+                            LightCommand::Solid { side, r, g, b } => {
+                                let name = side.set(
+                                    &mut state_copy.lights,
+                                    crate::lights::Mode::Solid(RGB8::new(r, g, b)),
+                                );
+                                uwrite!(cli.writer(), "Set {} to solid RGB({},{},{})\r\n", name, r, g, b)?;
+                            }
+                            LightCommand::Off { side } => {
+                                let name = side.set(&mut state_copy.lights, crate::lights::Mode::Off);
+                                uwrite!(cli.writer(), "Turned off {}\r\n", name)?;
+                            }
+                            LightCommand::Rainbow {
+                                side,
+                                saturation,
+                                reversed,
+                            } => {
+                                let mut pattern = crate::lights::RainbowPattern::new(500)
+                                    .with_saturation(saturation.unwrap_or(u8::MAX));
+                                if reversed == Some(Switch::On) {
+                                    pattern = pattern.reversed();
+                                }
+                                let name =
+                                    side.set(&mut state_copy.lights, crate::lights::Mode::Rainbow(pattern));
+                                uwrite!(cli.writer(), "Set {} to rainbow pattern\r\n", name)?;
+                            }
+                            LightCommand::Pulse {
+                                side,
+                                r,
+                                g,
+                                b,
+                                shape,
+                            } => {
                                 let color = RGB8::new(r, g, b);
-                                let pattern = crate::lights::PulsePattern::new(color, 1000);
-                                match side {
-                                    Side::Left => {
-                                        state_copy.lights.left =
-                                            crate::lights::Mode::Pulse(pattern);
+                                let pattern = crate::lights::PulsePattern::new(color, 1000)
+                                    .with_shape(shape.unwrap_or_default());
+                                let name = side.set(&mut state_copy.lights, crate::lights::Mode::Pulse(pattern));
+                                uwrite!(cli.writer(), "Set {} to pulse RGB({},{},{})\r\n", name, r, g, b)?;
+                            }
+                            LightCommand::Gradient {
+                                side,
+                                r1,
+                                g1,
+                                b1,
+                                r2,
+                                g2,
+                                b2,
+                                rotate,
+                                mirrored,
+                            } => {
+                                let mut pattern = crate::lights::GradientPattern::new(
+                                    RGB8::new(r1, g1, b1),
+                                    RGB8::new(r2, g2, b2),
+                                )
+                                .with_rotation(rotate.unwrap_or(0));
+                                if mirrored == Some(Switch::On) {
+                                    pattern = pattern.mirrored();
+                                }
+                                let mode = crate::lights::Mode::Gradient(pattern);
+                                let name = side.set(&mut state_copy.lights, mode);
+                                uwrite!(cli.writer(), "Set {} to ", name)?;
+                                display_light_mode(cli.writer(), &mode)?;
+                                uwrite!(cli.writer(), "\r\n")?;
+                            }
+                            LightCommand::Beat { side, r, g, b } => {
+                                let pattern =
+                                    crate::lights::BeatPattern::new(RGB8::new(r, g, b), 150);
+                                let name =
+                                    side.set(&mut state_copy.lights, crate::lights::Mode::BeatPulse(pattern));
+                                uwrite!(
+                                    cli.writer(),
+                                    "Set {} to beat pulse RGB({},{},{})\r\n",
+                                    name,
+                                    r,
+                                    g,
+                                    b
+                                )?;
+                            }
+                            LightCommand::Comet { side, r, g, b } => {
+                                let pattern =
+                                    crate::lights::CometPattern::new(RGB8::new(r, g, b), 100);
+                                let name = side.set(&mut state_copy.lights, crate::lights::Mode::Comet(pattern));
+                                uwrite!(cli.writer(), "Set {} to comet RGB({},{},{})\r\n", name, r, g, b)?;
+                            }
+                            LightCommand::Theater {
+                                side,
+                                r,
+                                g,
+                                b,
+                                spacing,
+                            } => {
+                                let spacing = spacing.unwrap_or(3).max(1);
+                                let pattern = crate::lights::TheaterChasePattern::new(
+                                    RGB8::new(r, g, b),
+                                    spacing,
+                                    100,
+                                );
+                                let name =
+                                    side.set(&mut state_copy.lights, crate::lights::Mode::TheaterChase(pattern));
+                                uwrite!(
+                                    cli.writer(),
+                                    "Set {} to theater chase RGB({},{},{}) every {} LEDs\r\n",
+                                    name,
+                                    r,
+                                    g,
+                                    b,
+                                    spacing
+                                )?;
+                            }
+                            LightCommand::Sparkle { side, r, g, b } => {
+                                if let (Some(r), Some(g), Some(b)) = (r, g, b) {
+                                    let name = side.set(
+                                        &mut state_copy.lights,
+                                        crate::lights::Mode::Sparkle(crate::lights::SparklePattern::new(
+                                            RGB8::new(r, g, b),
+                                        )),
+                                    );
+                                    uwrite!(
+                                        cli.writer(),
+                                        "Set {} to sparkle RGB({},{},{})\r\n",
+                                        name,
+                                        r,
+                                        g,
+                                        b
+                                    )?;
+                                } else {
+                                    let name = side.set(
+                                        &mut state_copy.lights,
+                                        crate::lights::Mode::Sparkle(crate::lights::SparklePattern::default()),
+                                    );
+                                    uwrite!(cli.writer(), "Set {} to sparkle in random hues\r\n", name)?;
+                                }
+                            }
+                            LightCommand::Brightness { value } => {
+                                state_copy.lights.brightness = value;
+                                uwrite!(cli.writer(), "Set brightness to {}\r\n", value)?;
+                            }
+                            LightCommand::Auto { switch } => {
+                                state_copy.lights.auto_brightness = switch == Switch::On;
+                                uwrite!(
+                                    cli.writer(),
+                                    "Automatic brightness {}\r\n",
+                                    if state_copy.lights.auto_brightness { "on" } else { "off" }
+                                )?;
+                            }
+                            LightCommand::Transition { ms } => {
+                                state_copy.lights.transition_ms = ms;
+                                uwrite!(cli.writer(), "Set light transition to {} ms\r\n", ms)?;
+                            }
+                            LightCommand::Dither { switch } => {
+                                state_copy.lights.dither = switch == Switch::On;
+                                uwrite!(
+                                    cli.writer(),
+                                    "Brightness dithering {}\r\n",
+                                    if state_copy.lights.dither { "on" } else { "off" }
+                                )?;
+                            }
+                            LightCommand::Battery { side } => {
+                                let name = side.set(&mut state_copy.lights, crate::lights::Mode::BatteryGauge);
+                                uwrite!(cli.writer(), "Set {} to battery gauge\r\n", name)?;
+                            }
+                            LightCommand::Progress { side, percent } => {
+                                let percent = percent.min(100);
+                                let name = side.set(&mut state_copy.lights, crate::lights::progress(percent));
+                                uwrite!(cli.writer(), "Set {} to progress {}%\r\n", name, percent)?;
+                            }
+                            LightCommand::Segment {
+                                side,
+                                start,
+                                len,
+                                r,
+                                g,
+                                b,
+                            } => match (start, len, r, g, b) {
+                                (SegmentStart::Clear, None, None, None, None) => {
+                                    let name = side.set(
+                                        &mut state_copy.lights,
+                                        crate::lights::Mode::Segments(
+                                            crate::lights::SegmentsPattern::new(),
+                                        ),
+                                    );
+                                    uwrite!(cli.writer(), "Cleared the segments of {}\r\n", name)?;
+                                }
+                                (SegmentStart::Led(start), Some(len), Some(r), Some(g), Some(b)) => {
+                                    // Arcs add up on a ring already showing some.
+                                    let pattern = match side.mode(&state_copy.lights) {
+                                        crate::lights::Mode::Segments(pattern) => *pattern,
+                                        _ => crate::lights::SegmentsPattern::new(),
+                                    };
+                                    let segment = crate::lights::Segment::new(start, len, RGB8::new(r, g, b));
+                                    let name = side.set(
+                                        &mut state_copy.lights,
+                                        crate::lights::Mode::Segments(pattern.with_segment(segment)),
+                                    );
+                                    uwrite!(
+                                        cli.writer(),
+                                        "Set {} arc of {} LEDs from LED {} to RGB({},{},{})\r\n",
+                                        name,
+                                        segment.length,
+                                        start,
+                                        r,
+                                        g,
+                                        b
+                                    )?;
+                                }
+                                _ => {
+                                    uwrite!(
+                                        cli.writer(),
+                                        "Expected clear alone, or a start, length, and red, green, and blue values\r\n"
+                                    )?;
+                                }
+                            },
+                            LightCommand::Palette {
+                                side,
+                                palette,
+                                speed,
+                                spread,
+                            } => {
+                                let mut pattern =
+                                    crate::lights::PaletteCyclePattern::new(palette, speed.unwrap_or(4000));
+                                if spread == Some(Switch::Off) {
+                                    pattern = pattern.unified();
+                                }
+                                let name =
+                                    side.set(&mut state_copy.lights, crate::lights::Mode::PaletteCycle(pattern));
+                                uwrite!(cli.writer(), "Set {} to {} palette\r\n", name, palette.name())?;
+                            }
+                            LightCommand::Palettes => {
+                                uwrite!(cli.writer(), "Available palettes:\r\n")?;
+                                for palette in crate::lights::PaletteId::ALL {
+                                    uwrite!(cli.writer(), "  {}\r\n", palette.name())?;
+                                }
+                            }
+                            LightCommand::Playlist { action } => match action {
+                                PlaylistCommand::Show => {
+                                    let playlist = &state_copy.lights.playlist;
+                                    if playlist.entries().is_empty() {
+                                        uwrite!(cli.writer(), "Light playlist is empty\r\n")?;
+                                    } else {
+                                        uwrite!(cli.writer(), "Light playlist:\r\n")?;
+                                        for entry in playlist.entries() {
+                                            uwrite!(
+                                                cli.writer(),
+                                                "  {} for {} s\r\n",
+                                                entry.preset.name(),
+                                                entry.duration_s
+                                            )?;
+                                        }
+                                    }
+                                    uwrite!(cli.writer(), "Available presets: ")?;
+                                    for (i, preset) in crate::lights::patterns::Preset::ALL.iter().enumerate() {
+                                        if i > 0 {
+                                            uwrite!(cli.writer(), ", ")?;
+                                        }
+                                        uwrite!(cli.writer(), "{}", preset.name())?;
+                                    }
+                                    uwrite!(cli.writer(), "\r\n")?;
+                                }
+                                PlaylistCommand::Add { preset, seconds } => {
+                                    let playlist = &mut state_copy.lights.playlist;
+                                    if playlist.is_full() {
                                         uwrite!(
                                             cli.writer(),
-                                            "Set left light to pulse RGB({},{},{})\r\n",
-                                            r,
-                                            g,
-                                            b
+                                            "Light playlist is full at {} entries\r\n",
+                                            crate::lights::Playlist::MAX_ENTRIES
                                         )?;
-                                    }
-                                    Side::Right => {
-                                        state_copy.lights.right =
-                                            crate::lights::Mode::Pulse(pattern);
+                                    } else {
+                                        *playlist = playlist
+                                            .with_entry(crate::lights::PlaylistEntry::new(preset, seconds));
                                         uwrite!(
                                             cli.writer(),
-                                            "Set right light to pulse RGB({},{},{})\r\n",
-                                            r,
-                                            g,
-                                            b
+                                            "Added {} for {} s to the light playlist\r\n",
+                                            preset.name(),
+                                            seconds
                                         )?;
                                     }
                                 }
+                                PlaylistCommand::Clear => {
+                                    state_copy.lights.playlist = crate::lights::Playlist::new();
+                                    uwrite!(cli.writer(), "Cleared the light playlist\r\n")?;
+                                }
+                            },
+                            LightCommand::Strobe {
+                                side,
+                                r,
+                                g,
+                                b,
+                                on,
+                                off,
+                                count,
+                            } => {
+                                let mut pattern = crate::lights::StrobePattern::new(
+                                    RGB8::new(r, g, b),
+                                    on.unwrap_or(50),
+                                    off.unwrap_or(100).max(crate::lights::StrobePattern::MIN_OFF_MS),
+                                );
+                                if let Some(count) = count {
+                                    pattern = pattern.with_count(count);
+                                }
+                                let name = side.set(&mut state_copy.lights, crate::lights::Mode::Strobe(pattern));
+                                uwrite!(cli.writer(), "Set {} to strobe RGB({},{},{})\r\n", name, r, g, b)?;
                             }
-                            LightCommand::Brightness { value } => {
-                                state_copy.lights.brightness = value;
-                                uwrite!(cli.writer(), "Set brightness to {}\r\n", value)?;
+                            LightCommand::Whitebalance { white, g, b } => match (white, g, b) {
+                                (WhitePoint::Preset(correction), None, None) => {
+                                    state_copy.lights.white_balance = correction;
+                                    uwrite!(cli.writer(), "Set white balance to ")?;
+                                    display_white_balance(cli.writer(), correction)?;
+                                    uwrite!(cli.writer(), "\r\n")?;
+                                }
+                                (WhitePoint::Red(r), Some(g), Some(b)) => {
+                                    let correction = crate::lights::ColorCorrection::new(r, g, b);
+                                    state_copy.lights.white_balance = correction;
+                                    uwrite!(cli.writer(), "Set white balance to ")?;
+                                    display_white_balance(cli.writer(), correction)?;
+                                    uwrite!(cli.writer(), "\r\n")?;
+                                }
+                                _ => {
+                                    uwrite!(
+                                        cli.writer(),
+                                        "Expected a preset alone, or red, green, and blue scales\r\n"
+                                    )?;
+                                }
+                            },
+                            LightCommand::Offset { side, n } => {
+                                #[allow(clippy::cast_possible_truncation)]
+                                let n = (usize::from(n) % crate::lights::LED_COUNT) as u8;
+                                let (offset, name) = match side {
+                                    Side::Left => (&mut state_copy.lights.rotation_offset_left, "left"),
+                                    Side::Right => {
+                                        (&mut state_copy.lights.rotation_offset_right, "right")
+                                    }
+                                };
+                                *offset = n;
+                                uwrite!(cli.writer(), "Set {} ring top to LED {}\r\n", name, n)?;
+                            }
+                            LightCommand::Reverse { side, switch } => {
+                                let (reversed, name) = match side {
+                                    Side::Left => (&mut state_copy.lights.reversed_left, "Left"),
+                                    Side::Right => (&mut state_copy.lights.reversed_right, "Right"),
+                                };
+                                *reversed = switch.map_or(!*reversed, |switch| switch == Switch::On);
+                                uwrite!(
+                                    cli.writer(),
+                                    "{} ring wired {}\r\n",
+                                    name,
+                                    if *reversed { "counter-clockwise" } else { "clockwise" }
+                                )?;
                             }
                         },
                         Command::Servo { action } => match action {
-                            ServoCommand::Get { side } => {
-                                let value = match side {
-                                    Side::Left => state_copy.servos.left,
-                                    Side::Right => state_copy.servos.right,
+                            ServoCommand::Get { servo } => {
+                                let mode = match servo {
+                                    ServoName::Left => Some(&state_copy.servos.left),
+                                    ServoName::Right => Some(&state_copy.servos.right),
+                                    ServoName::Aux0 => state_copy.servos.aux0.as_ref(),
+                                    ServoName::Aux1 => state_copy.servos.aux1.as_ref(),
                                 };
-                                uwrite!(cli.writer(), "Servo {:?}: {}\r\n", side, value)?;
+                                uwrite!(cli.writer(), "Servo {:?}: ", servo)?;
+                                match mode {
+                                    Some(mode) => display_servo_mode(cli.writer(), mode)?,
+                                    None => uwrite!(cli.writer(), "unset")?,
+                                }
+                                uwrite!(cli.writer(), "\r\n")?;
                             }
-                            ServoCommand::Set { side, value } => match side {
-                                Side::Left => {
-                                    state_copy.servos.left = value;
+                            ServoCommand::Set { servo, value } => match servo {
+                                ServoName::Left => {
+                                    state_copy.servos.left = crate::state::ServoMode::Static(value);
                                     uwrite!(cli.writer(), "Set left servo to {}\r\n", value)?;
                                 }
-                                Side::Right => {
-                                    state_copy.servos.right = value;
+                                ServoName::Right => {
+                                    state_copy.servos.right =
+                                        crate::state::ServoMode::Static(value);
                                     uwrite!(cli.writer(), "Set right servo to {}\r\n", value)?;
                                 }
+                                ServoName::Aux0 => {
+                                    state_copy.servos.aux0 =
+                                        Some(crate::state::ServoMode::Static(value));
+                                    uwrite!(cli.writer(), "Set aux0 servo to {}\r\n", value)?;
+                                }
+                                ServoName::Aux1 => {
+                                    state_copy.servos.aux1 =
+                                        Some(crate::state::ServoMode::Static(value));
+                                    uwrite!(cli.writer(), "Set aux1 servo to {}\r\n", value)?;
+                                }
+                            },
+                            ServoCommand::Gesture { side, name } => match (side, name) {
+                                (GestureSide::List, _) => {
+                                    uwrite!(cli.writer(), "Available gestures:\r\n")?;
+                                    for gesture in Gesture::ALL {
+                                        uwrite!(cli.writer(), "  {}\r\n", gesture.name())?;
+                                    }
+                                }
+                                (GestureSide::Target(_), None) => {
+                                    uwrite!(
+                                        cli.writer(),
+                                        "Missing gesture name, expected one of: "
+                                    )?;
+                                    display_gesture_names(cli.writer())?;
+                                    uwrite!(cli.writer(), "\r\n")?;
+                                }
+                                (GestureSide::Target(target), Some(GestureName(gesture))) => {
+                                    if gestures
+                                        .try_send(GestureRequest { target, gesture })
+                                        .is_ok()
+                                    {
+                                        uwrite!(
+                                            cli.writer(),
+                                            "Playing gesture: {}\r\n",
+                                            gesture.name()
+                                        )?;
+                                    } else {
+                                        uwrite!(cli.writer(), "Gesture queue full, try again\r\n")?;
+                                    }
+                                }
                             },
                         },
                         Command::Audio { action } => match action {
                             AudioCommand::Get => {
-                                uwrite!(cli.writer(), "Audio - Mode: ")?;
-                                display_audio_mode(cli.writer(), &state_copy.speakers.mode)?;
+                                uwrite!(cli.writer(), "Audio - Left: ")?;
+                                display_audio_mode(cli.writer(), &state_copy.speakers.left)?;
+                                uwrite!(cli.writer(), ", Right: ")?;
+                                display_audio_mode(cli.writer(), &state_copy.speakers.right)?;
                                 uwrite!(
                                     cli.writer(),
-                                    ", Volume: {}\r\n",
-                                    state_copy.speakers.volume
+                                    ", Volume: {}{}{}, Balance: ",
+                                    state_copy.speakers.volume,
+                                    if state_copy.speakers.muted { " (muted)" } else { "" },
+                                    if state_copy.speakers.paused { " (paused)" } else { "" }
                                 )?;
+                                display_balance(cli.writer(), state_copy.speakers.balance)?;
+                                uwrite!(cli.writer(), "\r\n")?;
                             }
-                            AudioCommand::Silent => {
-                                state_copy.speakers.mode = crate::audio::Mode::Silent;
-                                uwrite!(cli.writer(), "Set audio to silent\r\n")?;
+                            AudioCommand::Silent { side } => {
+                                *state_copy.speakers.mode_mut(side.into()) =
+                                    crate::audio::Mode::Silent;
+                                uwrite!(cli.writer(), "Set {:?} audio to silent\r\n", side)?;
                             }
-                            AudioCommand::Tone { freq, duration } => {
-                                let note = crate::audio::Note::new(f32::from(freq), duration);
-                                state_copy.speakers.mode = crate::audio::Mode::Tone(note);
+                            AudioCommand::Tone {
+                                side,
+                                freq,
+                                duration,
+                                waveform,
+                            } => {
+                                let waveform = waveform.unwrap_or_default();
+                                let note = crate::audio::Note::new(f32::from(freq), duration)
+                                    .with_waveform(waveform);
+                                *state_copy.speakers.mode_mut(side.into()) =
+                                    crate::audio::Mode::Tone(note);
                                 uwrite!(
                                     cli.writer(),
-                                    "Playing tone: {}Hz for {}ms\r\n",
+                                    "Playing {} tone on {:?}: {}Hz for {}ms\r\n",
+                                    waveform.name(),
+                                    side,
                                     freq,
                                     duration
                                 )?;
                             }
-                            AudioCommand::Chiptune { name } => {
-                                let sequence = match name {
-                                    ChiptuneName::Coin => crate::audio::chiptunes::coin_collect(),
-                                    ChiptuneName::PowerUp => crate::audio::chiptunes::power_up(),
-                                    ChiptuneName::LevelComplete => {
-                                        crate::audio::chiptunes::level_complete()
+                            AudioCommand::Chiptune { side, names } => {
+                                // A single chiptune plays as it is, only a chain of them needs a playlist.
+                                let mode = match names {
+                                    ChiptuneNames::Several(playlist) => match playlist.tunes() {
+                                        [preset] => crate::audio::Mode::NamedChiptune((*preset).into()),
+                                        _ => crate::audio::Mode::Playlist(playlist),
+                                    },
+                                    ChiptuneNames::One(name) => crate::audio::Mode::NamedChiptune(name),
+                                };
+                                *state_copy.speakers.mode_mut(side.into()) = mode;
+                                uwrite!(cli.writer(), "Playing chiptune on {:?}: ", side)?;
+                                match names {
+                                    ChiptuneNames::One(name) => uwrite!(cli.writer(), "{}", name.as_str())?,
+                                    ChiptuneNames::Several(playlist) => display_playlist(cli.writer(), &playlist)?,
+                                }
+                                uwrite!(cli.writer(), "\r\n")?;
+                            }
+                            AudioCommand::Rtttl { side, tune } => {
+                                match crate::audio::ChiptuneSequence::from_rtttl(tune) {
+                                    Ok(sequence) => {
+                                        *state_copy.speakers.mode_mut(side.into()) =
+                                            crate::audio::Mode::Chiptune(sequence);
+                                        uwrite!(
+                                            cli.writer(),
+                                            "Playing ringtone on {:?}: {} notes\r\n",
+                                            side,
+                                            sequence.length
+                                        )?;
                                     }
-                                    ChiptuneName::GameOver => crate::audio::chiptunes::game_over(),
-                                    ChiptuneName::MenuSelect => {
-                                        crate::audio::chiptunes::menu_select()
+                                    Err(err) => {
+                                        uwrite!(cli.writer(), "Invalid ringtone: {:?}\r\n", err)?;
                                     }
-                                    ChiptuneName::Alert => crate::audio::chiptunes::alert(),
-                                    ChiptuneName::Happy => crate::audio::chiptunes::happy(),
-                                    ChiptuneName::Sad => crate::audio::chiptunes::sad(),
-                                    ChiptuneName::Startup => crate::audio::chiptunes::startup(),
-                                    ChiptuneName::Shutdown => crate::audio::chiptunes::shutdown(),
-                                };
-                                state_copy.speakers.mode = crate::audio::Mode::Chiptune(sequence);
-                                uwrite!(cli.writer(), "Playing chiptune: {:?}\r\n", name)?;
+                                }
+                            }
+                            AudioCommand::Noise {
+                                side,
+                                amplitude,
+                                smoothing,
+                                wobble,
+                            } => {
+                                let noise = crate::audio::Noise::new(amplitude)
+                                    .with_smoothing(smoothing.unwrap_or_default())
+                                    .with_wobble(f32::from(wobble.unwrap_or_default()));
+                                *state_copy.speakers.mode_mut(side.into()) =
+                                    crate::audio::Mode::Noise(noise);
+                                uwrite!(
+                                    cli.writer(),
+                                    "Playing noise on {:?}: amplitude {}, smoothing {}, wobble {}Hz\r\n",
+                                    side,
+                                    amplitude,
+                                    noise.smoothing,
+                                    wobble.unwrap_or_default()
+                                )?;
+                            }
+                            AudioCommand::Sweep {
+                                side,
+                                start,
+                                end,
+                                duration,
+                                repeat,
+                            } => {
+                                let repeat = repeat == Some(Switch::On);
+                                let sweep =
+                                    crate::audio::Sweep::new(f32::from(start), f32::from(end), duration)
+                                        .with_repeat(repeat);
+                                *state_copy.speakers.mode_mut(side.into()) =
+                                    crate::audio::Mode::Sweep(sweep);
+                                uwrite!(
+                                    cli.writer(),
+                                    "Sweeping on {:?}: {}Hz to {}Hz over {}ms{}\r\n",
+                                    side,
+                                    start,
+                                    end,
+                                    duration,
+                                    if repeat { ", back and forth" } else { "" }
+                                )?;
+                            }
+                            AudioCommand::Dial { digits, tone, gap } => {
+                                let dial = crate::audio::dtmf::Dial::new(digits).with_timing(
+                                    tone.unwrap_or(crate::audio::dtmf::Dial::TONE_MS),
+                                    gap.unwrap_or(crate::audio::dtmf::Dial::GAP_MS),
+                                );
+                                state_copy.speakers.left = crate::audio::Mode::Dtmf(dial);
+                                state_copy.speakers.right = crate::audio::Mode::Dtmf(dial);
+                                uwrite!(cli.writer(), "Dialing {}\r\n", dial.digits.as_str())?;
+                            }
+                            AudioCommand::Stream { side, id, looping } => {
+                                let mut clip = crate::audio::RemoteClip::new(id);
+                                if looping == Some(Switch::On) {
+                                    clip = clip.with_loop();
+                                }
+                                *state_copy.speakers.mode_mut(side.into()) =
+                                    crate::audio::Mode::RemoteAudio(clip);
+                                uwrite!(
+                                    cli.writer(),
+                                    "Streaming clip {} on {:?}{}\r\n",
+                                    id,
+                                    side,
+                                    if clip.looping { ", looping" } else { "" }
+                                )?;
+                            }
+                            AudioCommand::Mute => {
+                                state_copy.speakers.muted = true;
+                                uwrite!(cli.writer(), "Muted the speakers\r\n")?;
+                            }
+                            AudioCommand::Unmute => {
+                                state_copy.speakers.muted = false;
+                                uwrite!(cli.writer(), "Unmuted the speakers\r\n")?;
+                            }
+                            AudioCommand::Pause => {
+                                state_copy.speakers.paused = true;
+                                uwrite!(cli.writer(), "Paused the speakers\r\n")?;
+                            }
+                            AudioCommand::Resume => {
+                                state_copy.speakers.paused = false;
+                                uwrite!(cli.writer(), "Resumed the speakers\r\n")?;
+                            }
+                            AudioCommand::Play {
+                                name: ChiptuneName(name),
+                            } => {
+                                let id = state_copy
+                                    .speakers
+                                    .event
+                                    .map_or(1, |event| event.id.wrapping_add(1).max(1));
+                                state_copy.speakers.event = Some(crate::audio::Event::new(
+                                    id,
+                                    crate::audio::Sound::Chiptune(name),
+                                ));
+                                uwrite!(cli.writer(), "Playing {} once\r\n", name.as_str())?;
                             }
                             AudioCommand::Volume { value } => {
                                 state_copy.speakers.volume = value;
                                 uwrite!(cli.writer(), "Set volume to {}\r\n", value)?;
                             }
+                            AudioCommand::Balance {
+                                value: Balance(balance),
+                            } => {
+                                state_copy.speakers.balance = balance;
+                                uwrite!(cli.writer(), "Set balance to ")?;
+                                display_balance(cli.writer(), balance)?;
+                                uwrite!(cli.writer(), "\r\n")?;
+                            }
+                        },
+                        Command::Imu { action } => {
+                            if status.motion.is_present() {
+                                match action {
+                                    ImuCommand::Get => {
+                                        let (pitch, roll) = status.motion.tilt();
+                                        uwrite!(
+                                            cli.writer(),
+                                            "IMU - Pitch: {}, Roll: {}, Shakes: {}\r\n",
+                                            pitch,
+                                            roll,
+                                            status.motion.shakes()
+                                        )?;
+                                    }
+                                    ImuCommand::Calibrate => {
+                                        status.motion.request_calibration();
+                                        uwrite!(
+                                            cli.writer(),
+                                            "Calibrating, hold your head level and still\r\n"
+                                        )?;
+                                    }
+                                }
+                            } else {
+                                uwrite!(cli.writer(), "No IMU detected\r\n")?;
+                            }
+                        }
+                        Command::Touch { action } => match action {
+                            TouchCommand::Get => {
+                                let (taps, strokes) = status.touch.counts();
+                                let (left_raw, left_baseline) = status.touch.pad(0);
+                                let (right_raw, right_baseline) = status.touch.pad(1);
+                                uwrite!(
+                                    cli.writer(),
+                                    "Touch - Left: {} (baseline {}), Right: {} (baseline {}), Threshold: {}%, Taps: {}, Strokes: {}\r\n",
+                                    left_raw,
+                                    left_baseline,
+                                    right_raw,
+                                    right_baseline,
+                                    state_copy.touch.threshold_pct,
+                                    taps,
+                                    strokes
+                                )?;
+                                if !status.touch.is_present() {
+                                    uwrite!(cli.writer(), "No touch pads set up\r\n")?;
+                                }
+                            }
+                            TouchCommand::Threshold { value } => {
+                                let value = value.clamp(1, 100);
+                                state_copy.touch.threshold_pct = value;
+                                uwrite!(cli.writer(), "Set touch threshold to {}%\r\n", value)?;
+                            }
+                            TouchCommand::Calibrate => {
+                                status.touch.request_calibration();
+                                uwrite!(
+                                    cli.writer(),
+                                    "Calibrating, keep your hands off the ears\r\n"
+                                )?;
+                            }
+                        },
+                        Command::Sleep { action } => match action {
+                            SleepCommand::Get => {
+                                uwrite!(
+                                    cli.writer(),
+                                    "Sleep - Idle timeout: {}s, Wake after: {}s, Woke by: {}\r\n",
+                                    state_copy.sleep.idle_timeout_s,
+                                    state_copy.sleep.wake_after_s,
+                                    status.sleep.wake_cause().name()
+                                )?;
+                            }
+                            SleepCommand::Now => {
+                                if status.sleep.can_wake(state_copy.sleep.wake_after_s) {
+                                    status.sleep.request();
+                                    uwrite!(cli.writer(), "Going to sleep\r\n")?;
+                                } else {
+                                    uwrite!(
+                                        cli.writer(),
+                                        "Nothing could wake the ears up, set a wake timer first\r\n"
+                                    )?;
+                                }
+                            }
+                            SleepCommand::Idle { seconds } => {
+                                state_copy.sleep.idle_timeout_s = seconds;
+                                uwrite!(cli.writer(), "Set idle timeout to {}s\r\n", seconds)?;
+                            }
+                            SleepCommand::Wake { seconds } => {
+                                state_copy.sleep.wake_after_s = seconds;
+                                uwrite!(cli.writer(), "Set wake timer to {}s\r\n", seconds)?;
+                            }
                         },
                     }
                     Ok(())
                 }),
             );
 
-            // Write the modified state back if it changed
-            {
-                let current_state = *state.read().await;
-                if current_state != state_copy {
-                    let mut writable_state = state.write().await;
-                    *writable_state = state_copy;
-                }
+            // Write the modified state back, which is a no-op for commands that only read it
+            let changed = *locked_state != state_copy;
+            *locked_state = state_copy;
+            drop(locked_state);
+            if changed {
+                crate::state::CHANGED.signal(());
+            }
+            status.timing.record(task, started);
+        }
+    }
+}
+
+/// Writes encoded log frames as hex, ending a line after each frame's zero terminator.
+fn write_frames_hex<W>(writer: &mut W, frames: &[u8]) -> Result<(), W::Error>
+where
+    W: ufmt::uWrite + ?Sized,
+{
+    for &byte in frames {
+        writer.write_str(core::str::from_utf8(&hex_byte(byte)).unwrap_or("??"))?;
+        if byte == 0 {
+            writer.write_str("\r\n")?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns a byte as two lowercase hex digits.
+fn hex_byte(byte: u8) -> [u8; 2] {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    [
+        DIGITS[usize::from(byte >> 4)],
+        DIGITS[usize::from(byte & 0xf)],
+    ]
+}
+
+/// MAC address displayed as colon-separated hex bytes.
+struct MacAddress([u8; 6]);
+
+impl uDebug for MacAddress {
+    fn fmt<W>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        for (i, &byte) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(":")?;
             }
+            f.write_str(core::str::from_utf8(&hex_byte(byte)).unwrap_or("??"))?;
         }
+        Ok(())
+    }
+}
+
+/// Helper function to display the battery charge on a line of its own.
+fn display_battery<W>(writer: &mut W, percent: Option<u8>) -> Result<(), W::Error>
+where
+    W: ufmt::uWrite + ?Sized,
+{
+    match percent {
+        Some(percent) => uwrite!(writer, "  Battery: {}%\r\n", percent),
+        None => uwrite!(writer, "  Battery: unknown\r\n"),
+    }
+}
+
+/// Helper function to display a white balance correction, by name if it is a preset.
+fn display_white_balance<W>(
+    writer: &mut W,
+    correction: crate::lights::ColorCorrection,
+) -> Result<(), W::Error>
+where
+    W: ufmt::uWrite + ?Sized,
+{
+    match correction.name() {
+        Some(name) => uwrite!(writer, "{}", name),
+        None => uwrite!(
+            writer,
+            "R {} G {} B {}",
+            correction.r,
+            correction.g,
+            correction.b
+        ),
     }
 }
 
@@ -542,33 +2364,225 @@ where
 {
     match mode {
         crate::lights::Mode::Off => uwrite!(writer, "Off"),
+        crate::lights::Mode::Progress(p) => uwrite!(
+            writer,
+            "Progress {}% RGB({},{},{})",
+            p.percent(),
+            p.color.r,
+            p.color.g,
+            p.color.b
+        ),
+        crate::lights::Mode::Segments(p) => {
+            uwrite!(writer, "Segments")?;
+            for segment in p.segments() {
+                uwrite!(
+                    writer,
+                    " {}+{} RGB({},{},{})",
+                    segment.start,
+                    segment.length,
+                    segment.color.r,
+                    segment.color.g,
+                    segment.color.b
+                )?;
+            }
+            Ok(())
+        }
+        crate::lights::Mode::PaletteCycle(p) => uwrite!(
+            writer,
+            "Palette {}{}",
+            p.palette.name(),
+            if p.spread { "" } else { " (unified)" }
+        ),
+        crate::lights::Mode::Strobe(p) => {
+            uwrite!(
+                writer,
+                "Strobe RGB({},{},{}), {} ms on, {} ms off",
+                p.color.r,
+                p.color.g,
+                p.color.b,
+                p.on_ms,
+                p.off_ms.max(crate::lights::StrobePattern::MIN_OFF_MS)
+            )?;
+            if let Some(count) = p.count {
+                uwrite!(writer, ", {} flashes", count)?;
+            }
+            Ok(())
+        }
+        crate::lights::Mode::BatteryGauge => uwrite!(writer, "Battery gauge"),
+        crate::lights::Mode::MirrorOther => uwrite!(writer, "Mirror of the other side"),
         crate::lights::Mode::Solid(color) => {
             uwrite!(writer, "Solid RGB({},{},{})", color.r, color.g, color.b)
         }
-        crate::lights::Mode::Gradient(c1, c2) => {
+        crate::lights::Mode::Gradient(p) => {
             uwrite!(
                 writer,
                 "Gradient RGB({},{},{}) to RGB({},{},{})",
-                c1.r,
-                c1.g,
-                c1.b,
-                c2.r,
-                c2.g,
-                c2.b
-            )
+                p.start.r,
+                p.start.g,
+                p.start.b,
+                p.end.r,
+                p.end.g,
+                p.end.b
+            )?;
+            if p.rotate_ms != 0 {
+                uwrite!(writer, ", turning every {} ms", p.rotate_ms)?;
+            }
+            if p.mirrored {
+                uwrite!(writer, ", mirrored")?;
+            }
+            Ok(())
         }
         crate::lights::Mode::Chase(_) => uwrite!(writer, "Chase"),
         crate::lights::Mode::Pulse(p) => {
             uwrite!(
                 writer,
-                "Pulse RGB({},{},{})",
+                "Pulse RGB({},{},{}) {}",
                 p.color.r,
                 p.color.g,
-                p.color.b
+                p.color.b,
+                p.shape.name()
             )
         }
-        crate::lights::Mode::Rainbow(_) => uwrite!(writer, "Rainbow"),
+        crate::lights::Mode::Rainbow(p) => uwrite!(
+            writer,
+            "Rainbow{}{}",
+            if p.saturation < u8::MAX {
+                " (pastel)"
+            } else {
+                ""
+            },
+            if p.reversed { " (reversed)" } else { "" }
+        ),
         crate::lights::Mode::Custom(_) => uwrite!(writer, "Custom"),
+        crate::lights::Mode::Animation(animation) => uwrite!(
+            writer,
+            "Animation ({} frames{})",
+            animation.frames().len(),
+            if animation.looping { ", looping" } else { "" }
+        ),
+        crate::lights::Mode::Vu(_) => uwrite!(writer, "VU meter"),
+        crate::lights::Mode::BeatPulse(p) => uwrite!(
+            writer,
+            "Beat pulse RGB({},{},{})",
+            p.color.r,
+            p.color.g,
+            p.color.b
+        ),
+        crate::lights::Mode::Fire(_) => uwrite!(writer, "Fire"),
+        crate::lights::Mode::Comet(p) => uwrite!(
+            writer,
+            "Comet RGB({},{},{})",
+            p.color.r,
+            p.color.g,
+            p.color.b
+        ),
+        crate::lights::Mode::TheaterChase(p) => uwrite!(
+            writer,
+            "Theater chase RGB({},{},{}) every {} LEDs",
+            p.color.r,
+            p.color.g,
+            p.color.b,
+            p.spacing
+        ),
+        crate::lights::Mode::Sparkle(p) => match p.color {
+            Some(color) => uwrite!(writer, "Sparkle RGB({},{},{})", color.r, color.g, color.b),
+            None => uwrite!(writer, "Sparkle (random hues)"),
+        },
+    }
+}
+
+/// Helper function to display servo mode information.
+fn display_servo_mode<W>(writer: &mut W, mode: &crate::state::ServoMode) -> Result<(), W::Error>
+where
+    W: ufmt::uWrite + ?Sized,
+{
+    match mode {
+        crate::state::ServoMode::Static(position) => uwrite!(writer, "Static {}", position),
+        crate::state::ServoMode::Sweep { min, max, speed_ms } => {
+            uwrite!(writer, "Sweep {}-{} ({}ms)", min, max, speed_ms)
+        }
+        crate::state::ServoMode::Twitch {
+            center,
+            amplitude,
+            interval_ms,
+        } => uwrite!(
+            writer,
+            "Twitch {}+/-{} ({}ms)",
+            center,
+            amplitude,
+            interval_ms
+        ),
+        crate::state::ServoMode::Reactive {
+            rest,
+            perk,
+            threshold,
+            release,
+        } => uwrite!(
+            writer,
+            "Reactive {}/{} (level {}/{})",
+            rest,
+            perk,
+            threshold,
+            release
+        ),
+    }
+}
+
+/// Helper function to display peripheral health information.
+fn display_health<W>(writer: &mut W, health: &crate::status::Health) -> Result<(), W::Error>
+where
+    W: ufmt::uWrite + ?Sized,
+{
+    uwrite!(
+        writer,
+        "{} ({} in a row, {} total failures)",
+        if health.is_degraded() {
+            "DEGRADED"
+        } else {
+            "ok"
+        },
+        health.consecutive_failures(),
+        health.total_failures()
+    )
+}
+
+/// Helper function to display the comma-separated list of gesture names.
+fn display_gesture_names<W>(writer: &mut W) -> Result<(), W::Error>
+where
+    W: ufmt::uWrite + ?Sized,
+{
+    for (i, gesture) in Gesture::ALL.iter().enumerate() {
+        if i > 0 {
+            uwrite!(writer, ", ")?;
+        }
+        uwrite!(writer, "{}", gesture.name())?;
+    }
+    Ok(())
+}
+
+/// Writes the names of the chiptunes in `playlist`, separated by commas.
+fn display_playlist<W>(writer: &mut W, playlist: &crate::audio::Playlist) -> Result<(), W::Error>
+where
+    W: ufmt::uWrite + ?Sized,
+{
+    for (i, preset) in playlist.tunes().iter().enumerate() {
+        if i > 0 {
+            uwrite!(writer, ", ")?;
+        }
+        uwrite!(writer, "{}", preset.name())?;
+    }
+    Ok(())
+}
+
+/// Helper function to display the speaker balance, as how far it leans to which side.
+fn display_balance<W>(writer: &mut W, balance: i8) -> Result<(), W::Error>
+where
+    W: ufmt::uWrite + ?Sized,
+{
+    match balance {
+        0 => uwrite!(writer, "center"),
+        ..0 => uwrite!(writer, "L{}", balance.unsigned_abs()),
+        _ => uwrite!(writer, "R{}", balance),
     }
 }
 
@@ -582,32 +2596,63 @@ where
         crate::audio::Mode::Tone(note) => {
             uwrite!(
                 writer,
-                "Tone ({}Hz, {}ms)",
+                "Tone ({}Hz, {}ms, {})",
                 note.frequency as u32,
-                note.duration_ms
+                note.duration_ms,
+                note.waveform.name()
             )
         }
         crate::audio::Mode::Chiptune(_) => uwrite!(writer, "Chiptune"),
+        crate::audio::Mode::NamedChiptune(name) => {
+            uwrite!(writer, "Chiptune ({})", name.as_str())
+        }
+        crate::audio::Mode::Playlist(playlist) => {
+            uwrite!(writer, "Playlist (")?;
+            display_playlist(writer, playlist)?;
+            uwrite!(writer, ")")
+        }
         crate::audio::Mode::Audio(_) => uwrite!(writer, "Audio Clip"),
+        crate::audio::Mode::RemoteAudio(clip) => uwrite!(
+            writer,
+            "Remote Clip ({}{})",
+            clip.id,
+            if clip.looping { ", looping" } else { "" }
+        ),
+        crate::audio::Mode::Noise(noise) => uwrite!(
+            writer,
+            "Noise ({}, smoothing {}, wobble {}Hz)",
+            noise.amplitude,
+            noise.smoothing,
+            noise.wobble_hz as u32
+        ),
+        crate::audio::Mode::Sweep(sweep) => uwrite!(
+            writer,
+            "Sweep ({}Hz to {}Hz, {}ms{})",
+            sweep.start_hz as u32,
+            sweep.end_hz as u32,
+            sweep.duration_ms,
+            if sweep.repeat { ", repeating" } else { "" }
+        ),
+        crate::audio::Mode::Dtmf(dial) => uwrite!(
+            writer,
+            "DTMF ({}, {}ms on, {}ms off)",
+            dial.digits.as_str(),
+            dial.tone_ms,
+            dial.gap_ms
+        ),
     }
 }
 
-impl uDebug for ChiptuneName {
+impl uDebug for crate::audio::rtttl::Error {
     fn fmt<W>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error>
     where
         W: ufmt::uWrite + ?Sized,
     {
         match self {
-            ChiptuneName::Coin => f.write_str("Coin"),
-            ChiptuneName::PowerUp => f.write_str("PowerUp"),
-            ChiptuneName::LevelComplete => f.write_str("LevelComplete"),
-            ChiptuneName::GameOver => f.write_str("GameOver"),
-            ChiptuneName::MenuSelect => f.write_str("MenuSelect"),
-            ChiptuneName::Alert => f.write_str("Alert"),
-            ChiptuneName::Happy => f.write_str("Happy"),
-            ChiptuneName::Sad => f.write_str("Sad"),
-            ChiptuneName::Startup => f.write_str("Startup"),
-            ChiptuneName::Shutdown => f.write_str("Shutdown"),
+            Self::Sections => f.write_str("expected name:defaults:notes"),
+            Self::Default => f.write_str("bad default"),
+            Self::Note(index) => uwrite!(f, "bad note {}", index + 1),
+            Self::TooLong => f.write_str("more than 64 notes"),
         }
     }
 }
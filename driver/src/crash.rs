@@ -0,0 +1,307 @@
+//! Keeping track of crashes across the resets that follow them.
+//!
+//! The firmware's panic handler forces the outputs into a safe state, then writes a [`Record`] of the panic into RTC
+//! memory, which survives the reset that follows. Watchdog and brownout resets leave no chance to write anything, so
+//! the next boot records those itself. Either way the record stays in RTC memory until it is cleared with
+//! `system lastcrash clear`, and [`Report`] in the runtime status gives the command line access to it.
+//!
+//! RTC memory does not keep its contents without power, so a record is lost when the ears are switched off.
+
+use core::cell::{Cell, RefCell};
+use core::fmt::Write as _;
+
+use critical_section::Mutex;
+
+use crate::resets::Cause;
+
+/// Size in 32-bit words of an encoded record.
+pub const RECORD_WORDS: usize = 72;
+
+/// Longest source file path kept in a record, in bytes. Longer paths keep their end.
+pub const FILE_LEN: usize = 64;
+
+/// Longest panic message kept in a record, in bytes. Longer messages are cut short.
+pub const MESSAGE_LEN: usize = 160;
+
+/// Longest firmware version kept in a record, in bytes.
+pub const VERSION_LEN: usize = 16;
+
+/// Marks a record, and changes whenever the record layout does.
+const RECORD_MAGIC: u32 = 0xCA7E_DEAE;
+
+/// Number of header words (magic, cause, uptime, line, column, lengths, checksum) before the recorded bytes.
+const RECORD_HEADER_WORDS: usize = 7;
+
+/// Why and when the firmware crashed, and where if it panicked.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::crash::{Record, Stored, RECORD_WORDS};
+/// use catears::resets::Cause;
+///
+/// let record = Record::new(Cause::Panic, 3600, "src/main.rs", 42, 7, format_args!("servo {} stuck", 1));
+/// assert_eq!(record.message.as_str(), "servo 1 stuck");
+/// assert_eq!(record.version.as_str(), env!("CARGO_PKG_VERSION"));
+///
+/// let mut words = [0u32; RECORD_WORDS];
+/// record.encode(&mut words);
+/// assert_eq!(Record::decode(&words), Stored::Crash(record));
+///
+/// // A damaged record is told apart from no record at all.
+/// words[3] ^= 1;
+/// assert_eq!(Record::decode(&words), Stored::Corrupted);
+/// assert_eq!(Record::decode(&[0u32; RECORD_WORDS]), Stored::Empty);
+///
+/// // Resets that give the firmware no chance to write anything have no location.
+/// let record = Record::reset(Cause::Watchdog, 120);
+/// assert!(!record.has_location());
+/// record.encode(&mut words);
+/// assert_eq!(Record::decode(&words), Stored::Crash(record));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    /// What reset the device.
+    pub cause: Cause,
+    /// Time in seconds the device had been up when it crashed.
+    pub uptime_s: u32,
+    /// Version of the firmware that crashed.
+    pub version: heapless::String<VERSION_LEN>,
+    /// Source file the panic happened in, empty if the crash was not a panic.
+    pub file: heapless::String<FILE_LEN>,
+    /// Line the panic happened on.
+    pub line: u32,
+    /// Column the panic happened at.
+    pub column: u32,
+    /// Panic message.
+    pub message: heapless::String<MESSAGE_LEN>,
+}
+
+impl Record {
+    /// Creates a new record of the running firmware, cutting the file path and message down to size.
+    #[must_use]
+    pub fn new(
+        cause: Cause,
+        uptime_s: u32,
+        file: &str,
+        line: u32,
+        column: u32,
+        message: core::fmt::Arguments<'_>,
+    ) -> Self {
+        let mut start = file.len().saturating_sub(FILE_LEN);
+        while !file.is_char_boundary(start) {
+            start += 1;
+        }
+        let mut record = Self {
+            cause,
+            uptime_s,
+            version: heapless::String::new(),
+            file: heapless::String::new(),
+            line,
+            column,
+            message: heapless::String::new(),
+        };
+        // All of them fit by construction or are cut short by the truncating writer, so none can fail.
+        let _ = Truncating(&mut record.version).write_str(env!("CARGO_PKG_VERSION"));
+        let _ = record.file.push_str(&file[start..]);
+        let _ = Truncating(&mut record.message).write_fmt(message);
+        record
+    }
+
+    /// Creates a new record of a panic after `uptime_s` seconds up.
+    #[must_use]
+    pub fn from_panic(info: &core::panic::PanicInfo<'_>, uptime_s: u32) -> Self {
+        match info.location() {
+            Some(location) => Self::new(
+                Cause::Panic,
+                uptime_s,
+                location.file(),
+                location.line(),
+                location.column(),
+                format_args!("{}", info.message()),
+            ),
+            None => Self::new(
+                Cause::Panic,
+                uptime_s,
+                "unknown",
+                0,
+                0,
+                format_args!("{}", info.message()),
+            ),
+        }
+    }
+
+    /// Creates a new record of a reset that happened without a panic, after `uptime_s` seconds up.
+    #[must_use]
+    pub fn reset(cause: Cause, uptime_s: u32) -> Self {
+        Self::new(cause, uptime_s, "", 0, 0, format_args!(""))
+    }
+
+    /// Returns whether the record knows where the firmware crashed.
+    #[must_use]
+    pub fn has_location(&self) -> bool {
+        !self.file.is_empty()
+    }
+
+    /// Serializes the record into `words`.
+    pub fn encode(&self, words: &mut [u32; RECORD_WORDS]) {
+        let mut bytes = [0u8; (RECORD_WORDS - RECORD_HEADER_WORDS) * 4];
+        let mut len = 0;
+        for field in [
+            self.version.as_bytes(),
+            self.file.as_bytes(),
+            self.message.as_bytes(),
+        ] {
+            bytes[len..len + field.len()].copy_from_slice(field);
+            len += field.len();
+        }
+
+        words.fill(0);
+        words[0] = RECORD_MAGIC;
+        words[1] = u32::from(self.cause.to_u8());
+        words[2] = self.uptime_s;
+        words[3] = self.line;
+        words[4] = self.column;
+        // Every length fits in a byte by construction.
+        #[allow(clippy::cast_possible_truncation)]
+        let lengths = [
+            self.version.len() as u8,
+            self.file.len() as u8,
+            self.message.len() as u8,
+            0,
+        ];
+        words[5] = u32::from_le_bytes(lengths);
+        for (word, chunk) in words[RECORD_HEADER_WORDS..]
+            .iter_mut()
+            .zip(bytes.chunks_exact(4))
+        {
+            *word = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        }
+        words[6] = Self::checksum(words);
+    }
+
+    /// Deserializes a record encoded with [`Record::encode`].
+    #[must_use]
+    pub fn decode(words: &[u32; RECORD_WORDS]) -> Stored {
+        if words[0] != RECORD_MAGIC {
+            return Stored::Empty;
+        }
+        Self::decode_fields(words).map_or(Stored::Corrupted, Stored::Crash)
+    }
+
+    fn decode_fields(words: &[u32; RECORD_WORDS]) -> Option<Self> {
+        if Self::checksum(words) != words[6] {
+            return None;
+        }
+        let [version_len, file_len, message_len, _] = words[5].to_le_bytes().map(usize::from);
+        if version_len > VERSION_LEN || file_len > FILE_LEN || message_len > MESSAGE_LEN {
+            return None;
+        }
+        let cause = Cause::from_u8(u8::try_from(words[1]).ok()?);
+
+        let mut bytes = [0u8; (RECORD_WORDS - RECORD_HEADER_WORDS) * 4];
+        for (chunk, word) in bytes.chunks_exact_mut(4).zip(&words[RECORD_HEADER_WORDS..]) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        let (version, rest) = bytes.split_at(version_len);
+        let (file, rest) = rest.split_at(file_len);
+        let message = &rest[..message_len];
+        Some(Self {
+            cause,
+            uptime_s: words[2],
+            version: core::str::from_utf8(version).ok()?.try_into().ok()?,
+            file: core::str::from_utf8(file).ok()?.try_into().ok()?,
+            line: words[3],
+            column: words[4],
+            message: core::str::from_utf8(message).ok()?.try_into().ok()?,
+        })
+    }
+
+    /// Returns the checksum of every word but the magic and the checksum itself.
+    fn checksum(words: &[u32; RECORD_WORDS]) -> u32 {
+        let mut bytes = [0u8; (RECORD_WORDS - 2) * 4];
+        for (chunk, word) in bytes
+            .chunks_exact_mut(4)
+            .zip(words[1..6].iter().chain(&words[RECORD_HEADER_WORDS..]))
+        {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        crate::sleep::checksum(&bytes)
+    }
+}
+
+/// What was found where a [`Record`] is kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(
+    clippy::large_enum_variant,
+    reason = "only ever held once, in the runtime status"
+)]
+pub enum Stored {
+    /// No record, because nothing crashed since the last clear.
+    Empty,
+    /// A record that was damaged, so nothing in it can be trusted.
+    Corrupted,
+    /// A crash.
+    Crash(Record),
+}
+
+/// Writer that drops whatever does not fit instead of failing.
+struct Truncating<'a, const N: usize>(&'a mut heapless::String<N>);
+
+impl<const N: usize> core::fmt::Write for Truncating<'_, N> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for c in s.chars() {
+            if self.0.push(c).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The last crash, for the command line to read and clear.
+pub struct Report {
+    stored: Mutex<RefCell<Stored>>,
+    /// Clears the record wherever it is kept.
+    forget: Mutex<Cell<fn()>>,
+}
+
+impl Report {
+    /// Creates a new report without a crash.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            stored: Mutex::new(RefCell::new(Stored::Empty)),
+            forget: Mutex::new(Cell::new(|| {})),
+        }
+    }
+
+    /// Publishes what was found where the last crash is kept, and how to clear it there.
+    pub fn publish(&self, stored: Stored, forget: fn()) {
+        critical_section::with(|cs| {
+            self.stored.replace(cs, stored);
+            self.forget.borrow(cs).set(forget);
+        });
+    }
+
+    /// Returns the last crash.
+    #[must_use]
+    pub fn get(&self) -> Stored {
+        critical_section::with(|cs| self.stored.borrow_ref(cs).clone())
+    }
+
+    /// Forgets the last crash, here and wherever it is kept.
+    pub fn clear(&self) {
+        let forget = critical_section::with(|cs| {
+            self.stored.replace(cs, Stored::Empty);
+            self.forget.borrow(cs).get()
+        });
+        forget();
+    }
+}
+
+impl Default for Report {
+    fn default() -> Self {
+        Self::new()
+    }
+}
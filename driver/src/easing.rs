@@ -0,0 +1,149 @@
+//! Easing curves, for anything that moves from one value to another over time.
+//!
+//! Fades of the lights and moves of the servos alike go from a start to an end, and how they get there is a matter of
+//! taste: at a steady pace, or speeding up and slowing down. An [`Easing`] names such a curve, so that a pattern or a
+//! move can pick one in its JSON, and [`ease`] maps how far along it is in time to how far along it is in value.
+
+use serde::{Deserialize, Serialize};
+
+/// Curve of a value from its start to its end over time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Easing {
+    /// At a steady pace.
+    #[default]
+    Linear,
+    /// Speeding up, with the square of the time.
+    InQuad,
+    /// Slowing down, with the square of the time.
+    OutQuad,
+    /// Speeding up, then slowing down, with the square of the time.
+    InOutQuad,
+    /// Speeding up, with the cube of the time.
+    InCubic,
+    /// Slowing down, with the cube of the time.
+    OutCubic,
+    /// Speeding up, then slowing down, with the cube of the time.
+    InOutCubic,
+    /// Speeding up, along a quarter of a sine wave.
+    InSine,
+    /// Slowing down, along a quarter of a sine wave.
+    OutSine,
+    /// Speeding up, then slowing down, along half a sine wave.
+    InOutSine,
+}
+
+impl Easing {
+    /// All curves.
+    pub const ALL: [Self; 10] = [
+        Self::Linear,
+        Self::InQuad,
+        Self::OutQuad,
+        Self::InOutQuad,
+        Self::InCubic,
+        Self::OutCubic,
+        Self::InOutCubic,
+        Self::InSine,
+        Self::OutSine,
+        Self::InOutSine,
+    ];
+}
+
+/// Returns how far from the start to the end a value following the curve `kind` is at `t` of the way through its
+/// time, both from 0.0 to 1.0. Times outside of that range are clamped to it.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::easing::{ease, Easing};
+///
+/// assert_eq!(ease(Easing::Linear, 0.25), 0.25);
+/// assert_eq!(ease(Easing::InQuad, 0.5), 0.25);
+/// assert_eq!(ease(Easing::OutCubic, 0.5), 0.875);
+/// assert!((ease(Easing::InOutSine, 0.5) - 0.5).abs() < 1e-6);
+/// assert_eq!(ease(Easing::InOutQuad, 1.5), 1.0);
+///
+/// // Every curve starts at the start, ends at the end, and never turns back on the way.
+/// for kind in Easing::ALL {
+///     assert!(ease(kind, 0.0).abs() < 1e-6, "{kind:?}");
+///     assert!((ease(kind, 1.0) - 1.0).abs() < 1e-6, "{kind:?}");
+///     let values: Vec<f32> = (0..=1000).map(|i| ease(kind, i as f32 / 1000.0)).collect();
+///     assert!(values.windows(2).all(|pair| pair[0] <= pair[1] + 1e-6), "{kind:?}");
+/// }
+/// ```
+#[must_use]
+pub fn ease(kind: Easing, t: f32) -> f32 {
+    use core::f32::consts::FRAC_PI_2;
+
+    let t = t.clamp(0.0, 1.0);
+    match kind {
+        Easing::Linear => t,
+        Easing::InQuad => t * t,
+        Easing::OutQuad => 1.0 - (1.0 - t) * (1.0 - t),
+        Easing::InOutQuad => {
+            if t < 0.5 {
+                2.0 * t * t
+            } else {
+                1.0 - 2.0 * (1.0 - t) * (1.0 - t)
+            }
+        }
+        Easing::InCubic => t * t * t,
+        Easing::OutCubic => 1.0 - (1.0 - t) * (1.0 - t) * (1.0 - t),
+        Easing::InOutCubic => {
+            if t < 0.5 {
+                4.0 * t * t * t
+            } else {
+                1.0 - 4.0 * (1.0 - t) * (1.0 - t) * (1.0 - t)
+            }
+        }
+        Easing::InSine => 1.0 - libm::cosf(t * FRAC_PI_2),
+        Easing::OutSine => libm::sinf(t * FRAC_PI_2),
+        Easing::InOutSine => (1.0 - libm::cosf(t * 2.0 * FRAC_PI_2)) / 2.0,
+    }
+}
+
+/// Returns [`ease`] on a scale of 0 to 255 rather than 0.0 to 1.0, both in and out, for code that works in 8-bit
+/// levels such as the brightness of the lights.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::easing::{ease_u8, Easing};
+///
+/// assert_eq!(ease_u8(Easing::Linear, 100), 100);
+/// assert_eq!(ease_u8(Easing::InQuad, 128), 64);
+///
+/// // The ends stay exact, and no step turns back.
+/// for kind in Easing::ALL {
+///     assert_eq!(ease_u8(kind, 0), 0, "{kind:?}");
+///     assert_eq!(ease_u8(kind, 255), 255, "{kind:?}");
+///     assert!((0..255).all(|t| ease_u8(kind, t) <= ease_u8(kind, t + 1)), "{kind:?}");
+/// }
+/// ```
+#[must_use]
+pub fn ease_u8(kind: Easing, t: u8) -> u8 {
+    let eased = ease(kind, f32::from(t) / 255.0) * 255.0;
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let eased = libm::roundf(eased) as u8;
+    eased
+}
+
+/// Returns the value `t` of the way from `from` to `to`, both 8-bit levels such as a channel of a color.
+///
+/// Past either end, the value carries on the same way until it stops at 0 or 255.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::easing::mix;
+///
+/// assert_eq!(mix(0, 200, 0.5), 100);
+/// assert_eq!(mix(200, 0, 0.25), 150);
+/// assert_eq!(mix(10, 20, 3.0), 40);
+/// assert_eq!(mix(10, 20, -2.0), 0);
+/// ```
+#[must_use]
+pub fn mix(from: u8, to: u8, t: f32) -> u8 {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let mixed = (f32::from(from) + (f32::from(to) - f32::from(from)) * t) as u8;
+    mixed
+}
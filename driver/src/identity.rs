@@ -0,0 +1,68 @@
+//! Stable per-device identity.
+//!
+//! Every device is named after a base name and the last two bytes of its MAC address, like `catears-3f2a`, so that two
+//! pairs on the same network never share a DHCP hostname and can be told apart. The base name can be changed at build
+//! time through the `DEVICE_BASE_NAME` environment variable, and `DEVICE_NAME` replaces the derived name altogether for
+//! anyone who wants a fixed one.
+
+use core::fmt::Write as _;
+
+/// Base name that the MAC suffix is appended to.
+pub const BASE_NAME: &str = match option_env!("DEVICE_BASE_NAME") {
+    Some(name) => name,
+    None => "catears",
+};
+
+/// Fixed device name that replaces the derived one, if set.
+const FIXED_NAME: Option<&str> = option_env!("DEVICE_NAME");
+
+/// Device name, short enough to be a DHCP hostname.
+pub type Name = heapless::String<32>;
+
+/// Returns the station MAC address of the device.
+#[must_use]
+pub fn mac() -> [u8; 6] {
+    esp_hal::efuse::Efuse::mac_address()
+}
+
+/// Returns the name of the device, derived from its MAC address unless a fixed name was set at build time.
+#[must_use]
+pub fn device_name() -> Name {
+    name(BASE_NAME, FIXED_NAME, mac())
+}
+
+/// Returns the fixed name if there is one, and otherwise `base` followed by a suffix from the last two bytes of `mac`.
+///
+/// Names that are too long are cut short, keeping the suffix.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::identity::name;
+///
+/// let mac = [0x24, 0x58, 0x7c, 0x01, 0x3f, 0x2a];
+/// assert_eq!(name("catears", None, mac).as_str(), "catears-3f2a");
+/// assert_eq!(name("catears", Some("ziyad-ears"), mac).as_str(), "ziyad-ears");
+/// ```
+#[must_use]
+pub fn name(base: &str, fixed: Option<&str>, mac: [u8; 6]) -> Name {
+    let mut name = Name::new();
+    if let Some(fixed) = fixed {
+        push_truncated(&mut name, fixed, 0);
+    } else {
+        // The suffix is a dash and four hex digits.
+        push_truncated(&mut name, base, 5);
+        // The suffix always fits, since room was left for it above.
+        let _ = write!(name, "-{:02x}{:02x}", mac[4], mac[5]);
+    }
+    name
+}
+
+/// Appends as much of `s` to `name` as fits while leaving `reserve` bytes free.
+fn push_truncated(name: &mut Name, s: &str, reserve: usize) {
+    for c in s.chars() {
+        if name.len() + c.len_utf8() + reserve > name.capacity() || name.push(c).is_err() {
+            break;
+        }
+    }
+}
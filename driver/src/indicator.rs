@@ -0,0 +1,99 @@
+//! On-board status LED.
+//!
+//! The XIAO's user LED sits next to the USB port, out of sight while the ears are worn but handy on the bench. The
+//! indicator task picks an [`Indication`] from the power switch, the link state, and the health of the remote state
+//! fetch, and plays its blink pattern on the LED.
+
+/// Number of consecutive failed remote state fetches after which the indicator reports them.
+pub const FETCH_FAILURE_THRESHOLD: u32 = 3;
+
+/// One step of a blink pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Step {
+    /// LED brightness in percent.
+    pub brightness_pct: u8,
+    /// Time in milliseconds the step lasts.
+    pub duration_ms: u16,
+}
+
+impl Step {
+    const fn new(brightness_pct: u8, duration_ms: u16) -> Self {
+        Self {
+            brightness_pct,
+            duration_ms,
+        }
+    }
+}
+
+/// What the status LED is showing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Indication {
+    /// Dark, because the power switch is off or the LED is disabled.
+    Off,
+    /// Slow blink while the network is connecting.
+    Connecting,
+    /// Solid dim while connected and fetching the state without trouble.
+    Healthy,
+    /// Double blink while the remote state fetch keeps failing.
+    FetchFailing,
+}
+
+/// Blink pattern of every indication, indexed by [`Indication`], played in a loop.
+const PATTERNS: [&[Step]; 4] = [
+    // Off
+    &[Step::new(0, 500)],
+    // Connecting
+    &[Step::new(100, 500), Step::new(0, 500)],
+    // Healthy
+    &[Step::new(5, 500)],
+    // FetchFailing
+    &[
+        Step::new(100, 100),
+        Step::new(0, 100),
+        Step::new(100, 100),
+        Step::new(0, 700),
+    ],
+];
+
+impl Indication {
+    /// Picks the indication to show.
+    ///
+    /// # Parameters
+    ///
+    /// * `enabled` - Whether the status LED is enabled at all
+    /// * `power` - Whether the global power switch is on
+    /// * `connected` - Whether the network is connected and configured
+    /// * `fetch_failures` - Number of consecutive failed remote state fetches
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use catears::indicator::Indication;
+    ///
+    /// assert_eq!(Indication::select(true, true, false, 0), Indication::Connecting);
+    /// assert_eq!(Indication::select(true, true, true, 1), Indication::Healthy);
+    /// assert_eq!(Indication::select(true, true, true, 3), Indication::FetchFailing);
+    ///
+    /// // The power switch and the stealth flag both win over everything else.
+    /// assert_eq!(Indication::select(true, false, true, 3), Indication::Off);
+    /// assert_eq!(Indication::select(false, true, false, 0), Indication::Off);
+    /// ```
+    #[must_use]
+    pub const fn select(enabled: bool, power: bool, connected: bool, fetch_failures: u32) -> Self {
+        if !enabled || !power {
+            Self::Off
+        } else if !connected {
+            Self::Connecting
+        } else if fetch_failures >= FETCH_FAILURE_THRESHOLD {
+            Self::FetchFailing
+        } else {
+            Self::Healthy
+        }
+    }
+
+    /// Returns the blink pattern of the indication.
+    #[must_use]
+    pub const fn pattern(self) -> &'static [Step] {
+        PATTERNS[self as usize]
+    }
+}
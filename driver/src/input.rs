@@ -0,0 +1,174 @@
+//! Physical input handling.
+//!
+//! This module contains hardware-independent classifiers for physical inputs. The firmware feeds them debounced levels
+//! and timestamps, and acts on the events they produce.
+
+/// Timing configuration for a [`Button`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    /// Time in milliseconds the input must be stable before a level change is accepted.
+    pub debounce_ms: u64,
+    /// Time in milliseconds the button must be held for a press to count as a long press.
+    pub long_press_ms: u64,
+    /// Time in milliseconds after a release within which a second press counts as a double press.
+    pub double_press_ms: u64,
+}
+
+impl Config {
+    /// Default timings for a momentary push button.
+    pub const DEFAULT: Self = Self {
+        debounce_ms: 20,
+        long_press_ms: 1500,
+        double_press_ms: 300,
+    };
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Events produced by a [`Button`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// A single short press, reported once the double press window has passed.
+    ShortPress,
+    /// Two short presses in quick succession.
+    DoublePress,
+    /// The button was held for at least [`Config::long_press_ms`], reported while it is still held.
+    LongPress,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    /// Button released, no press in progress.
+    Idle,
+    /// Button held down since `since_ms`, after `clicks` completed short presses.
+    Pressed { since_ms: u64, clicks: u8 },
+    /// Button released at `at_ms` after one short press, waiting to see if a second press follows.
+    Released { at_ms: u64 },
+    /// Button still held after a long press was reported.
+    LongHeld,
+}
+
+/// Short/double/long press classifier for a single momentary button.
+///
+/// The button is driven by two inputs: [`Button::on_level`] whenever the debounced level changes, and
+/// [`Button::on_tick`] once the time returned by [`Button::deadline`] has been reached. Both return the event
+/// recognized at that point, if any.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::input::{Button, Config, Event};
+///
+/// let mut button = Button::new(Config::DEFAULT);
+///
+/// // A quick tap is reported as a short press once the double press window has passed.
+/// assert_eq!(button.on_level(true, 0), None);
+/// assert_eq!(button.on_level(false, 100), None);
+/// assert_eq!(button.deadline(), Some(400));
+/// assert_eq!(button.on_tick(400), Some(Event::ShortPress));
+///
+/// // Two taps in quick succession are a double press.
+/// button.on_level(true, 1000);
+/// button.on_level(false, 1100);
+/// button.on_level(true, 1200);
+/// assert_eq!(button.on_level(false, 1300), Some(Event::DoublePress));
+///
+/// // Holding the button is reported as a long press without waiting for the release.
+/// button.on_level(true, 2000);
+/// assert_eq!(button.on_tick(3499), None);
+/// assert_eq!(button.on_tick(3500), Some(Event::LongPress));
+/// assert_eq!(button.on_level(false, 4000), None);
+/// assert_eq!(button.deadline(), None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Button {
+    config: Config,
+    phase: Phase,
+}
+
+impl Button {
+    /// Creates a new released button.
+    #[must_use]
+    pub const fn new(config: Config) -> Self {
+        Self {
+            config,
+            phase: Phase::Idle,
+        }
+    }
+
+    /// Returns the configuration of the button.
+    #[must_use]
+    pub const fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Handles a debounced level change at `now_ms`, where `pressed` is the new level.
+    ///
+    /// Repeated reports of the same level are ignored.
+    pub fn on_level(&mut self, pressed: bool, now_ms: u64) -> Option<Event> {
+        match (self.phase, pressed) {
+            (Phase::Idle, true) => {
+                self.phase = Phase::Pressed {
+                    since_ms: now_ms,
+                    clicks: 0,
+                };
+                None
+            }
+            (Phase::Released { .. }, true) => {
+                self.phase = Phase::Pressed {
+                    since_ms: now_ms,
+                    clicks: 1,
+                };
+                None
+            }
+            (Phase::Pressed { clicks, .. }, false) => {
+                if clicks >= 1 {
+                    self.phase = Phase::Idle;
+                    Some(Event::DoublePress)
+                } else {
+                    self.phase = Phase::Released { at_ms: now_ms };
+                    None
+                }
+            }
+            (Phase::LongHeld, false) => {
+                self.phase = Phase::Idle;
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Handles the passage of time, reporting events that are recognized by timeouts rather than level changes.
+    pub fn on_tick(&mut self, now_ms: u64) -> Option<Event> {
+        match self.phase {
+            Phase::Pressed { since_ms, .. }
+                if now_ms.saturating_sub(since_ms) >= self.config.long_press_ms =>
+            {
+                self.phase = Phase::LongHeld;
+                Some(Event::LongPress)
+            }
+            Phase::Released { at_ms }
+                if now_ms.saturating_sub(at_ms) >= self.config.double_press_ms =>
+            {
+                self.phase = Phase::Idle;
+                Some(Event::ShortPress)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the time at which [`Button::on_tick`] should next be called, or `None` if only a level change can
+    /// produce the next event.
+    #[must_use]
+    pub const fn deadline(&self) -> Option<u64> {
+        match self.phase {
+            Phase::Pressed { since_ms, .. } => Some(since_ms + self.config.long_press_ms),
+            Phase::Released { at_ms } => Some(at_ms + self.config.double_press_ms),
+            Phase::Idle | Phase::LongHeld => None,
+        }
+    }
+}
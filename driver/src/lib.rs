@@ -1,4 +1,9 @@
-#![no_std]
+//! Firmware library for the cat ears.
+//!
+//! Everything that does not need the hardware lives here, for the firmware binary to use. With the `std` feature the
+//! ESP-specific modules are left out, so that the rest builds on the host, e.g. for `examples/simulate.rs`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
 #![deny(unsafe_code)]
 #![warn(
     clippy::pedantic,
@@ -15,9 +20,31 @@
     duration of a data transfer."
 )]
 
+pub mod ambient;
 pub mod audio;
+pub mod battery;
+#[cfg(not(feature = "std"))]
 pub mod cmdline;
+pub mod crash;
+pub mod easing;
+#[cfg(not(feature = "std"))]
+pub mod identity;
+pub mod indicator;
+pub mod input;
 pub mod lights;
+pub mod logging;
+pub mod motion;
+#[cfg(not(feature = "std"))]
 pub mod networking;
+pub mod polling;
+pub mod resets;
 pub mod servo;
+pub mod sleep;
+pub mod startup;
 pub mod state;
+pub mod status;
+pub mod supply;
+pub mod thermal;
+pub mod timing;
+pub mod touch;
+pub mod watchdog;
@@ -1,10 +1,24 @@
+pub mod render;
+
+use defmt::warn;
 use serde::{Deserialize, Serialize};
 use smart_leds::RGB8;
 
+use crate::audio::beat::Beat;
+use crate::audio::dsp::{Band, Levels};
+use crate::easing::{ease, Easing};
+
+/// Number of LEDs in each ring.
+///
+/// Everything sized by the rings follows this, from the patterns to the buffers of the LED task, so building for rings
+/// of another size is a change here alone.
+pub const LED_COUNT: usize = 12;
+
 /// Light modes for the LED rings.
 ///
-/// Defines various lighting patterns and effects available for the 12-LED rings in each ear.
+/// Defines various lighting patterns and effects available for the [`LED_COUNT`]-LED rings in each ear.
 #[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[allow(clippy::large_enum_variant)]
 pub enum Mode {
     /// All LEDs off.
     #[default]
@@ -13,8 +27,8 @@ pub enum Mode {
     /// All LEDs set to a single solid color.
     Solid(RGB8),
 
-    /// Gradient between two colors across the ring.
-    Gradient(RGB8, RGB8),
+    /// Gradient between two colors across the ring, optionally rotating around it.
+    Gradient(GradientPattern),
 
     /// Chase pattern with configurable parameters.
     Chase(ChasePattern),
@@ -27,6 +41,168 @@ pub enum Mode {
 
     /// Custom pattern with individual LED control.
     Custom(LedPattern),
+
+    /// Custom frames with individual LED control, shown one after another.
+    Animation(Animation),
+
+    /// VU meter following the microphone level.
+    Vu(VuPattern),
+
+    /// Flash on every note of the chiptune the speaker on the same side plays.
+    BeatPulse(BeatPattern),
+
+    /// Flickering flames rising around the ring.
+    Fire(FirePattern),
+
+    /// Random LEDs briefly lighting up over a background.
+    Sparkle(SparklePattern),
+
+    /// A single bright LED circling the ring, trailing a fading tail.
+    Comet(CometPattern),
+
+    /// Every few LEDs lit, shifting along by one each step like the lights around a theater marquee.
+    TheaterChase(TheaterChasePattern),
+
+    /// Battery gauge, lighting up as much of the ring as there is charge left, in green, then yellow once low, then
+    /// red with the last LED blinking once critically low. Pulses slowly in grey while the charge is unknown.
+    BatteryGauge,
+
+    /// Progress bar filling the ring clockwise from the top, such as for a long download.
+    Progress(ProgressPattern),
+
+    /// Arcs of the ring in colors of their own, the rest off.
+    Segments(SegmentsPattern),
+
+    /// Colors of a built-in [`Palette`] sweeping around the ring, or through the whole ring at once.
+    PaletteCycle(PaletteCyclePattern),
+
+    /// Hard flashes of one color, optionally only so many of them.
+    Strobe(StrobePattern),
+
+    /// Whatever the ring on the other side shows, flipped so that it looks mirrored across the head.
+    ///
+    /// Only one ring can mirror the other, with both set to mirror each other there is nothing to show and both are
+    /// off.
+    MirrorOther,
+}
+
+/// Gradient pattern configuration.
+///
+/// A still gradient runs from `start` at the first LED to `end` at the last. A rotating one goes around the ring once
+/// every `rotate_ms`, and a mirrored one ramps from `start` to `end` over one half of the ring and back over the other,
+/// which keeps a rotating gradient from showing a seam where the two colors meet.
+///
+/// Serialized as an array of the two colors, followed by the rotation and mirroring only when either is in use, so
+/// gradients saved as a plain pair of colors still parse.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::lights::{GradientPattern, Mode};
+/// use smart_leds::RGB8;
+///
+/// let json = r#"{"Gradient":[{"r":0,"g":0,"b":255},{"r":0,"g":255,"b":255}]}"#;
+/// let (mode, _) = serde_json_core::from_str::<Mode>(json).unwrap();
+/// let ocean = GradientPattern::new(RGB8::new(0, 0, 255), RGB8::new(0, 255, 255));
+/// assert_eq!(mode, Mode::Gradient(ocean));
+/// let mut out = [0u8; 128];
+/// let len = serde_json_core::to_slice(&mode, &mut out).unwrap();
+/// assert_eq!(&out[..len], json.as_bytes());
+///
+/// let idle = Mode::Gradient(ocean.with_rotation(6000).mirrored());
+/// let len = serde_json_core::to_slice(&idle, &mut out).unwrap();
+/// assert_eq!(&out[..len], br#"{"Gradient":[{"r":0,"g":0,"b":255},{"r":0,"g":255,"b":255},6000,true]}"#);
+/// assert_eq!(serde_json_core::from_slice::<Mode>(&out[..len]).unwrap().0, idle);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GradientPattern {
+    /// Color at the start of the gradient.
+    pub start: RGB8,
+    /// Color at the end of the gradient.
+    pub end: RGB8,
+    /// Time in milliseconds the gradient takes to go around the ring once, zero to hold still.
+    pub rotate_ms: u16,
+    /// Whether the gradient ramps up over one half of the ring and back down over the other.
+    pub mirrored: bool,
+}
+
+impl GradientPattern {
+    /// Creates a new still gradient from `start` to `end`.
+    #[must_use]
+    pub const fn new(start: RGB8, end: RGB8) -> Self {
+        Self {
+            start,
+            end,
+            rotate_ms: 0,
+            mirrored: false,
+        }
+    }
+
+    /// Sets the time the gradient takes to go around the ring once.
+    #[must_use]
+    pub const fn with_rotation(mut self, rotate_ms: u16) -> Self {
+        self.rotate_ms = rotate_ms;
+        self
+    }
+
+    /// Ramps the gradient up over one half of the ring and back down over the other.
+    #[must_use]
+    pub const fn mirrored(mut self) -> Self {
+        self.mirrored = true;
+        self
+    }
+}
+
+impl Serialize for GradientPattern {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeTuple;
+
+        let animated = self.rotate_ms != 0 || self.mirrored;
+        let mut tuple = serializer.serialize_tuple(if animated { 4 } else { 2 })?;
+        tuple.serialize_element(&self.start)?;
+        tuple.serialize_element(&self.end)?;
+        if animated {
+            tuple.serialize_element(&self.rotate_ms)?;
+            tuple.serialize_element(&self.mirrored)?;
+        }
+        tuple.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for GradientPattern {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        /// Reads the two colors, then the rotation and mirroring if they are there.
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = GradientPattern;
+
+            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                formatter
+                    .write_str("two colors, optionally followed by a rotation time and mirroring")
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<GradientPattern, A::Error> {
+                let start = seq
+                    .next_element()?
+                    .ok_or_else(|| serde::de::Error::invalid_length(0, &self))?;
+                let end = seq
+                    .next_element()?
+                    .ok_or_else(|| serde::de::Error::invalid_length(1, &self))?;
+                Ok(GradientPattern {
+                    start,
+                    end,
+                    rotate_ms: seq.next_element()?.unwrap_or_default(),
+                    mirrored: seq.next_element()?.unwrap_or_default(),
+                })
+            }
+        }
+
+        deserializer.deserialize_seq(Visitor)
+    }
 }
 
 /// Chase pattern configuration for LED animation.
@@ -36,12 +212,16 @@ pub struct ChasePattern {
     pub color: RGB8,
     /// Background color (default is off).
     pub background: RGB8,
-    /// Number of LEDs in the chase segment (1-12).
+    /// Number of LEDs in the chase segment, from 1 up to [`LED_COUNT`].
     pub length: u8,
     /// Speed of rotation in milliseconds per step.
     pub speed_ms: u16,
     /// Direction of rotation (true = clockwise).
     pub clockwise: bool,
+    /// Whether the chase jumps a whole LED every step for a retro look, instead of gliding smoothly between LEDs.
+    /// Defaults to gliding when absent.
+    #[serde(default)]
+    pub stepped: bool,
 }
 
 impl ChasePattern {
@@ -54,6 +234,60 @@ impl ChasePattern {
             length,
             speed_ms,
             clockwise: true,
+            stepped: false,
+        }
+    }
+
+    /// Sets the background color.
+    #[must_use]
+    pub const fn with_background(mut self, background: RGB8) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// Sets counter-clockwise rotation.
+    #[must_use]
+    pub const fn counter_clockwise(mut self) -> Self {
+        self.clockwise = false;
+        self
+    }
+
+    /// Makes the chase jump a whole LED every step instead of gliding between LEDs.
+    #[must_use]
+    pub const fn stepped(mut self) -> Self {
+        self.stepped = true;
+        self
+    }
+}
+
+/// Comet pattern configuration.
+///
+/// The head moves one LED every `speed_ms` at full brightness, and every LED it leaves behind keeps fading toward the
+/// background, so each LED of the tail keeps `tail` 256ths of the brightness of the one ahead of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CometPattern {
+    /// Color of the head.
+    pub color: RGB8,
+    /// Background color (default is off).
+    pub background: RGB8,
+    /// Brightness each LED of the tail keeps of the one ahead of it, in 256ths (higher gives a longer tail).
+    pub tail: u8,
+    /// Speed of rotation in milliseconds per step.
+    pub speed_ms: u16,
+    /// Direction of rotation (true = clockwise).
+    pub clockwise: bool,
+}
+
+impl CometPattern {
+    /// Creates a new comet pattern with a tail of about four LEDs.
+    #[must_use]
+    pub const fn new(color: RGB8, speed_ms: u16) -> Self {
+        Self {
+            color,
+            background: RGB8::new(0, 0, 0),
+            tail: 160,
+            speed_ms,
+            clockwise: true,
         }
     }
 
@@ -64,6 +298,13 @@ impl ChasePattern {
         self
     }
 
+    /// Sets the brightness each LED of the tail keeps of the one ahead of it, in 256ths.
+    #[must_use]
+    pub const fn with_tail(mut self, tail: u8) -> Self {
+        self.tail = tail;
+        self
+    }
+
     /// Sets counter-clockwise rotation.
     #[must_use]
     pub const fn counter_clockwise(mut self) -> Self {
@@ -72,6 +313,121 @@ impl ChasePattern {
     }
 }
 
+/// Theater chase pattern configuration.
+///
+/// Every `spacing`th LED is lit and the lit LEDs shift along by one every `speed_ms`. A spacing that does not divide
+/// the number of LEDs leaves one shorter gap where the ring wraps around, between its last LED and its first; on the
+/// 12 LED rings the spacings 2, 3, 4, and 6 wrap evenly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TheaterChasePattern {
+    /// Color of the lit LEDs.
+    pub color: RGB8,
+    /// Background color (default is off).
+    pub background: RGB8,
+    /// Number of LEDs from one lit LED to the next (at least 1).
+    pub spacing: u8,
+    /// Speed of the shift in milliseconds per step.
+    pub speed_ms: u16,
+}
+
+impl TheaterChasePattern {
+    /// Creates a new theater chase pattern.
+    #[must_use]
+    pub const fn new(color: RGB8, spacing: u8, speed_ms: u16) -> Self {
+        Self {
+            color,
+            background: RGB8::new(0, 0, 0),
+            spacing,
+            speed_ms,
+        }
+    }
+
+    /// Sets the background color.
+    #[must_use]
+    pub const fn with_background(mut self, background: RGB8) -> Self {
+        self.background = background;
+        self
+    }
+}
+
+/// Progress bar configuration.
+///
+/// The ring fills clockwise from the top LED as `value` goes from 0 to 255, with the LED at the edge of the bar lit in
+/// proportion to how far the bar reaches into it, so the bar moves smoothly rather than an LED at a time. Like every
+/// other pattern, it follows the orientation of the ring it is shown on.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::lights::{progress, Mode, ProgressPattern};
+///
+/// let Mode::Progress(bar) = progress(50) else { unreachable!() };
+/// assert_eq!(bar.value, 128);
+/// assert_eq!(bar.percent(), 50);
+/// assert_eq!(bar.color, ProgressPattern::COLOR);
+///
+/// assert_eq!(ProgressPattern::new(0, ProgressPattern::COLOR).with_percent(100).value, 255);
+/// assert_eq!(ProgressPattern::new(0, ProgressPattern::COLOR).with_percent(150).value, 255);
+/// assert_eq!(ProgressPattern::new(255, ProgressPattern::COLOR).with_percent(0).value, 0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProgressPattern {
+    /// How far along, from 0 for none to 255 for done.
+    pub value: u8,
+    /// Color of the filled part of the ring.
+    pub color: RGB8,
+    /// Color of the rest of the ring. Defaults to off when absent.
+    #[serde(default)]
+    pub background: RGB8,
+}
+
+impl ProgressPattern {
+    /// Default color of the bar.
+    pub const COLOR: RGB8 = RGB8::new(0, 160, 255);
+
+    /// Creates a new progress bar at `value` out of 255.
+    #[must_use]
+    pub const fn new(value: u8, color: RGB8) -> Self {
+        Self {
+            value,
+            color,
+            background: RGB8::new(0, 0, 0),
+        }
+    }
+
+    /// Sets the background color.
+    #[must_use]
+    pub const fn with_background(mut self, background: RGB8) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// Sets how far along the bar is in percent, capped at 100.
+    #[must_use]
+    pub const fn with_percent(mut self, percent: u8) -> Self {
+        let percent = if percent < 100 { percent } else { 100 };
+        #[allow(clippy::cast_possible_truncation)]
+        let value = ((percent as u16 * 255 + 50) / 100) as u8;
+        self.value = value;
+        self
+    }
+
+    /// Returns how far along the bar is in percent, rounded.
+    #[must_use]
+    pub const fn percent(&self) -> u8 {
+        #[allow(clippy::cast_possible_truncation)]
+        let percent = ((self.value as u16 * 100 + 127) / 255) as u8;
+        percent
+    }
+}
+
+/// Returns a progress bar in the default color at `percent`, for anything that wants to show how far along it is by
+/// setting a ring to it.
+#[must_use]
+pub const fn progress(percent: u8) -> Mode {
+    Mode::Progress(ProgressPattern::new(0, ProgressPattern::COLOR).with_percent(percent))
+}
+
 /// Pulse/breathing pattern configuration.
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct PulsePattern {
@@ -83,6 +439,94 @@ pub struct PulsePattern {
     pub max_brightness: u8,
     /// Duration of one complete pulse cycle in milliseconds.
     pub period_ms: u16,
+    /// How the brightness rises and falls over a cycle. Defaults to [`PulseShape::Sine`] when absent.
+    #[serde(default)]
+    pub shape: PulseShape,
+}
+
+/// How the brightness of a [`PulsePattern`] rises and falls over a cycle.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::lights::{Mode, PulsePattern, PulseShape};
+/// use smart_leds::RGB8;
+///
+/// // Pulses saved before there were shapes still breathe the way they did.
+/// let json = r#"{"Pulse":{"color":{"r":255,"g":0,"b":0},"min_brightness":0,"max_brightness":255,"period_ms":500}}"#;
+/// let (mode, _) = serde_json_core::from_str::<Mode>(json).unwrap();
+/// assert_eq!(mode, Mode::Pulse(PulsePattern::new(RGB8::new(255, 0, 0), 500)));
+///
+/// let json = r#"{"color":{"r":255,"g":0,"b":0},"min_brightness":0,"max_brightness":255,"period_ms":500,
+///     "shape":{"Square":50}}"#;
+/// let (pulse, _) = serde_json_core::from_str::<PulsePattern>(json).unwrap();
+/// assert_eq!(pulse.shape, PulseShape::Square(50));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PulseShape {
+    /// Smooth breathing, starting halfway up.
+    #[default]
+    Sine,
+    /// Linear breathing, up from the minimum to the maximum and back down.
+    Triangle,
+    /// Ramps up from the minimum, then snaps back down.
+    SawtoothUp,
+    /// Snaps up to the maximum, then ramps down.
+    SawtoothDown,
+    /// Blinks hard between the two, at the maximum for this percentage of each cycle.
+    Square(u8),
+}
+
+impl PulseShape {
+    /// Returns the human-readable name of the shape.
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Sine => "sine",
+            Self::Triangle => "triangle",
+            Self::SawtoothUp => "sawtooth up",
+            Self::SawtoothDown => "sawtooth down",
+            Self::Square(_) => "square",
+        }
+    }
+
+    /// Returns how far up from the minimum to the maximum brightness the shape is at `phase` into a cycle, both from
+    /// 0.0 to 1.0.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use catears::lights::PulseShape;
+    ///
+    /// assert_eq!(PulseShape::Triangle.level(0.25), 0.5);
+    /// assert_eq!(PulseShape::Triangle.level(0.5), 1.0);
+    /// assert_eq!(PulseShape::SawtoothUp.level(0.75), 0.75);
+    /// assert_eq!(PulseShape::SawtoothDown.level(0.75), 0.25);
+    /// assert_eq!(PulseShape::Square(25).level(0.2), 1.0);
+    /// assert_eq!(PulseShape::Square(25).level(0.3), 0.0);
+    /// assert!((PulseShape::Sine.level(0.0) - 0.5).abs() < 1e-6);
+    /// ```
+    #[must_use]
+    pub fn level(&self, phase: f32) -> f32 {
+        match self {
+            // A sine wave is a triangle wave eased along half a sine on the way up and down, starting a quarter of a
+            // cycle in so that it rises from the middle.
+            Self::Sine => ease(
+                Easing::InOutSine,
+                Self::Triangle.level((phase + 0.25) % 1.0),
+            ),
+            Self::Triangle => ease(Easing::Linear, 1.0 - (2.0 * phase - 1.0).abs()),
+            Self::SawtoothUp => phase,
+            Self::SawtoothDown => 1.0 - phase,
+            Self::Square(duty) => {
+                if phase * 100.0 < f32::from(*duty) {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
 }
 
 impl PulsePattern {
@@ -94,9 +538,17 @@ impl PulsePattern {
             min_brightness: 0,
             max_brightness: 255,
             period_ms,
+            shape: PulseShape::Sine,
         }
     }
 
+    /// Sets how the brightness rises and falls over a cycle.
+    #[must_use]
+    pub const fn with_shape(mut self, shape: PulseShape) -> Self {
+        self.shape = shape;
+        self
+    }
+
     /// Sets the brightness range.
     #[must_use]
     pub const fn with_brightness_range(mut self, min: u8, max: u8) -> Self {
@@ -104,17 +556,51 @@ impl PulsePattern {
         self.max_brightness = max;
         self
     }
+
+    /// Returns the brightness at `phase` of a cycle, from 0.0 at its start to 1.0 at its end.
+    ///
+    /// A range given upside down pulses between the same two brightnesses.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use catears::lights::{PulsePattern, PulseShape};
+    /// use smart_leds::RGB8;
+    ///
+    /// let pulse = PulsePattern::new(RGB8::new(255, 0, 0), 1000).with_shape(PulseShape::Triangle);
+    /// assert_eq!(pulse.with_brightness_range(40, 200).brightness(0.0), 40);
+    /// assert_eq!(pulse.with_brightness_range(40, 200).brightness(0.5), 200);
+    /// assert_eq!(pulse.with_brightness_range(200, 40).brightness(0.0), 40);
+    /// assert_eq!(pulse.with_brightness_range(200, 40).brightness(0.5), 200);
+    /// assert_eq!(pulse.with_brightness_range(255, 0).brightness(0.5), 255);
+    /// assert_eq!(pulse.with_brightness_range(90, 90).brightness(0.25), 90);
+    /// ```
+    #[must_use]
+    pub fn brightness(&self, phase: f32) -> u8 {
+        let low = self.min_brightness.min(self.max_brightness);
+        let high = self.min_brightness.max(self.max_brightness);
+        let brightness = f32::from(low) + f32::from(high - low) * self.shape.level(phase);
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let brightness = brightness as u8;
+        brightness
+    }
 }
 
 /// Rainbow pattern configuration.
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct RainbowPattern {
-    /// Speed of color cycling in milliseconds per hue step.
+    /// Time in milliseconds the colors take to go once around the color wheel.
     pub speed_ms: u16,
     /// Whether to spread the rainbow across all LEDs (true) or cycle all together (false).
     pub spread: bool,
     /// Brightness level (0-255).
     pub brightness: u8,
+    /// Whether the colors turn the other way around the ring. Defaults to off when absent.
+    #[serde(default)]
+    pub reversed: bool,
+    /// Saturation of the colors (0-255), lower for pastel shades. Defaults to full saturation when absent.
+    #[serde(default = "default_saturation")]
+    pub saturation: u8,
 }
 
 impl RainbowPattern {
@@ -125,6 +611,8 @@ impl RainbowPattern {
             speed_ms,
             spread: true,
             brightness: 255,
+            reversed: false,
+            saturation: 255,
         }
     }
 
@@ -141,99 +629,1394 @@ impl RainbowPattern {
         self.brightness = brightness;
         self
     }
-}
-
-/// Custom LED pattern with individual control.
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
-pub struct LedPattern {
-    /// Individual LED colors (12 LEDs per ring).
-    pub leds: [RGB8; 12],
-    /// Whether this pattern should loop/repeat.
-    pub looping: bool,
-}
-
-impl LedPattern {
-    /// Creates a new custom pattern with all LEDs off.
-    #[must_use]
-    pub const fn new() -> Self {
-        Self {
-            leds: [RGB8::new(0, 0, 0); 12],
-            looping: false,
-        }
-    }
 
-    /// Creates a pattern from a slice of colors.
-    ///
-    /// # Panics
-    ///
-    /// Panics if the slice doesn't contain exactly 12 colors.
+    /// Turns the colors the other way around the ring.
     #[must_use]
-    pub fn from_colors(colors: &[RGB8]) -> Self {
-        assert_eq!(colors.len(), 12, "LedPattern requires exactly 12 colors");
-        let mut pattern = Self::new();
-        for (i, &color) in colors.iter().enumerate() {
-            pattern.leds[i] = color;
-        }
-        pattern
+    pub const fn reversed(mut self) -> Self {
+        self.reversed = true;
+        self
     }
 
-    /// Enables looping for animated patterns.
+    /// Sets the saturation of the colors, lower for pastel shades.
     #[must_use]
-    pub const fn with_loop(mut self) -> Self {
-        self.looping = true;
+    pub const fn with_saturation(mut self, saturation: u8) -> Self {
+        self.saturation = saturation;
         self
     }
 }
 
-impl Default for LedPattern {
-    fn default() -> Self {
-        Self::new()
-    }
+const fn default_saturation() -> u8 {
+    u8::MAX
 }
 
-/// Predefined light patterns for common effects.
-pub mod patterns {
-    use super::{ChasePattern, LedPattern, Mode, PulsePattern, RainbowPattern};
-    use smart_leds::RGB8;
-
-    /// Police/emergency light pattern (red and blue).
-    #[must_use]
-    pub fn police() -> Mode {
-        Mode::Chase(
-            ChasePattern::new(RGB8::new(255, 0, 0), 6, 100).with_background(RGB8::new(0, 0, 255)),
-        )
-    }
+/// Strobe configuration.
+///
+/// The whole ring shows `color` for `on_ms` and goes dark for `off_ms`, switching at once rather than fading. With a
+/// `count`, the ring stays dark after that many flashes, which makes for a notification. However short `off_ms` is
+/// asked to be, the ring stays dark for at least [`StrobePattern::MIN_OFF_MS`], so that no setting can keep the rings,
+/// and the power rail behind them, switching at a punishing duty cycle.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::lights::{Mode, StrobePattern};
+/// use smart_leds::RGB8;
+///
+/// let white = RGB8::new(255, 255, 255);
+/// let strobe = StrobePattern::new(white, 50, 100).with_count(2);
+/// assert!(strobe.is_on(0));
+/// assert!(strobe.is_on(49));
+/// assert!(!strobe.is_on(50));
+/// assert!(strobe.is_on(150));
+///
+/// // After the last flash the ring stays dark.
+/// assert!(!strobe.is_on(300));
+/// assert!(!strobe.is_on(10_000));
+///
+/// // However short the dark time is asked to be, it lasts at least the minimum.
+/// let json = r#"{"Strobe":{"color":{"r":255,"g":0,"b":0},"on_ms":500,"off_ms":0}}"#;
+/// let (mode, _) = serde_json_core::from_str::<Mode>(json).unwrap();
+/// let Mode::Strobe(strobe) = mode else { unreachable!() };
+/// assert_eq!(strobe.count, None);
+/// assert!(!strobe.is_on(500));
+/// assert!(!strobe.is_on(500 + u32::from(StrobePattern::MIN_OFF_MS) - 1));
+/// assert!(strobe.is_on(500 + u32::from(StrobePattern::MIN_OFF_MS)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StrobePattern {
+    /// Color of the flashes.
+    pub color: RGB8,
+    /// Time in milliseconds each flash lasts.
+    pub on_ms: u16,
+    /// Time in milliseconds the ring is dark between flashes, at least [`StrobePattern::MIN_OFF_MS`].
+    pub off_ms: u16,
+    /// Number of flashes before the ring stays dark, or `None` to keep flashing.
+    #[serde(default)]
+    pub count: Option<u16>,
+}
 
-    /// Soft breathing white light.
-    #[must_use]
-    pub fn breathing() -> Mode {
-        Mode::Pulse(
-            PulsePattern::new(RGB8::new(255, 255, 255), 3000).with_brightness_range(20, 255),
-        )
-    }
+impl StrobePattern {
+    /// Shortest time in milliseconds the ring is dark between flashes.
+    pub const MIN_OFF_MS: u16 = 30;
 
-    /// Fast rainbow cycle.
+    /// Creates a new strobe that keeps flashing.
     #[must_use]
-    pub fn party() -> Mode {
-        Mode::Rainbow(RainbowPattern::new(50))
+    pub const fn new(color: RGB8, on_ms: u16, off_ms: u16) -> Self {
+        Self {
+            color,
+            on_ms,
+            off_ms,
+            count: None,
+        }
     }
 
-    /// Alert pattern (flashing red).
+    /// Stops flashing after `count` flashes.
     #[must_use]
-    pub fn alert() -> Mode {
-        Mode::Pulse(PulsePattern::new(RGB8::new(255, 0, 0), 500))
+    pub const fn with_count(mut self, count: u16) -> Self {
+        self.count = Some(count);
+        self
     }
 
-    /// Success pattern (green pulse).
+    /// Returns the time in milliseconds from the start of one flash to the start of the next.
     #[must_use]
-    pub fn success() -> Mode {
-        Mode::Pulse(PulsePattern::new(RGB8::new(0, 255, 0), 1000).with_brightness_range(50, 255))
+    pub fn period_ms(&self) -> u32 {
+        u32::from(self.on_ms) + u32::from(self.off_ms.max(Self::MIN_OFF_MS))
     }
 
-    /// Loading/thinking pattern (blue chase).
+    /// Returns whether the ring is lit `elapsed_ms` after the strobe started.
     #[must_use]
-    pub fn loading() -> Mode {
-        Mode::Chase(ChasePattern::new(RGB8::new(0, 100, 255), 3, 150))
+    pub fn is_on(&self, elapsed_ms: u32) -> bool {
+        let period_ms = self.period_ms();
+        let flashes = elapsed_ms / period_ms;
+        self.count.is_none_or(|count| flashes < u32::from(count))
+            && elapsed_ms % period_ms < u32::from(self.on_ms)
+    }
+}
+
+/// Palette cycle pattern configuration.
+///
+/// The colors of the palette go by once every `speed_ms`. Spread around the ring, the whole palette shows at once and
+/// turns; otherwise the whole ring shows one color at a time.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::lights::{Mode, PaletteCyclePattern, PaletteId};
+///
+/// // Remote JSON picks the palette by name.
+/// let json = r#"{"PaletteCycle":{"palette":"Ocean","speed_ms":4000,"spread":true}}"#;
+/// let (mode, _) = serde_json_core::from_str::<Mode>(json).unwrap();
+/// assert_eq!(mode, Mode::PaletteCycle(PaletteCyclePattern::new(PaletteId::Ocean, 4000)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaletteCyclePattern {
+    /// Palette to cycle through.
+    pub palette: PaletteId,
+    /// Time in milliseconds the palette takes to go by once.
+    pub speed_ms: u16,
+    /// Whether to spread the palette around the ring (true) or show one color on every LED (false).
+    pub spread: bool,
+}
+
+impl PaletteCyclePattern {
+    /// Creates a new palette cycle, spread around the ring.
+    #[must_use]
+    pub const fn new(palette: PaletteId, speed_ms: u16) -> Self {
+        Self {
+            palette,
+            speed_ms,
+            spread: true,
+        }
+    }
+
+    /// Shows one color of the palette on every LED at a time.
+    #[must_use]
+    pub const fn unified(mut self) -> Self {
+        self.spread = false;
+        self
+    }
+}
+
+/// Sixteen colors to pick smoothly from, for effects that move through a range of colors.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::lights::{Palette, PaletteId};
+/// use smart_leds::RGB8;
+///
+/// let palette = Palette::new(core::array::from_fn(|i| RGB8::new(i as u8 * 16, 0, 255 - i as u8 * 16)));
+///
+/// // Every sixteenth index lands on an entry, and the ones in between blend the two entries around them.
+/// assert_eq!(palette.color(0), RGB8::new(0, 0, 255));
+/// assert_eq!(palette.color(16), RGB8::new(16, 0, 239));
+/// assert_eq!(palette.color(24), RGB8::new(24, 0, 231));
+///
+/// // Past the last entry, the colors blend back into the first, so a palette can cycle without a seam.
+/// assert_eq!(palette.color(240), RGB8::new(240, 0, 15));
+/// assert_eq!(palette.color(248), RGB8::new(120, 0, 135));
+///
+/// // The built-in palettes are picked by name.
+/// assert_eq!(PaletteId::from_name("Heat"), Some(PaletteId::Heat));
+/// assert_eq!(PaletteId::Heat.palette().color(0), RGB8::new(0, 0, 0));
+/// assert_eq!(PaletteId::from_name("lava"), None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    /// Colors from the start of the palette to its end.
+    pub colors: [RGB8; 16],
+}
+
+impl Palette {
+    /// Creates a new palette.
+    #[must_use]
+    pub const fn new(colors: [RGB8; 16]) -> Self {
+        Self { colors }
+    }
+
+    /// Returns the color at `index` of 256 steps through the palette, blended linearly between the two nearest
+    /// entries.
+    #[must_use]
+    pub fn color(&self, index: u8) -> RGB8 {
+        let entry = usize::from(index >> 4);
+        let from = self.colors[entry];
+        let to = self.colors[(entry + 1) % self.colors.len()];
+        let fraction = u16::from(index & 0x0f);
+        let blend = |from: u8, to: u8| {
+            #[allow(clippy::cast_possible_truncation)]
+            let blended =
+                ((u16::from(from) * (16 - fraction) + u16::from(to) * fraction) / 16) as u8;
+            blended
+        };
+        RGB8::new(
+            blend(from.r, to.r),
+            blend(from.g, to.g),
+            blend(from.b, to.b),
+        )
+    }
+}
+
+/// Shorthand for the colors of the built-in palettes.
+const fn rgb(r: u8, g: u8, b: u8) -> RGB8 {
+    RGB8::new(r, g, b)
+}
+
+/// Black through the reds and yellows of glowing embers to white.
+const HEAT: Palette = Palette::new([
+    rgb(0, 0, 0),
+    rgb(51, 0, 0),
+    rgb(102, 0, 0),
+    rgb(153, 0, 0),
+    rgb(204, 0, 0),
+    rgb(255, 0, 0),
+    rgb(255, 51, 0),
+    rgb(255, 102, 0),
+    rgb(255, 153, 0),
+    rgb(255, 204, 0),
+    rgb(255, 255, 0),
+    rgb(255, 255, 51),
+    rgb(255, 255, 102),
+    rgb(255, 255, 153),
+    rgb(255, 255, 204),
+    rgb(255, 255, 255),
+]);
+
+/// Deep blues, sea greens, and the aqua of breaking waves.
+const OCEAN: Palette = Palette::new([
+    rgb(25, 25, 112),
+    rgb(0, 0, 139),
+    rgb(25, 25, 112),
+    rgb(0, 0, 128),
+    rgb(0, 0, 139),
+    rgb(0, 0, 205),
+    rgb(46, 139, 87),
+    rgb(0, 128, 128),
+    rgb(95, 158, 160),
+    rgb(0, 0, 255),
+    rgb(0, 139, 139),
+    rgb(100, 149, 237),
+    rgb(127, 255, 212),
+    rgb(46, 139, 87),
+    rgb(0, 255, 255),
+    rgb(135, 206, 250),
+]);
+
+/// Purples, pinks, reds, and golds, with no greens to dull them.
+const PARTY: Palette = Palette::new([
+    rgb(85, 0, 171),
+    rgb(132, 0, 124),
+    rgb(181, 0, 75),
+    rgb(229, 0, 27),
+    rgb(232, 23, 0),
+    rgb(184, 71, 0),
+    rgb(171, 119, 0),
+    rgb(171, 171, 0),
+    rgb(171, 85, 0),
+    rgb(221, 34, 0),
+    rgb(242, 0, 14),
+    rgb(194, 0, 62),
+    rgb(143, 0, 113),
+    rgb(95, 0, 161),
+    rgb(47, 0, 208),
+    rgb(0, 7, 249),
+]);
+
+/// Greens of the forest floor and the canopy.
+const FOREST: Palette = Palette::new([
+    rgb(0, 100, 0),
+    rgb(0, 100, 0),
+    rgb(85, 107, 47),
+    rgb(0, 100, 0),
+    rgb(0, 128, 0),
+    rgb(34, 139, 34),
+    rgb(107, 142, 35),
+    rgb(0, 128, 0),
+    rgb(46, 139, 87),
+    rgb(102, 205, 170),
+    rgb(34, 139, 34),
+    rgb(152, 251, 152),
+    rgb(154, 205, 50),
+    rgb(50, 205, 50),
+    rgb(85, 107, 47),
+    rgb(34, 139, 34),
+]);
+
+/// Built-in palettes, picked by name in remote JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PaletteId {
+    /// Black through the reds and yellows of glowing embers to white.
+    Heat,
+    /// Deep blues, sea greens, and the aqua of breaking waves.
+    Ocean,
+    /// Purples, pinks, reds, and golds.
+    Party,
+    /// Greens of the forest floor and the canopy.
+    Forest,
+}
+
+impl PaletteId {
+    /// All built-in palettes, in display order.
+    pub const ALL: [Self; 4] = [Self::Heat, Self::Ocean, Self::Party, Self::Forest];
+
+    /// Returns the lowercase name of the palette.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Heat => "heat",
+            Self::Ocean => "ocean",
+            Self::Party => "party",
+            Self::Forest => "forest",
+        }
+    }
+
+    /// Looks up a palette by its (case-insensitive) name.
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|palette| palette.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Returns the colors of the palette.
+    #[must_use]
+    pub const fn palette(self) -> &'static Palette {
+        match self {
+            Self::Heat => &HEAT,
+            Self::Ocean => &OCEAN,
+            Self::Party => &PARTY,
+            Self::Forest => &FOREST,
+        }
+    }
+}
+
+/// Sound-reactive VU meter configuration.
+///
+/// The number of lit LEDs follows the measured microphone level, with colors fading from `low` at the first LED to
+/// `high` at the last.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct VuPattern {
+    /// Color of the first LED.
+    pub low: RGB8,
+    /// Color of the last LED.
+    pub high: RGB8,
+    /// Frequency band the meter follows.
+    pub band: Band,
+    /// Level multiplier in sixteenths (16 = unity gain).
+    pub gain: u8,
+}
+
+impl VuPattern {
+    /// Creates a new VU meter following the full signal at unity gain.
+    #[must_use]
+    pub const fn new(low: RGB8, high: RGB8) -> Self {
+        Self {
+            low,
+            high,
+            band: Band::Full,
+            gain: 16,
+        }
+    }
+
+    /// Sets the frequency band the meter follows.
+    #[must_use]
+    pub const fn with_band(mut self, band: Band) -> Self {
+        self.band = band;
+        self
+    }
+
+    /// Sets the level multiplier in sixteenths.
+    #[must_use]
+    pub const fn with_gain(mut self, gain: u8) -> Self {
+        self.gain = gain;
+        self
+    }
+
+    /// Returns how many of `count` LEDs should be lit for the given levels.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use catears::audio::dsp::Levels;
+    /// use catears::lights::VuPattern;
+    /// use smart_leds::RGB8;
+    ///
+    /// let pattern = VuPattern::new(RGB8::new(0, 255, 0), RGB8::new(255, 0, 0));
+    /// let level = |rms| Levels { rms, ..Levels::default() };
+    /// assert_eq!(pattern.lit(&level(0), 12), 0);
+    /// assert_eq!(pattern.lit(&level(128), 12), 6);
+    /// assert_eq!(pattern.lit(&level(255), 12), 12);
+    /// // Doubling the gain saturates at the full ring.
+    /// assert_eq!(pattern.with_gain(32).lit(&level(200), 12), 12);
+    /// ```
+    #[must_use]
+    pub fn lit(&self, levels: &Levels, count: usize) -> usize {
+        let level = (u32::from(levels.band(self.band)) * u32::from(self.gain) / 16).min(255);
+        // Round to the nearest LED so that a mid-scale level lights half the ring.
+        (level as usize * count + 127) / 255
+    }
+}
+
+/// Configuration of a ring that flashes in time with the speaker on its side.
+///
+/// The ring lights up in `color` as each note of a chiptune starts and fades back to `background`, see
+/// [`crate::audio::beat`]. With no chiptune playing, it flashes every `period_ms` instead.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BeatPattern {
+    /// Color of a flash.
+    pub color: RGB8,
+    /// Color between flashes (default is off).
+    pub background: RGB8,
+    /// How long a flash takes to fade, in milliseconds. A chiptune with a tempo cuts it short at the length of a beat.
+    pub decay_ms: u16,
+    /// Time between flashes in milliseconds while no chiptune is playing.
+    pub period_ms: u16,
+}
+
+impl BeatPattern {
+    /// Creates a new beat pattern flashing `color` over a dark ring, fading over `decay_ms`, and every 500 ms without
+    /// a chiptune to follow.
+    #[must_use]
+    pub const fn new(color: RGB8, decay_ms: u16) -> Self {
+        Self {
+            color,
+            background: RGB8::new(0, 0, 0),
+            decay_ms,
+            period_ms: 500,
+        }
+    }
+
+    /// Sets the background color.
+    #[must_use]
+    pub const fn with_background(mut self, background: RGB8) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// Sets the time between flashes without a chiptune to follow.
+    #[must_use]
+    pub const fn with_period(mut self, period_ms: u16) -> Self {
+        self.period_ms = period_ms;
+        self
+    }
+
+    /// Returns how much of a flash shows, from 1.0 as it starts to 0.0 once it has faded, given the latest `beat` of the
+    /// speaker, or `elapsed_ms` of animation to count out the steady flashes by without one.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use catears::audio::beat::Beat;
+    /// use catears::lights::BeatPattern;
+    /// use smart_leds::RGB8;
+    ///
+    /// let pattern = BeatPattern::new(RGB8::new(255, 0, 255), 200).with_period(1000);
+    ///
+    /// // Following a chiptune, a flash fades over the decay time after each note.
+    /// let beat = |since_ms, bpm| Some(Beat { count: 3, since_ms, bpm });
+    /// assert_eq!(pattern.flash(beat(0, None), 0), 1.0);
+    /// assert_eq!(pattern.flash(beat(100, None), 0), 0.5);
+    /// assert_eq!(pattern.flash(beat(300, None), 0), 0.0);
+    /// // At 600 bpm a beat lasts 100 ms, which is all the time a flash has.
+    /// assert_eq!(pattern.flash(beat(50, Some(600)), 0), 0.5);
+    ///
+    /// // Without one, it flashes once a period.
+    /// assert_eq!(pattern.flash(None, 2100), 0.5);
+    /// ```
+    #[must_use]
+    pub fn flash(&self, beat: Option<Beat>, elapsed_ms: u32) -> f32 {
+        let mut decay_ms = u32::from(self.decay_ms);
+        let since_ms = match beat {
+            Some(beat) => {
+                if let Some(period_ms) = beat.period_ms() {
+                    decay_ms = decay_ms.min(period_ms);
+                }
+                beat.since_ms
+            }
+            None => elapsed_ms % u32::from(self.period_ms).max(1),
+        };
+        if since_ms >= decay_ms {
+            return 0.0;
+        }
+        #[allow(clippy::cast_precision_loss)]
+        let faded = since_ms as f32 / decay_ms as f32;
+        1.0 - faded
+    }
+}
+
+/// Flickering fire configuration.
+///
+/// Each LED has a heat that cools a little every 10 ms and drifts up the ring from the first LED, where new sparks
+/// flare up at random. Heat shows through a palette from black through red and orange to yellow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FirePattern {
+    /// How quickly the flames cool (0-255). Higher values give shorter flames.
+    pub cooling: u8,
+    /// Chance of a new spark every 10 ms, in 256ths. Higher values give a busier fire.
+    pub sparking: u8,
+}
+
+impl FirePattern {
+    /// Creates a new fire of medium height and liveliness.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            cooling: 55,
+            sparking: 120,
+        }
+    }
+
+    /// Sets how quickly the flames cool.
+    #[must_use]
+    pub const fn with_cooling(mut self, cooling: u8) -> Self {
+        self.cooling = cooling;
+        self
+    }
+
+    /// Sets the chance of a new spark.
+    #[must_use]
+    pub const fn with_sparking(mut self, sparking: u8) -> Self {
+        self.sparking = sparking;
+        self
+    }
+}
+
+impl Default for FirePattern {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Sparkle configuration.
+///
+/// Every 10 ms a new sparkle may light up on a random LED, in `color` or in a random hue of its own, and fade back into
+/// `background` over `decay_ms`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SparklePattern {
+    /// Color of the sparkles, or `None` for a random hue each.
+    #[serde(default)]
+    pub color: Option<RGB8>,
+    /// Background color (default is off).
+    pub background: RGB8,
+    /// Chance of a new sparkle every 10 ms, in 256ths.
+    pub chance: u8,
+    /// Time in milliseconds a sparkle takes to fade.
+    pub decay_ms: u16,
+}
+
+impl SparklePattern {
+    /// Creates a new pattern of `color` sparkles over a dark ring, a few a second, fading over 400 ms.
+    #[must_use]
+    pub const fn new(color: RGB8) -> Self {
+        Self {
+            color: Some(color),
+            background: RGB8::new(0, 0, 0),
+            chance: 20,
+            decay_ms: 400,
+        }
+    }
+
+    /// Gives each sparkle a random hue of its own instead of a single color.
+    #[must_use]
+    pub const fn with_random_hues(mut self) -> Self {
+        self.color = None;
+        self
+    }
+
+    /// Sets the background color.
+    #[must_use]
+    pub const fn with_background(mut self, background: RGB8) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// Sets the chance of a new sparkle every 10 ms, in 256ths.
+    #[must_use]
+    pub const fn with_chance(mut self, chance: u8) -> Self {
+        self.chance = chance;
+        self
+    }
+
+    /// Sets the time a sparkle takes to fade.
+    #[must_use]
+    pub const fn with_decay(mut self, decay_ms: u16) -> Self {
+        self.decay_ms = decay_ms;
+        self
+    }
+}
+
+impl Default for SparklePattern {
+    /// Sparkles in random hues over a dark ring.
+    fn default() -> Self {
+        Self::new(RGB8::new(255, 255, 255)).with_random_hues()
+    }
+}
+
+/// Custom LED pattern with individual control, shown as a single still frame. See [`Animation`] for frames that change.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::lights::{LedPattern, LED_COUNT};
+/// use smart_leds::RGB8;
+///
+/// let red = r#"{"r":255,"g":0,"b":0}"#;
+/// let pattern = |count| {
+///     let leds = vec![red; count].join(",");
+///     let json = format!(r#"{{"leds":[{leds}],"looping":false}}"#);
+///     serde_json_core::from_str::<LedPattern>(&json).unwrap().0
+/// };
+///
+/// // A pattern made for a ring of another size still shows, cut short or with the LEDs it is missing left off.
+/// assert_eq!(pattern(LED_COUNT).leds, [RGB8::new(255, 0, 0); LED_COUNT]);
+/// assert_eq!(pattern(LED_COUNT + 4).leds, [RGB8::new(255, 0, 0); LED_COUNT]);
+/// let short = pattern(LED_COUNT - 2).leds;
+/// assert_eq!(short[..LED_COUNT - 2], [RGB8::new(255, 0, 0); LED_COUNT - 2]);
+/// assert_eq!(short[LED_COUNT - 2..], [RGB8::default(); 2]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LedPattern {
+    /// Individual LED colors, one per LED of the ring.
+    #[serde(deserialize_with = "deserialize_leds")]
+    pub leds: [RGB8; LED_COUNT],
+    /// Whether this pattern should loop/repeat.
+    pub looping: bool,
+}
+
+impl LedPattern {
+    /// Creates a new custom pattern with all LEDs off.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            leds: [RGB8::new(0, 0, 0); LED_COUNT],
+            looping: false,
+        }
+    }
+
+    /// Creates a pattern from a slice of colors.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the slice doesn't contain exactly [`LED_COUNT`] colors.
+    #[must_use]
+    pub fn from_colors(colors: &[RGB8]) -> Self {
+        assert_eq!(
+            colors.len(),
+            LED_COUNT,
+            "LedPattern requires a color for every LED"
+        );
+        let mut pattern = Self::new();
+        for (i, &color) in colors.iter().enumerate() {
+            pattern.leds[i] = color;
+        }
+        pattern
+    }
+
+    /// Enables looping for animated patterns.
+    #[must_use]
+    pub const fn with_loop(mut self) -> Self {
+        self.looping = true;
+        self
+    }
+}
+
+impl Default for LedPattern {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Reads the colors of a ring from a list of any length, so that patterns made for rings of another size still show.
+///
+/// Colors past the last LED are dropped, and LEDs left without one are off, with a warning either way.
+fn deserialize_leds<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<[RGB8; LED_COUNT], D::Error> {
+    /// Reads any number of colors, keeping the first [`LED_COUNT`].
+    struct Visitor;
+
+    impl<'de> serde::de::Visitor<'de> for Visitor {
+        type Value = [RGB8; LED_COUNT];
+
+        fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+            formatter.write_str("a list of LED colors")
+        }
+
+        fn visit_seq<A: serde::de::SeqAccess<'de>>(
+            self,
+            mut seq: A,
+        ) -> Result<Self::Value, A::Error> {
+            let mut leds = [RGB8::new(0, 0, 0); LED_COUNT];
+            let mut count = 0;
+            while let Some(color) = seq.next_element()? {
+                if let Some(led) = leds.get_mut(count) {
+                    *led = color;
+                }
+                count += 1;
+            }
+            if count > LED_COUNT {
+                warn!(
+                    "Got {} LED colors for a ring of {}, dropping the rest",
+                    count, LED_COUNT
+                );
+            } else if count < LED_COUNT {
+                warn!(
+                    "Got {} LED colors for a ring of {}, leaving the rest off",
+                    count, LED_COUNT
+                );
+            }
+            Ok(leds)
+        }
+    }
+
+    deserializer.deserialize_seq(Visitor)
+}
+
+/// One frame of an [`Animation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnimationFrame {
+    /// Individual LED colors, one per LED of the ring.
+    #[serde(deserialize_with = "deserialize_leds")]
+    pub leds: [RGB8; LED_COUNT],
+    /// Time in milliseconds the frame shows before the next one, rounded up to a whole 10 ms frame.
+    pub hold_ms: u16,
+}
+
+impl AnimationFrame {
+    /// Creates a frame showing `leds` for `hold_ms`.
+    #[must_use]
+    pub const fn new(leds: [RGB8; LED_COUNT], hold_ms: u16) -> Self {
+        Self { leds, hold_ms }
+    }
+}
+
+/// Custom animation of up to [`Animation::MAX_FRAMES`] frames, each shown for its own time.
+///
+/// Once the last frame has shown, a looping animation starts over from the first, and any other holds the last frame
+/// until the mode changes. Only the frames in use are serialized.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::lights::{Animation, AnimationFrame, Mode, LED_COUNT};
+/// use smart_leds::RGB8;
+///
+/// let red = AnimationFrame::new([RGB8::new(255, 0, 0); LED_COUNT], 200);
+/// let off = AnimationFrame::new([RGB8::new(0, 0, 0); LED_COUNT], 100);
+/// let blink = Animation::new().with_frame(red).with_frame(off).with_loop();
+/// assert_eq!(blink.frames(), [red, off]);
+///
+/// // Frames past the maximum are dropped.
+/// let long = (0..20).fold(Animation::new(), |animation, _| animation.with_frame(off));
+/// assert_eq!(long.frames().len(), Animation::MAX_FRAMES);
+///
+/// // Remote JSON can define one, and gets back only the frames it gave.
+/// let frame = |lit| {
+///     let leds: Vec<&str> = (0..LED_COUNT)
+///         .map(|i| if i == lit { r#"{"r":255,"g":0,"b":0}"# } else { r#"{"r":0,"g":0,"b":0}"# })
+///         .collect();
+///     format!(r#"{{"leds":[{}],"hold_ms":100}}"#, leds.join(","))
+/// };
+/// let frames: Vec<String> = (0..4).map(frame).collect();
+/// let json = format!(r#"{{"Animation":{{"frames":[{}],"looping":true}}}}"#, frames.join(","));
+/// let (mode, _) = serde_json_core::from_str::<Mode>(&json).unwrap();
+/// let Mode::Animation(animation) = mode else { panic!() };
+/// assert_eq!(animation.frames().len(), 4);
+/// assert!(animation.looping);
+/// let mut out = vec![0u8; json.len()];
+/// let len = serde_json_core::to_slice(&mode, &mut out).unwrap();
+/// assert_eq!(&out[..len], json.as_bytes());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Animation {
+    /// Frames in the order they show.
+    frames: Frames,
+    /// Whether the animation starts over after the last frame instead of holding it.
+    #[serde(default)]
+    pub looping: bool,
+}
+
+impl Animation {
+    /// Most frames an animation holds.
+    pub const MAX_FRAMES: usize = 8;
+
+    /// Creates an empty animation, which leaves the ring dark.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            frames: Frames {
+                frames: [AnimationFrame::new([RGB8::new(0, 0, 0); LED_COUNT], 0); Self::MAX_FRAMES],
+                len: 0,
+            },
+            looping: false,
+        }
+    }
+
+    /// Adds `frame` after the others, or drops it if the animation already has [`Self::MAX_FRAMES`] frames.
+    #[must_use]
+    pub const fn with_frame(mut self, frame: AnimationFrame) -> Self {
+        if self.frames.len < Self::MAX_FRAMES {
+            self.frames.frames[self.frames.len] = frame;
+            self.frames.len += 1;
+        }
+        self
+    }
+
+    /// Enables looping.
+    #[must_use]
+    pub const fn with_loop(mut self) -> Self {
+        self.looping = true;
+        self
+    }
+
+    /// Returns the frames in use, in order.
+    #[must_use]
+    pub fn frames(&self) -> &[AnimationFrame] {
+        &self.frames.frames[..self.frames.len]
+    }
+}
+
+impl Default for Animation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The frames of an animation up to its length, which is all that is serialized of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Frames {
+    frames: [AnimationFrame; Animation::MAX_FRAMES],
+    len: usize,
+}
+
+impl Serialize for Frames {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(&self.frames[..self.len])
+    }
+}
+
+impl<'de> Deserialize<'de> for Frames {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        /// Reads up to [`Animation::MAX_FRAMES`] frames.
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = Frames;
+
+            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                formatter.write_str("at most 8 frames")
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<Frames, A::Error> {
+                let mut frames = Animation::new().frames;
+                while let Some(frame) = seq.next_element()? {
+                    let Some(slot) = frames.frames.get_mut(frames.len) else {
+                        return Err(serde::de::Error::invalid_length(frames.len + 1, &self));
+                    };
+                    *slot = frame;
+                    frames.len += 1;
+                }
+                Ok(frames)
+            }
+        }
+
+        deserializer.deserialize_seq(Visitor)
+    }
+}
+
+/// An arc of `length` LEDs of a ring starting at LED `start` and going clockwise, wrapping around past the last LED.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Segment {
+    /// Index of the first LED of the arc.
+    pub start: u8,
+    /// Number of LEDs in the arc, at most [`Segment::MAX_LENGTH`].
+    pub length: u8,
+    /// Color of the arc.
+    pub color: RGB8,
+}
+
+impl Segment {
+    /// Longest arc, all the way around a ring.
+    #[allow(clippy::cast_possible_truncation)]
+    pub const MAX_LENGTH: u8 = LED_COUNT as u8;
+
+    /// Creates a new arc, with the length cut down to [`Segment::MAX_LENGTH`].
+    #[must_use]
+    pub const fn new(start: u8, length: u8, color: RGB8) -> Self {
+        Self {
+            start,
+            length: if length < Self::MAX_LENGTH {
+                length
+            } else {
+                Self::MAX_LENGTH
+            },
+            color,
+        }
+    }
+}
+
+/// Up to [`SegmentsPattern::MAX_SEGMENTS`] arcs of a ring, lit in their own colors over a dark ring.
+///
+/// Where arcs overlap, the one added later wins. Serialized as an array of only the arcs in use.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::lights::{Mode, Segment, SegmentsPattern, LED_COUNT};
+/// use smart_leds::RGB8;
+///
+/// let amber = RGB8::new(255, 140, 0);
+/// let blue = RGB8::new(0, 0, 255);
+/// let arcs = SegmentsPattern::new()
+///     .with_segment(Segment::new(0, 4, amber))
+///     .with_segment(Segment::new(8, 4, blue));
+/// assert_eq!(arcs.segments(), [Segment::new(0, 4, amber), Segment::new(8, 4, blue)]);
+///
+/// // An arc at the same start replaces the one there, and past the maximum the oldest arc makes room.
+/// let arcs = arcs.with_segment(Segment::new(0, 2, blue));
+/// assert_eq!(arcs.segments(), [Segment::new(8, 4, blue), Segment::new(0, 2, blue)]);
+/// let full = (0..6).fold(SegmentsPattern::new(), |arcs, start| arcs.with_segment(Segment::new(start, 1, blue)));
+/// assert_eq!(full.segments().len(), SegmentsPattern::MAX_SEGMENTS);
+/// assert_eq!(full.segments()[0].start, 2);
+///
+/// // Lengths past a whole ring are cut down.
+/// assert_eq!(usize::from(Segment::new(3, 40, amber).length), LED_COUNT);
+///
+/// // Remote JSON can define them, and gets back only the arcs it gave.
+/// let json = r#"{"Segments":[{"start":10,"length":4,"color":{"r":0,"g":0,"b":255}}]}"#;
+/// let (mode, _) = serde_json_core::from_str::<Mode>(json).unwrap();
+/// assert_eq!(mode, Mode::Segments(SegmentsPattern::new().with_segment(Segment::new(10, 4, blue))));
+/// let mut out = [0u8; 256];
+/// let len = serde_json_core::to_slice(&mode, &mut out).unwrap();
+/// assert_eq!(&out[..len], json.as_bytes());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SegmentsPattern {
+    /// Arcs in the order they were added.
+    segments: [Segment; SegmentsPattern::MAX_SEGMENTS],
+    /// Number of arcs in use.
+    len: usize,
+}
+
+impl SegmentsPattern {
+    /// Most arcs a ring holds.
+    pub const MAX_SEGMENTS: usize = 4;
+
+    /// Creates a pattern without any arcs, which leaves the ring dark.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            segments: [Segment::new(0, 0, RGB8::new(0, 0, 0)); Self::MAX_SEGMENTS],
+            len: 0,
+        }
+    }
+
+    /// Adds `segment` over the others, replacing an arc with the same start, or dropping the oldest arc if there are
+    /// already [`Self::MAX_SEGMENTS`].
+    #[must_use]
+    pub fn with_segment(mut self, segment: Segment) -> Self {
+        if let Some(same) = self
+            .segments()
+            .iter()
+            .position(|arc| arc.start == segment.start)
+        {
+            self.remove(same);
+        } else if self.len == Self::MAX_SEGMENTS {
+            self.remove(0);
+        }
+        self.segments[self.len] = Segment::new(segment.start, segment.length, segment.color);
+        self.len += 1;
+        self
+    }
+
+    /// Returns the arcs in use, in the order they were added.
+    #[must_use]
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments[..self.len]
+    }
+
+    /// Removes the arc at `index`, keeping the others in order.
+    fn remove(&mut self, index: usize) {
+        self.segments.copy_within(index + 1..self.len, index);
+        self.len -= 1;
+    }
+}
+
+impl Default for SegmentsPattern {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Serialize for SegmentsPattern {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.segments())
+    }
+}
+
+impl<'de> Deserialize<'de> for SegmentsPattern {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        /// Reads up to [`SegmentsPattern::MAX_SEGMENTS`] arcs.
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = SegmentsPattern;
+
+            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                formatter.write_str("at most 4 segments")
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<SegmentsPattern, A::Error> {
+                let mut pattern = SegmentsPattern::new();
+                while let Some(segment) = seq.next_element::<Segment>()? {
+                    let Some(slot) = pattern.segments.get_mut(pattern.len) else {
+                        return Err(serde::de::Error::invalid_length(pattern.len + 1, &self));
+                    };
+                    *slot = Segment::new(segment.start, segment.length, segment.color);
+                    pattern.len += 1;
+                }
+                Ok(pattern)
+            }
+        }
+
+        deserializer.deserialize_seq(Visitor)
+    }
+}
+
+/// White balance correction of the LED rings, as a scale out of 255 for each channel.
+///
+/// WS2812s tend to show a bluish white, so turning blue (and a little green) down warms the rings up to match other
+/// lights. The correction is applied to the colors as they are written out, after the patterns are rendered and
+/// scaled to the brightness, and before any gamma correction.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::lights::ColorCorrection;
+/// use smart_leds::RGB8;
+///
+/// // The neutral preset leaves every color as it is, even at full brightness.
+/// for color in [RGB8::new(0, 0, 0), RGB8::new(1, 128, 254), RGB8::new(255, 255, 255)] {
+///     assert_eq!(ColorCorrection::NEUTRAL.apply(color), color);
+/// }
+///
+/// // Channels are only ever scaled down, so full white saturates at the correction itself.
+/// let white = RGB8::new(255, 255, 255);
+/// assert_eq!(ColorCorrection::TUNGSTEN.apply(white), RGB8::new(255, 214, 170));
+/// assert_eq!(ColorCorrection::new(0, 128, 255).apply(RGB8::new(200, 200, 200)), RGB8::new(0, 100, 200));
+///
+/// assert_eq!(ColorCorrection::from_name("Cool"), Some(ColorCorrection::COOL));
+/// assert_eq!(ColorCorrection::from_name("daylight"), None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColorCorrection {
+    /// Scale of the red channel (0-255, 255 leaves it unchanged).
+    pub r: u8,
+    /// Scale of the green channel (0-255, 255 leaves it unchanged).
+    pub g: u8,
+    /// Scale of the blue channel (0-255, 255 leaves it unchanged).
+    pub b: u8,
+}
+
+impl ColorCorrection {
+    /// No correction at all.
+    pub const NEUTRAL: Self = Self::new(255, 255, 255);
+
+    /// Warm white, like an incandescent bulb.
+    pub const TUNGSTEN: Self = Self::new(255, 214, 170);
+
+    /// Cool white, like an overcast sky.
+    pub const COOL: Self = Self::new(201, 226, 255);
+
+    /// Named presets, in the order they are listed.
+    pub const PRESETS: [(&'static str, Self); 3] = [
+        ("neutral", Self::NEUTRAL),
+        ("tungsten", Self::TUNGSTEN),
+        ("cool", Self::COOL),
+    ];
+
+    /// Creates a correction that scales the channels by `r`, `g`, and `b` out of 255.
+    #[must_use]
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Returns the preset called `name`, ignoring case, or `None` if there is no such preset.
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::PRESETS
+            .iter()
+            .find(|(preset, _)| preset.eq_ignore_ascii_case(name))
+            .map(|&(_, correction)| correction)
+    }
+
+    /// Returns the name of the preset this correction matches, if any.
+    #[must_use]
+    pub fn name(self) -> Option<&'static str> {
+        Self::PRESETS
+            .iter()
+            .find(|&&(_, correction)| correction == self)
+            .map(|&(name, _)| name)
+    }
+
+    /// Returns `color` with each channel scaled by the correction.
+    #[must_use]
+    pub fn apply(self, color: RGB8) -> RGB8 {
+        let scale = |channel: u8, factor: u8| {
+            #[allow(clippy::cast_possible_truncation)]
+            let channel = (u16::from(channel) * u16::from(factor) / 255) as u8;
+            channel
+        };
+        RGB8::new(
+            scale(color.r, self.r),
+            scale(color.g, self.g),
+            scale(color.b, self.b),
+        )
+    }
+}
+
+impl Default for ColorCorrection {
+    fn default() -> Self {
+        Self::NEUTRAL
+    }
+}
+
+/// One entry of a [`Playlist`], a preset shown for `duration_s`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlaylistEntry {
+    /// Preset to show.
+    pub preset: patterns::Preset,
+    /// Time in seconds to show it for.
+    pub duration_s: u16,
+}
+
+impl PlaylistEntry {
+    /// Creates a new entry showing `preset` for `duration_s`.
+    #[must_use]
+    pub const fn new(preset: patterns::Preset, duration_s: u16) -> Self {
+        Self { preset, duration_s }
+    }
+}
+
+/// Up to [`Playlist::MAX_ENTRIES`] light presets shown on both rings one after another, each for its own time, starting
+/// over after the last.
+///
+/// Entries name presets rather than holding whole modes, so that a playlist stays small enough for the state. While a
+/// playlist has entries it takes over both rings, crossfading from one entry to the next, and once it is cleared the
+/// rings go back to their own modes. Serialized as an array of only the entries in use.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::lights::patterns::Preset;
+/// use catears::lights::{Playlist, PlaylistEntry};
+///
+/// let playlist = Playlist::new()
+///     .with_entry(PlaylistEntry::new(Preset::Party, 30))
+///     .with_entry(PlaylistEntry::new(Preset::Fire, 30))
+///     .with_entry(PlaylistEntry::new(Preset::Stardust, 60));
+/// assert_eq!(playlist.total_ms(), 120_000);
+///
+/// // Each entry shows for its own time, and the playlist starts over after the last.
+/// assert_eq!(playlist.at(0), Some(Preset::Party));
+/// assert_eq!(playlist.at(29_999), Some(Preset::Party));
+/// assert_eq!(playlist.at(30_000), Some(Preset::Fire));
+/// assert_eq!(playlist.at(90_000), Some(Preset::Stardust));
+/// assert_eq!(playlist.at(120_000 * 3 + 45_000), Some(Preset::Fire));
+///
+/// // An empty playlist, or one with no time to show anything, leaves the rings alone.
+/// assert_eq!(Playlist::new().at(0), None);
+/// assert_eq!(Playlist::new().with_entry(PlaylistEntry::new(Preset::Fire, 0)).at(0), None);
+///
+/// // Past the maximum, entries are left out.
+/// let full = (0..10).fold(Playlist::new(), |playlist, _| playlist.with_entry(PlaylistEntry::new(Preset::Ocean, 5)));
+/// assert!(full.is_full());
+/// assert_eq!(full.entries().len(), Playlist::MAX_ENTRIES);
+///
+/// // Remote JSON names the presets, and gets back only the entries it gave.
+/// let json = r#"[{"preset":"Party","duration_s":30},{"preset":"Fire","duration_s":30}]"#;
+/// let (parsed, _) = serde_json_core::from_str::<Playlist>(json).unwrap();
+/// assert_eq!(parsed.entries(), &playlist.entries()[..2]);
+/// let mut out = [0u8; 256];
+/// let len = serde_json_core::to_slice(&parsed, &mut out).unwrap();
+/// assert_eq!(&out[..len], json.as_bytes());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Playlist {
+    /// Entries in the order they are shown.
+    entries: [PlaylistEntry; Playlist::MAX_ENTRIES],
+    /// Number of entries in use.
+    len: usize,
+}
+
+impl Playlist {
+    /// Most entries a playlist holds.
+    pub const MAX_ENTRIES: usize = 8;
+
+    /// Creates an empty playlist, which leaves the rings to their own modes.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            entries: [PlaylistEntry::new(patterns::Preset::Police, 0); Self::MAX_ENTRIES],
+            len: 0,
+        }
+    }
+
+    /// Adds `entry` after the others, unless there are already [`Self::MAX_ENTRIES`].
+    #[must_use]
+    pub const fn with_entry(mut self, entry: PlaylistEntry) -> Self {
+        if self.len < Self::MAX_ENTRIES {
+            self.entries[self.len] = entry;
+            self.len += 1;
+        }
+        self
+    }
+
+    /// Returns whether there is no room for another entry.
+    #[must_use]
+    pub const fn is_full(&self) -> bool {
+        self.len == Self::MAX_ENTRIES
+    }
+
+    /// Returns the entries in use, in the order they are shown.
+    #[must_use]
+    pub fn entries(&self) -> &[PlaylistEntry] {
+        &self.entries[..self.len]
+    }
+
+    /// Returns the time in milliseconds it takes to go through every entry once.
+    #[must_use]
+    pub fn total_ms(&self) -> u32 {
+        self.entries()
+            .iter()
+            .map(|entry| u32::from(entry.duration_s) * 1000)
+            .sum()
+    }
+
+    /// Returns the preset showing `elapsed_ms` after the playlist started, or `None` if there is nothing to show.
+    #[must_use]
+    pub fn at(&self, elapsed_ms: u32) -> Option<patterns::Preset> {
+        let total_ms = self.total_ms();
+        if total_ms == 0 {
+            return None;
+        }
+        let mut into_ms = elapsed_ms % total_ms;
+        self.entries().iter().find_map(|entry| {
+            let duration_ms = u32::from(entry.duration_s) * 1000;
+            if into_ms < duration_ms {
+                Some(entry.preset)
+            } else {
+                into_ms -= duration_ms;
+                None
+            }
+        })
+    }
+}
+
+impl Default for Playlist {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Serialize for Playlist {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.entries())
+    }
+}
+
+impl<'de> Deserialize<'de> for Playlist {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        /// Reads up to [`Playlist::MAX_ENTRIES`] entries.
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = Playlist;
+
+            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                formatter.write_str("at most 8 playlist entries")
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<Playlist, A::Error> {
+                let mut playlist = Playlist::new();
+                while let Some(entry) = seq.next_element()? {
+                    let Some(slot) = playlist.entries.get_mut(playlist.len) else {
+                        return Err(serde::de::Error::invalid_length(playlist.len + 1, &self));
+                    };
+                    *slot = entry;
+                    playlist.len += 1;
+                }
+                Ok(playlist)
+            }
+        }
+
+        deserializer.deserialize_seq(Visitor)
+    }
+}
+
+/// Predefined light patterns for common effects.
+pub mod patterns {
+    use super::{
+        ChasePattern, CometPattern, FirePattern, GradientPattern, LedPattern, Mode, PulsePattern,
+        PulseShape, RainbowPattern, SparklePattern, StrobePattern, TheaterChasePattern,
+    };
+    use serde::{Deserialize, Serialize};
+    use smart_leds::RGB8;
+
+    /// All presets, in the order they are cycled through.
+    ///
+    /// [`Preset`] names these for picking them by name, along with presets left out of the cycle.
+    pub const ALL: [fn() -> Mode; 12] = [
+        police,
+        breathing,
+        party,
+        alert,
+        success,
+        loading,
+        cat_eyes,
+        notification,
+        fire,
+        ocean,
+        stardust,
+        marquee,
+    ];
+
+    /// Returns the preset following `mode` in [`ALL`], or the first preset if `mode` is not a preset.
+    #[must_use]
+    pub fn next(mode: &Mode) -> Mode {
+        let index = ALL
+            .iter()
+            .position(|preset| preset() == *mode)
+            .map_or(0, |i| (i + 1) % ALL.len());
+        ALL[index]()
+    }
+
+    /// Police/emergency light pattern (red and blue).
+    #[must_use]
+    pub fn police() -> Mode {
+        Mode::Chase(
+            ChasePattern::new(RGB8::new(255, 0, 0), 6, 100).with_background(RGB8::new(0, 0, 255)),
+        )
+    }
+
+    /// Soft breathing white light.
+    #[must_use]
+    pub fn breathing() -> Mode {
+        Mode::Pulse(
+            PulsePattern::new(RGB8::new(255, 255, 255), 3000).with_brightness_range(20, 255),
+        )
+    }
+
+    /// Fast rainbow cycle.
+    #[must_use]
+    pub fn party() -> Mode {
+        Mode::Rainbow(RainbowPattern::new(50))
+    }
+
+    /// Alert pattern (flashing red).
+    #[must_use]
+    pub fn alert() -> Mode {
+        Mode::Pulse(PulsePattern::new(RGB8::new(255, 0, 0), 500).with_shape(PulseShape::Square(50)))
+    }
+
+    /// Success pattern (green pulse).
+    #[must_use]
+    pub fn success() -> Mode {
+        Mode::Pulse(PulsePattern::new(RGB8::new(0, 255, 0), 1000).with_brightness_range(50, 255))
+    }
+
+    /// Loading/thinking pattern (blue comet).
+    #[must_use]
+    pub fn loading() -> Mode {
+        Mode::Comet(CometPattern::new(RGB8::new(0, 100, 255), 100))
     }
 
     /// Cat eyes pattern (two amber dots).
@@ -252,15 +2035,186 @@ pub mod patterns {
         Mode::Pulse(PulsePattern::new(RGB8::new(0, 150, 255), 2000).with_brightness_range(30, 200))
     }
 
-    /// Fire effect (red-orange gradient).
+    /// Fire effect (flickering flames).
     #[must_use]
     pub fn fire() -> Mode {
-        Mode::Gradient(RGB8::new(255, 0, 0), RGB8::new(255, 150, 0))
+        Mode::Fire(FirePattern::new())
     }
 
     /// Ocean effect (blue-cyan gradient).
     #[must_use]
     pub fn ocean() -> Mode {
-        Mode::Gradient(RGB8::new(0, 0, 255), RGB8::new(0, 255, 255))
+        Mode::Gradient(GradientPattern::new(
+            RGB8::new(0, 0, 255),
+            RGB8::new(0, 255, 255),
+        ))
+    }
+
+    /// Magic dust (pale sparkles over a faint violet glow), best at low brightness.
+    #[must_use]
+    pub fn stardust() -> Mode {
+        Mode::Sparkle(
+            SparklePattern::new(RGB8::new(200, 200, 255))
+                .with_background(RGB8::new(6, 0, 12))
+                .with_chance(40)
+                .with_decay(600),
+        )
+    }
+
+    /// Theater marquee (warm white bulbs chasing in threes).
+    #[must_use]
+    pub fn marquee() -> Mode {
+        Mode::TheaterChase(
+            TheaterChasePattern::new(RGB8::new(255, 180, 80), 3, 150)
+                .with_background(RGB8::new(20, 8, 0)),
+        )
+    }
+
+    /// Camera flash (a pre-flash, then a bright white flash), after which the ring stays dark.
+    ///
+    /// Not part of [`ALL`], as it is over too soon to cycle through.
+    #[must_use]
+    pub fn camera_flash() -> Mode {
+        Mode::Strobe(StrobePattern::new(RGB8::new(255, 255, 255), 40, 160).with_count(2))
+    }
+
+    /// One of the presets, to pick it by name, such as in a [`super::Playlist`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use catears::lights::patterns::{self, Preset};
+    ///
+    /// assert_eq!(Preset::from_name("Stardust"), Some(Preset::Stardust));
+    /// assert_eq!(Preset::from_name("disco"), None);
+    /// assert_eq!(Preset::CatEyes.mode(), patterns::cat_eyes());
+    /// assert!(Preset::ALL.iter().all(|&preset| Preset::from_name(preset.name()) == Some(preset)));
+    ///
+    /// // Every preset in the cycle has a name.
+    /// assert!(patterns::ALL.iter().all(|mode| Preset::ALL.iter().any(|preset| preset.mode() == mode())));
+    /// ```
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+    pub enum Preset {
+        /// See [`police`].
+        #[default]
+        Police,
+        /// See [`breathing`].
+        Breathing,
+        /// See [`party`].
+        Party,
+        /// See [`alert`].
+        Alert,
+        /// See [`success`].
+        Success,
+        /// See [`loading`].
+        Loading,
+        /// See [`cat_eyes`].
+        CatEyes,
+        /// See [`notification`].
+        Notification,
+        /// See [`fire`].
+        Fire,
+        /// See [`ocean`].
+        Ocean,
+        /// See [`stardust`].
+        Stardust,
+        /// See [`marquee`].
+        Marquee,
+        /// See [`camera_flash`].
+        CameraFlash,
+    }
+
+    impl Preset {
+        /// Every preset.
+        pub const ALL: [Self; 13] = [
+            Self::Police,
+            Self::Breathing,
+            Self::Party,
+            Self::Alert,
+            Self::Success,
+            Self::Loading,
+            Self::CatEyes,
+            Self::Notification,
+            Self::Fire,
+            Self::Ocean,
+            Self::Stardust,
+            Self::Marquee,
+            Self::CameraFlash,
+        ];
+
+        /// Returns the name of the preset, all lowercase without spaces.
+        #[must_use]
+        pub const fn name(self) -> &'static str {
+            match self {
+                Self::Police => "police",
+                Self::Breathing => "breathing",
+                Self::Party => "party",
+                Self::Alert => "alert",
+                Self::Success => "success",
+                Self::Loading => "loading",
+                Self::CatEyes => "cateyes",
+                Self::Notification => "notification",
+                Self::Fire => "fire",
+                Self::Ocean => "ocean",
+                Self::Stardust => "stardust",
+                Self::Marquee => "marquee",
+                Self::CameraFlash => "cameraflash",
+            }
+        }
+
+        /// Looks up a preset by its (case-insensitive) name.
+        #[must_use]
+        pub fn from_name(name: &str) -> Option<Self> {
+            Self::ALL
+                .into_iter()
+                .find(|preset| preset.name().eq_ignore_ascii_case(name))
+        }
+
+        /// Returns the light mode of the preset.
+        #[must_use]
+        pub fn mode(self) -> Mode {
+            match self {
+                Self::Police => police(),
+                Self::Breathing => breathing(),
+                Self::Party => party(),
+                Self::Alert => alert(),
+                Self::Success => success(),
+                Self::Loading => loading(),
+                Self::CatEyes => cat_eyes(),
+                Self::Notification => notification(),
+                Self::Fire => fire(),
+                Self::Ocean => ocean(),
+                Self::Stardust => stardust(),
+                Self::Marquee => marquee(),
+                Self::CameraFlash => camera_flash(),
+            }
+        }
+    }
+}
+
+/// One-shot flashes shown on top of the current light mode.
+pub mod flashes {
+    use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel};
+    use serde::{Deserialize, Serialize};
+    use smart_leds::RGB8;
+
+    /// Channel used to request one-shot flashes from the LED control task.
+    pub type FlashChannel = Channel<CriticalSectionRawMutex, Flash, 4>;
+
+    /// A short burst of a solid color on both rings, after which the current light mode resumes.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct Flash {
+        /// Color of the flash, scaled by the global brightness like any other mode.
+        pub color: RGB8,
+        /// Duration of the flash in milliseconds.
+        pub duration_ms: u16,
+    }
+
+    impl Flash {
+        /// Creates a new flash.
+        #[must_use]
+        pub const fn new(color: RGB8, duration_ms: u16) -> Self {
+            Self { color, duration_ms }
+        }
     }
 }
@@ -0,0 +1,1313 @@
+//! Rendering of light modes into LED colors.
+//!
+//! The LED task calls [`render`] once per ring every frame with the time since the last one, blends it through a
+//! [`Crossfade`] while the ring is changing modes, and writes the result out.
+//! Nothing in here touches the hardware, so the same rendering also drives the host simulator.
+
+use smart_leds::hsv::{hsv2rgb, Hsv};
+use smart_leds::RGB8;
+
+use super::{
+    Animation, AnimationFrame, CometPattern, FirePattern, Mode, PulseShape, SparklePattern,
+    LED_COUNT,
+};
+use crate::audio::beat::Beat;
+use crate::audio::dsp::Levels;
+use crate::battery::{CRITICAL_PERCENT, LOW_PERCENT};
+use crate::easing::mix;
+
+/// Animation progress of a single ring, carried from one frame to the next.
+///
+/// Every animation is a function of the time it has been running, so frames can come at any rate. Starting over from the
+/// default state restarts every animation from its first frame. The random ones, like [`Mode::Fire`], also follow the
+/// state's seed, so that two rings seeded apart do not mirror each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PatternState {
+    /// Time in milliseconds the animation has been running, wrapping around after about 49 days.
+    elapsed_ms: u32,
+    /// Frame of a [`Mode::Animation`] showing.
+    frame: usize,
+    /// Value of `elapsed_ms` when the frame showing started.
+    frame_started_ms: u32,
+    /// Level of each LED of the modes that keep one, such as the heat of a [`Mode::Fire`] or the glow of a
+    /// [`Mode::Sparkle`] or [`Mode::Comet`], repeating around rings of more LEDs.
+    levels: [u8; LED_CELLS],
+    /// Hue of each LED of a [`Mode::Sparkle`] in random hues.
+    hues: [u8; LED_CELLS],
+    /// Number of 10 ms steps the modes that keep LED levels have taken.
+    steps: u32,
+    /// How far a [`Mode::Pulse`] is through its cycle, in 2^32ths of it, see [`advance_phase`].
+    phase: u32,
+    /// State of the xorshift generator behind the random animations, never zero.
+    random: u32,
+}
+
+/// Number of LEDs the modes that keep LED levels keep them for, one per LED of a ring.
+const LED_CELLS: usize = LED_COUNT;
+
+/// Fractions of an LED the smooth chase is positioned in.
+const SUBPIXELS: u32 = 256;
+
+/// Time in milliseconds the LED task aims to leave from one frame to the next.
+///
+/// Every animation follows the time that actually passed between frames, so a late frame only shows less smoothly,
+/// never slower. Nothing moves faster than a step per frame, though, as the ring could not show it anyway.
+pub const FRAME_MS: u32 = 10;
+
+/// Time in milliseconds the last LED of a critically low battery gauge is on, and then off.
+const BATTERY_BLINK_MS: u32 = 500;
+
+/// Time in milliseconds of one slow pulse of a battery gauge while the charge is unknown.
+const BATTERY_UNKNOWN_PERIOD_MS: u32 = 4000;
+
+/// Color at the peak of the pulse of a battery gauge while the charge is unknown.
+const BATTERY_UNKNOWN_COLOR: RGB8 = RGB8::new(80, 80, 80);
+
+/// Most 10 ms steps the modes that keep LED levels catch up on in one frame, so a long gap between frames does not
+/// stall the next one.
+const CATCH_UP_STEPS: u32 = 10;
+
+impl PatternState {
+    /// Seed of the random animations of [`PatternState::new`].
+    pub const DEFAULT_SEED: u32 = 0x2545_f491;
+
+    /// Creates a new state at the first frame of every animation.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self::with_seed(Self::DEFAULT_SEED)
+    }
+
+    /// Creates a new state at the first frame of every animation, with the random ones seeded by `seed`.
+    #[must_use]
+    pub const fn with_seed(seed: u32) -> Self {
+        Self {
+            elapsed_ms: 0,
+            frame: 0,
+            frame_started_ms: 0,
+            levels: [0; LED_CELLS],
+            hues: [0; LED_CELLS],
+            steps: 0,
+            phase: 0,
+            // Xorshift gets stuck at zero.
+            random: if seed == 0 { Self::DEFAULT_SEED } else { seed },
+        }
+    }
+
+    /// Goes back to the first frame of every animation, keeping the random ones on their own course.
+    pub const fn restart(&mut self) {
+        *self = Self {
+            random: self.random,
+            ..Self::new()
+        };
+    }
+
+    /// Returns the time in milliseconds the animation has been running.
+    #[must_use]
+    pub const fn elapsed_ms(&self) -> u32 {
+        self.elapsed_ms
+    }
+}
+
+impl Default for PatternState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Renders the frame of `mode` for a ring of `N` LEDs after advancing its animation in `state` by `elapsed_ms`.
+///
+/// Every color is scaled by `brightness_scale`, the VU meter follows `levels`, a beat pulse follows `beat`, the latest
+/// beat of the speaker on the same side, and a battery gauge follows `battery`, the charge in percent if it is known.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::beat::Beat;
+/// use catears::audio::dsp::Levels;
+/// use catears::lights::render::{render, PatternState};
+/// use catears::lights::{
+///     Animation, AnimationFrame, BeatPattern, ChasePattern, CometPattern, FirePattern, GradientPattern, LedPattern,
+///     Mode, PaletteCyclePattern, PaletteId, ProgressPattern, PulsePattern, PulseShape, RainbowPattern, Segment,
+///     SegmentsPattern, SparklePattern, StrobePattern, TheaterChasePattern, VuPattern, LED_COUNT,
+/// };
+/// use smart_leds::RGB8;
+///
+/// let levels = Levels::default();
+/// let rgb = |r, g, b| RGB8::new(r, g, b);
+///
+/// // A solid color at half brightness.
+/// let mut state = PatternState::new();
+/// let frame: [RGB8; LED_COUNT] = render(&Mode::Solid(rgb(200, 100, 50)), &mut state, 128, &levels, None, None, 10);
+/// assert_eq!(frame, [rgb(100, 50, 25); LED_COUNT]);
+///
+/// // Off is black at any brightness, and so is a mirrored ring until the caller fills it in.
+/// for mode in [Mode::Off, Mode::MirrorOther] {
+///     let frame: [RGB8; LED_COUNT] = render(&mode, &mut state, 255, &levels, None, None, 10);
+///     assert_eq!(frame, [rgb(0, 0, 0); LED_COUNT]);
+/// }
+///
+/// // A custom pattern shows its LEDs as they are, scaled by the brightness.
+/// let colors: [RGB8; LED_COUNT] = core::array::from_fn(|i| rgb(20 * (i % 12) as u8, 0, 200));
+/// let mode = Mode::Custom(LedPattern::from_colors(&colors));
+/// assert_eq!(render(&mode, &mut state, 255, &levels, None, None, 10), colors);
+/// let frame: [RGB8; LED_COUNT] = render(&mode, &mut state, 128, &levels, None, None, 10);
+/// assert_eq!(frame, colors.map(|color| rgb(color.r / 2, 0, 100)));
+///
+/// // A gradient from the first LED to the last, in even steps.
+/// let mode = Mode::Gradient(GradientPattern::new(rgb(0, 0, 0), rgb(220, 110, 0)));
+/// let frame: [RGB8; LED_COUNT] = render(&mode, &mut state, 255, &levels, None, None, 10);
+/// assert_eq!(frame[0], rgb(0, 0, 0));
+/// assert_eq!(frame[LED_COUNT - 1], rgb(220, 110, 0));
+/// assert!(frame.iter().enumerate().all(|(i, color)| {
+///     color.r.abs_diff((220 * i / (LED_COUNT - 1)) as u8) <= 1 && color.g == color.r / 2 && color.b == 0
+/// }));
+///
+/// // Mirrored, it ramps up one half of the ring and down the other, and with a full turn every 100 ms per LED, it
+/// // turns one LED along every 100 ms.
+/// let rotation = (LED_COUNT * 100) as u16;
+/// let mode = Mode::Gradient(GradientPattern::new(rgb(0, 0, 0), rgb(240, 120, 0)).with_rotation(rotation).mirrored());
+/// let mut state = PatternState::new();
+/// let frame: [RGB8; LED_COUNT] = render(&mode, &mut state, 255, &levels, None, None, 0);
+/// assert_eq!(frame[0], rgb(0, 0, 0));
+/// assert!((1..=LED_COUNT / 2).all(|i| frame[i - 1].r < frame[i].r));
+/// let close = |a: &[RGB8], b: &[RGB8]| a.iter().zip(b).all(|(a, b)| a.r.abs_diff(b.r) <= 1 && a.g.abs_diff(b.g) <= 1);
+/// assert!(close(&frame[1..], &frame[1..].iter().rev().copied().collect::<Vec<_>>()));
+/// let turned: [RGB8; LED_COUNT] = render(&mode, &mut state, 255, &levels, None, None, 100);
+/// assert!(close(&turned[1..], &frame[..LED_COUNT - 1]));
+///
+/// // A three LED chase moving one LED every 100 ms glides two and a half LEDs in after 250 ms, lighting the LEDs it
+/// // only half covers halfway.
+/// let red = rgb(255, 0, 0);
+/// let dim = rgb(0, 0, 10);
+/// let half = rgb(127, 0, 5);
+/// let mode = Mode::Chase(ChasePattern::new(red, 3, 100).with_background(dim));
+/// let mut state = PatternState::new();
+/// let frame: [RGB8; LED_COUNT] = render(&mode, &mut state, 255, &levels, None, None, 250);
+/// let mut expected = [dim; LED_COUNT];
+/// expected[2..6].copy_from_slice(&[half, red, red, half]);
+/// assert_eq!(frame, expected);
+/// // Once it reaches the last LED, it wraps around to the first.
+/// let frame: [RGB8; LED_COUNT] = render(&mode, &mut state, 255, &levels, None, None, (LED_COUNT * 100 - 400) as u32);
+/// let mut expected = [dim; LED_COUNT];
+/// expected[..2].copy_from_slice(&[red, half]);
+/// expected[LED_COUNT - 2..].copy_from_slice(&[half, red]);
+/// assert_eq!(frame, expected);
+///
+/// // Stepped, it is two whole LEDs in.
+/// let mode = Mode::Chase(ChasePattern::new(red, 3, 100).with_background(dim).stepped());
+/// let mut state = PatternState::new();
+/// let frame: [RGB8; LED_COUNT] = render(&mode, &mut state, 255, &levels, None, None, 250);
+/// let mut expected = [dim; LED_COUNT];
+/// expected[2..5].fill(red);
+/// assert_eq!(frame, expected);
+///
+/// // An animation shows each frame for its hold time, and a looping one starts over after the last.
+/// let frames = [AnimationFrame::new([red; LED_COUNT], 100), AnimationFrame::new([dim; LED_COUNT], 50)];
+/// let blink = Animation::new().with_frame(frames[0]).with_frame(frames[1]);
+/// for (mode, last) in [(Mode::Animation(blink.with_loop()), red), (Mode::Animation(blink), dim)] {
+///     let mut state = PatternState::new();
+///     let mut at = |elapsed_ms| render::<LED_COUNT>(&mode, &mut state, 255, &levels, None, None, elapsed_ms)[0];
+///     assert_eq!([at(0), at(90), at(10), at(40), at(10)], [red, red, dim, dim, last]);
+///     // Even a long gap between frames lands on the right one.
+///     assert_eq!(at(150 * 1000), last);
+/// }
+///
+/// // A fire flickers from frame to frame, and two rings seeded apart burn differently.
+/// let mode = Mode::Fire(FirePattern::new());
+/// let (mut left, mut right) = (PatternState::with_seed(1), PatternState::with_seed(2));
+/// let frames: Vec<([RGB8; LED_COUNT], [RGB8; LED_COUNT])> = (0..100)
+///     .map(|_| {
+///         let left = render(&mode, &mut left, 255, &levels, None, None, 10);
+///         (left, render(&mode, &mut right, 255, &levels, None, None, 10))
+///     })
+///     .collect();
+/// assert!(frames.windows(2).any(|pair| pair[0].0 != pair[1].0));
+/// assert!(frames.iter().any(|(left, right)| left != right));
+/// assert!(frames.iter().any(|(left, _)| left.iter().any(|color| color.r > 200)));
+/// let frame: [RGB8; LED_COUNT] = render(&mode, &mut left, 0, &levels, None, None, 10);
+/// assert_eq!(frame, [rgb(0, 0, 0); LED_COUNT]);
+///
+/// // Sparkles light up at random over the background and fade away.
+/// let white = rgb(255, 255, 255);
+/// let mode = Mode::Sparkle(SparklePattern::new(white).with_background(dim).with_chance(255));
+/// let mut state = PatternState::new();
+/// let frame: [RGB8; LED_COUNT] = render(&mode, &mut state, 255, &levels, None, None, 10);
+/// assert_eq!(frame.iter().filter(|&&color| color == white).count(), 1);
+/// assert_eq!(frame.iter().filter(|&&color| color == dim).count(), LED_COUNT - 1);
+/// let quiet = Mode::Sparkle(SparklePattern::new(white).with_background(dim).with_chance(0));
+/// for _ in 0..50 {
+///     render::<LED_COUNT>(&quiet, &mut state, 255, &levels, None, None, 10);
+/// }
+/// assert_eq!(render::<LED_COUNT>(&quiet, &mut state, 255, &levels, None, None, 10), [dim; LED_COUNT]);
+///
+/// // A comet moving one LED every 100 ms is on the fifth LED after 450 ms, its tail fading behind it.
+/// let comet = Mode::Comet(CometPattern::new(red, 100).with_background(dim));
+/// let mut state = PatternState::new();
+/// for _ in 0..44 {
+///     render::<LED_COUNT>(&comet, &mut state, 255, &levels, None, None, 10);
+/// }
+/// let frame: [RGB8; LED_COUNT] = render(&comet, &mut state, 255, &levels, None, None, 10);
+/// assert_eq!(frame[4], red);
+/// assert!((1..4).all(|i| frame[i - 1].r < frame[i].r));
+/// assert!(frame[5..].iter().all(|&color| color == dim));
+///
+/// // A theater chase lights every third LED, shifted one LED along every 100 ms.
+/// let mode = Mode::TheaterChase(TheaterChasePattern::new(red, 3, 100).with_background(dim));
+/// let mut state = PatternState::new();
+/// let frame: [RGB8; LED_COUNT] = render(&mode, &mut state, 255, &levels, None, None, 150);
+/// let expected: [RGB8; LED_COUNT] = core::array::from_fn(|i| if i % 3 == 1 { red } else { dim });
+/// assert_eq!(frame, expected);
+///
+/// // Animations follow the time that passed, however it was split into frames.
+/// let mode = Mode::Pulse(PulsePattern::new(red, 1000));
+/// let (mut steady, mut jittery) = (PatternState::new(), PatternState::new());
+/// let mut frames = Vec::new();
+/// for elapsed_ms in [7, 13, 10, 30, 10, 10, 30] {
+///     frames.push(render::<LED_COUNT>(&mode, &mut jittery, 255, &levels, None, None, elapsed_ms));
+/// }
+/// assert_eq!(frames.last(), Some(&render(&mode, &mut steady, 255, &levels, None, None, 110)));
+///
+/// // A square pulse blinks hard, even with its brightness range given upside down.
+/// let blink = PulsePattern::new(red, 1000).with_shape(PulseShape::Square(50));
+/// let mode = Mode::Pulse(blink.with_brightness_range(255, 0));
+/// let mut state = PatternState::new();
+/// assert_eq!(render::<LED_COUNT>(&mode, &mut state, 255, &levels, None, None, 400), [red; LED_COUNT]);
+/// assert_eq!(render::<LED_COUNT>(&mode, &mut state, 255, &levels, None, None, 200), [rgb(0, 0, 0); LED_COUNT]);
+///
+/// // A rainbow spread around the ring, at its first frame, goes once around the color wheel from red through green
+/// // and blue.
+/// let mode = Mode::Rainbow(RainbowPattern::new(1000));
+/// let mut state = PatternState::new();
+/// let frame: [RGB8; LED_COUNT] = render(&mode, &mut state, 255, &levels, None, None, 0);
+/// assert_eq!(frame[0], rgb(255, 0, 0));
+/// assert_eq!(frame[LED_COUNT / 3].g, 255);
+/// assert_eq!(frame[2 * LED_COUNT / 3].b, 255);
+///
+/// // With a full cycle every 100 ms per LED, a rainbow turns one LED along every 100 ms, and a reversed one the other
+/// // way.
+/// let start = frame;
+/// let cycle = (LED_COUNT * 100) as u16;
+/// let mode = Mode::Rainbow(RainbowPattern::new(cycle));
+/// let turned: [RGB8; LED_COUNT] = render(&mode, &mut state, 255, &levels, None, None, 100);
+/// assert_eq!(turned[..LED_COUNT - 1], start[1..]);
+/// let mode = Mode::Rainbow(RainbowPattern::new(cycle).reversed());
+/// let turned: [RGB8; LED_COUNT] = render(&mode, &mut PatternState::new(), 255, &levels, None, None, 100);
+/// assert_eq!(turned[1..], start[..LED_COUNT - 1]);
+///
+/// // A pastel rainbow mixes in white.
+/// let mode = Mode::Rainbow(RainbowPattern::new(1000).unified().with_saturation(128));
+/// let frame: [RGB8; LED_COUNT] = render(&mode, &mut PatternState::new(), 255, &levels, None, None, 0);
+/// assert!(frame[0].r > frame[0].g && frame[0].g == frame[0].b && frame[0].g > 100);
+///
+/// // A progress bar fills the ring clockwise from the top, lighting the LED at its edge as far as it reaches into it.
+/// let bar = |value| {
+///     let mode = Mode::Progress(ProgressPattern::new(value, red).with_background(dim));
+///     let frame: [RGB8; LED_COUNT] = render(&mode, &mut PatternState::new(), 255, &levels, None, None, 0);
+///     frame
+/// };
+/// assert_eq!(bar(0), [dim; LED_COUNT]);
+/// assert_eq!(bar(255), [red; LED_COUNT]);
+/// // Filled to halfway into the LED past the middle of the ring.
+/// let middle = LED_COUNT / 2;
+/// let frame = bar((255 * (2 * middle + 1) / (2 * LED_COUNT)) as u8);
+/// assert!(frame[..middle].iter().all(|&color| color == red));
+/// assert!(frame[middle].r > 100 && frame[middle].r < 155);
+/// assert!(frame[middle + 1..].iter().all(|&color| color == dim));
+///
+/// // Arcs wrap around past the last LED, and where they overlap the later one wins.
+/// let arcs = SegmentsPattern::new()
+///     .with_segment(Segment::new((LED_COUNT - 2) as u8, 4, red))
+///     .with_segment(Segment::new(1, 2, dim));
+/// let frame: [RGB8; LED_COUNT] = render(&Mode::Segments(arcs), &mut PatternState::new(), 255, &levels, None, None, 0);
+/// let mut expected = [rgb(0, 0, 0); LED_COUNT];
+/// expected[..3].copy_from_slice(&[red, dim, dim]);
+/// expected[LED_COUNT - 2..].fill(red);
+/// assert_eq!(frame, expected);
+///
+/// // A palette cycle spreads the palette around the ring and turns it, a sixteenth of the way every sixteenth of its
+/// // cycle.
+/// let palette = PaletteId::Party.palette();
+/// let mode = Mode::PaletteCycle(PaletteCyclePattern::new(PaletteId::Party, 1600));
+/// let mut state = PatternState::new();
+/// let frame: [RGB8; LED_COUNT] = render(&mode, &mut state, 255, &levels, None, None, 0);
+/// assert_eq!(frame[0], palette.colors[0]);
+/// assert!((0..LED_COUNT).all(|i| frame[i] == palette.color((i * 256 / LED_COUNT) as u8)));
+/// let frame: [RGB8; LED_COUNT] = render(&mode, &mut state, 255, &levels, None, None, 100);
+/// assert_eq!(frame[0], palette.colors[1]);
+///
+/// // A VU meter lights as much of the ring as the level reaches, fading from its low color to its high one.
+/// let vu = VuPattern::new(rgb(0, 255, 0), rgb(255, 0, 0));
+/// let loud = Levels { rms: 128, ..Levels::default() };
+/// let lit = vu.lit(&loud, LED_COUNT);
+/// let frame: [RGB8; LED_COUNT] = render(&Mode::Vu(vu), &mut PatternState::new(), 255, &loud, None, None, 0);
+/// assert_eq!(frame[0], rgb(0, 255, 0));
+/// assert!(frame[lit - 1].r > 0 && frame[lit - 1].g > 0);
+/// assert!(frame[lit..].iter().all(|&color| color == rgb(0, 0, 0)));
+///
+/// // A beat pulse flashes as a note starts and fades back into its background.
+/// let mode = Mode::BeatPulse(BeatPattern::new(red, 200));
+/// let beat = |since_ms| Some(Beat { count: 1, since_ms, bpm: None });
+/// let frame: [RGB8; LED_COUNT] = render(&mode, &mut PatternState::new(), 255, &levels, beat(0), None, 0);
+/// assert_eq!(frame, [red; LED_COUNT]);
+/// let frame: [RGB8; LED_COUNT] = render(&mode, &mut PatternState::new(), 255, &levels, beat(300), None, 0);
+/// assert_eq!(frame, [rgb(0, 0, 0); LED_COUNT]);
+///
+/// // A strobe switches hard between its color and black, and stays black after its last flash.
+/// let mode = Mode::Strobe(StrobePattern::new(red, 50, 100).with_count(1));
+/// let mut state = PatternState::new();
+/// assert_eq!(render::<LED_COUNT>(&mode, &mut state, 255, &levels, None, None, 40), [red; LED_COUNT]);
+/// assert_eq!(render::<LED_COUNT>(&mode, &mut state, 255, &levels, None, None, 10), [rgb(0, 0, 0); LED_COUNT]);
+/// assert_eq!(render::<LED_COUNT>(&mode, &mut state, 255, &levels, None, None, 150), [rgb(0, 0, 0); LED_COUNT]);
+///
+/// // A battery gauge lights as much of the ring as there is charge left, yellow from 50% down and red below 20%.
+/// let gauge = |percent, elapsed_ms| {
+///     let frame: [RGB8; LED_COUNT] =
+///         render(&Mode::BatteryGauge, &mut PatternState::new(), 255, &levels, None, percent, elapsed_ms);
+///     frame
+/// };
+/// let frame = gauge(Some(60), 0);
+/// let lit = (60 * LED_COUNT).div_ceil(100);
+/// assert!(frame[..lit].iter().all(|&color| color == rgb(0, 255, 0)));
+/// assert!(frame[lit..].iter().all(|&color| color == rgb(0, 0, 0)));
+/// let frame = gauge(Some(20), 0);
+/// let lit = (20 * LED_COUNT).div_ceil(100);
+/// assert!(frame[..lit].iter().all(|&color| color == rgb(255, 180, 0)));
+/// assert!(frame[lit..].iter().all(|&color| color == rgb(0, 0, 0)));
+/// assert_eq!(gauge(Some(100), 0), [rgb(0, 255, 0); LED_COUNT]);
+///
+/// // Critically low, the last LED lit blinks.
+/// let lit = (5 * LED_COUNT).div_ceil(100);
+/// assert_eq!(gauge(Some(5), 0)[lit - 1], rgb(255, 0, 0));
+/// assert_eq!(gauge(Some(5), 500)[lit - 1], rgb(0, 0, 0));
+///
+/// // Without a reading, the whole ring pulses slowly in grey.
+/// let frame = gauge(None, 1000);
+/// assert!(frame.iter().all(|&color| color == frame[0] && color.r == color.b && color.r > 60));
+/// assert_eq!(gauge(None, 3000), [rgb(0, 0, 0); LED_COUNT]);
+/// ```
+#[must_use]
+#[allow(clippy::too_many_lines)]
+pub fn render<const N: usize>(
+    mode: &Mode,
+    state: &mut PatternState,
+    brightness_scale: u8,
+    levels: &Levels,
+    beat: Option<Beat>,
+    battery: Option<u8>,
+    elapsed_ms: u32,
+) -> [RGB8; N] {
+    state.elapsed_ms = state.elapsed_ms.wrapping_add(elapsed_ms);
+    let t = state.elapsed_ms;
+    let mut colors = [RGB8::new(0, 0, 0); N];
+
+    match mode {
+        Mode::Off | Mode::MirrorOther => {
+            // All LEDs off - already initialized to black, a mirrored ring is filled in by the caller
+        }
+        Mode::Solid(color) => {
+            let scaled = scale_brightness(*color, brightness_scale);
+            colors.fill(scaled);
+        }
+        Mode::Gradient(pattern) if pattern.rotate_ms == 0 && !pattern.mirrored => {
+            for (i, color) in colors.iter_mut().enumerate() {
+                let interpolated = interpolate_color(pattern.start, pattern.end, position(i, N));
+                *color = scale_brightness(interpolated, brightness_scale);
+            }
+        }
+        Mode::Gradient(pattern) => {
+            // How far around the ring the gradient has turned, as a fraction of a turn.
+            let rotate_ms = u32::from(pattern.rotate_ms);
+            #[allow(clippy::cast_precision_loss)]
+            let turned = if rotate_ms == 0 {
+                0.0
+            } else {
+                (t % rotate_ms) as f32 / rotate_ms as f32
+            };
+            for (i, color) in colors.iter_mut().enumerate() {
+                // Where the LED is along the gradient, going around the ring rather than end to end so it can turn.
+                #[allow(clippy::cast_precision_loss)]
+                let mut along = i as f32 / N as f32 - turned;
+                if along < 0.0 {
+                    along += 1.0;
+                }
+                if pattern.mirrored {
+                    along = 1.0 - (2.0 * along - 1.0).abs();
+                }
+                let interpolated = interpolate_color(pattern.start, pattern.end, along);
+                *color = scale_brightness(interpolated, brightness_scale);
+            }
+        }
+        Mode::Chase(pattern) if !pattern.stepped => {
+            let speed_ms = step_ms(pattern.speed_ms);
+            // Rings are a handful of LEDs.
+            #[allow(clippy::cast_possible_truncation)]
+            let leds = N as u32;
+            let ring = leds * SUBPIXELS;
+            // Position of the head in 256ths of an LED, taken within one turn so the arithmetic cannot overflow.
+            let head = (t % (speed_ms * leds).max(1)) * SUBPIXELS / speed_ms;
+            let length = u32::from(pattern.length).min(leds) * SUBPIXELS;
+            // The segment runs ahead of the head clockwise and behind it counter-clockwise, as in the stepped chase.
+            let start = if pattern.clockwise {
+                head
+            } else {
+                (head + ring + SUBPIXELS).saturating_sub(length) % ring.max(1)
+            };
+            for (led, color) in (0..).zip(colors.iter_mut()) {
+                let covered = coverage(led * SUBPIXELS, start, length, ring);
+                #[allow(clippy::cast_precision_loss)]
+                let blended = interpolate_color(
+                    pattern.background,
+                    pattern.color,
+                    covered as f32 / SUBPIXELS as f32,
+                );
+                *color = scale_brightness(blended, brightness_scale);
+            }
+        }
+        Mode::Chase(pattern) => {
+            // The chase moves one LED per step.
+            let current_step = steps(t, pattern.speed_ms) as usize % N.max(1);
+
+            // Fill background
+            let bg = scale_brightness(pattern.background, brightness_scale);
+            colors.fill(bg);
+
+            // Draw chase pattern
+            for i in 0..usize::from(pattern.length).min(N) {
+                let pos = if pattern.clockwise {
+                    (current_step + i) % N
+                } else {
+                    (N + current_step - i) % N
+                };
+                colors[pos] = scale_brightness(pattern.color, brightness_scale);
+            }
+        }
+        Mode::TheaterChase(pattern) => {
+            // The lit LEDs shift one LED per step.
+            let spacing = usize::from(pattern.spacing.max(1));
+            let current_step = steps(t, pattern.speed_ms) as usize % spacing;
+
+            let bg = scale_brightness(pattern.background, brightness_scale);
+            let lit = scale_brightness(pattern.color, brightness_scale);
+            for (i, color) in colors.iter_mut().enumerate() {
+                // Counted from the first LED, so a spacing that does not divide the ring keeps its uneven gap in one
+                // place, at the wrap, instead of carrying it around.
+                *color = if i % spacing == current_step { lit } else { bg };
+            }
+        }
+        Mode::Progress(pattern) => {
+            // Rings are a handful of LEDs.
+            #[allow(clippy::cast_possible_truncation)]
+            let leds = N as u32;
+            // End of the bar in 256ths of an LED.
+            let filled = u32::from(pattern.value) * leds * SUBPIXELS / 255;
+            for (led, color) in (0..).zip(colors.iter_mut()) {
+                let covered = filled.saturating_sub(led * SUBPIXELS).min(SUBPIXELS);
+                #[allow(clippy::cast_precision_loss)]
+                let blended = interpolate_color(
+                    pattern.background,
+                    pattern.color,
+                    covered as f32 / SUBPIXELS as f32,
+                );
+                *color = scale_brightness(blended, brightness_scale);
+            }
+        }
+        Mode::Segments(pattern) => {
+            // Later arcs are drawn over earlier ones.
+            for segment in pattern.segments() {
+                let color = scale_brightness(segment.color, brightness_scale);
+                for offset in 0..usize::from(segment.length).min(N) {
+                    colors[(usize::from(segment.start) + offset) % N] = color;
+                }
+            }
+        }
+        Mode::PaletteCycle(pattern) => {
+            let palette = pattern.palette.palette();
+            let cycle_ms = step_ms(pattern.speed_ms);
+            #[allow(clippy::cast_possible_truncation)]
+            let offset = ((t % cycle_ms) * 256 / cycle_ms) as u8;
+            for (i, color) in colors.iter_mut().enumerate() {
+                let index = if pattern.spread {
+                    #[allow(clippy::cast_possible_truncation)]
+                    let along = (i * 256 / N) as u8;
+                    offset.wrapping_add(along)
+                } else {
+                    offset
+                };
+                *color = scale_brightness(palette.color(index), brightness_scale);
+            }
+        }
+        Mode::Strobe(pattern) => {
+            if pattern.is_on(t) {
+                colors.fill(scale_brightness(pattern.color, brightness_scale));
+            }
+        }
+        Mode::BatteryGauge => {
+            if let Some(percent) = battery {
+                let color = if percent > 50 {
+                    RGB8::new(0, 255, 0)
+                } else if percent >= LOW_PERCENT {
+                    RGB8::new(255, 180, 0)
+                } else {
+                    RGB8::new(255, 0, 0)
+                };
+                // Any charge at all lights at least one LED.
+                let lit = (usize::from(percent.min(100)) * N)
+                    .div_ceil(100)
+                    .clamp(1, N);
+                colors[..lit].fill(scale_brightness(color, brightness_scale));
+                if percent < CRITICAL_PERCENT && t % (2 * BATTERY_BLINK_MS) >= BATTERY_BLINK_MS {
+                    if let Some(last) = colors[..lit].last_mut() {
+                        *last = RGB8::new(0, 0, 0);
+                    }
+                }
+            } else {
+                #[allow(clippy::cast_precision_loss)]
+                let phase =
+                    (t % BATTERY_UNKNOWN_PERIOD_MS) as f32 / BATTERY_UNKNOWN_PERIOD_MS as f32;
+                let grey = interpolate_color(
+                    RGB8::new(0, 0, 0),
+                    BATTERY_UNKNOWN_COLOR,
+                    PulseShape::Sine.level(phase),
+                );
+                colors.fill(scale_brightness(grey, brightness_scale));
+            }
+        }
+        Mode::Pulse(pattern) => {
+            // Carried from frame to frame rather than taken from the elapsed time, so that a change of period carries
+            // on from wherever the pulse was.
+            state.phase = advance_phase(state.phase, elapsed_ms, pattern.period_ms);
+            #[allow(clippy::cast_precision_loss)]
+            let phase = state.phase as f32 / PHASE_CYCLE as f32;
+
+            let pulsed = scale_brightness(pattern.color, pattern.brightness(phase));
+            let final_color = scale_brightness(pulsed, brightness_scale);
+            colors.fill(final_color);
+        }
+        Mode::Rainbow(pattern) => {
+            // The hue goes once around the color wheel per cycle, straight from the elapsed time, so even the
+            // slowest rainbows keep turning.
+            let cycle_ms = step_ms(pattern.speed_ms);
+            #[allow(clippy::cast_possible_truncation)]
+            let turned = ((t % cycle_ms) * 256 / cycle_ms) as u8;
+            // The hues spread along the ring rise clockwise, so turning them forward moves the colors
+            // counter-clockwise.
+            let base_hue = if pattern.reversed {
+                turned.wrapping_neg()
+            } else {
+                turned
+            };
+
+            if pattern.spread {
+                // Rainbow spread across all LEDs
+                let spread = 256 / N.max(1);
+                for (i, color) in colors.iter_mut().enumerate() {
+                    #[allow(clippy::cast_possible_truncation)]
+                    let hue = base_hue.wrapping_add((i * spread) as u8);
+                    let hsv = Hsv {
+                        hue,
+                        sat: pattern.saturation,
+                        val: pattern.brightness,
+                    };
+                    *color = scale_brightness(hsv2rgb(hsv), brightness_scale);
+                }
+            } else {
+                // All LEDs same color
+                let hsv = Hsv {
+                    hue: base_hue,
+                    sat: pattern.saturation,
+                    val: pattern.brightness,
+                };
+                let color = scale_brightness(hsv2rgb(hsv), brightness_scale);
+                colors.fill(color);
+            }
+        }
+        Mode::Custom(pattern) => {
+            for (color, led) in colors.iter_mut().zip(pattern.leds) {
+                *color = scale_brightness(led, brightness_scale);
+            }
+        }
+        Mode::Animation(animation) => {
+            if let Some(frame) = advance(animation, state) {
+                for (color, led) in colors.iter_mut().zip(frame.leds) {
+                    *color = scale_brightness(led, brightness_scale);
+                }
+            }
+        }
+        Mode::Vu(pattern) => {
+            let lit = pattern.lit(levels, N);
+            for (i, color) in colors.iter_mut().take(lit).enumerate() {
+                let interpolated = interpolate_color(pattern.low, pattern.high, position(i, N));
+                *color = scale_brightness(interpolated, brightness_scale);
+            }
+        }
+        Mode::BeatPulse(pattern) => {
+            let flashed =
+                interpolate_color(pattern.background, pattern.color, pattern.flash(beat, t));
+            colors.fill(scale_brightness(flashed, brightness_scale));
+        }
+        Mode::Sparkle(pattern) => {
+            sparkle(*pattern, state);
+            for (i, color) in colors.iter_mut().enumerate() {
+                let cell = i % LED_CELLS;
+                let glow = pattern.color.unwrap_or_else(|| {
+                    hsv2rgb(Hsv {
+                        hue: state.hues[cell],
+                        sat: 255,
+                        val: 255,
+                    })
+                });
+                let glowing = interpolate_color(
+                    pattern.background,
+                    glow,
+                    f32::from(state.levels[cell]) / 255.0,
+                );
+                *color = scale_brightness(glowing, brightness_scale);
+            }
+        }
+        Mode::Comet(pattern) => {
+            comet(*pattern, state, N);
+            for (i, color) in colors.iter_mut().enumerate() {
+                let glowing = interpolate_color(
+                    pattern.background,
+                    pattern.color,
+                    f32::from(state.levels[i % LED_CELLS]) / 255.0,
+                );
+                *color = scale_brightness(glowing, brightness_scale);
+            }
+        }
+        Mode::Fire(pattern) => {
+            burn(*pattern, state);
+            for (i, color) in colors.iter_mut().enumerate() {
+                *color =
+                    scale_brightness(heat_color(state.levels[i % LED_CELLS]), brightness_scale);
+            }
+        }
+    }
+
+    colors
+}
+
+/// Crossfade of a ring from the light mode it showed to a new one.
+///
+/// The mode faded away from keeps animating underneath the fade, in a copy of its own [`PatternState`]. A fade started
+/// while another is under way starts over from the colors the ring last showed instead, held still, so the ring never
+/// jumps back to a mode it had mostly faded away from.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::dsp::Levels;
+/// use catears::lights::render::{render, Crossfade, PatternState};
+/// use catears::lights::{Mode, LED_COUNT};
+/// use smart_leds::RGB8;
+///
+/// let levels = Levels::default();
+/// let red = RGB8::new(200, 0, 0);
+/// let blue = RGB8::new(0, 0, 200);
+/// let mut fade = Crossfade::<LED_COUNT>::new();
+/// let mut state = PatternState::new();
+/// let frame = |fade: &mut Crossfade<LED_COUNT>, state: &mut PatternState, mode: &Mode| {
+///     let colors = render(mode, state, 255, &levels, None, None, 50);
+///     fade.blend(colors, 200, 50, |from, from_state| render(from, from_state, 255, &levels, None, None, 50))
+/// };
+///
+/// // Switching from red to blue blends linearly between the two, a quarter of the way every 50 ms of 200.
+/// fade.start(Mode::Solid(red), state, [red; LED_COUNT]);
+/// assert_eq!(frame(&mut fade, &mut state, &Mode::Solid(blue)), [RGB8::new(150, 0, 50); LED_COUNT]);
+/// assert!(fade.is_fading());
+///
+/// // Switching again halfway starts over from the colors last shown.
+/// let shown = frame(&mut fade, &mut state, &Mode::Solid(blue));
+/// assert_eq!(shown, [RGB8::new(100, 0, 100); LED_COUNT]);
+/// fade.start(Mode::Solid(blue), state, shown);
+/// for _ in 0..3 {
+///     frame(&mut fade, &mut state, &Mode::Off);
+/// }
+/// assert_eq!(frame(&mut fade, &mut state, &Mode::Off), [RGB8::new(0, 0, 0); LED_COUNT]);
+/// assert!(!fade.is_fading());
+///
+/// // A ring that mirrored the other has no colors of its own, so it fades away from the ones it showed.
+/// fade.start(Mode::MirrorOther, state, [red; LED_COUNT]);
+/// assert_eq!(frame(&mut fade, &mut state, &Mode::Off), [RGB8::new(150, 0, 0); LED_COUNT]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Crossfade<const N: usize> {
+    /// What the ring is fading away from, if it is fading.
+    from: Option<FadeFrom<N>>,
+    /// Time in milliseconds the fade has been running.
+    elapsed_ms: u32,
+}
+
+/// What a [`Crossfade`] fades away from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(clippy::large_enum_variant)]
+enum FadeFrom<const N: usize> {
+    /// A light mode still animating in its own state.
+    Mode(Mode, PatternState),
+    /// The colors a ring showed when a fade was interrupted.
+    Colors([RGB8; N]),
+}
+
+impl<const N: usize> Crossfade<N> {
+    /// Creates a new crossfade with nothing fading.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            from: None,
+            elapsed_ms: 0,
+        }
+    }
+
+    /// Starts fading away from `mode` animating in `state`, or from `shown`, the colors the ring last showed, if a
+    /// fade is already under way or the ring mirrored the other one, which it cannot render on its own.
+    pub fn start(&mut self, mode: Mode, state: PatternState, shown: [RGB8; N]) {
+        self.from = Some(match (self.from, mode) {
+            (Some(_), _) | (None, Mode::MirrorOther) => FadeFrom::Colors(shown),
+            (None, mode) => FadeFrom::Mode(mode, state),
+        });
+        self.elapsed_ms = 0;
+    }
+
+    /// Returns whether a fade is under way.
+    #[must_use]
+    pub const fn is_fading(&self) -> bool {
+        self.from.is_some()
+    }
+
+    /// Blends the frame `to` of the new mode over what the ring is fading away from, after advancing the fade by
+    /// `elapsed_ms` of `duration_ms`, and returns the colors to show.
+    ///
+    /// A mode faded away from is rendered with `render_from`, which should scale it to the same brightness as `to`.
+    /// Once the fade is over, `to` is returned as it is.
+    pub fn blend(
+        &mut self,
+        to: [RGB8; N],
+        duration_ms: u16,
+        elapsed_ms: u32,
+        render_from: impl FnOnce(&Mode, &mut PatternState) -> [RGB8; N],
+    ) -> [RGB8; N] {
+        self.elapsed_ms = self.elapsed_ms.saturating_add(elapsed_ms);
+        if self.elapsed_ms >= u32::from(duration_ms) {
+            self.from = None;
+        }
+        let from = match &mut self.from {
+            None => return to,
+            Some(FadeFrom::Mode(mode, state)) => render_from(mode, state),
+            Some(FadeFrom::Colors(colors)) => *colors,
+        };
+        #[allow(clippy::cast_precision_loss)]
+        let t = self.elapsed_ms as f32 / f32::from(duration_ms);
+        let mut colors = to;
+        for (color, from) in colors.iter_mut().zip(from) {
+            *color = interpolate_color(from, *color, t);
+        }
+        colors
+    }
+}
+
+impl<const N: usize> Default for Crossfade<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Ramp of the brightness up from nothing, so that the rings fade in rather than coming on at full brightness.
+///
+/// The ramp follows a clock in milliseconds that may wrap, rather than the frames, so it keeps going while the rings
+/// are not being written.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::lights::render::BrightnessRamp;
+///
+/// let mut ramp = BrightnessRamp::new(2000);
+/// assert_eq!(ramp.level(0), u8::MAX);
+///
+/// ramp.restart(u32::MAX - 499);
+/// assert_eq!(ramp.level(u32::MAX - 499), 0);
+/// assert_eq!(ramp.level(500), 127);
+/// assert!(ramp.is_ramping());
+/// assert_eq!(ramp.level(1500), u8::MAX);
+/// assert!(!ramp.is_ramping());
+///
+/// // Without a duration there is nothing to ramp.
+/// let mut ramp = BrightnessRamp::new(0);
+/// ramp.restart(0);
+/// assert_eq!(ramp.level(0), u8::MAX);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BrightnessRamp {
+    /// Time in milliseconds the ramp takes.
+    duration_ms: u16,
+    /// Time in milliseconds the ramp started at, if it is under way.
+    started_ms: Option<u32>,
+}
+
+impl BrightnessRamp {
+    /// Default time the ramp takes, slow enough to be easy on the eyes in the dark and on the supply.
+    pub const DURATION_MS: u16 = 2000;
+
+    /// Creates a ramp that takes `duration_ms`, zero for none, and has not started.
+    #[must_use]
+    pub const fn new(duration_ms: u16) -> Self {
+        Self {
+            duration_ms,
+            started_ms: None,
+        }
+    }
+
+    /// Starts the ramp over from nothing at `now_ms`, such as on boot or on waking up.
+    pub fn restart(&mut self, now_ms: u32) {
+        self.started_ms = Some(now_ms);
+    }
+
+    /// Returns whether the ramp is under way.
+    #[must_use]
+    pub const fn is_ramping(&self) -> bool {
+        self.started_ms.is_some()
+    }
+
+    /// Returns the scale of the brightness at `now_ms`, from 0 as the ramp starts up to 255 once it is over.
+    pub fn level(&mut self, now_ms: u32) -> u8 {
+        let Some(started_ms) = self.started_ms else {
+            return u8::MAX;
+        };
+        let elapsed_ms = now_ms.wrapping_sub(started_ms);
+        if elapsed_ms >= u32::from(self.duration_ms) {
+            self.started_ms = None;
+            return u8::MAX;
+        }
+        u8::try_from(elapsed_ms * 255 / u32::from(self.duration_ms)).unwrap_or(u8::MAX)
+    }
+}
+
+impl Default for BrightnessRamp {
+    fn default() -> Self {
+        Self::new(Self::DURATION_MS)
+    }
+}
+
+/// Moves `state` on to the frame of `animation` that shows at its elapsed time, and returns that frame, or `None` if
+/// the animation has no frames.
+fn advance<'a>(animation: &'a Animation, state: &mut PatternState) -> Option<&'a AnimationFrame> {
+    // Frames show for whole 10 ms frames, which also keeps a loop of zero-length frames from spinning forever.
+    let hold = |frame: &AnimationFrame| (u32::from(frame.hold_ms).div_ceil(10)).max(1) * 10;
+    let frames = animation.frames();
+    if state.frame >= frames.len() {
+        state.frame = 0;
+        state.frame_started_ms = state.elapsed_ms;
+    }
+
+    // Whole loops that went by at once, such as after a long gap between frames, are skipped in one go.
+    let cycle_ms: u32 = frames.iter().map(hold).sum();
+    let behind = state.elapsed_ms.wrapping_sub(state.frame_started_ms);
+    if animation.looping && cycle_ms > 0 && behind >= cycle_ms {
+        state.frame_started_ms = state
+            .frame_started_ms
+            .wrapping_add(behind - behind % cycle_ms);
+    }
+
+    loop {
+        let frame = frames.get(state.frame)?;
+        let hold_ms = hold(frame);
+        if state.elapsed_ms.wrapping_sub(state.frame_started_ms) < hold_ms {
+            return Some(frame);
+        }
+        if state.frame + 1 < frames.len() {
+            state.frame += 1;
+        } else if animation.looping {
+            state.frame = 0;
+        } else {
+            // The last frame holds until the mode changes.
+            return Some(frame);
+        }
+        state.frame_started_ms = state.frame_started_ms.wrapping_add(hold_ms);
+    }
+}
+
+/// Returns the time in milliseconds of a step of a pattern moving every `speed_ms`, no faster than one step a frame.
+const fn step_ms(speed_ms: u16) -> u32 {
+    let speed_ms = speed_ms as u32;
+    if speed_ms > FRAME_MS {
+        speed_ms
+    } else {
+        FRAME_MS
+    }
+}
+
+/// Returns how many steps of a pattern moving every `speed_ms` have passed after `elapsed_ms`.
+///
+/// Speeds faster than the frame rate saturate at one step every [`FRAME_MS`], so the ring never skips LEDs.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::lights::render::steps;
+///
+/// // Every speed counts for itself, however long the frames take.
+/// assert_eq!(steps(209, 105), 1);
+/// assert_eq!(steps(210, 105), 2);
+/// assert_eq!(steps(210, 100), 2);
+/// assert_eq!(steps(299, 100), 2);
+///
+/// // Speeds faster than a frame step once a frame, and a zero speed does not divide by zero.
+/// assert_eq!(steps(50, 3), 5);
+/// assert_eq!(steps(50, 0), 5);
+///
+/// // Slow speeds hold every step for as long as they ask, all the way up to a step a minute and more.
+/// for speed_ms in [10, 100, 2550, 5000, u16::MAX] {
+///     let speed = u32::from(speed_ms);
+///     assert_eq!(steps(speed - 1, speed_ms), 0);
+///     assert_eq!(steps(speed, speed_ms), 1);
+///     assert_eq!(steps(12 * speed - 1, speed_ms), 11);
+///     assert_eq!(steps(12 * speed, speed_ms), 12);
+/// }
+/// assert_eq!(steps(u32::MAX, u16::MAX), 65537);
+/// ```
+#[must_use]
+pub const fn steps(elapsed_ms: u32, speed_ms: u16) -> u32 {
+    elapsed_ms / step_ms(speed_ms)
+}
+
+/// One whole cycle of a phase, see [`advance_phase`].
+const PHASE_CYCLE: u64 = 1 << 32;
+
+/// Returns `phase`, a fraction of a cycle counted in 2^32ths of it, moved on by `elapsed_ms` of a cycle `period_ms`
+/// long.
+///
+/// The phase wraps around at the end of every cycle, so it never jumps, and a change of period only changes how fast
+/// it moves on from where it is.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::lights::render::advance_phase;
+///
+/// const HALF: u32 = 1 << 31;
+/// const QUARTER: u32 = 1 << 30;
+///
+/// assert_eq!(advance_phase(0, 500, 1000), HALF);
+/// assert_eq!(advance_phase(0, 250, 1000), QUARTER);
+///
+/// // Past the end of the cycle, the phase starts the next one, however long it has been running.
+/// assert_eq!(advance_phase(3 * QUARTER, 500, 1000), QUARTER);
+/// assert_eq!(advance_phase(u32::MAX, 250, 1000), QUARTER - 1);
+///
+/// // Halfway through a slow cycle, a faster one carries on from halfway.
+/// let phase = advance_phase(0, 2000, 4000);
+/// assert_eq!(phase, HALF);
+/// assert_eq!(advance_phase(phase, 250, 1000), 3 * QUARTER);
+///
+/// // A zero period moves on as fast as a one millisecond one.
+/// assert_eq!(advance_phase(0, 1, 0), 0);
+/// assert_eq!(advance_phase(QUARTER, 0, 0), QUARTER);
+/// ```
+#[must_use]
+pub const fn advance_phase(phase: u32, elapsed_ms: u32, period_ms: u16) -> u32 {
+    let period_ms = if period_ms == 0 { 1 } else { period_ms as u64 };
+    #[allow(clippy::cast_possible_truncation)]
+    let advanced = ((elapsed_ms as u64 % period_ms) * PHASE_CYCLE / period_ms) as u32;
+    phase.wrapping_add(advanced)
+}
+
+/// Returns how many whole 10 ms steps the LED levels in `state` are behind its elapsed time, however the frames fall,
+/// and counts them as taken.
+fn steps_due(state: &mut PatternState) -> u32 {
+    let taken = state.elapsed_ms / FRAME_MS;
+    let due = taken.wrapping_sub(state.steps).min(CATCH_UP_STEPS);
+    state.steps = taken;
+    due
+}
+
+/// Advances the xorshift generator in `random` and returns its next value.
+fn next_random(random: &mut u32) -> u32 {
+    *random ^= *random << 13;
+    *random ^= *random >> 17;
+    *random ^= *random << 5;
+    *random
+}
+
+/// Brings the heat of a fire in `state` up to its elapsed time.
+fn burn(pattern: FirePattern, state: &mut PatternState) {
+    for _ in 0..steps_due(state) {
+        burn_step(pattern, &mut state.levels, &mut state.random);
+    }
+}
+
+/// Advances the heat of a fire by one 10 ms step: every LED cools, heat drifts up from the first LED, and maybe a new
+/// spark flares up near it.
+fn burn_step(pattern: FirePattern, heat: &mut [u8; LED_CELLS], random: &mut u32) {
+    let mut next = || next_random(random);
+
+    #[allow(clippy::cast_possible_truncation)]
+    let most_cooling = (u32::from(pattern.cooling) * 10 / LED_CELLS as u32 + 2).min(255) as u8;
+    for cell in heat.iter_mut() {
+        #[allow(clippy::cast_possible_truncation)]
+        let cooling = (next() % (u32::from(most_cooling) + 1)) as u8;
+        *cell = cell.saturating_sub(cooling);
+    }
+
+    for i in (2..LED_CELLS).rev() {
+        // A weighted average of bytes is a byte.
+        #[allow(clippy::cast_possible_truncation)]
+        {
+            heat[i] = ((u16::from(heat[i - 1]) + 2 * u16::from(heat[i - 2])) / 3) as u8;
+        }
+    }
+
+    if next() % 256 < u32::from(pattern.sparking) {
+        let cell = next() as usize % 3;
+        #[allow(clippy::cast_possible_truncation)]
+        let spark = 160 + (next() % 96) as u8;
+        heat[cell] = heat[cell].saturating_add(spark);
+    }
+}
+
+/// Brings the sparkles in `state` up to its elapsed time: every 10 ms each one fades a step, and maybe a new one lights
+/// up on a random LED.
+fn sparkle(pattern: SparklePattern, state: &mut PatternState) {
+    // A sparkle fades from full to nothing over its decay time.
+    let fade = u8::try_from(2550 / u32::from(pattern.decay_ms).max(10)).unwrap_or(u8::MAX);
+    for _ in 0..steps_due(state) {
+        for level in &mut state.levels {
+            *level = level.saturating_sub(fade.max(1));
+        }
+        if next_random(&mut state.random) % 256 < u32::from(pattern.chance) {
+            let cell = next_random(&mut state.random) as usize % LED_CELLS;
+            state.levels[cell] = u8::MAX;
+            state.hues[cell] = next_random(&mut state.random).to_le_bytes()[3];
+        }
+    }
+}
+
+/// Brings the comet in `state` up to its elapsed time on a ring of `len` LEDs: every 10 ms each LED fades a little, so
+/// that it has faded by the tail factor each time the head moves on, and the LED the head is on lights up in full.
+fn comet(pattern: CometPattern, state: &mut PatternState, len: usize) {
+    // The head moves one LED per step, fading the tail by the tail factor over the 10 ms steps in between.
+    #[allow(clippy::cast_precision_loss)]
+    let fade = libm::powf(
+        f32::from(pattern.tail) / 256.0,
+        FRAME_MS as f32 / step_ms(pattern.speed_ms) as f32,
+    );
+    let len = len.clamp(1, LED_CELLS);
+    let due = steps_due(state);
+    for frame in state.steps.wrapping_sub(due)..state.steps {
+        for level in &mut state.levels {
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let faded = (f32::from(*level) * fade) as u8;
+            *level = faded;
+        }
+        let position = steps(frame.wrapping_mul(FRAME_MS), pattern.speed_ms) as usize % len;
+        let head = if pattern.clockwise {
+            position
+        } else {
+            (len - position) % len
+        };
+        state.levels[head] = u8::MAX;
+    }
+}
+
+/// Returns how much of the LED starting at `led` a segment of `length` starting at `start` covers, in [`SUBPIXELS`],
+/// on a ring `ring` around, all of them in [`SUBPIXELS`] too.
+///
+/// A segment that runs past the end of the ring wraps around to its start.
+fn coverage(led: u32, start: u32, length: u32, ring: u32) -> u32 {
+    // Distance from the start of the segment to the LED, going around the ring.
+    let from_start = (led + ring - start) % ring.max(1);
+    let ahead = length.saturating_sub(from_start).min(SUBPIXELS);
+    let wrapped = (from_start + SUBPIXELS).saturating_sub(ring).min(length);
+    (ahead + wrapped).min(SUBPIXELS)
+}
+
+/// Returns the color of fire at `heat`, from black when cold through red and orange to yellow at its hottest.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::lights::render::heat_color;
+/// use smart_leds::RGB8;
+///
+/// assert_eq!(heat_color(0), RGB8::new(0, 0, 0));
+/// assert_eq!(heat_color(85), RGB8::new(255, 0, 0));
+/// assert_eq!(heat_color(170), RGB8::new(255, 128, 0));
+/// assert_eq!(heat_color(255), RGB8::new(255, 255, 0));
+/// ```
+#[must_use]
+pub fn heat_color(heat: u8) -> RGB8 {
+    const PALETTE: [RGB8; 4] = [
+        RGB8::new(0, 0, 0),
+        RGB8::new(255, 0, 0),
+        RGB8::new(255, 128, 0),
+        RGB8::new(255, 255, 0),
+    ];
+    let scaled = u16::from(heat) * 3;
+    let segment = usize::from(scaled / 255).min(PALETTE.len() - 2);
+    #[allow(clippy::cast_possible_truncation)]
+    let fraction = (scaled - segment as u16 * 255) as u8;
+    interpolate_color(
+        PALETTE[segment],
+        PALETTE[segment + 1],
+        f32::from(fraction) / 255.0,
+    )
+}
+
+/// Returns how far along a ring of `count` LEDs the LED at `index` is, from 0.0 at the first to 1.0 at the last.
+#[allow(clippy::cast_precision_loss)]
+fn position(index: usize, count: usize) -> f32 {
+    index as f32 / count.saturating_sub(1).max(1) as f32
+}
+
+/// Rearranges the rendered `colors` of a ring for the way it is mounted, where `offset` is the index of the LED at the
+/// top and a `reversed` ring counts its LEDs counter-clockwise.
+///
+/// Every pattern is rendered with its first LED at the top going clockwise, so this is the last step before the
+/// colors are written out.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::lights::render::orient;
+/// use smart_leds::RGB8;
+///
+/// let colors: [RGB8; 4] = core::array::from_fn(|i| RGB8::new(i as u8, 0, 0));
+/// let reds = |colors: [RGB8; 4]| colors.map(|color| color.r);
+/// assert_eq!(reds(orient(colors, 0, false)), [0, 1, 2, 3]);
+/// assert_eq!(reds(orient(colors, 1, false)), [3, 0, 1, 2]);
+/// assert_eq!(reds(orient(colors, 0, true)), [0, 3, 2, 1]);
+/// assert_eq!(reds(orient(colors, 1, true)), [1, 0, 3, 2]);
+/// ```
+#[must_use]
+pub fn orient<const N: usize>(colors: [RGB8; N], offset: u8, reversed: bool) -> [RGB8; N] {
+    let offset = usize::from(offset);
+    core::array::from_fn(|led| {
+        // Position of the LED counted from the top, in the direction the ring is wired.
+        let from_top = (led + N - offset % N.max(1)) % N;
+        colors[if reversed {
+            (N - from_top) % N
+        } else {
+            from_top
+        }]
+    })
+}
+
+/// Flips the rendered `colors` of a ring about its top LED, for a ring showing [`Mode::MirrorOther`].
+///
+/// Both rings count clockwise as seen from the front, so a pattern turning clockwise on one ear turns
+/// counter-clockwise on the other, as if mirrored across the head.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::lights::render::mirror;
+/// use smart_leds::RGB8;
+///
+/// let colors: [RGB8; 4] = core::array::from_fn(|i| RGB8::new(i as u8, 0, 0));
+/// assert_eq!(mirror(colors).map(|color| color.r), [0, 3, 2, 1]);
+/// assert_eq!(mirror(mirror(colors)), colors);
+/// ```
+#[must_use]
+pub fn mirror<const N: usize>(colors: [RGB8; N]) -> [RGB8; N] {
+    orient(colors, 0, true)
+}
+
+/// Scales the brightness of `color` by `scale`, where 255 leaves it unchanged.
+#[must_use]
+pub fn scale_brightness(color: RGB8, scale: u8) -> RGB8 {
+    #[allow(clippy::cast_possible_truncation)]
+    let r = ((u16::from(color.r) * u16::from(scale)) / 255) as u8;
+    #[allow(clippy::cast_possible_truncation)]
+    let g = ((u16::from(color.g) * u16::from(scale)) / 255) as u8;
+    #[allow(clippy::cast_possible_truncation)]
+    let b = ((u16::from(color.b) * u16::from(scale)) / 255) as u8;
+
+    RGB8::new(r, g, b)
+}
+
+/// Temporal dithering of the brightness of a ring, for smooth fades and colors that do not drop out at low brightness.
+///
+/// Scaling the brightness down in 8 bits rounds each channel down to a whole step, so at low brightness fades go up in
+/// visible steps and dim channels round away to nothing. A dither instead keeps the part of a step each channel of
+/// each LED rounded away, and carries it into the next frame, so that the channel alternates between the two steps
+/// around it and averages out to the level asked for. At [`FRAME_MS`] per frame the alternating is too quick to see,
+/// though a camera may still catch it.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::lights::render::{scale_brightness, Dither};
+/// use smart_leds::RGB8;
+///
+/// // At a brightness of 10, a red of 200 should show as 7.84, and a dim blue of 20 as 0.78.
+/// let color = RGB8::new(200, 0, 20);
+/// assert_eq!(scale_brightness(color, 10), RGB8::new(7, 0, 0));
+///
+/// let mut dither = Dither::<1>::new();
+/// let frames: Vec<RGB8> = (0..255).map(|_| dither.scale([color], 10)[0]).collect();
+///
+/// // Each frame shows one of the two steps around the level, and the blue no longer drops out.
+/// assert!(frames.iter().all(|frame| frame.r == 7 || frame.r == 8));
+/// assert!(frames.iter().all(|frame| frame.b <= 1));
+///
+/// // In the long run, the average matches the level asked for, well within a step.
+/// let average = |channel: fn(&RGB8) -> u8| frames.iter().map(|frame| f32::from(channel(frame))).sum::<f32>() / 255.0;
+/// assert!((average(|frame| frame.r) - 200.0 * 10.0 / 255.0).abs() < 1.0 / 255.0);
+/// assert!((average(|frame| frame.b) - 20.0 * 10.0 / 255.0).abs() < 1.0 / 255.0);
+///
+/// // Full brightness and black come through as they are.
+/// assert_eq!(dither.scale([RGB8::new(255, 128, 0)], 255), [RGB8::new(255, 128, 0)]);
+/// assert_eq!(dither.scale([RGB8::new(255, 128, 0)], 0), [RGB8::default()]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dither<const N: usize> {
+    /// Part of a step, out of 255, that each channel of each LED rounded away so far.
+    errors: [[u8; 3]; N],
+}
+
+impl<const N: usize> Dither<N> {
+    /// Creates a new dither with nothing rounded away yet.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            errors: [[0; 3]; N],
+        }
+    }
+
+    /// Scales the brightness of `colors` by `scale`, where 255 leaves them unchanged, carrying what is rounded away
+    /// into the next frame.
+    pub fn scale(&mut self, colors: [RGB8; N], scale: u8) -> [RGB8; N] {
+        let mut scaled = colors;
+        for (color, errors) in scaled.iter_mut().zip(&mut self.errors) {
+            for (channel, error) in [&mut color.r, &mut color.g, &mut color.b]
+                .into_iter()
+                .zip(errors)
+            {
+                // At most 255 * 255 + 254, which still fits.
+                let total = u16::from(*channel) * u16::from(scale) + u16::from(*error);
+                #[allow(clippy::cast_possible_truncation)]
+                let step = (total / 255) as u8;
+                #[allow(clippy::cast_possible_truncation)]
+                let rest = (total % 255) as u8;
+                *channel = step;
+                *error = rest;
+            }
+        }
+        scaled
+    }
+}
+
+impl<const N: usize> Default for Dither<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Blends linearly from `start` at `t = 0.0` to `end` at `t = 1.0`.
+#[must_use]
+pub fn interpolate_color(start: RGB8, end: RGB8, t: f32) -> RGB8 {
+    RGB8::new(
+        mix(start.r, end.r, t),
+        mix(start.g, end.g, t),
+        mix(start.b, end.b, t),
+    )
+}
@@ -0,0 +1,214 @@
+//! Buffering of log frames for reading them without a debugger.
+//!
+//! The firmware's global logger writes every encoded defmt frame both to RTT and into the [`LogSink`] in the runtime
+//! status. From there the network log task ships the frames to a UDP collector, and `system log dump` prints them over
+//! the command line. Frames are kept in their encoded (rzcobs) form, so every frame ends with a zero byte and the host
+//! decodes them with `defmt-print` and the firmware ELF.
+
+use core::cell::RefCell;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use critical_section::Mutex;
+
+/// Capacity in bytes of the log buffer.
+pub const LOG_BUFFER_LEN: usize = 4096;
+
+/// Longest encoded frame that is buffered. Longer frames are dropped.
+pub const MAX_FRAME_LEN: usize = 256;
+
+/// Ring buffer of complete encoded log frames that drops the oldest frames when it runs out of room.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::logging::FrameRing;
+///
+/// let mut ring = FrameRing::<8>::new();
+/// ring.write(&[1, 2]);
+/// ring.write(&[0]);
+/// ring.end_frame();
+/// ring.write(&[3, 4, 5, 0]);
+/// ring.end_frame();
+/// assert_eq!(ring.len(), 7);
+///
+/// // Making room for a new frame evicts whole frames from the front.
+/// ring.write(&[6, 7, 0]);
+/// ring.end_frame();
+/// assert_eq!(ring.dropped(), 1);
+///
+/// // Only whole frames are taken out.
+/// let mut out = [0u8; 5];
+/// assert_eq!(ring.take_frames(&mut out), 4);
+/// assert_eq!(&out[..4], &[3, 4, 5, 0]);
+/// assert_eq!(ring.take_frames(&mut out), 3);
+/// assert!(ring.is_empty());
+/// ```
+#[derive(Debug, Clone)]
+pub struct FrameRing<const N: usize> {
+    bytes: heapless::Deque<u8, N>,
+    frame: heapless::Vec<u8, MAX_FRAME_LEN>,
+    truncated: bool,
+    dropped: u32,
+}
+
+impl<const N: usize> FrameRing<N> {
+    /// Creates a new empty ring.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            bytes: heapless::Deque::new(),
+            frame: heapless::Vec::new(),
+            truncated: false,
+            dropped: 0,
+        }
+    }
+
+    /// Appends encoded bytes to the frame being written.
+    pub fn write(&mut self, bytes: &[u8]) {
+        if self.frame.extend_from_slice(bytes).is_err() {
+            self.truncated = true;
+        }
+    }
+
+    /// Commits the frame being written, evicting the oldest frames if needed to make room for it.
+    ///
+    /// A frame that did not fit into [`MAX_FRAME_LEN`] or the ring itself is dropped instead, since a truncated frame
+    /// cannot be decoded.
+    pub fn end_frame(&mut self) {
+        if self.truncated || self.frame.len() > N {
+            self.dropped = self.dropped.saturating_add(1);
+        } else {
+            while self.bytes.capacity() - self.bytes.len() < self.frame.len() {
+                self.drop_oldest();
+            }
+            for &byte in &self.frame {
+                // Room was made above.
+                let _ = self.bytes.push_back(byte);
+            }
+        }
+        self.frame.clear();
+        self.truncated = false;
+    }
+
+    /// Moves as many whole frames as fit into `out`, oldest first, and returns the number of bytes written.
+    pub fn take_frames(&mut self, out: &mut [u8]) -> usize {
+        let mut written = 0;
+        while let Some(len) = self
+            .bytes
+            .iter()
+            .position(|&byte| byte == 0)
+            .map(|end| end + 1)
+        {
+            if written + len > out.len() {
+                break;
+            }
+            for slot in &mut out[written..written + len] {
+                *slot = self.bytes.pop_front().unwrap_or_default();
+            }
+            written += len;
+        }
+        written
+    }
+
+    /// Returns the number of buffered bytes.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Returns whether no frames are buffered.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Returns the number of frames dropped since boot, either evicted to make room or too long to buffer.
+    #[must_use]
+    pub const fn dropped(&self) -> u32 {
+        self.dropped
+    }
+
+    fn drop_oldest(&mut self) {
+        while let Some(byte) = self.bytes.pop_front() {
+            if byte == 0 {
+                break;
+            }
+        }
+        self.dropped = self.dropped.saturating_add(1);
+    }
+}
+
+impl<const N: usize> Default for FrameRing<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Log buffer shared between the global logger and its readers.
+///
+/// Every method only holds a short critical section, so logging never waits on a reader.
+pub struct LogSink {
+    ring: Mutex<RefCell<FrameRing<LOG_BUFFER_LEN>>>,
+    network: AtomicBool,
+}
+
+impl LogSink {
+    /// Creates a new empty sink with network shipping enabled.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            ring: Mutex::new(RefCell::new(FrameRing::new())),
+            network: AtomicBool::new(true),
+        }
+    }
+
+    /// Appends encoded bytes to the frame being logged.
+    pub fn write(&self, bytes: &[u8]) {
+        critical_section::with(|cs| self.ring.borrow_ref_mut(cs).write(bytes));
+    }
+
+    /// Commits the frame being logged.
+    pub fn end_frame(&self) {
+        critical_section::with(|cs| self.ring.borrow_ref_mut(cs).end_frame());
+    }
+
+    /// Moves as many whole frames as fit into `out`, oldest first, and returns the number of bytes written.
+    pub fn take_frames(&self, out: &mut [u8]) -> usize {
+        critical_section::with(|cs| self.ring.borrow_ref_mut(cs).take_frames(out))
+    }
+
+    /// Returns the number of buffered bytes.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        critical_section::with(|cs| self.ring.borrow_ref(cs).len())
+    }
+
+    /// Returns whether no frames are buffered.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of frames dropped since boot.
+    #[must_use]
+    pub fn dropped(&self) -> u32 {
+        critical_section::with(|cs| self.ring.borrow_ref(cs).dropped())
+    }
+
+    /// Enables or disables shipping the buffered frames over the network.
+    pub fn set_network(&self, enabled: bool) {
+        self.network.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Returns whether the buffered frames are shipped over the network.
+    #[must_use]
+    pub fn network(&self) -> bool {
+        self.network.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for LogSink {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -21,30 +21,41 @@
     duration of a data transfer."
 )]
 
-use defmt::{debug, info, warn};
+use catears::audio::synth::Sample;
+use catears::lights::render::{mirror, orient, render, scale_brightness};
+use catears::lights::LED_COUNT;
+use catears::resets::Cause as ResetCause;
+use catears::startup::{Outcome, Stage};
+use catears::watchdog::Task;
+use core::{
+    cell::{Cell, RefCell},
+    convert::Infallible,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use defmt::{debug, error, info, warn};
 use embassy_executor::Spawner;
 use embassy_net::{
     dns::DnsSocket,
     tcp::client::{TcpClient, TcpClientState},
+    udp::PacketMetadata,
     Stack,
 };
 use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, rwlock::RwLock};
 use embassy_time::{Timer, WithTimeout as _};
-use embedded_io_async::Write as _;
+use embedded_io_async::{Read as _, Write as _};
 use esp_hal::{
     clock::CpuClock,
-    dma_buffers,
     gpio::{Level, Output, OutputConfig},
     i2s::master::{I2s, I2sTx},
     mcpwm::{operator::PwmPinConfig, timer::PwmWorkingMode, McPwm, PeripheralClockConfig},
     rmt::{self, Rmt},
-    time::Rate,
+    rtc_cntl::Rtc,
+    time::{Instant as BootInstant, Rate},
+    timer::timg::{MwdtStage, MwdtStageAction, TimerGroup, Wdt},
     usb_serial_jtag::UsbSerialJtag,
 };
 use esp_hal_smartled::SmartLedsAdapterAsync;
-use panic_rtt_target as _;
 use reqwless::client::{HttpClient, TlsConfig, TlsVerify};
-use smart_leds::hsv::{hsv2rgb, Hsv};
 use smart_leds::SmartLedsWriteAsync;
 use static_cell::StaticCell;
 
@@ -55,10 +66,371 @@ esp_bootloader_esp_idf::esp_app_desc!();
 static STATE: RwLock<CriticalSectionRawMutex, catears::state::State> =
     RwLock::new(catears::state::State::default_const());
 
+static STATUS: catears::status::Status = catears::status::Status::new();
+
+static GESTURES: catears::servo::gestures::GestureChannel = embassy_sync::channel::Channel::new();
+
+static MIC_LEVEL: catears::audio::dsp::AudioLevel = catears::audio::dsp::AudioLevel::new();
+
+static FLASHES: catears::lights::flashes::FlashChannel = embassy_sync::channel::Channel::new();
+
+/// State stashed in RTC fast memory, which keeps its contents through deep sleep and soft resets.
+#[allow(
+    unsafe_code,
+    reason = "the ram attribute places the static in a dedicated link section"
+)]
+#[esp_hal::ram(unstable(rtc_fast, persistent))]
+static STATE_STASH: [portable_atomic::AtomicU32; catears::sleep::STASH_WORDS] =
+    [const { portable_atomic::AtomicU32::new(0) }; catears::sleep::STASH_WORDS];
+
+/// Global defmt logger that writes every frame both to RTT, for a debugger, and into the status log buffer, for
+/// reading the logs over the network or the command line.
+#[defmt::global_logger]
+struct Logger;
+
+/// Whether a frame is being logged, to catch reentrant logging.
+static LOGGER_TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// Interrupt state to restore once the frame being logged is done.
+static LOGGER_RESTORE: critical_section::Mutex<Cell<critical_section::RestoreState>> =
+    critical_section::Mutex::new(Cell::new(critical_section::RestoreState::invalid()));
+
+static LOGGER_ENCODER: critical_section::Mutex<RefCell<defmt::Encoder>> =
+    critical_section::Mutex::new(RefCell::new(defmt::Encoder::new()));
+
+/// RTT channel read by the debugger, set up first thing at boot.
+static RTT_CHANNEL: critical_section::Mutex<RefCell<Option<rtt_target::UpChannel>>> =
+    critical_section::Mutex::new(RefCell::new(None));
+
+// The logger holds a critical section from `acquire` to `release` so that frames from different contexts never
+// interleave, like `defmt-rtt` does.
+#[allow(
+    unsafe_code,
+    reason = "defmt loggers are unsafe to implement and hold a critical section across calls"
+)]
+// SAFETY: `acquire` enters a critical section and panics on reentrant use, and `release` leaves it again, so frames are
+// only ever written by one context at a time.
+unsafe impl defmt::Logger for Logger {
+    fn acquire() {
+        // SAFETY: The critical section is released in `release`, which defmt calls exactly once after each `acquire`.
+        let restore = unsafe { critical_section::acquire() };
+        // SAFETY: We are inside the critical section acquired above.
+        let cs = unsafe { critical_section::CriticalSection::new() };
+        assert!(
+            !LOGGER_TAKEN.swap(true, Ordering::Relaxed),
+            "defmt logger taken reentrantly"
+        );
+        LOGGER_RESTORE.borrow(cs).set(restore);
+        LOGGER_ENCODER
+            .borrow_ref_mut(cs)
+            .start_frame(|bytes| write_log_bytes(cs, bytes));
+    }
+
+    unsafe fn flush() {}
+
+    unsafe fn release() {
+        // SAFETY: defmt only calls `release` after `acquire`, which entered the critical section.
+        let cs = unsafe { critical_section::CriticalSection::new() };
+        LOGGER_ENCODER
+            .borrow_ref_mut(cs)
+            .end_frame(|bytes| write_log_bytes(cs, bytes));
+        STATUS.logs.end_frame();
+        LOGGER_TAKEN.store(false, Ordering::Relaxed);
+        // SAFETY: The restore state was stored by the matching `acquire`.
+        unsafe { critical_section::release(LOGGER_RESTORE.borrow(cs).get()) };
+    }
+
+    unsafe fn write(bytes: &[u8]) {
+        // SAFETY: defmt only calls `write` between `acquire` and `release`, inside the critical section.
+        let cs = unsafe { critical_section::CriticalSection::new() };
+        LOGGER_ENCODER
+            .borrow_ref_mut(cs)
+            .write(bytes, |bytes| write_log_bytes(cs, bytes));
+    }
+}
+
+fn write_log_bytes(cs: critical_section::CriticalSection<'_>, bytes: &[u8]) {
+    if let Some(channel) = RTT_CHANNEL.borrow_ref_mut(cs).as_mut() {
+        channel.write(bytes);
+    }
+    STATUS.logs.write(bytes);
+}
+
+/// Record of the last crash in RTC fast memory, which keeps its contents through the resets that follow it.
+#[allow(
+    unsafe_code,
+    reason = "the ram attribute places the static in a dedicated link section"
+)]
+#[esp_hal::ram(unstable(rtc_fast, persistent))]
+static CRASH_RECORD: [portable_atomic::AtomicU32; catears::crash::RECORD_WORDS] =
+    [const { portable_atomic::AtomicU32::new(0) }; catears::crash::RECORD_WORDS];
+
+/// Set to [`PANICKED_MAGIC`] by the panic handler, so the next boot can tell a panic from other resets.
+#[allow(
+    unsafe_code,
+    reason = "the ram attribute places the static in a dedicated link section"
+)]
+#[esp_hal::ram(unstable(rtc_fast, persistent))]
+static PANICKED: portable_atomic::AtomicU32 = portable_atomic::AtomicU32::new(0);
+
+/// Marks a crash record written by the panic handler since the last boot.
+const PANICKED_MAGIC: u32 = 0xCA7E_0BAD;
+
+/// Writes `record` into the crash record in RTC memory. Only atomic stores, so this is safe from the panic handler.
+fn write_crash_record(record: &catears::crash::Record) {
+    let mut words = [0u32; catears::crash::RECORD_WORDS];
+    record.encode(&mut words);
+    for (slot, word) in CRASH_RECORD.iter().zip(words) {
+        slot.store(word, Ordering::Relaxed);
+    }
+}
+
+/// Clears the crash record in RTC memory.
+fn clear_crash_record() {
+    for slot in &CRASH_RECORD {
+        slot.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Reboot bookkeeping in RTC fast memory, which keeps its contents through resets other than power on.
+#[allow(
+    unsafe_code,
+    reason = "the ram attribute places the static in a dedicated link section"
+)]
+#[esp_hal::ram(unstable(rtc_fast, persistent))]
+static RESET_LEDGER: [portable_atomic::AtomicU32; catears::resets::LEDGER_WORDS] =
+    [const { portable_atomic::AtomicU32::new(0) }; catears::resets::LEDGER_WORDS];
+
+/// Time between saves of the session uptime into the reset ledger.
+const UPTIME_SAVE_INTERVAL: embassy_time::Duration = embassy_time::Duration::from_secs(5);
+
+/// Classifies the chip's reset reason, telling panics apart from other resets by whether a panic was recorded.
+fn classify_reset(reason: Option<esp_hal::rtc_cntl::SocResetReason>, panicked: bool) -> ResetCause {
+    use esp_hal::rtc_cntl::SocResetReason;
+
+    match reason {
+        None | Some(SocResetReason::ChipPowerOn) => ResetCause::PowerOn,
+        Some(SocResetReason::CoreDeepSleep) => ResetCause::DeepSleep,
+        Some(SocResetReason::SysBrownOut) => ResetCause::Brownout,
+        _ if panicked => ResetCause::Panic,
+        Some(
+            SocResetReason::CoreMwdt0
+            | SocResetReason::CoreMwdt1
+            | SocResetReason::CoreRtcWdt
+            | SocResetReason::CpuMwdt0
+            | SocResetReason::CpuMwdt1
+            | SocResetReason::CpuRtcWdt
+            | SocResetReason::SysRtcWdt
+            | SocResetReason::SysSuperWdt,
+        ) => ResetCause::Watchdog,
+        Some(
+            SocResetReason::CoreSw
+            | SocResetReason::CpuSw
+            | SocResetReason::CoreUsbUart
+            | SocResetReason::CoreUsbJtag,
+        ) => ResetCause::Software,
+        Some(_) => ResetCause::Other,
+    }
+}
+
+/// Saves the reboot count and the current session uptime into the reset ledger.
+fn save_reset_ledger() {
+    let ledger = catears::resets::Ledger {
+        reboots: STATUS.resets.reboots(),
+        brownouts: STATUS.resets.brownouts(),
+        uptime_s: u32::try_from(BootInstant::now().duration_since_epoch().as_secs())
+            .unwrap_or(u32::MAX),
+    };
+    for (slot, word) in RESET_LEDGER.iter().zip(ledger.encode()) {
+        slot.store(word, Ordering::Relaxed);
+    }
+}
+
+/// Periodically saves the session uptime, so the next boot knows how long this session lasted.
+#[embassy_executor::task]
+async fn track_uptime() -> ! {
+    loop {
+        Timer::after(UPTIME_SAVE_INTERVAL).await;
+        save_reset_ledger();
+    }
+}
+
+/// Time between checks for a changed state to stash, which also limits how often the stash is rewritten.
+const STATE_STASH_INTERVAL: embassy_time::Duration = embassy_time::Duration::from_secs(2);
+
+/// Stashes `state` in RTC fast memory, leaving out a chiptune if needed to fit. Returns whether it was stashed at all.
+fn stash_state(state: &catears::state::State) -> bool {
+    let mut stash = [0u32; catears::sleep::STASH_WORDS];
+    let Some(trimmed) = catears::sleep::encode_trimmed(state, &mut stash) else {
+        return false;
+    };
+    if trimmed {
+        debug!(
+            "State: too large for RTC memory, stashing it without chiptunes or light animations"
+        );
+    }
+    for (slot, word) in STATE_STASH.iter().zip(stash) {
+        slot.store(word, Ordering::Relaxed);
+    }
+    true
+}
+
+/// Keeps the stash in RTC fast memory up to date with the state, so a soft reset can restore it right away instead of
+/// starting from the defaults until the remote state comes back.
+#[embassy_executor::task]
+async fn track_state(state: &'static RwLock<CriticalSectionRawMutex, catears::state::State>) -> ! {
+    let mut stashed = None;
+    loop {
+        let current = *state.read().await;
+        if stashed != Some(current) {
+            if !stash_state(&current) {
+                warn!("State does not fit in RTC memory, it will be lost on reset");
+            }
+            stashed = Some(current);
+        }
+        Timer::after(STATE_STASH_INTERVAL).await;
+    }
+}
+
+/// Sheds load for a while after a brownout reset, so the supply gets a chance to recover before the outputs draw full
+/// current again.
+///
+/// Brownouts in a row hold off full load for longer every time.
+#[embassy_executor::task]
+async fn shed_load(status: &'static catears::status::Status, config: catears::supply::Config) {
+    let brownouts = status.resets.brownouts();
+    let hold_s = config.hold_s(brownouts);
+    if hold_s == 0 {
+        return;
+    }
+
+    status.supply.start(config.shed_brightness);
+    warn!(
+        "Supply: brownout {} in a row, capping LED brightness at {} and holding the servos for {} s",
+        brownouts, config.shed_brightness, hold_s
+    );
+    Timer::after_secs(u64::from(hold_s)).await;
+    status.supply.stop();
+    info!("Supply: load shedding lifted");
+}
+
+/// Whether the LED rings are set up, so the panic handler blacks them out.
+static SAFE_STATE_LEDS: AtomicBool = AtomicBool::new(false);
+
+/// Whether the servos are set up, so the panic handler stops driving them.
+static SAFE_STATE_SERVOS: AtomicBool = AtomicBool::new(false);
+
+/// Whether the auxiliary servos are set up, so the panic handler stops driving them too.
+#[cfg(feature = "aux-servos")]
+static SAFE_STATE_AUX_SERVOS: AtomicBool = AtomicBool::new(false);
+
+/// Whether the speakers are set up, so the panic handler mutes them.
+static SAFE_STATE_SPEAKERS: AtomicBool = AtomicBool::new(false);
+
+/// Puts the hardware into a safe state, records the panic for the next boot, and resets the device.
+///
+/// Whatever was last commanded would otherwise stay active: the servos pushing against a stop, the LEDs at full
+/// brightness, and the speakers looping their last buffer. The reset is a software one rather than the watchdog's,
+/// since a panic during boot comes before the watchdog is running.
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo<'_>) -> ! {
+    static PANICKING: AtomicBool = AtomicBool::new(false);
+
+    // A panic while handling a panic just resets, the safe state is as good as it is going to get.
+    if !PANICKING.swap(true, Ordering::Relaxed) {
+        let _outputs = enter_safe_state();
+        save_reset_ledger();
+
+        let uptime_s =
+            u32::try_from(BootInstant::now().duration_since_epoch().as_secs()).unwrap_or(u32::MAX);
+        write_crash_record(&catears::crash::Record::from_panic(info, uptime_s));
+        PANICKED.store(PANICKED_MAGIC, Ordering::Relaxed);
+
+        // The logger panics on reentrant use, so a panic inside it goes unlogged.
+        if !LOGGER_TAKEN.load(Ordering::Relaxed) {
+            error!("{}", defmt::Display2Format(info));
+        }
+    }
+
+    esp_hal::system::software_reset()
+}
+
+/// Takes the outputs back from their drivers and turns them off, returning the pins that have to stay low.
+///
+/// This runs in the panic handler, so it cannot wait on anything: the pins are reconfigured directly, and the LED rings
+/// are blacked out with a blocking transmission.
+#[allow(
+    unsafe_code,
+    reason = "the panic handler steals the peripherals back from the tasks that owned them"
+)]
+fn enter_safe_state() -> [Option<Output<'static>>; 6] {
+    let low = |pin: esp_hal::gpio::AnyPin<'static>| {
+        Some(Output::new(pin, Level::Low, OutputConfig::default()))
+    };
+    let mut outputs = [None, None, None, None, None, None];
+
+    // SAFETY (for every steal below): The panic handler never returns, so the tasks that owned these peripherals never
+    // touch them again.
+    if SAFE_STATE_SERVOS.load(Ordering::Relaxed) {
+        // Without pulses the servos stop holding their position instead of straining against it.
+        outputs[0] = low(unsafe { esp_hal::peripherals::GPIO44::steal() }.into());
+        outputs[1] = low(unsafe { esp_hal::peripherals::GPIO2::steal() }.into());
+    }
+    #[cfg(feature = "aux-servos")]
+    if SAFE_STATE_AUX_SERVOS.load(Ordering::Relaxed) {
+        outputs[4] = low(unsafe { esp_hal::peripherals::GPIO15::steal() }.into());
+        outputs[5] = low(unsafe { esp_hal::peripherals::GPIO16::steal() }.into());
+    }
+    if SAFE_STATE_SPEAKERS.load(Ordering::Relaxed) {
+        // The amplifiers keep their clocks, but only ever read silent samples.
+        outputs[2] = low(unsafe { esp_hal::peripherals::GPIO7::steal() }.into());
+        outputs[3] = low(unsafe { esp_hal::peripherals::GPIO5::steal() }.into());
+    }
+    if SAFE_STATE_LEDS.load(Ordering::Relaxed) {
+        use esp_hal_smartled::SmartLedsAdapter;
+        use smart_leds::SmartLedsWrite as _;
+
+        let rmt = unsafe { esp_hal::peripherals::RMT::steal() };
+        if let Ok(rmt) = Rmt::new(rmt, Rate::from_mhz(80)) {
+            let blackout = [smart_leds::RGB8::default(); LED_COUNT];
+            let mut led_ring_left = SmartLedsAdapter::new(
+                rmt.channel1,
+                Output::new(
+                    unsafe { esp_hal::peripherals::GPIO43::steal() },
+                    Level::Low,
+                    OutputConfig::default(),
+                ),
+                esp_hal_smartled::smart_led_buffer!(LED_COUNT),
+            );
+            let _ = led_ring_left.write(blackout);
+            let mut led_ring_right = SmartLedsAdapter::new(
+                rmt.channel2,
+                Output::new(
+                    unsafe { esp_hal::peripherals::GPIO1::steal() },
+                    Level::Low,
+                    OutputConfig::default(),
+                ),
+                esp_hal_smartled::smart_led_buffer!(LED_COUNT),
+            );
+            let _ = led_ring_right.write(blackout);
+        }
+    }
+    outputs
+}
+
 #[esp_hal_embassy::main]
 async fn main(spawner: Spawner) -> ! {
     {
-        rtt_target::rtt_init_defmt!();
+        let channels = rtt_target::rtt_init! {
+            up: {
+                0: {
+                    size: 1024,
+                    mode: rtt_target::ChannelMode::NoBlockSkip,
+                    name: "defmt"
+                }
+            }
+        };
+        critical_section::with(|cs| RTT_CHANNEL.borrow_ref_mut(cs).replace(channels.up.0));
         info!("Logging initialized!");
     }
 
@@ -68,486 +440,2651 @@ async fn main(spawner: Spawner) -> ! {
         info!("Peripherals and HAL initialized!");
         peripherals
     };
+    info!("Device name: {}", catears::identity::device_name());
+    catears::audio::chiptunes::register(&CUSTOM_CHIPTUNES);
 
     let system_timer = esp_hal::timer::systimer::SystemTimer::new(peripherals.SYSTIMER);
     let rng = esp_hal::rng::Rng::new(peripherals.RNG.reborrow());
 
     {
+        let start = BootInstant::now();
         esp_hal_embassy::init(system_timer.alarm0);
-        info!("Embassy initialized!");
+        finish_stage(Stage::Embassy, start, Ok::<_, Infallible>(()));
     }
 
     {
+        let start = BootInstant::now();
         // esp_alloc::heap_allocator!(size: 64 * 1024);
         // COEX needs more RAM - so we've added some more
         esp_alloc::heap_allocator!(#[link_section = ".dram2_uninit"] size: 64 * 1024);
-        info!("Heap allocator initialized!");
+        // Goes after the internal heap, so everything that does not ask for external memory keeps using internal RAM.
+        #[cfg(feature = "psram")]
+        esp_alloc::psram_allocator!(peripherals.PSRAM, esp_hal::psram);
+        finish_stage(Stage::Heap, start, Ok::<_, Infallible>(()));
+    }
+
+    {
+        use catears::sleep::WakeCause;
+        use esp_hal::{rtc_cntl::SocResetReason, system::SleepSource};
+
+        let start = BootInstant::now();
+        let reset_reason = esp_hal::rtc_cntl::reset_reason(esp_hal::system::Cpu::ProCpu);
+        let cause = if reset_reason == Some(SocResetReason::CoreDeepSleep) {
+            match esp_hal::rtc_cntl::wakeup_cause() {
+                SleepSource::Ext0 => WakeCause::Button,
+                SleepSource::Timer => WakeCause::Timer,
+                _ => WakeCause::Other,
+            }
+        } else {
+            WakeCause::PowerOn
+        };
+        info!("Boot cause: {}", cause.name());
+        STATUS.sleep.set_wake_cause(cause);
+
+        let panicked = PANICKED.swap(0, Ordering::Relaxed) == PANICKED_MAGIC;
+        let reset_cause = classify_reset(reset_reason, panicked);
+        let previous = if reset_cause == ResetCause::PowerOn {
+            None
+        } else {
+            catears::resets::Ledger::decode(&core::array::from_fn(|i| {
+                RESET_LEDGER[i].load(Ordering::Relaxed)
+            }))
+        };
+        let ledger = catears::resets::Ledger::next(previous, reset_cause);
+        STATUS.resets.publish(reset_cause, previous, ledger);
+        save_reset_ledger();
+
+        match reset_cause {
+            // Whatever is in RTC memory after a power on is noise.
+            ResetCause::PowerOn => clear_crash_record(),
+            ResetCause::Watchdog | ResetCause::Brownout => {
+                write_crash_record(&catears::crash::Record::reset(
+                    reset_cause,
+                    previous.map_or(0, |previous| previous.uptime_s),
+                ));
+            }
+            _ => {}
+        }
+        STATUS.last_crash.publish(
+            catears::crash::Record::decode(&core::array::from_fn(|i| {
+                CRASH_RECORD[i].load(Ordering::Relaxed)
+            })),
+            clear_crash_record,
+        );
+        if let catears::crash::Stored::Crash(record) = STATUS.last_crash.get() {
+            if panicked {
+                warn!(
+                    "Reset after panic at {}:{}:{}: {}",
+                    record.file.as_str(),
+                    record.line,
+                    record.column,
+                    record.message.as_str()
+                );
+            }
+        }
+        if reset_cause.is_unexpected() {
+            warn!(
+                "Reset by {} after {} s up, {} resets since power on",
+                reset_cause.name(),
+                STATUS.resets.previous_uptime_s(),
+                ledger.reboots
+            );
+        } else {
+            info!(
+                "Reset by {}, {} resets since power on",
+                reset_cause.name(),
+                ledger.reboots
+            );
+        }
+
+        // Anything but a soft reset may have left garbage in RTC memory, even if it happens to validate.
+        let restore = cause.from_deep_sleep()
+            || matches!(
+                reset_cause,
+                ResetCause::Software | ResetCause::Panic | ResetCause::Watchdog
+            );
+        let result = if restore {
+            let stash = core::array::from_fn(|i| STATE_STASH[i].load(Ordering::Relaxed));
+            match catears::sleep::decode(&stash) {
+                Some(state) => {
+                    *STATE.write().await = state;
+                    info!("State restored from before {}!", reset_cause.name());
+                    Ok(())
+                }
+                None => Err("no valid state stashed before the reset, starting from defaults"),
+            }
+        } else {
+            Ok(())
+        };
+        finish_stage(Stage::StateLoad, start, result);
     }
 
     let networking_stack = {
-        let stack = catears::networking::init(
+        let start = BootInstant::now();
+        let result = catears::networking::init(
             catears::networking::Config {
                 client: esp_wifi::wifi::ClientConfiguration {
                     ssid: env!("WIFI_SSID").into(),
                     password: env!("WIFI_PASSWORD").into(),
                     ..Default::default()
                 },
-                dhcp_hostname: "catears".try_into().expect("hostname too long"),
+                dhcp_hostname: catears::identity::device_name(),
             },
             system_timer.alarm1,
             rng,
             peripherals.WIFI,
             &spawner,
         )
-        .await
-        .expect("Failed to initialize networking stack");
-        info!("Networking stack initialized!");
-        stack
+        .await;
+        // A stack that is up but not yet connected only finishes this stage once it connects, further down.
+        match result {
+            Ok(stack) => Some((stack, start)),
+            Err(e) => {
+                finish_stage(Stage::Networking, start, Err::<(), _>(e));
+                None
+            }
+        }
     };
 
-    let serial = {
-        let init_result = embassy_time::with_timeout(embassy_time::Duration::from_secs(1), async {
+    {
+        let start = BootInstant::now();
+        let result = embassy_time::with_timeout(embassy_time::Duration::from_secs(1), async {
             let mut serial = UsbSerialJtag::new(peripherals.USB_DEVICE).into_async();
             serial
                 .write_all(b"JTAG serial interface initialized!\r\n")
                 .await
-                .expect("Failed to write to serial");
-            serial.flush().await.expect("Failed to flush serial");
-            serial
+                .map_err(|_| "failed to write to serial")?;
+            serial.flush().await.map_err(|_| "failed to flush serial")?;
+            Ok::<_, &str>(serial)
         })
-        .await;
-        if let Ok(result) = init_result {
-            info!("JTAG serial interface initialized!");
-            Some(result)
-        } else {
-            info!("JTAG serial interface initialization timed out");
-            None
+        .await
+        .unwrap_or(Err("no host connected"));
+        if let Some(serial) = finish_stage(Stage::Cli, start, result) {
+            catears::cmdline::init(&STATE, &STATUS, &GESTURES, serial, &spawner).await;
         }
-    };
+    }
 
-    if let Some(serial) = serial {
-        catears::cmdline::init(&STATE, serial, &spawner).await;
-        info!("Command line interface initialized!");
-    } else {
-        warn!(
-            "JTAG serial interface not available, command line interface will not be initialized"
-        );
+    #[cfg(feature = "uart")]
+    {
+        use esp_hal::uart::{Config, Uart};
+
+        match Uart::new(
+            peripherals.UART1,
+            Config::default().with_baudrate(catears::cmdline::UART_BAUD),
+        ) {
+            Ok(uart) => {
+                let uart = uart
+                    .with_tx(peripherals.GPIO11)
+                    .with_rx(peripherals.GPIO14)
+                    .into_async();
+                catears::cmdline::init_uart(&STATE, &STATUS, &GESTURES, uart, &spawner);
+                info!(
+                    "UART command line initialized at {} baud!",
+                    catears::cmdline::UART_BAUD
+                );
+            }
+            Err(e) => warn!("UART not available, external controller disabled: {:?}", e),
+        }
     }
 
-    let (led_ring_left, led_ring_right) = {
-        let rmt = Rmt::new(peripherals.RMT, Rate::from_mhz(80))
-            .expect("Failed to initialize RMT")
-            .into_async();
-        let led_ring_left = SmartLedsAdapterAsync::new(
-            rmt.channel1,
-            Output::new(peripherals.GPIO43, Level::Low, OutputConfig::default()),
-            [0u32; esp_hal_smartled::buffer_size_async(12)],
-        );
-        let led_ring_right = SmartLedsAdapterAsync::new(
-            rmt.channel2,
-            Output::new(peripherals.GPIO1, Level::Low, OutputConfig::default()),
-            [0u32; esp_hal_smartled::buffer_size_async(12)],
-        );
-        info!("LEDs initialized!");
-        (led_ring_left, led_ring_right)
+    let led_rings = {
+        let start = BootInstant::now();
+        let result = Rmt::new(peripherals.RMT, Rate::from_mhz(80)).map(|rmt| {
+            let rmt = rmt.into_async();
+            let led_ring_left = SmartLedsAdapterAsync::new(
+                rmt.channel1,
+                Output::new(peripherals.GPIO43, Level::Low, OutputConfig::default()),
+                [0u32; esp_hal_smartled::buffer_size_async(LED_COUNT)],
+            );
+            let led_ring_right = SmartLedsAdapterAsync::new(
+                rmt.channel2,
+                Output::new(peripherals.GPIO1, Level::Low, OutputConfig::default()),
+                [0u32; esp_hal_smartled::buffer_size_async(LED_COUNT)],
+            );
+            (led_ring_left, led_ring_right)
+        });
+        finish_stage(Stage::Leds, start, result)
     };
-
-    let (servo_left, servo_right) = {
-        let clock_cfg = PeripheralClockConfig::with_frequency(Rate::from_mhz(1))
-            .expect("Failed to configure peripheral clock");
-        let mut mcpwm = McPwm::new(peripherals.MCPWM0, clock_cfg);
-        mcpwm.operator0.set_timer(&mcpwm.timer0);
-        mcpwm.operator1.set_timer(&mcpwm.timer0);
-        let timer_clock_cfg = clock_cfg
-            .timer_clock_with_frequency(19_999, PwmWorkingMode::Increase, Rate::from_hz(50))
-            .expect("Failed to configure timer clock");
-        mcpwm.timer0.start(timer_clock_cfg);
-        let (pin_a, pin_b) = mcpwm.operator0.with_pins(
-            Output::new(peripherals.GPIO44, Level::Low, OutputConfig::default()),
-            PwmPinConfig::UP_ACTIVE_HIGH,
-            Output::new(peripherals.GPIO2, Level::Low, OutputConfig::default()),
-            PwmPinConfig::UP_ACTIVE_HIGH,
-        );
-        let servo_left = catears::servo::Servo::new(pin_a, catears::servo::Config::MGG995);
-        let servo_right = catears::servo::Servo::new(pin_b, catears::servo::Config::MGG995);
-        info!("Servos initialized!");
-        (servo_left, servo_right)
+    SAFE_STATE_LEDS.store(led_rings.is_some(), Ordering::Relaxed);
+
+    let servos = {
+        let start = BootInstant::now();
+        let result =
+            PeripheralClockConfig::with_frequency(Rate::from_mhz(1)).and_then(|clock_cfg| {
+                let mut mcpwm = McPwm::new(peripherals.MCPWM0, clock_cfg);
+                mcpwm.operator0.set_timer(&mcpwm.timer0);
+                mcpwm.operator1.set_timer(&mcpwm.timer0);
+                let timer_clock_cfg = clock_cfg.timer_clock_with_frequency(
+                    19_999,
+                    PwmWorkingMode::Increase,
+                    Rate::from_hz(50),
+                )?;
+                mcpwm.timer0.start(timer_clock_cfg);
+                let (pin_a, pin_b) = mcpwm.operator0.with_pins(
+                    Output::new(peripherals.GPIO44, Level::Low, OutputConfig::default()),
+                    PwmPinConfig::UP_ACTIVE_HIGH,
+                    Output::new(peripherals.GPIO2, Level::Low, OutputConfig::default()),
+                    PwmPinConfig::UP_ACTIVE_HIGH,
+                );
+                let servo_left = catears::servo::Servo::new(pin_a, catears::servo::Config::MGG995);
+                let servo_right = catears::servo::Servo::new(pin_b, catears::servo::Config::MGG995);
+
+                // The second operator shares the 50 Hz timer and drives the auxiliary servos, if fitted.
+                #[cfg(feature = "aux-servos")]
+                let aux_servos: Option<AuxServos> = {
+                    let (pin_a, pin_b) = mcpwm.operator1.with_pins(
+                        Output::new(peripherals.GPIO15, Level::Low, OutputConfig::default()),
+                        PwmPinConfig::UP_ACTIVE_HIGH,
+                        Output::new(peripherals.GPIO16, Level::Low, OutputConfig::default()),
+                        PwmPinConfig::UP_ACTIVE_HIGH,
+                    );
+                    Some((
+                        catears::servo::Servo::new(pin_a, catears::servo::Config::MGG995),
+                        catears::servo::Servo::new(pin_b, catears::servo::Config::MGG995),
+                    ))
+                };
+                #[cfg(not(feature = "aux-servos"))]
+                let aux_servos: Option<AuxServos> = None;
+                Ok((servo_left, servo_right, aux_servos))
+            });
+        finish_stage(Stage::Servos, start, result)
     };
+    SAFE_STATE_SERVOS.store(servos.is_some(), Ordering::Relaxed);
+    #[cfg(feature = "aux-servos")]
+    SAFE_STATE_AUX_SERVOS.store(
+        servos
+            .as_ref()
+            .is_some_and(|(_, _, aux_servos)| aux_servos.is_some()),
+        Ordering::Relaxed,
+    );
 
-    let (i2s_tx_left, i2s_tx_right) = {
-        #[allow(clippy::manual_div_ceil)]
-        let (_, _, _, tx_descriptors_left) = dma_buffers!(0, 16 * 4096);
-        let i2s_tx_left = I2s::new(
-            peripherals.I2S0,
-            esp_hal::i2s::master::Standard::Philips,
-            esp_hal::i2s::master::DataFormat::Data16Channel16,
-            Rate::from_hz(44100),
-            peripherals.DMA_CH0,
-        )
-        .into_async()
-        .i2s_tx
-        .with_ws(peripherals.GPIO9) // Green
-        .with_bclk(peripherals.GPIO8) // White
-        .with_dout(peripherals.GPIO7) // Blue
-        .build(tx_descriptors_left);
+    let (speaker_left, speaker_right) = {
+        let start = BootInstant::now();
+
+        // The microphone shares the left speaker's I2S peripheral, using its otherwise idle RX half. Rebuilding the
+        // peripheral at another rate would cut the microphone off, so the left speaker stays at the synthesis rate.
+        #[cfg(feature = "microphone")]
+        let speaker_left = {
+            #[allow(clippy::manual_div_ceil)]
+            let (_, _, _, tx_descriptors_left) = esp_hal::dma_buffers!(0, 16 * 4096);
+            let i2s0 = I2s::new(
+                peripherals.I2S0,
+                esp_hal::i2s::master::Standard::Philips,
+                DATA_FORMAT,
+                Rate::from_hz(catears::audio::synth::SAMPLE_RATE),
+                peripherals.DMA_CH0,
+            )
+            .into_async();
 
+            #[allow(clippy::manual_div_ceil)]
+            let (_, rx_descriptors, _, _) =
+                esp_hal::dma_buffers!(core::mem::size_of::<Sample>() * MIC_BUFFER_LEN, 0);
+            let i2s_rx = i2s0
+                .i2s_rx
+                .with_ws(peripherals.GPIO38)
+                .with_bclk(peripherals.GPIO39)
+                .with_din(peripherals.GPIO40)
+                .build(rx_descriptors);
+            spawner
+                .spawn(listen(&STATUS, &MIC_LEVEL, i2s_rx))
+                .expect("Failed to spawn microphone task");
+            info!("Microphone initialized!");
+
+            let tx = i2s0
+                .i2s_tx
+                .with_ws(peripherals.GPIO9) // Green
+                .with_bclk(peripherals.GPIO8) // White
+                .with_dout(peripherals.GPIO7) // Blue
+                .build(tx_descriptors_left);
+            SpeakerOutput::Fixed(tx)
+        };
+        #[cfg(not(feature = "microphone"))]
+        let speaker_left = {
+            #[allow(clippy::manual_div_ceil)]
+            let (_, tx_descriptors_left) = esp_hal::dma_descriptors!(0, 16 * 4096);
+            SpeakerOutput::Rebuilt {
+                peripherals: SpeakerPeripherals::Left {
+                    i2s: peripherals.I2S0,
+                    dma: peripherals.DMA_CH0,
+                    ws: peripherals.GPIO9,
+                    bclk: peripherals.GPIO8,
+                    dout: peripherals.GPIO7,
+                },
+                descriptors: tx_descriptors_left,
+            }
+        };
         #[allow(clippy::manual_div_ceil)]
-        let (_, _, _, tx_descriptors_right) = dma_buffers!(0, 16 * 4096);
-        let i2s_tx_right = I2s::new(
-            peripherals.I2S1,
-            esp_hal::i2s::master::Standard::Philips,
-            esp_hal::i2s::master::DataFormat::Data16Channel16,
-            Rate::from_hz(44100),
-            peripherals.DMA_CH1,
+        let (_, tx_descriptors_right) = esp_hal::dma_descriptors!(0, 16 * 4096);
+        let speaker_right = SpeakerOutput::Rebuilt {
+            peripherals: SpeakerPeripherals::Right {
+                i2s: peripherals.I2S1,
+                dma: peripherals.DMA_CH1,
+                ws: peripherals.GPIO3,
+                bclk: peripherals.GPIO4,
+                dout: peripherals.GPIO5,
+            },
+            descriptors: tx_descriptors_right,
+        };
+
+        finish_stage(Stage::I2s, start, Ok::<_, Infallible>(()));
+        (speaker_left, speaker_right)
+    };
+    SAFE_STATE_SPEAKERS.store(true, Ordering::Relaxed);
+
+    if let Some((stack, _)) = networking_stack {
+        // The true random number generator borrows ADC1 only while it exists, leaving the ADC free for sensors.
+        let tls_seed = {
+            let mut trng =
+                esp_hal::rng::Trng::new(peripherals.RNG.reborrow(), peripherals.ADC1.reborrow());
+            (u64::from(trng.random()) << 32) | u64::from(trng.random())
+        };
+        spawner
+            .spawn(update_state(stack, tls_seed, &STATE, &STATUS))
+            .expect("Failed to spawn update state task");
+        for side in catears::audio::Side::ALL {
+            // Each connection gets a seed of its own, a seed reused between TLS sessions weakens them both.
+            let tls_seed = {
+                let mut trng = esp_hal::rng::Trng::new(
+                    peripherals.RNG.reborrow(),
+                    peripherals.ADC1.reborrow(),
+                );
+                (u64::from(trng.random()) << 32) | u64::from(trng.random())
+            };
+            spawner
+                .spawn(stream_clips(stack, tls_seed, side, &STATUS))
+                .expect("Failed to spawn clip streaming task");
+        }
+    }
+
+    if let Some((led_ring_left, led_ring_right)) = led_rings {
+        spawner
+            .spawn(control_leds(
+                &STATE,
+                &STATUS,
+                &MIC_LEVEL,
+                &FLASHES,
+                led_ring_left,
+                led_ring_right,
+            ))
+            .expect("Failed to spawn rainbow LED task");
+    }
+    if let Some((servo_left, servo_right, aux_servos)) = servos {
+        spawner
+            .spawn(control_servos(
+                &STATE,
+                &STATUS,
+                &GESTURES,
+                &MIC_LEVEL,
+                servo_left,
+                servo_right,
+                aux_servos,
+            ))
+            .expect("Failed to spawn servo control task");
+    }
+    {
+        let [left_buffers, right_buffers] = AUDIO_BUFFERS.init([[[0; AUDIO_BUFFER_LEN]; 2]; 2]);
+        for (side, output, buffers) in [
+            (catears::audio::Side::Left, speaker_left, left_buffers),
+            (catears::audio::Side::Right, speaker_right, right_buffers),
+        ] {
+            spawner
+                .spawn(control_speaker(&STATE, &STATUS, side, output, buffers))
+                .expect("Failed to spawn speaker control task");
+        }
+    }
+
+    {
+        // Momentary push button between D5 and ground, which also wakes the device from deep sleep.
+        #[cfg(feature = "button")]
+        let wake_button = Some(peripherals.GPIO6);
+        #[cfg(not(feature = "button"))]
+        let wake_button: Option<esp_hal::peripherals::GPIO6<'static>> = None;
+        STATUS.sleep.set_wake_button(wake_button.is_some());
+        spawner
+            .spawn(manage_sleep(
+                &STATE,
+                &STATUS,
+                &GESTURES,
+                Rtc::new(peripherals.LPWR),
+                wake_button,
+            ))
+            .expect("Failed to spawn sleep manager task");
+        info!("Sleep manager initialized!");
+    }
+
+    #[cfg(feature = "imu")]
+    let i2c = {
+        let start = BootInstant::now();
+        let result = esp_hal::i2c::master::I2c::new(
+            peripherals.I2C0,
+            esp_hal::i2c::master::Config::default().with_frequency(Rate::from_khz(400)),
         )
-        .into_async()
-        .i2s_tx
-        .with_ws(peripherals.GPIO3) // Green
-        .with_bclk(peripherals.GPIO4) // White
-        .with_dout(peripherals.GPIO5) // Blue
-        .build(tx_descriptors_right);
-
-        info!("I2S initialized!");
-        (i2s_tx_left, i2s_tx_right)
+        .map(|i2c| {
+            i2c.with_sda(peripherals.GPIO41)
+                .with_scl(peripherals.GPIO42)
+                .into_async()
+        });
+        finish_stage(Stage::Imu, start, result)
     };
+    #[cfg(not(feature = "imu"))]
+    STATUS.boot.record(Stage::Imu, Outcome::Skipped, 0);
+
+    #[cfg(feature = "imu")]
+    if let Some(i2c) = i2c {
+        // The IMU is optional hardware, so a missing sensor only disables motion reactions.
+        match catears::motion::Imu::probe(i2c).await {
+            Ok(imu) => {
+                info!("IMU initialized ({:?})!", imu.sensor());
+                STATUS.motion.set_present();
+                spawner
+                    .spawn(track_motion(
+                        &STATUS,
+                        &GESTURES,
+                        &FLASHES,
+                        imu,
+                        catears::motion::Config::DEFAULT,
+                    ))
+                    .expect("Failed to spawn motion task");
+            }
+            Err(e) => warn!("IMU not available, motion reactions disabled: {:?}", e),
+        }
+    }
 
-    spawner
-        .spawn(update_state(
-            networking_stack,
-            esp_hal::rng::Trng::new(peripherals.RNG.reborrow(), peripherals.ADC1),
-            &STATE,
-        ))
-        .expect("Failed to spawn update state task");
+    #[cfg(feature = "ambient")]
+    {
+        use esp_hal::analog::adc::{Adc, AdcConfig, Attenuation};
+
+        let mut adc_config = AdcConfig::new();
+        let pin = adc_config.enable_pin(peripherals.GPIO10, Attenuation::_11dB);
+        let adc = Adc::new(peripherals.ADC1, adc_config);
+        STATUS.ambient.set_present();
+        spawner
+            .spawn(sense_ambient_light(
+                &STATUS,
+                adc,
+                pin,
+                catears::ambient::Config::DEFAULT,
+            ))
+            .expect("Failed to spawn ambient light task");
+        info!("Ambient light sensor initialized!");
+    }
+
+    #[cfg(feature = "touch")]
+    {
+        use esp_hal::touch::{Touch, TouchPad};
+
+        let touch = Touch::continuous_mode(peripherals.TOUCH, None);
+        let left = TouchPad::new(peripherals.GPIO12, &touch);
+        let right = TouchPad::new(peripherals.GPIO13, &touch);
+        STATUS.touch.set_present();
+        spawner
+            .spawn(track_touch(
+                &STATE,
+                &STATUS,
+                &GESTURES,
+                &FLASHES,
+                touch,
+                (left, right),
+                catears::touch::Config::DEFAULT,
+            ))
+            .expect("Failed to spawn touch task");
+        info!("Touch pads initialized!");
+    }
 
+    {
+        let mut wdt = TimerGroup::new(peripherals.TIMG0).wdt;
+        wdt.set_timeout(MwdtStage::Stage0, esp_hal::time::Duration::from_secs(5));
+        wdt.set_stage_action(MwdtStage::Stage0, MwdtStageAction::ResetSystem);
+        wdt.enable();
+        spawner
+            .spawn(feed_watchdog(&STATUS, wdt))
+            .expect("Failed to spawn watchdog feeder task");
+        info!("Watchdog initialized!");
+    }
+
+    spawner
+        .spawn(monitor_memory(&STATUS))
+        .expect("Failed to spawn memory monitor task");
     spawner
-        .spawn(control_leds(&STATE, led_ring_left, led_ring_right))
-        .expect("Failed to spawn rainbow LED task");
+        .spawn(track_uptime())
+        .expect("Failed to spawn uptime task");
     spawner
-        .spawn(control_servos(&STATE, servo_left, servo_right))
-        .expect("Failed to spawn servo control task");
+        .spawn(track_state(&STATE))
+        .expect("Failed to spawn state stash task");
     spawner
-        .spawn(control_speakers(&STATE, i2s_tx_left, i2s_tx_right))
-        .expect("Failed to spawn speaker control task");
+        .spawn(report_timing(&STATUS))
+        .expect("Failed to spawn timing report task");
+    spawner
+        .spawn(shed_load(&STATUS, catears::supply::Config::DEFAULT))
+        .expect("Failed to spawn load shedding task");
+
+    {
+        let start = BootInstant::now();
+        let result = esp_hal::tsens::TemperatureSensor::new(
+            peripherals.TSENS,
+            esp_hal::tsens::Config::default(),
+        );
+        // Without the sensor the outputs are never throttled, which is how the firmware ran before it had one.
+        if let Some(sensor) = finish_stage(Stage::Thermal, start, result) {
+            spawner
+                .spawn(monitor_temperature(
+                    &STATUS,
+                    sensor,
+                    catears::thermal::Config::DEFAULT,
+                ))
+                .expect("Failed to spawn thermal monitor task");
+            info!("Temperature sensor initialized!");
+        }
+    }
+
+    {
+        use esp_hal::ledc::{
+            channel::{self, ChannelIFace as _},
+            timer::{self, TimerIFace as _},
+            LSGlobalClkSource, Ledc, LowSpeed,
+        };
+
+        // The XIAO's user LED on GPIO21, dimmed with PWM.
+        let mut ledc = Ledc::new(peripherals.LEDC);
+        ledc.set_global_slow_clock(LSGlobalClkSource::APBClk);
+        let timer = STATUS_LED_TIMER.init(ledc.timer::<LowSpeed>(timer::Number::Timer0));
+        let mut led = ledc.channel(channel::Number::Channel0, peripherals.GPIO21);
+        // The status LED is only a convenience, so failing to set it up is not worth degrading the boot over.
+        if let Err(e) = timer.configure(timer::config::Config {
+            duty: timer::config::Duty::Duty8Bit,
+            clock_source: timer::LSClockSource::APBClk,
+            frequency: Rate::from_khz(1),
+        }) {
+            warn!("Failed to configure status LED timer: {:?}", e);
+        } else if let Err(e) = led.configure(channel::config::Config {
+            timer: &*timer,
+            duty_pct: 100,
+            drive_mode: esp_hal::gpio::DriveMode::PushPull,
+        }) {
+            warn!("Failed to configure status LED channel: {:?}", e);
+        } else {
+            spawner
+                .spawn(indicate_status(
+                    &STATE,
+                    &STATUS,
+                    networking_stack.map(|(stack, _)| stack),
+                    led,
+                ))
+                .expect("Failed to spawn status LED task");
+            info!("Status LED initialized!");
+        }
+    }
+
+    if let Some((stack, start)) = networking_stack {
+        let result = stack
+            .wait_config_up()
+            .with_timeout(NETWORK_BOOT_TIMEOUT)
+            .await
+            .map_err(|_| "WiFi not connected yet, still trying in the background");
+        if finish_stage(Stage::Networking, start, result).is_some() {
+            if let Some(config) = stack.config_v4() {
+                info!("Stack initialized with IP: {}", config.address.address());
+            }
+        }
+    }
+
+    match (
+        networking_stack,
+        LOG_COLLECTOR.map(str::parse::<core::net::SocketAddrV4>),
+    ) {
+        (Some((stack, _)), Some(Ok(collector))) => {
+            spawner
+                .spawn(ship_logs(stack, &STATUS, collector))
+                .expect("Failed to spawn log shipping task");
+            info!("Shipping logs to {}", defmt::Display2Format(&collector));
+        }
+        (_, Some(Err(_))) => warn!("Invalid LOG_COLLECTOR address, logs will not be shipped"),
+        _ => {}
+    }
+
+    let outcome = STATUS.boot.overall();
+    info!("Boot finished: {}", outcome.name());
+    let color = match outcome {
+        Outcome::Ok => BOOT_OK_COLOR,
+        _ => BOOT_DEGRADED_COLOR,
+    };
+    if FLASHES
+        .try_send(catears::lights::flashes::Flash::new(color, BOOT_FLASH_MS))
+        .is_err()
+    {
+        warn!("Flash queue full, dropping boot flash");
+    }
 
     loop {
         Timer::after(embassy_time::Duration::from_millis(50)).await;
     }
 }
 
-static TCP_CLIENT_STATE: StaticCell<TcpClientState<8, 4096, 4096>> = StaticCell::new();
-static TLS_READ_BUFFER: StaticCell<[u8; 4 * 8192]> = StaticCell::new();
-static TLS_WRITE_BUFFER: StaticCell<[u8; 2 * 8192]> = StaticCell::new();
-static RESPONSE_BUFFER: StaticCell<[u8; 8192]> = StaticCell::new();
+/// Collector that logs are shipped to over UDP, as `ip:port`, set at build time through the `LOG_COLLECTOR`
+/// environment variable.
+///
+/// The datagrams carry raw encoded defmt frames, e.g. `socat -u UDP-RECV:<port> - | defmt-print -e <elf> stdin` on the
+/// collector decodes them.
+const LOG_COLLECTOR: Option<&str> = option_env!("LOG_COLLECTOR");
+
+/// Largest datagram of log frames sent to the collector.
+const LOG_DATAGRAM_LEN: usize = 1024;
+
+static LOG_SOCKET_META: StaticCell<[PacketMetadata; 8]> = StaticCell::new();
+static LOG_SOCKET_BUFFER: StaticCell<[u8; 2 * LOG_DATAGRAM_LEN]> = StaticCell::new();
 
+/// Streams the buffered log frames to the log collector while the network is up and shipping is enabled.
 #[embassy_executor::task]
-async fn update_state(
+async fn ship_logs(
     stack: Stack<'static>,
-    mut rng: esp_hal::rng::Trng<'static>,
-    state: &'static RwLock<CriticalSectionRawMutex, catears::state::State>,
-) {
-    let tcp_client_state = TCP_CLIENT_STATE.init(TcpClientState::new());
-    let tcp_client = TcpClient::new(stack, tcp_client_state);
-
-    #[allow(clippy::large_stack_arrays)]
-    let read_buffer = TLS_READ_BUFFER.init([0u8; 4 * 8192]);
-    let write_buffer = TLS_WRITE_BUFFER.init([0u8; 2 * 8192]);
-    let response_buffer = RESPONSE_BUFFER.init([0u8; 8192]);
-    let tls_config = TlsConfig::new(
-        // Note that this is bad randomness since we're casting the u32 to a u64, but we don't really care.
-        u64::from(rng.random()),
-        read_buffer,
-        write_buffer,
-        TlsVerify::None,
+    status: &'static catears::status::Status,
+    collector: core::net::SocketAddrV4,
+) -> ! {
+    use embassy_net::udp::UdpSocket;
+
+    // The socket never receives anything, so it only needs transmit buffers.
+    let mut socket = UdpSocket::new(
+        stack,
+        &mut [],
+        &mut [],
+        LOG_SOCKET_META.init([PacketMetadata::EMPTY; 8]),
+        LOG_SOCKET_BUFFER.init([0u8; 2 * LOG_DATAGRAM_LEN]),
     );
+    if let Err(e) = socket.bind(0) {
+        error!(
+            "Failed to bind log socket, logs will not be shipped: {:?}",
+            e
+        );
+        core::future::pending::<()>().await;
+    }
 
-    let dns_socket = DnsSocket::new(stack);
-    let mut http_client = HttpClient::new_with_tls(&tcp_client, &dns_socket, tls_config);
-
+    let collector = embassy_net::IpEndpoint::new((*collector.ip()).into(), collector.port());
+    let mut datagram = [0u8; LOG_DATAGRAM_LEN];
+    // Only the first of a run of failures is logged, since every log line would otherwise cause another one.
+    let mut failing = false;
     loop {
-        let mut request = http_client
-            .request(
-                reqwless::request::Method::GET,
-                "https://storage.googleapis.com/ziyadedher/catears.json",
-            )
-            .await
-            .expect("Failed to create HTTP request");
-
-        let response = request
-            .send(response_buffer)
-            .with_timeout(embassy_time::Duration::from_secs(1))
-            .await
-            .expect("Timed out waiting for HTTP response")
-            .expect("Failed to send HTTP request or recieve response");
-        debug!("HTTP response status: {}", response.status);
-        let response_body = response
-            .body()
-            .read_to_end()
-            .await
-            .expect("Failed to read HTTP response body");
-        let response_body_str =
-            core::str::from_utf8(response_body).unwrap_or("Invalid UTF-8 response");
-        debug!("HTTP response body: {}", response_body_str);
-
-        match serde_json_core::from_str::<catears::state::State>(response_body_str) {
-            Ok((new_state, _)) => {
-                debug!("Successfully parsed state from JSON");
-                state.write().await.clone_from(&new_state);
-                debug!("State updated from remote");
-            }
-            Err(_e) => {
-                warn!("Failed to parse JSON state");
+        if !stack.is_config_up() || !status.logs.network() {
+            Timer::after(embassy_time::Duration::from_secs(1)).await;
+            continue;
+        }
+        let len = status.logs.take_frames(&mut datagram);
+        if len == 0 {
+            Timer::after(embassy_time::Duration::from_millis(200)).await;
+            continue;
+        }
+        match socket.send_to(&datagram[..len], collector).await {
+            Ok(()) => failing = false,
+            Err(e) if !failing => {
+                warn!("Failed to ship logs: {:?}", e);
+                failing = true;
             }
+            Err(_) => {}
         }
-
-        Timer::after(embassy_time::Duration::from_millis(100)).await;
     }
 }
 
-static AUDIO_BUFFER: StaticCell<[i16; 8192]> = StaticCell::new();
+/// Time the networking boot stage waits for a connection before reporting itself as degraded.
+const NETWORK_BOOT_TIMEOUT: embassy_time::Duration = embassy_time::Duration::from_secs(15);
+
+/// Color flashed on both rings once every boot stage succeeded.
+const BOOT_OK_COLOR: smart_leds::RGB8 = smart_leds::RGB8::new(0, 255, 0);
+
+/// Color flashed on both rings once boot finished with some subsystem degraded.
+const BOOT_DEGRADED_COLOR: smart_leds::RGB8 = smart_leds::RGB8::new(255, 120, 0);
+
+/// Duration of the boot outcome flash.
+const BOOT_FLASH_MS: u16 = 500;
+
+/// Records the result of a boot stage that started at `start` into the boot report.
+///
+/// Returns the stage's value if it succeeded. A failed stage is logged and reported as degraded, and only disables
+/// the subsystem it set up.
+fn finish_stage<T, E: defmt::Format>(
+    stage: Stage,
+    start: BootInstant,
+    result: Result<T, E>,
+) -> Option<T> {
+    let duration_ms = u32::try_from(start.elapsed().as_millis()).unwrap_or(u32::MAX);
+    match result {
+        Ok(value) => {
+            info!("Boot stage {} ok ({}ms)", stage.name(), duration_ms);
+            STATUS.boot.record(stage, Outcome::Ok, duration_ms);
+            Some(value)
+        }
+        Err(e) => {
+            warn!(
+                "Boot stage {} degraded ({}ms): {:?}",
+                stage.name(),
+                duration_ms,
+                e
+            );
+            STATUS.boot.record(stage, Outcome::Degraded, duration_ms);
+            None
+        }
+    }
+}
 
-#[allow(clippy::too_many_lines)]
+/// Pets the hardware watchdog for as long as every supervised task keeps stamping its heartbeat.
+///
+/// When a task goes silent, the feeder logs which one and stops petting so that the watchdog resets the chip.
 #[embassy_executor::task]
-async fn control_speakers(
-    state: &'static RwLock<CriticalSectionRawMutex, catears::state::State>,
-    mut left: I2sTx<'static, esp_hal::Async>,
-    mut right: I2sTx<'static, esp_hal::Async>,
+async fn feed_watchdog(
+    status: &'static catears::status::Status,
+    mut wdt: Wdt<esp_hal::peripherals::TIMG0<'static>>,
 ) -> ! {
-    let audio_buffer = AUDIO_BUFFER.init([0i16; 8192]);
-
-    info!("Speaker control task started");
-
+    let mut reported = false;
     loop {
-        let speaker_state = state.read().await.speakers;
-
-        match speaker_state.mode {
-            catears::audio::Mode::Silent => {
-                debug!("Playing silence");
-                // Send silence
-                audio_buffer.fill(0);
-                let audio_bytes: &mut [u8] = bytemuck::cast_slice_mut(&mut audio_buffer[..]);
-                let _ = left.write_dma_async(audio_bytes).await;
-                let _ = right.write_dma_async(audio_bytes).await;
-                Timer::after(embassy_time::Duration::from_millis(100)).await;
-            }
-            catears::audio::Mode::Tone(note) => {
-                let volume = note.volume.unwrap_or(speaker_state.volume);
-                #[allow(clippy::cast_precision_loss)]
-                let amplitude = (32767.0 * f32::from(volume) / 255.0) * 0.5;
-                debug!(
-                    "Playing tone: frequency={}Hz, duration={}ms, volume={}, amplitude={}",
-                    note.frequency, note.duration_ms, volume, amplitude
-                );
-
-                generate_tone_with_amplitude(
-                    note.frequency,
-                    note.duration_ms,
-                    amplitude,
-                    audio_buffer,
-                    &mut left,
-                    &mut right,
-                )
-                .await;
-            }
-            catears::audio::Mode::Chiptune(sequence) => {
-                debug!(
-                    "Playing chiptune: length={}, looping={}, default_volume={}",
-                    sequence.length, sequence.looping, sequence.default_volume
+        if let Some((task, silent_ms)) = status.heartbeats.stale_task() {
+            if !reported {
+                error!(
+                    "The {} task has not checked in for {}ms, letting the watchdog reset the chip",
+                    task.name(),
+                    silent_ms
                 );
-                // Play a chiptune sequence
-                let default_volume = sequence.default_volume;
-                let master_volume = speaker_state.volume;
-
-                loop {
-                    for (i, note) in sequence.notes[..usize::from(sequence.length)]
-                        .iter()
-                        .enumerate()
-                    {
-                        let note_volume = note.volume.unwrap_or(default_volume);
-                        debug!(
-                            "Playing note {}/{}: frequency={}Hz, duration={}ms, volume={}",
-                            i + 1,
-                            sequence.length,
-                            note.frequency,
-                            note.duration_ms,
-                            note_volume
-                        );
-
-                        // Calculate combined amplitude with master volume
-                        #[allow(clippy::cast_precision_loss)]
-                        let amplitude = (32767.0 * f32::from(note_volume) / 255.0)
-                            * (f32::from(master_volume) / 255.0)
-                            * 0.5;
-
-                        generate_tone_with_amplitude(
-                            note.frequency,
-                            note.duration_ms,
-                            amplitude,
-                            audio_buffer,
-                            &mut left,
-                            &mut right,
-                        )
-                        .await;
-
-                        // Check if mode changed
-                        if state.read().await.speakers.mode != speaker_state.mode {
-                            debug!("Audio mode changed, breaking from note playback");
-                            break;
-                        }
-                    }
-
-                    if !sequence.looping || state.read().await.speakers.mode != speaker_state.mode {
-                        debug!("Chiptune sequence complete or mode changed");
-                        break;
-                    }
-                    debug!("Looping chiptune sequence");
-                }
-            }
-            catears::audio::Mode::Audio(_clip) => {
-                // TODO: Implement raw audio playback
-                warn!("Raw audio playback not yet implemented");
-                Timer::after(embassy_time::Duration::from_millis(100)).await;
+                reported = true;
             }
+        } else {
+            wdt.feed();
         }
+        Timer::after(embassy_time::Duration::from_secs(1)).await;
     }
 }
 
-async fn generate_tone_with_amplitude(
-    frequency: f32,
-    duration_ms: u16,
-    amplitude: f32,
-    audio_buffer: &mut [i16; 8192],
-    left: &mut I2sTx<'static, esp_hal::Async>,
-    right: &mut I2sTx<'static, esp_hal::Async>,
-) {
-    const HARDWARE_SAMPLE_RATE: f32 = 44100.0;
-    const FADE_SAMPLES: usize = 220;
-
-    // Calculate samples needed for this note duration
-    #[allow(
-        clippy::cast_possible_truncation,
-        clippy::cast_sign_loss,
-        clippy::cast_precision_loss
-    )]
-    let total_samples = ((HARDWARE_SAMPLE_RATE * f32::from(duration_ms)) / 1000.0) as usize;
-    let stereo_samples = (total_samples * 2).min(8192);
-
-    // Generate the tone
-    if frequency > 0.0 {
-        for i in 0..stereo_samples / 2 {
-            #[allow(clippy::cast_precision_loss)]
-            let phase = 2.0 * core::f32::consts::PI * frequency * i as f32 / HARDWARE_SAMPLE_RATE;
-            let sine_value = libm::sinf(phase);
-
-            // Apply fade in/out envelope to reduce pops
-            let envelope = calculate_envelope(i, stereo_samples / 2, FADE_SAMPLES);
-
-            #[allow(clippy::cast_possible_truncation)]
-            let sample = (sine_value * amplitude * envelope) as i16;
+/// Free heap in bytes below which a warning is logged.
+const LOW_HEAP_WARNING_BYTES: usize = 8 * 1024;
 
-            audio_buffer[i * 2] = sample; // Left
-            audio_buffer[i * 2 + 1] = sample; // Right
-        }
-    } else {
-        // Generate silence for rests
-        audio_buffer
-            .iter_mut()
-            .take(stereo_samples)
-            .for_each(|sample| *sample = 0);
+/// Periodically samples the heap allocator into the status.
+///
+/// Allocation failures are not caught here: the default allocation error handler panics with the requested size in the
+/// message, which the panic handler forwards to the log.
+#[embassy_executor::task]
+async fn monitor_memory(status: &'static catears::status::Status) -> ! {
+    loop {
+        let stats = esp_alloc::HEAP.stats();
+        status.memory.record(
+            stats.size,
+            stats.current_usage,
+            stats.max_usage,
+            LOW_HEAP_WARNING_BYTES,
+        );
+        Timer::after(embassy_time::Duration::from_secs(5)).await;
     }
+}
 
-    let audio_bytes: &mut [u8] = bytemuck::cast_slice_mut(&mut audio_buffer[..stereo_samples]);
+/// Time between reports of the task that overran its budget the most.
+const TIMING_REPORT_INTERVAL: embassy_time::Duration = embassy_time::Duration::from_secs(60);
 
-    if let Err(e) = left.write_dma_async(audio_bytes).await {
-        info!("Left channel DMA write failed: {:?}", e);
-    }
-    if let Err(e) = right.write_dma_async(audio_bytes).await {
-        info!("Right channel DMA write failed: {:?}", e);
+/// Periodically logs the task that overran its iteration budget the most, so overruns show up in shipped logs.
+///
+/// Nothing is logged while no task overran since the last report.
+#[embassy_executor::task]
+async fn report_timing(status: &'static catears::status::Status) -> ! {
+    let mut reported_overruns = 0;
+    loop {
+        Timer::after(TIMING_REPORT_INTERVAL).await;
+        let Some((task, stats)) = status.timing.worst() else {
+            reported_overruns = 0;
+            continue;
+        };
+        if stats.overruns != reported_overruns {
+            warn!(
+                "Timing: {} overran its {} us budget {} times, longest {} us",
+                task.name(),
+                catears::timing::budget_us(task),
+                stats.overruns,
+                stats.max_us
+            );
+            reported_overruns = stats.overruns;
+        }
     }
-
-    Timer::after(embassy_time::Duration::from_millis(duration_ms.into())).await;
 }
 
-fn calculate_envelope(sample_index: usize, total_samples: usize, fade_samples: usize) -> f32 {
-    if sample_index < fade_samples {
-        // Fade in
-        #[allow(clippy::cast_precision_loss)]
-        let fade_progress = sample_index as f32 / fade_samples as f32;
-        fade_progress
-    } else if sample_index > total_samples.saturating_sub(fade_samples) {
-        // Fade out
-        #[allow(clippy::cast_precision_loss)]
-        let fade_progress = (total_samples - sample_index) as f32 / fade_samples as f32;
-        fade_progress
-    } else {
-        // Full volume
-        1.0
-    }
-}
+/// Periodically reads the chip temperature into the status and throttles the outputs when it gets too hot.
+///
+/// Every change of thermal level is logged, so throttling never goes unnoticed.
+#[embassy_executor::task]
+async fn monitor_temperature(
+    status: &'static catears::status::Status,
+    sensor: esp_hal::tsens::TemperatureSensor<'static>,
+    config: catears::thermal::Config,
+) -> ! {
+    use catears::thermal::{Governor, Level};
+
+    let mut governor = Governor::new(config);
+    loop {
+        #[allow(clippy::cast_possible_truncation)]
+        let celsius = libm::roundf(sensor.get_temperature().to_celsius()) as i16;
+        let previous = governor.level();
+        let level = governor.update(celsius);
+        status.thermal.publish(celsius, &governor);
+
+        match level {
+            _ if level == previous => {}
+            Level::Critical => error!(
+                "Thermal: chip at {}C, forcing the outputs off until it cools down",
+                celsius
+            ),
+            Level::Throttled => warn!(
+                "Thermal: chip at {}C, capping LED brightness at {}",
+                celsius, config.throttle_brightness
+            ),
+            Level::Normal => info!("Thermal: chip cooled to {}C, throttling lifted", celsius),
+        }
+
+        Timer::after_millis(config.sample_interval_ms).await;
+    }
+}
+
+static STATUS_LED_TIMER: StaticCell<esp_hal::ledc::timer::Timer<'static, esp_hal::ledc::LowSpeed>> =
+    StaticCell::new();
+
+/// Plays the blink pattern of the current indication on the on-board status LED.
+///
+/// The indication is picked again after every full pattern, so changes show up within about a second.
+#[embassy_executor::task]
+async fn indicate_status(
+    state: &'static RwLock<CriticalSectionRawMutex, catears::state::State>,
+    status: &'static catears::status::Status,
+    stack: Option<Stack<'static>>,
+    led: esp_hal::ledc::channel::Channel<'static, esp_hal::ledc::LowSpeed>,
+) -> ! {
+    use catears::indicator::Indication;
+    use esp_hal::ledc::channel::ChannelIFace as _;
+
+    let mut previous = None;
+    loop {
+        let indication = {
+            let state = state.read().await;
+            Indication::select(
+                state.status_led && !status.sleep.is_entering(),
+                state.power && !status.thermal.is_critical(),
+                stack.is_some_and(|stack| stack.is_config_up()),
+                status.remote.consecutive_failures(),
+            )
+        };
+        if previous != Some(indication) {
+            debug!("Status LED: {}", indication);
+            previous = Some(indication);
+        }
+
+        for step in indication.pattern() {
+            // The LED is wired active low, so a full duty cycle turns it off.
+            if let Err(e) = led.set_duty(100 - step.brightness_pct) {
+                debug!("Failed to set status LED duty: {:?}", e);
+            }
+            Timer::after_millis(u64::from(step.duration_ms)).await;
+        }
+    }
+}
+
+/// Time given to the outputs to wind down before going to sleep.
+const SLEEP_WIND_DOWN: embassy_time::Duration = embassy_time::Duration::from_secs(1);
+
+/// Time allowed for disconnecting from WiFi before going to sleep regardless.
+const SLEEP_DISCONNECT_TIMEOUT: embassy_time::Duration = embassy_time::Duration::from_secs(3);
+
+/// Handles the button while awake and puts the device into deep sleep once asked to or once it has been idle for long
+/// enough.
+///
+/// The button lives here rather than in a task of its own because its pin doubles as the deep sleep wake source.
+#[embassy_executor::task]
+async fn manage_sleep(
+    state: &'static RwLock<CriticalSectionRawMutex, catears::state::State>,
+    status: &'static catears::status::Status,
+    gestures: &'static catears::servo::gestures::GestureChannel,
+    mut rtc: Rtc<'static>,
+    wake_button: Option<esp_hal::peripherals::GPIO6<'static>>,
+) -> ! {
+    use esp_hal::rtc_cntl::sleep::{Ext0WakeupSource, TimerWakeupSource, WakeSource, WakeupLevel};
+
+    let sleep = wait_for_sleep(state, status);
+    #[cfg(feature = "button")]
+    let mut wake_button = wake_button;
+    #[cfg(feature = "button")]
+    let sleep = async {
+        if let Some(pin) = wake_button.as_mut() {
+            let button = esp_hal::gpio::Input::new(
+                pin.reborrow(),
+                esp_hal::gpio::InputConfig::default().with_pull(esp_hal::gpio::Pull::Up),
+            );
+            info!("Button initialized!");
+            embassy_futures::select::select(
+                handle_button(state, gestures, button, catears::input::Config::DEFAULT),
+                sleep,
+            )
+            .await;
+        } else {
+            sleep.await;
+        }
+    };
+    #[cfg(not(feature = "button"))]
+    let _ = gestures;
+    sleep.await;
+
+    info!("Going to sleep...");
+    status.sleep.begin();
+    Timer::after(SLEEP_WIND_DOWN).await;
+
+    let current = *state.read().await;
+    if !stash_state(&current) {
+        warn!("State does not fit in RTC memory, it will be lost while asleep");
+    }
+
+    if catears::networking::disconnect()
+        .with_timeout(SLEEP_DISCONNECT_TIMEOUT)
+        .await
+        .is_err()
+    {
+        warn!("Timed out disconnecting from WiFi");
+    }
+
+    let timer = (current.sleep.wake_after_s > 0).then(|| {
+        TimerWakeupSource::new(core::time::Duration::from_secs(u64::from(
+            current.sleep.wake_after_s,
+        )))
+    });
+    // The button pulls the pin low when pressed.
+    let button = wake_button.map(|pin| Ext0WakeupSource::new(pin, WakeupLevel::Low));
+    let mut sources: heapless::Vec<&dyn WakeSource, 2> = heapless::Vec::new();
+    if let Some(timer) = &timer {
+        let _ = sources.push(timer);
+    }
+    if let Some(button) = &button {
+        let _ = sources.push(button);
+    }
+
+    info!("Entering deep sleep");
+    rtc.sleep_deep(&sources)
+}
+
+/// Waits until sleep is requested from the command line or the state has been idle for its configured timeout.
+///
+/// Requests are ignored when nothing could wake the device up again.
+async fn wait_for_sleep(
+    state: &'static RwLock<CriticalSectionRawMutex, catears::state::State>,
+    status: &'static catears::status::Status,
+) {
+    let mut idle = catears::sleep::IdleTimer::new();
+    loop {
+        let current = *state.read().await;
+        let requested = status.sleep.take_request();
+        let idle_expired = idle.update(&current, embassy_time::Instant::now().as_millis());
+        if (requested || idle_expired) && status.sleep.can_wake(current.sleep.wake_after_s) {
+            if idle_expired {
+                info!("Idle for {}s", current.sleep.idle_timeout_s);
+            }
+            return;
+        }
+        Timer::after(embassy_time::Duration::from_secs(1)).await;
+    }
+}
+
+/// Chiptunes of this firmware's own, played by name alongside the predefined ones.
+static CUSTOM_CHIPTUNES: [catears::audio::chiptunes::Custom; 1] =
+    [catears::audio::chiptunes::Custom {
+        name: "meow",
+        sequence: meow,
+    }];
+
+/// Rising then falling two-note call, like a short meow.
+fn meow() -> catears::audio::ChiptuneSequence {
+    use catears::audio::{Note, Vibrato, Waveform};
+
+    catears::audio::ChiptuneSequence::from_notes(&[
+        Note::new(660.0, 120).with_waveform(Waveform::Triangle),
+        Note::new(880.0, 150).with_waveform(Waveform::Triangle),
+        Note::new(620.0, 300)
+            .with_waveform(Waveform::Triangle)
+            .with_vibrato(Vibrato::new(30, 5.0)),
+    ])
+}
+
+/// Gesture played on both ears when the button is double pressed.
+#[cfg(feature = "button")]
+const BUTTON_EMOTE: catears::servo::gestures::Gesture = catears::servo::gestures::Gesture::Wiggle;
+
+/// Classifies presses of the physical button and applies the corresponding actions.
+///
+/// A short press cycles both rings through the light presets, a long press toggles the power switch, and a double press
+/// plays [`BUTTON_EMOTE`].
+#[cfg(feature = "button")]
+async fn handle_button(
+    state: &'static RwLock<CriticalSectionRawMutex, catears::state::State>,
+    gestures: &'static catears::servo::gestures::GestureChannel,
+    mut pin: esp_hal::gpio::Input<'_>,
+    config: catears::input::Config,
+) -> ! {
+    use catears::input::{Button, Event};
+    use catears::servo::gestures::{GestureRequest, Target};
+    use embassy_futures::select::{select, Either};
+    use embassy_time::Instant;
+
+    let mut button = Button::new(config);
+
+    loop {
+        let edge = if let Some(deadline) = button.deadline() {
+            let timeout = Timer::at(Instant::from_millis(deadline));
+            matches!(
+                select(pin.wait_for_any_edge(), timeout).await,
+                Either::First(())
+            )
+        } else {
+            pin.wait_for_any_edge().await;
+            true
+        };
+
+        let event = if edge {
+            let now = Instant::now().as_millis();
+            // Let the contacts settle before sampling the level.
+            Timer::after_millis(config.debounce_ms).await;
+            button.on_level(pin.is_low(), now)
+        } else {
+            button.on_tick(Instant::now().as_millis())
+        };
+
+        match event {
+            Some(Event::ShortPress) => {
+                info!("Button short press, switching to the next light preset");
+                state.write().await.next_light_preset();
+                catears::state::CHANGED.signal(());
+            }
+            Some(Event::LongPress) => {
+                let mut state = state.write().await;
+                state.toggle_power();
+                catears::state::CHANGED.signal(());
+                info!(
+                    "Button long press, turned power {}",
+                    if state.power { "on" } else { "off" }
+                );
+            }
+            Some(Event::DoublePress) => {
+                info!("Button double press, playing {}", BUTTON_EMOTE.name());
+                let request = GestureRequest {
+                    target: Target::Both,
+                    gesture: BUTTON_EMOTE,
+                };
+                if gestures.try_send(request).is_err() {
+                    warn!("Gesture queue full, dropping button emote");
+                }
+            }
+            None => {}
+        }
+    }
+}
+
+static TCP_CLIENT_STATE: StaticCell<TcpClientState<8, 4096, 4096>> = StaticCell::new();
+
+/// Length in bytes of the TLS record buffers, and of the buffer the HTTP response is read into.
+const TLS_READ_BUFFER_LEN: usize = 4 * 8192;
+const TLS_WRITE_BUFFER_LEN: usize = 2 * 8192;
+const RESPONSE_BUFFER_LEN: usize = 8192;
+
+#[cfg(not(feature = "psram"))]
+static TLS_READ_BUFFER: StaticCell<[u8; TLS_READ_BUFFER_LEN]> = StaticCell::new();
+#[cfg(not(feature = "psram"))]
+static TLS_WRITE_BUFFER: StaticCell<[u8; TLS_WRITE_BUFFER_LEN]> = StaticCell::new();
+#[cfg(not(feature = "psram"))]
+static RESPONSE_BUFFER: StaticCell<[u8; RESPONSE_BUFFER_LEN]> = StaticCell::new();
+
+/// Allocates a zeroed buffer of `len` bytes in PSRAM that lives for the rest of the program.
+///
+/// Only for buffers the CPU works on: DMA descriptors and the buffers DMA reads from must stay in internal RAM.
+#[cfg(feature = "psram")]
+#[allow(
+    unsafe_code,
+    reason = "esp-alloc only hands out raw allocations from a specific memory region"
+)]
+fn psram_buffer(len: usize) -> &'static mut [u8] {
+    let layout = core::alloc::Layout::array::<u8>(len).expect("PSRAM buffer too large");
+    // SAFETY: `layout` is non-zero in size for every buffer allocated here, and the allocation is checked for failure
+    // and fully initialized before it is handed out. It is never freed, so it is valid for `'static`.
+    unsafe {
+        let buffer =
+            esp_alloc::HEAP.alloc_caps(esp_alloc::MemoryCapability::External.into(), layout);
+        assert!(!buffer.is_null(), "Out of PSRAM allocating {} bytes", len);
+        buffer.write_bytes(0, len);
+        core::slice::from_raw_parts_mut(buffer, len)
+    }
+}
+
+/// Why fetching the remote state failed.
+#[derive(Debug, defmt::Format)]
+enum FetchError {
+    /// The HTTP request could not be sent or its response could not be read.
+    Http(reqwless::Error),
+    /// No response arrived in time.
+    Timeout,
+    /// The response was not a valid state.
+    Parse,
+}
+
+/// Longest entity tag kept for conditional requests. Longer tags are ignored.
+const ETAG_LEN: usize = 64;
+
+/// HTTP status of a conditional request whose resource has not changed.
+const HTTP_NOT_MODIFIED: u16 = 304;
+
+/// Polls the remote state and applies it, polling quickly after changes and backing off while nothing changes.
+#[embassy_executor::task]
+async fn update_state(
+    stack: Stack<'static>,
+    tls_seed: u64,
+    state: &'static RwLock<CriticalSectionRawMutex, catears::state::State>,
+    status: &'static catears::status::Status,
+) {
+    let tcp_client_state = TCP_CLIENT_STATE.init(TcpClientState::new());
+    let tcp_client = TcpClient::new(stack, tcp_client_state);
+
+    // None of these is touched by DMA, the network stack copies in and out of them, so they can live in PSRAM.
+    #[cfg(feature = "psram")]
+    let (read_buffer, write_buffer, response_buffer) = (
+        psram_buffer(TLS_READ_BUFFER_LEN),
+        psram_buffer(TLS_WRITE_BUFFER_LEN),
+        psram_buffer(RESPONSE_BUFFER_LEN),
+    );
+    #[cfg(not(feature = "psram"))]
+    #[allow(clippy::large_stack_arrays)]
+    let (read_buffer, write_buffer, response_buffer) = (
+        TLS_READ_BUFFER.init([0u8; TLS_READ_BUFFER_LEN]),
+        TLS_WRITE_BUFFER.init([0u8; TLS_WRITE_BUFFER_LEN]),
+        RESPONSE_BUFFER.init([0u8; RESPONSE_BUFFER_LEN]),
+    );
+    let tls_config = TlsConfig::new(tls_seed, read_buffer, write_buffer, TlsVerify::None);
+
+    let dns_socket = DnsSocket::new(stack);
+    let mut http_client = HttpClient::new_with_tls(&tcp_client, &dns_socket, tls_config);
+
+    let mut schedule = catears::polling::Schedule::new();
+    let mut seen = *state.read().await;
+    // Entity tag of the last remote state, so that an unchanged state costs a 304 instead of a download.
+    let mut etag = heapless::String::<ETAG_LEN>::new();
+    loop {
+        if status.sleep.is_entering() {
+            // The network is about to go away, stop polling so that it can be torn down cleanly.
+            core::future::pending::<()>().await;
+        }
+        status.heartbeats.stamp(Task::UpdateState);
+        if !stack.is_config_up() {
+            // Keep checking in while the network task (re)connects.
+            Timer::after(embassy_time::Duration::from_secs(1)).await;
+            continue;
+        }
+
+        let started = embassy_time::Instant::now();
+        let result = async {
+            let conditional = [("If-None-Match", etag.as_str())];
+            let mut request = http_client
+                .request(
+                    reqwless::request::Method::GET,
+                    "https://storage.googleapis.com/ziyadedher/catears.json",
+                )
+                .await
+                .map_err(FetchError::Http)?
+                .headers(if etag.is_empty() {
+                    &conditional[..0]
+                } else {
+                    &conditional[..]
+                });
+            let response = request
+                .send(&mut response_buffer[..])
+                .with_timeout(embassy_time::Duration::from_secs(1))
+                .await
+                .map_err(|_| FetchError::Timeout)?
+                .map_err(FetchError::Http)?;
+            debug!("HTTP response status: {}", response.status);
+            if response.status.0 == HTTP_NOT_MODIFIED {
+                return Ok(None);
+            }
+            let new_etag = response
+                .headers()
+                .find(|(name, _)| name.eq_ignore_ascii_case("etag"))
+                .and_then(|(_, value)| core::str::from_utf8(value).ok())
+                .and_then(|value| value.try_into().ok())
+                .unwrap_or_default();
+            let response_body = response
+                .body()
+                .read_to_end()
+                .await
+                .map_err(FetchError::Http)?;
+            let response_body_str =
+                core::str::from_utf8(response_body).unwrap_or("Invalid UTF-8 response");
+            debug!("HTTP response body: {}", response_body_str);
+            serde_json_core::from_str::<catears::state::State>(response_body_str)
+                .map(|(new_state, _)| Some((new_state, new_etag)))
+                .map_err(|_| FetchError::Parse)
+        }
+        .await;
+
+        if let Some(Some((new_state, new_etag))) =
+            status.remote.record("Remote state fetch", result)
+        {
+            etag = new_etag;
+            let mut current = state.write().await;
+            if *current != new_state {
+                current.clone_from(&new_state);
+                catears::state::CHANGED.signal(());
+                debug!("State updated from remote");
+            }
+        }
+
+        // Changes from the command line or the button count just like changes from the remote.
+        let current = *state.read().await;
+        let now_ms = embassy_time::Instant::now().as_millis();
+        let interval_ms = if current == seen {
+            let interval_ms = schedule.unchanged(&current.polling, now_ms);
+            debug!("State unchanged, polling again in {} ms", interval_ms);
+            interval_ms
+        } else {
+            seen = current;
+            let interval_ms = schedule.changed(&current.polling, now_ms);
+            debug!(
+                "State changed, polling every {} ms for a while",
+                interval_ms
+            );
+            interval_ms
+        };
+        status.timing.record(Task::UpdateState, started);
+        Timer::after_millis(u64::from(interval_ms)).await;
+    }
+}
+
+/// Links between each speaker and the task that streams its remote clips, left first.
+static CLIP_LINKS: [catears::audio::stream::Link; 2] = [
+    catears::audio::stream::Link::new(),
+    catears::audio::stream::Link::new(),
+];
+
+/// Returns the link the speaker on `side` streams its remote clips through.
+fn clip_link(side: catears::audio::Side) -> &'static catears::audio::stream::Link {
+    match side {
+        catears::audio::Side::Left => &CLIP_LINKS[0],
+        catears::audio::Side::Right => &CLIP_LINKS[1],
+    }
+}
+
+/// Length in bytes of the TLS record buffers for streaming clips. Reading needs room for a whole record of the largest
+/// size, while writing only sends the request.
+const CLIP_TLS_READ_BUFFER_LEN: usize = 16_640;
+const CLIP_TLS_WRITE_BUFFER_LEN: usize = 4096;
+/// Length in bytes of the buffer the response headers of a clip are read into.
+const CLIP_RESPONSE_BUFFER_LEN: usize = 2048;
+/// Length in bytes of each chunk of a clip passed on to the speaker.
+const CLIP_CHUNK_LEN: usize = 512;
+
+/// How long connecting, or each chunk of a clip, may take before the download counts as failed.
+const CLIP_TIMEOUT: embassy_time::Duration = embassy_time::Duration::from_secs(5);
+
+static CLIP_TCP_CLIENT_STATES: [StaticCell<TcpClientState<1, 1024, 4096>>; 2] =
+    [StaticCell::new(), StaticCell::new()];
+
+#[cfg(not(feature = "psram"))]
+#[allow(clippy::type_complexity)]
+static CLIP_BUFFERS: [StaticCell<(
+    [u8; CLIP_TLS_READ_BUFFER_LEN],
+    [u8; CLIP_TLS_WRITE_BUFFER_LEN],
+    [u8; CLIP_RESPONSE_BUFFER_LEN],
+)>; 2] = [StaticCell::new(), StaticCell::new()];
+
+/// Why streaming a remote clip failed.
+#[derive(Debug, defmt::Format)]
+enum StreamError {
+    /// The network is down, or about to go down for sleep.
+    Offline,
+    /// The HTTP request could not be sent or its response could not be read.
+    Http(reqwless::Error),
+    /// The server took too long to connect or to send the next chunk.
+    Timeout,
+    /// The server answered with an HTTP status other than success, such as 404 for a clip that does not exist.
+    Status(u16),
+    /// The speaker no longer wants the clip.
+    Stopped,
+}
+
+/// Downloads the remote clips the speaker on `side` asks for into its [`clip_link`], one at a time.
+#[embassy_executor::task(pool_size = 2)]
+async fn stream_clips(
+    stack: Stack<'static>,
+    tls_seed: u64,
+    side: catears::audio::Side,
+    status: &'static catears::status::Status,
+) {
+    let index = match side {
+        catears::audio::Side::Left => 0,
+        catears::audio::Side::Right => 1,
+    };
+    let tcp_client_state = CLIP_TCP_CLIENT_STATES[index].init(TcpClientState::new());
+    let tcp_client = TcpClient::new(stack, tcp_client_state);
+
+    #[cfg(feature = "psram")]
+    let (read_buffer, write_buffer, response_buffer) = (
+        psram_buffer(CLIP_TLS_READ_BUFFER_LEN),
+        psram_buffer(CLIP_TLS_WRITE_BUFFER_LEN),
+        psram_buffer(CLIP_RESPONSE_BUFFER_LEN),
+    );
+    #[cfg(not(feature = "psram"))]
+    #[allow(clippy::large_stack_arrays)]
+    let (read_buffer, write_buffer, response_buffer) = CLIP_BUFFERS[index].init((
+        [0u8; CLIP_TLS_READ_BUFFER_LEN],
+        [0u8; CLIP_TLS_WRITE_BUFFER_LEN],
+        [0u8; CLIP_RESPONSE_BUFFER_LEN],
+    ));
+    let tls_config = TlsConfig::new(tls_seed, read_buffer, write_buffer, TlsVerify::None);
+
+    let dns_socket = DnsSocket::new(stack);
+    let mut http_client = HttpClient::new_with_tls(&tcp_client, &dns_socket, tls_config);
+
+    let link = clip_link(side);
+    loop {
+        let request = link.requested().await;
+        let url = catears::audio::stream::url(request.id);
+        debug!("Streaming {} to the {} speaker", url.as_str(), side.name());
+
+        let result = async {
+            if status.sleep.is_entering() || !stack.is_config_up() {
+                return Err(StreamError::Offline);
+            }
+            let mut http_request = http_client
+                .request(reqwless::request::Method::GET, &url)
+                .with_timeout(CLIP_TIMEOUT)
+                .await
+                .map_err(|_| StreamError::Timeout)?
+                .map_err(StreamError::Http)?;
+            let response = http_request
+                .send(&mut response_buffer[..])
+                .with_timeout(CLIP_TIMEOUT)
+                .await
+                .map_err(|_| StreamError::Timeout)?
+                .map_err(StreamError::Http)?;
+            if !response.status.is_successful() {
+                return Err(StreamError::Status(response.status.0));
+            }
+
+            let mut body = response.body().reader();
+            let mut chunk = [0u8; CLIP_CHUNK_LEN];
+            loop {
+                let len = body
+                    .read(&mut chunk)
+                    .with_timeout(CLIP_TIMEOUT)
+                    .await
+                    .map_err(|_| StreamError::Timeout)?
+                    .map_err(StreamError::Http)?;
+                if len == 0 {
+                    return Ok(());
+                }
+                if !link.write(request, &chunk[..len]).await {
+                    return Err(StreamError::Stopped);
+                }
+            }
+        }
+        .await;
+
+        match result {
+            Ok(()) => {
+                debug!(
+                    "Finished streaming clip {} to the {} speaker",
+                    request.id,
+                    side.name()
+                );
+                link.finish(request, true);
+            }
+            Err(StreamError::Stopped) => {
+                debug!(
+                    "The {} speaker stopped streaming clip {}",
+                    side.name(),
+                    request.id
+                );
+            }
+            Err(error) => {
+                warn!(
+                    "Failed to stream clip {} to the {} speaker: {}",
+                    request.id,
+                    side.name(),
+                    error
+                );
+                link.finish(request, false);
+            }
+        }
+    }
+}
+
+/// Samples the accelerometer, publishes the readings, and triggers the configured reactions to head motion.
+#[cfg(feature = "imu")]
+#[embassy_executor::task]
+async fn track_motion(
+    status: &'static catears::status::Status,
+    gestures: &'static catears::servo::gestures::GestureChannel,
+    flashes: &'static catears::lights::flashes::FlashChannel,
+    mut imu: catears::motion::Imu<esp_hal::i2c::master::I2c<'static, esp_hal::Async>>,
+    config: catears::motion::Config,
+) -> ! {
+    use embassy_time::{Duration, Instant, Ticker};
+
+    let mut tracker = catears::motion::Tracker::new(config);
+    let mut ticker = Ticker::every(Duration::from_millis(config.sample_interval_ms));
+
+    loop {
+        status.heartbeats.stamp(Task::Motion);
+        ticker.next().await;
+        let started = Instant::now();
+
+        if status.motion.take_calibration_request() {
+            info!("Calibrating IMU");
+            tracker.calibrate();
+        }
+        let calibrating = tracker.is_calibrating();
+
+        let Some(reading) = status.imu.record("IMU", imu.read().await) else {
+            continue;
+        };
+        let sample = tracker.update(reading, Instant::now().as_millis());
+        status.motion.publish(&sample);
+        status.timing.record(Task::Motion, started);
+        if calibrating && !tracker.is_calibrating() {
+            info!("IMU calibrated, offset {:?}", tracker.offset());
+        }
+
+        let Some(event) = sample.event else {
+            continue;
+        };
+        debug!("Motion event {:?}", event);
+        send_reaction(gestures, flashes, config.reaction(event), "motion");
+    }
+}
+
+/// Samples both touch pads, publishes the readings, and triggers the configured reactions to petting.
+#[cfg(feature = "touch")]
+#[embassy_executor::task]
+async fn track_touch(
+    state: &'static RwLock<CriticalSectionRawMutex, catears::state::State>,
+    status: &'static catears::status::Status,
+    gestures: &'static catears::servo::gestures::GestureChannel,
+    flashes: &'static catears::lights::flashes::FlashChannel,
+    // Owning the peripheral keeps the pads measuring.
+    _touch: esp_hal::touch::Touch<'static, esp_hal::touch::Continuous, esp_hal::Blocking>,
+    pads: (
+        esp_hal::touch::TouchPad<
+            esp_hal::peripherals::GPIO12<'static>,
+            esp_hal::touch::Continuous,
+            esp_hal::Blocking,
+        >,
+        esp_hal::touch::TouchPad<
+            esp_hal::peripherals::GPIO13<'static>,
+            esp_hal::touch::Continuous,
+            esp_hal::Blocking,
+        >,
+    ),
+    config: catears::touch::Config,
+) -> ! {
+    use embassy_time::{Duration, Instant, Ticker};
+
+    let (mut left, mut right) = pads;
+    let mut trackers = [catears::touch::Tracker::new(config); 2];
+    let mut ticker = Ticker::every(Duration::from_millis(config.sample_interval_ms));
+
+    loop {
+        status.heartbeats.stamp(Task::Touch);
+        ticker.next().await;
+        let started = Instant::now();
+
+        if status.touch.take_calibration_request() {
+            info!("Calibrating touch pads");
+            trackers
+                .iter_mut()
+                .for_each(catears::touch::Tracker::calibrate);
+        }
+
+        let touch = state.read().await.touch;
+        let now_ms = Instant::now().as_millis();
+        for (index, raw) in [left.read(), right.read()].into_iter().enumerate() {
+            let sample = trackers[index].update(raw, touch.threshold_pct, now_ms);
+            status.touch.publish(index, &sample);
+            if let Some(event) = sample.event {
+                debug!("Touch event {:?} on pad {}", event, index);
+                send_reaction(gestures, flashes, touch.reaction(event), "touch");
+            }
+        }
+        status.timing.record(Task::Touch, started);
+    }
+}
+
+/// Samples the ambient light sensor and publishes the brightness scale it calls for.
+#[cfg(feature = "ambient")]
+#[embassy_executor::task]
+async fn sense_ambient_light(
+    status: &'static catears::status::Status,
+    mut adc: esp_hal::analog::adc::Adc<
+        'static,
+        esp_hal::peripherals::ADC1<'static>,
+        esp_hal::Blocking,
+    >,
+    mut pin: esp_hal::analog::adc::AdcPin<
+        esp_hal::peripherals::GPIO10<'static>,
+        esp_hal::peripherals::ADC1<'static>,
+    >,
+    config: catears::ambient::Config,
+) -> ! {
+    let mut dimmer = catears::ambient::Dimmer::new(config);
+    loop {
+        let previous = dimmer.scale();
+        let scale = dimmer.update(adc.read_blocking(&mut pin));
+        status.ambient.publish(&dimmer);
+        if scale != previous {
+            debug!(
+                "Ambient light at {}, brightness scale {}",
+                dimmer.level(),
+                scale
+            );
+        }
+        Timer::after_millis(config.sample_interval_ms).await;
+    }
+}
+
+/// Plays a reaction on both ears, dropping whatever does not fit in the queues.
+#[cfg(any(feature = "imu", feature = "touch"))]
+fn send_reaction(
+    gestures: &'static catears::servo::gestures::GestureChannel,
+    flashes: &'static catears::lights::flashes::FlashChannel,
+    reaction: catears::motion::Reaction,
+    source: &str,
+) {
+    use catears::servo::gestures::{GestureRequest, Target};
+
+    if let Some(gesture) = reaction.gesture {
+        let request = GestureRequest {
+            target: Target::Both,
+            gesture,
+        };
+        if gestures.try_send(request).is_err() {
+            warn!("Gesture queue full, dropping {} reaction", source);
+        }
+    }
+    if let Some(flash) = reaction.flash {
+        if flashes.try_send(flash).is_err() {
+            warn!("Flash queue full, dropping {} reaction", source);
+        }
+    }
+}
+
+/// Number of interleaved samples read from the microphone per DMA transfer (about 12 ms at 44.1 kHz stereo).
+#[cfg(feature = "microphone")]
+const MIC_BUFFER_LEN: usize = 1024;
+
+#[cfg(feature = "microphone")]
+static MIC_BUFFER: StaticCell<[Sample; MIC_BUFFER_LEN]> = StaticCell::new();
+
+/// Continuously reads the microphone and publishes its level for the sound-reactive light and servo modes.
+#[cfg(feature = "microphone")]
+#[embassy_executor::task]
+async fn listen(
+    status: &'static catears::status::Status,
+    level: &'static catears::audio::dsp::AudioLevel,
+    mut rx: esp_hal::i2s::master::I2sRx<'static, esp_hal::Async>,
+) -> ! {
+    let buffer = MIC_BUFFER.init([0; MIC_BUFFER_LEN]);
+    let mut meter = catears::audio::dsp::LevelMeter::new();
+
+    loop {
+        let result = rx
+            .read_dma_async(bytemuck::cast_slice_mut(buffer.as_mut_slice()))
+            .await;
+        if status.microphone.record("Microphone", result).is_none() {
+            Timer::after(embassy_time::Duration::from_millis(100)).await;
+            continue;
+        }
+        // A mono mic with L/R tied low only drives the left slot of each frame.
+        level.publish(
+            meter.process(
+                buffer
+                    .iter()
+                    .step_by(2)
+                    .map(|&sample| catears::audio::synth::narrow(sample)),
+            ),
+        );
+    }
+}
+
+/// Length in samples of each audio buffer, interleaved left and right (about 46 ms at 44.1 kHz stereo).
+const AUDIO_BUFFER_LEN: usize = 4096;
+
+/// Layout of the samples on the I2S bus of the speakers (and the microphone), one [`Sample`] per slot.
+#[cfg(not(feature = "dac-32bit"))]
+const DATA_FORMAT: esp_hal::i2s::master::DataFormat =
+    esp_hal::i2s::master::DataFormat::Data16Channel16;
+
+/// Layout of the samples on the I2S bus of the speakers (and the microphone), one [`Sample`] per slot.
+#[cfg(feature = "dac-32bit")]
+const DATA_FORMAT: esp_hal::i2s::master::DataFormat =
+    esp_hal::i2s::master::DataFormat::Data32Channel32;
+
+/// Audio buffers of the left and right speakers, two each. Each speaker task fills one of its buffers while the DMA
+/// plays the other.
+///
+/// The I2S DMA reads straight out of these, so they stay in internal RAM even with PSRAM.
+static AUDIO_BUFFERS: StaticCell<[[[Sample; AUDIO_BUFFER_LEN]; 2]; 2]> = StaticCell::new();
+
+/// Ids of the last sound events the left and right speakers played, see [`catears::audio::Event`].
+static PLAYED_EVENTS: [portable_atomic::AtomicU32; 2] =
+    [const { portable_atomic::AtomicU32::new(0) }; 2];
+
+/// Returns the id of the last sound event the speaker on `side` played.
+fn played_event(side: catears::audio::Side) -> &'static portable_atomic::AtomicU32 {
+    match side {
+        catears::audio::Side::Left => &PLAYED_EVENTS[0],
+        catears::audio::Side::Right => &PLAYED_EVENTS[1],
+    }
+}
+
+/// Plays the audio mode of the speaker on `side`.
+///
+/// Each speaker runs a task of its own, so a long chiptune on one side never holds up the other.
+#[embassy_executor::task(pool_size = 2)]
+async fn control_speaker(
+    state: &'static RwLock<CriticalSectionRawMutex, catears::state::State>,
+    status: &'static catears::status::Status,
+    side: catears::audio::Side,
+    mut output: SpeakerOutput,
+    buffers: &'static mut [[Sample; AUDIO_BUFFER_LEN]; 2],
+) -> ! {
+    info!("Speaker control task started on the {} side", side.name());
+
+    // An event sent before the speaker started is old news.
+    if let Some(event) = state.read().await.speakers.event {
+        played_event(side).store(event.id, Ordering::Relaxed);
+    }
+
+    loop {
+        match &mut output {
+            SpeakerOutput::Fixed(tx) => {
+                let rate = catears::audio::synth::SAMPLE_RATE;
+                while play_next(tx, rate, false, buffers, state, status, side).await {}
+            }
+            SpeakerOutput::Rebuilt {
+                peripherals,
+                descriptors,
+            } => {
+                let rate = {
+                    let state = state.read().await;
+                    catears::audio::synth::output_rate(&audio_mode(&state, status, side))
+                };
+                debug!("Starting the {} speaker at {}Hz", side.name(), rate);
+                // The output only borrows the peripherals, and gives them back once the next mode needs another rate.
+                let mut tx = peripherals.build(rate, descriptors);
+                while play_next(&mut tx, rate, true, buffers, state, status, side).await {}
+            }
+        }
+    }
+}
+
+/// Plays what is next on the speaker on `side` through `tx`, which runs at `rate`: the pending event, or else the audio
+/// mode until it ends or changes.
+///
+/// Returns `false` without playing anything if `tx` is `rebuildable` and the mode calls for another rate, for the
+/// caller to rebuild it at that rate.
+#[allow(clippy::too_many_lines)]
+async fn play_next(
+    tx: &mut I2sTx<'_, esp_hal::Async>,
+    rate: u32,
+    rebuildable: bool,
+    buffers: &mut [[Sample; AUDIO_BUFFER_LEN]; 2],
+    state: &'static RwLock<CriticalSectionRawMutex, catears::state::State>,
+    status: &'static catears::status::Status,
+    side: catears::audio::Side,
+) -> bool {
+    status.heartbeats.stamp(Task::speaker(side));
+
+    let (mode, event, speaker_state) = {
+        let state = state.read().await;
+        let event = state.pending_event(played_event(side).load(Ordering::Relaxed));
+        (audio_mode(&state, status, side), event, state.speakers)
+    };
+    if rebuildable && catears::audio::synth::output_rate(&mode) != rate {
+        return false;
+    }
+
+    // The event plays in place of the speaker's own mode, which starts over once it is done. Unless it is
+    // interrupted by a newer event, sleep or the heat, it only ever plays once.
+    if let Some(event) = event.filter(|event| event.mode() == mode) {
+        if play_event(&event, buffers, tx, state, status, side).await {
+            played_event(side).store(event.id, Ordering::Relaxed);
+        }
+        return true;
+    }
+
+    match mode {
+        catears::audio::Mode::Silent => {
+            debug!("Playing silence on the {} side", side.name());
+            let [buffer, _] = buffers.each_mut();
+            while play_silence(buffer, tx, state, status, side, &mode, MODE_POLL_INTERVAL).await {}
+        }
+        catears::audio::Mode::Tone(note) => {
+            let volume = note.volume.unwrap_or(speaker_state.volume);
+            // The master volume is applied as the tone streams, so a tone without a volume renders at full volume.
+            let amplitude =
+                catears::audio::synth::amplitude(note.volume.unwrap_or(u8::MAX), u8::MAX);
+            debug!(
+                "Playing tone on the {} side: frequency={}Hz, duration={}ms, volume={}, amplitude={}, waveform={}",
+                side.name(),
+                note.frequency,
+                note.duration_ms,
+                volume,
+                amplitude,
+                note.waveform.name()
+            );
+
+            let mut tone = catears::audio::synth::ToneGenerator::new(&note, amplitude);
+            if stream(
+                |buffer| tone.fill(buffer),
+                buffers,
+                tx,
+                state,
+                status,
+                side,
+                &mode,
+            )
+            .await
+            {
+                debug!("Tone complete");
+                if note.repeat {
+                    // An empty tone would otherwise replay without ever yielding.
+                    if note.duration_ms == 0 {
+                        let [buffer, _] = buffers.each_mut();
+                        play_silence(buffer, tx, state, status, side, &mode, MODE_POLL_INTERVAL)
+                            .await;
+                    }
+                } else {
+                    go_silent(state, side, &mode).await;
+                }
+            } else {
+                debug!("Audio mode changed, stopping tone");
+            }
+        }
+        catears::audio::Mode::Chiptune(sequence) => {
+            play_chiptune(&sequence, buffers, tx, state, status, side, &mode).await;
+        }
+        catears::audio::Mode::NamedChiptune(name) => {
+            if let Some(sequence) = name.sequence() {
+                debug!(
+                    "Playing chiptune {} on the {} side",
+                    name.as_str(),
+                    side.name()
+                );
+                play_chiptune(&sequence, buffers, tx, state, status, side, &mode).await;
+            } else {
+                warn!(
+                    "No chiptune is named {}, silencing the {} side",
+                    name.as_str(),
+                    side.name()
+                );
+                go_silent(state, side, &mode).await;
+            }
+        }
+        catears::audio::Mode::Playlist(playlist) => {
+            debug!(
+                "Playing playlist on the {} side: length={}, looping={}",
+                side.name(),
+                playlist.length,
+                playlist.looping
+            );
+            let mut generator = catears::audio::synth::PlaylistGenerator::new(&playlist, u8::MAX);
+            if stream(
+                |buffer| generator.fill(buffer),
+                buffers,
+                tx,
+                state,
+                status,
+                side,
+                &mode,
+            )
+            .await
+            {
+                debug!("Playlist complete");
+            } else {
+                debug!("Audio mode changed, stopping playlist");
+            }
+        }
+        catears::audio::Mode::Audio(clip) => {
+            debug!(
+                "Playing clip on the {} side: {} bytes, {}Hz, {} bits, stereo={}, looping={}",
+                side.name(),
+                clip.data.len(),
+                clip.sample_rate,
+                clip.bits_per_sample,
+                clip.is_stereo,
+                clip.looping
+            );
+            // With the speaker running at the clip's own rate, every sample is a frame as it is.
+            let clip = if rate == clip.sample_rate {
+                clip.native()
+            } else {
+                clip
+            };
+            // A looping clip wraps around within a buffer, and its position is moved back a whole number of
+            // loops once it is well into them, so that it never overflows.
+            let period = catears::audio::synth::clip_loop_frames(&clip);
+            let looped = catears::audio::synth::clip_frames(&clip)
+                .saturating_add(1)
+                .saturating_add(period);
+            let mut position = 0;
+            let fill = |buffer: &mut [Sample]| {
+                let frames = catears::audio::synth::fill_clip(buffer, &clip, position, u8::MAX);
+                position += frames;
+                if period > 0 && position >= looped {
+                    position -= period;
+                }
+                frames
+            };
+            if stream(fill, buffers, tx, state, status, side, &mode).await {
+                debug!("Clip complete");
+                // The clip played once, hold silence until the mode changes.
+                let [buffer, _] = buffers.each_mut();
+                while play_silence(buffer, tx, state, status, side, &mode, MODE_POLL_INTERVAL).await
+                {
+                }
+            } else {
+                debug!("Audio mode changed, stopping clip");
+            }
+        }
+        catears::audio::Mode::RemoteAudio(clip) => {
+            debug!(
+                "Streaming clip {} on the {} side, looping={}",
+                clip.id,
+                side.name(),
+                clip.looping
+            );
+            let link = clip_link(side);
+            let request = link.start(clip.id);
+            let result =
+                play_remote_clip(link, request, buffers, tx, state, status, side, &mode).await;
+            link.stop();
+            match result {
+                Ok(true) => {
+                    debug!("Remote clip complete");
+                    if !clip.looping {
+                        // Like an embedded clip, hold silence until the mode changes.
+                        let [buffer, _] = buffers.each_mut();
+                        while play_silence(
+                            buffer,
+                            tx,
+                            state,
+                            status,
+                            side,
+                            &mode,
+                            MODE_POLL_INTERVAL,
+                        )
+                        .await
+                        {}
+                    }
+                }
+                Ok(false) => debug!("Audio mode changed, stopping remote clip"),
+                Err(error) => {
+                    warn!(
+                        "Could not play clip {} on the {} side: {}",
+                        clip.id,
+                        side.name(),
+                        error
+                    );
+                    go_silent(state, side, &mode).await;
+                }
+            }
+        }
+        catears::audio::Mode::Noise(noise) => {
+            debug!(
+                "Playing noise on the {} side: amplitude={}, smoothing={}, wobble={}Hz",
+                side.name(),
+                noise.amplitude,
+                noise.smoothing,
+                noise.wobble_hz
+            );
+            // Any seed sounds the same, it only has to differ between the two sides.
+            #[allow(clippy::cast_possible_truncation)]
+            let seed = embassy_time::Instant::now().as_ticks() as u32;
+            let mut generator = catears::audio::synth::NoiseGenerator::new(&noise, u8::MAX, seed);
+            // Noise never runs out, so this only returns once the mode changes.
+            stream(
+                |buffer| generator.fill(buffer),
+                buffers,
+                tx,
+                state,
+                status,
+                side,
+                &mode,
+            )
+            .await;
+            debug!("Audio mode changed, stopping noise");
+        }
+        catears::audio::Mode::Sweep(sweep) => {
+            debug!(
+                "Playing sweep on the {} side: {}Hz to {}Hz over {}ms, repeat={}",
+                side.name(),
+                sweep.start_hz,
+                sweep.end_hz,
+                sweep.duration_ms,
+                sweep.repeat
+            );
+            let amplitude = catears::audio::synth::amplitude(u8::MAX, u8::MAX);
+            let mut generator = catears::audio::synth::SweepGenerator::new(&sweep, amplitude);
+            if stream(
+                |buffer| generator.fill(buffer),
+                buffers,
+                tx,
+                state,
+                status,
+                side,
+                &mode,
+            )
+            .await
+            {
+                debug!("Sweep complete");
+                go_silent(state, side, &mode).await;
+            } else {
+                debug!("Audio mode changed, stopping sweep");
+            }
+        }
+        catears::audio::Mode::Dtmf(dial) => {
+            debug!(
+                "Dialing {} on the {} side",
+                dial.digits.as_str(),
+                side.name()
+            );
+            for symbol in dial.digits.invalid() {
+                warn!("Skipping {}, which is not a DTMF key", symbol);
+            }
+            let sequence = dial.sequence();
+            if play_chiptune(&sequence, buffers, tx, state, status, side, &mode).await {
+                go_silent(state, side, &mode).await;
+            }
+        }
+    }
+    true
+}
+
+/// Plays the sound `event` once on the speaker on `side`, returning whether it played to the end or was dropped, rather
+/// than interrupted.
+async fn play_event(
+    event: &catears::audio::Event,
+    buffers: &mut [[Sample; AUDIO_BUFFER_LEN]; 2],
+    tx: &mut I2sTx<'_, esp_hal::Async>,
+    state: &'static RwLock<CriticalSectionRawMutex, catears::state::State>,
+    status: &'static catears::status::Status,
+    side: catears::audio::Side,
+) -> bool {
+    debug!(
+        "Playing sound event {} on the {} side",
+        event.id,
+        side.name()
+    );
+    let mode = event.mode();
+    match mode {
+        catears::audio::Mode::Tone(note) => {
+            let amplitude =
+                catears::audio::synth::amplitude(note.volume.unwrap_or(u8::MAX), u8::MAX);
+            let mut tone = catears::audio::synth::ToneGenerator::new(&note, amplitude);
+            stream(
+                |buffer| tone.fill(buffer),
+                buffers,
+                tx,
+                state,
+                status,
+                side,
+                &mode,
+            )
+            .await
+        }
+        catears::audio::Mode::Chiptune(sequence) => {
+            play_chiptune(&sequence, buffers, tx, state, status, side, &mode).await
+        }
+        _ => {
+            if let catears::audio::Sound::Chiptune(name) = event.sound {
+                warn!(
+                    "No chiptune is named {}, dropping sound event {}",
+                    name.as_str(),
+                    event.id
+                );
+            }
+            true
+        }
+    }
+}
+
+/// Plays `sequence` on the speaker on `side` until it ends, or until the audio mode changes away from `mode`, returning
+/// whether it played to the end.
+async fn play_chiptune(
+    sequence: &catears::audio::ChiptuneSequence,
+    buffers: &mut [[Sample; AUDIO_BUFFER_LEN]; 2],
+    tx: &mut I2sTx<'_, esp_hal::Async>,
+    state: &'static RwLock<CriticalSectionRawMutex, catears::state::State>,
+    status: &'static catears::status::Status,
+    side: catears::audio::Side,
+    mode: &catears::audio::Mode,
+) -> bool {
+    debug!(
+        "Playing chiptune on the {} side: length={}, looping={}, default_volume={}",
+        side.name(),
+        sequence.length,
+        sequence.looping,
+        sequence.default_volume
+    );
+    let mut chiptune = catears::audio::synth::ChiptuneGenerator::new(sequence, u8::MAX);
+    let beats = beat_clock(side);
+    let mut published = 0;
+    let complete = stream(
+        |buffer| {
+            let frames = chiptune.fill(buffer);
+            let started = chiptune.notes_started();
+            if started != published {
+                published = started;
+                beats.publish(started, now_ms(), sequence.bpm);
+            }
+            frames
+        },
+        buffers,
+        tx,
+        state,
+        status,
+        side,
+        mode,
+    )
+    .await;
+    beats.stop();
+    if complete {
+        debug!("Chiptune complete");
+    } else {
+        debug!("Audio mode changed, stopping chiptune");
+    }
+    complete
+}
+
+/// Beats of the chiptunes playing on the left and right speakers, for the lights to keep time with.
+static BEATS: [catears::audio::beat::Clock; 2] = [
+    catears::audio::beat::Clock::new(),
+    catears::audio::beat::Clock::new(),
+];
+
+/// Returns the clock the speaker on `side` publishes the beats of its chiptunes to.
+fn beat_clock(side: catears::audio::Side) -> &'static catears::audio::beat::Clock {
+    match side {
+        catears::audio::Side::Left => &BEATS[0],
+        catears::audio::Side::Right => &BEATS[1],
+    }
+}
+
+/// Returns the time since boot in milliseconds, on the wrapping clock [`catears::audio::beat::Clock`] keeps.
+fn now_ms() -> u32 {
+    #[allow(clippy::cast_possible_truncation)]
+    let now = embassy_time::Instant::now().as_millis() as u32;
+    now
+}
+
+/// Why a remote clip stopped playing early.
+#[derive(Debug, defmt::Format)]
+enum RemoteClipError {
+    /// Too little of the clip arrived in time to start playing.
+    Timeout,
+    /// The download failed, which the streaming task warns about with the reason.
+    Download,
+    /// The clip is not a WAV file the speakers can play.
+    Wav(catears::audio::wav::Error),
+}
+
+/// How long a remote clip may take to buffer enough of itself to start playing.
+const REMOTE_CLIP_PREFILL_TIMEOUT: embassy_time::Duration = embassy_time::Duration::from_secs(10);
+
+/// Plays the remote clip asked for by `request` as it streams in through `link`, like [`stream`] does, returning
+/// whether it played to the end.
+///
+/// Playback starts once [`catears::audio::stream::PREFILL_LEN`] bytes have arrived, or the whole clip if it is shorter,
+/// so that the download has a head start. If it falls behind anyway, the speaker plays silence until it catches up.
+#[allow(clippy::too_many_arguments)]
+async fn play_remote_clip(
+    link: &'static catears::audio::stream::Link,
+    request: catears::audio::stream::Request,
+    buffers: &mut [[Sample; AUDIO_BUFFER_LEN]; 2],
+    tx: &mut I2sTx<'_, esp_hal::Async>,
+    state: &'static RwLock<CriticalSectionRawMutex, catears::state::State>,
+    status: &'static catears::status::Status,
+    side: catears::audio::Side,
+    mode: &catears::audio::Mode,
+) -> Result<bool, RemoteClipError> {
+    use catears::audio::stream::{Decoder, Progress, Read, HEADER_LEN, PREFILL_LEN};
+
+    let deadline = embassy_time::Instant::now() + REMOTE_CLIP_PREFILL_TIMEOUT;
+    loop {
+        match link.progress(request) {
+            Progress::Finished => break,
+            Progress::Streaming if link.buffered() >= PREFILL_LEN => break,
+            Progress::Failed => return Err(RemoteClipError::Download),
+            Progress::Pending | Progress::Streaming => {}
+        }
+        if embassy_time::Instant::now() >= deadline {
+            return Err(RemoteClipError::Timeout);
+        }
+        if !play_silence(
+            &mut buffers[0],
+            tx,
+            state,
+            status,
+            side,
+            mode,
+            MODE_POLL_INTERVAL,
+        )
+        .await
+        {
+            return Ok(false);
+        }
+    }
+
+    // The header comes first, and once the clip is buffered it has all arrived.
+    let mut bytes = [0u8; HEADER_LEN];
+    let mut end = 0;
+    while end < bytes.len() {
+        match link.read(request, &mut bytes[end..]) {
+            Read::Bytes(len) => end += len,
+            Read::Waiting | Read::Finished => break,
+            Read::Failed => return Err(RemoteClipError::Download),
+        }
+    }
+    let (mut decoder, mut start) = Decoder::new(&bytes[..end]).map_err(RemoteClipError::Wav)?;
+
+    let mut finished = false;
+    let mut failed = false;
+    let fill = |buffer: &mut [Sample]| {
+        let capacity = buffer.len() / 2;
+        let mut frames = 0;
+        loop {
+            if start == end && !finished {
+                match link.read(request, &mut bytes) {
+                    Read::Bytes(len) => (start, end) = (0, len),
+                    Read::Waiting => {}
+                    Read::Finished => finished = true,
+                    Read::Failed => {
+                        failed = true;
+                        return frames;
+                    }
+                }
+            }
+            let decoded = decoder.decode(&bytes[start..end], &mut buffer[2 * frames..], finished);
+            start += decoded.consumed;
+            frames += decoded.frames;
+            if frames == capacity || decoder.is_over() {
+                return frames;
+            }
+            if decoded.consumed == 0 && decoded.frames == 0 {
+                if finished {
+                    return frames;
+                }
+                // The download fell behind, play silence rather than stopping.
+                debug!("Remote clip underrun on the {} side", side.name());
+                buffer[2 * frames..].fill(0);
+                return capacity;
+            }
+        }
+    };
+    let complete = stream(fill, buffers, tx, state, status, side, mode).await;
+    if failed {
+        Err(RemoteClipError::Download)
+    } else {
+        Ok(complete)
+    }
+}
+
+/// Switches the speaker on `side` back to silence once `mode` has played out, unless the mode changed in the meantime.
+async fn go_silent(
+    state: &'static RwLock<CriticalSectionRawMutex, catears::state::State>,
+    side: catears::audio::Side,
+    mode: &catears::audio::Mode,
+) {
+    let mut state = state.write().await;
+    let mode_now = state.speakers.mode_mut(side);
+    if *mode_now == *mode {
+        *mode_now = catears::audio::Mode::Silent;
+    }
+}
+
+/// Least time silence plays for at a go while the speaker waits for something to change, such as a resume.
+const MODE_POLL_INTERVAL: embassy_time::Duration = embassy_time::Duration::from_millis(50);
+
+/// Streams audio to the speaker on `side` until `fill` runs out, rendering into one buffer while the DMA plays the
+/// other. Returns `false` if it was cut short because the audio mode changed away from `mode`, which is checked after
+/// every buffer.
+///
+/// `fill` renders the next stretch of audio at full volume into a buffer and returns how many frames it produced, zero
+/// once it is over. If it takes longer than the previous buffer takes to play, the speaker runs dry in between, which
+/// is logged as an underrun.
+///
+/// The master volume and the balance are applied on the way out through a [`catears::audio::synth::VolumeRamp`], so
+/// changing them does not click. A change of mode ramps the volume down to silence too, playing on for up to
+/// [`catears::audio::synth::VOLUME_RAMP_MS`] rather than cutting the wave off and popping.
+///
+/// Muting the speakers ramps them down the same way while `fill` carries on, so the sound picks up where it would
+/// have been once unmuted. Pausing them ramps them down and then stops calling `fill`, so it picks up where it left off.
+async fn stream(
+    mut fill: impl FnMut(&mut [Sample]) -> usize,
+    buffers: &mut [[Sample; AUDIO_BUFFER_LEN]; 2],
+    tx: &mut I2sTx<'_, esp_hal::Async>,
+    state: &'static RwLock<CriticalSectionRawMutex, catears::state::State>,
+    status: &'static catears::status::Status,
+    side: catears::audio::Side,
+    mode: &catears::audio::Mode,
+) -> bool {
+    let target = |state: &catears::state::State| {
+        let volume = match mode {
+            // A tone with a volume of its own plays at that volume whatever the master volume is.
+            catears::audio::Mode::Tone(catears::audio::Note {
+                volume: Some(_), ..
+            }) => u8::MAX,
+            _ => state.speakers.volume,
+        };
+        catears::audio::synth::balanced(volume, state.speakers.balance, side)
+    };
+    let paused_already = state.read().await.speakers.paused;
+    if paused_already && !wait_while_paused(&mut buffers[0], tx, state, status, side, mode).await {
+        return false;
+    }
+    let volume = {
+        let state = state.read().await;
+        if state.speakers.muted {
+            0
+        } else {
+            target(&state)
+        }
+    };
+    let mut ramp =
+        catears::audio::synth::VolumeRamp::new(volume, catears::audio::synth::VOLUME_RAMP_MS);
+    let mut stopping = false;
+    let mut paused = false;
+
+    let [mut playing, mut next] = buffers.each_mut();
+    let mut len = fill(&mut playing[..]) * 2;
+    ramp.apply(&mut playing[..len], volume);
+    while len > 0 {
+        status.heartbeats.stamp(Task::speaker(side));
+        let (played_at, (next_len, filled_at)) = embassy_futures::join::join(
+            async {
+                write_speaker(status, side, tx, playing, len).await;
+                embassy_time::Instant::now()
+            },
+            async { (fill(&mut next[..]) * 2, embassy_time::Instant::now()) },
+        )
+        .await;
+        if stopping && ramp.volume() <= 0.0 {
+            // The buffer that just played ramped all the way down.
+            break;
+        }
+        if paused && ramp.volume() <= 0.0 {
+            // Hold on to the buffer rendered next, which plays as the volume ramps back up.
+            debug!("Pausing the {} speaker", side.name());
+            if !wait_while_paused(playing, tx, state, status, side, mode).await {
+                return false;
+            }
+            debug!("Resuming the {} speaker", side.name());
+        }
+        if next_len > 0 && filled_at > played_at {
+            let dry_us = u32::try_from((filled_at - played_at).as_micros()).unwrap_or(u32::MAX);
+            let count = underruns(status, side).record(dry_us);
+            warn!(
+                "The {} speaker ran dry for {} us, {} times since boot",
+                side.name(),
+                dry_us,
+                count
+            );
+        }
+
+        let volume = {
+            let state = state.read().await;
+            if audio_mode(&state, status, side) != *mode {
+                stopping = true;
+            }
+            paused = state.speakers.paused;
+            if stopping || paused || state.speakers.muted {
+                0
+            } else {
+                target(&state)
+            }
+        };
+        ramp.apply(&mut next[..next_len], volume);
+        core::mem::swap(&mut playing, &mut next);
+        len = next_len;
+    }
+    !stopping
+}
+
+/// I2S output of a speaker, which plays clips at their own sample rate and everything else at
+/// [`catears::audio::synth::SAMPLE_RATE`], see [`catears::audio::synth::output_rate`].
+enum SpeakerOutput {
+    /// Output that shares its peripheral with the microphone, and so stays at the rate it was built at.
+    #[cfg_attr(not(feature = "microphone"), allow(dead_code))]
+    Fixed(I2sTx<'static, esp_hal::Async>),
+    /// Peripherals and DMA descriptors of an output that is built anew whenever the mode calls for another rate.
+    ///
+    /// Each build only borrows them, so switching back and forth costs nothing but the time it takes. The output is
+    /// only rebuilt between modes, once every transfer on it is done.
+    Rebuilt {
+        peripherals: SpeakerPeripherals,
+        descriptors: &'static mut [esp_hal::dma::DmaDescriptor],
+    },
+}
+
+/// I2S peripheral, DMA channel, and pins of a speaker.
+enum SpeakerPeripherals {
+    /// Left speaker, unless the microphone shares its peripheral.
+    #[cfg_attr(feature = "microphone", allow(dead_code))]
+    Left {
+        i2s: esp_hal::peripherals::I2S0<'static>,
+        dma: esp_hal::peripherals::DMA_CH0<'static>,
+        ws: esp_hal::peripherals::GPIO9<'static>,
+        bclk: esp_hal::peripherals::GPIO8<'static>,
+        dout: esp_hal::peripherals::GPIO7<'static>,
+    },
+    /// Right speaker.
+    Right {
+        i2s: esp_hal::peripherals::I2S1<'static>,
+        dma: esp_hal::peripherals::DMA_CH1<'static>,
+        ws: esp_hal::peripherals::GPIO3<'static>,
+        bclk: esp_hal::peripherals::GPIO4<'static>,
+        dout: esp_hal::peripherals::GPIO5<'static>,
+    },
+}
+
+impl SpeakerPeripherals {
+    /// Builds an output at `rate` with `descriptors`, which holds on to the peripherals for as long as it lives.
+    fn build<'a>(
+        &'a mut self,
+        rate: u32,
+        descriptors: &'a mut [esp_hal::dma::DmaDescriptor],
+    ) -> I2sTx<'a, esp_hal::Async> {
+        use esp_hal::i2s::master::Standard;
+
+        match self {
+            Self::Left {
+                i2s,
+                dma,
+                ws,
+                bclk,
+                dout,
+            } => I2s::new(
+                i2s.reborrow(),
+                Standard::Philips,
+                DATA_FORMAT,
+                Rate::from_hz(rate),
+                dma.reborrow(),
+            )
+            .into_async()
+            .i2s_tx
+            .with_ws(ws.reborrow()) // Green
+            .with_bclk(bclk.reborrow()) // White
+            .with_dout(dout.reborrow()) // Blue
+            .build(descriptors),
+            Self::Right {
+                i2s,
+                dma,
+                ws,
+                bclk,
+                dout,
+            } => I2s::new(
+                i2s.reborrow(),
+                Standard::Philips,
+                DATA_FORMAT,
+                Rate::from_hz(rate),
+                dma.reborrow(),
+            )
+            .into_async()
+            .i2s_tx
+            .with_ws(ws.reborrow()) // Green
+            .with_bclk(bclk.reborrow()) // White
+            .with_dout(dout.reborrow()) // Blue
+            .build(descriptors),
+        }
+    }
+}
+
+/// Plays the first `len` samples of `buffer` on the speaker on `side`, and publishes its level into the status.
+///
+/// The level is that of this buffer alone, with no smoothing across buffers, which is about 46 ms of sound.
+async fn write_speaker(
+    status: &'static catears::status::Status,
+    side: catears::audio::Side,
+    tx: &mut I2sTx<'_, esp_hal::Async>,
+    buffer: &mut [Sample; AUDIO_BUFFER_LEN],
+    len: usize,
+) {
+    let started = embassy_time::Instant::now();
+    let result = tx
+        .write_dma_async(bytemuck::cast_slice_mut(&mut buffer[..len]))
+        .await;
+    match side {
+        catears::audio::Side::Left => status.speaker_left.record("Left speaker", result),
+        catears::audio::Side::Right => status.speaker_right.record("Right speaker", result),
+    };
+    // Both slots of each frame are measured as one signal, which keeps the RMS and peak exact but blurs the bands.
+    let levels = catears::audio::dsp::LevelMeter::new().process(
+        buffer[..len]
+            .iter()
+            .map(|&sample| catears::audio::synth::narrow(sample)),
+    );
+    match side {
+        catears::audio::Side::Left => status.output_left.publish(levels),
+        catears::audio::Side::Right => status.output_right.publish(levels),
+    }
+    status.timing.record(Task::speaker(side), started);
+}
+
+/// Returns the audio mode to play on `side`, which is silence while going to sleep or too hot.
+fn audio_mode(
+    state: &catears::state::State,
+    status: &catears::status::Status,
+    side: catears::audio::Side,
+) -> catears::audio::Mode {
+    if status.sleep.is_entering() || status.thermal.is_critical() {
+        catears::audio::Mode::Silent
+    } else {
+        state.audio_mode(side, played_event(side).load(Ordering::Relaxed))
+    }
+}
+
+/// Plays silence on the speaker on `side` for at least `duration`, returning `false` early if the audio mode on `side`
+/// changes away from `mode`.
+///
+/// Silence is written out a buffer at a time, back to back, rather than leaving the speaker idle. An I2S DMA that runs
+/// dry and starts up again makes some amplifiers tick. Each buffer lasts about 46 ms, so a change of mode is noticed
+/// within one of them. `buffer` is overwritten with zeros.
+async fn play_silence(
+    buffer: &mut [Sample; AUDIO_BUFFER_LEN],
+    tx: &mut I2sTx<'_, esp_hal::Async>,
+    state: &'static RwLock<CriticalSectionRawMutex, catears::state::State>,
+    status: &'static catears::status::Status,
+    side: catears::audio::Side,
+    mode: &catears::audio::Mode,
+    duration: embassy_time::Duration,
+) -> bool {
+    buffer.fill(0);
+    let deadline = embassy_time::Instant::now() + duration;
+    loop {
+        status.heartbeats.stamp(Task::speaker(side));
+        if audio_mode(&*state.read().await, status, side) != *mode {
+            return false;
+        }
+        if embassy_time::Instant::now() >= deadline {
+            return true;
+        }
+        write_speaker(status, side, tx, buffer, AUDIO_BUFFER_LEN).await;
+    }
+}
+
+/// Plays silence on the speaker on `side` until the speakers are resumed, like [`play_silence`], returning `false`
+/// early if the audio mode on `side` changes away from `mode`.
+async fn wait_while_paused(
+    buffer: &mut [Sample; AUDIO_BUFFER_LEN],
+    tx: &mut I2sTx<'_, esp_hal::Async>,
+    state: &'static RwLock<CriticalSectionRawMutex, catears::state::State>,
+    status: &'static catears::status::Status,
+    side: catears::audio::Side,
+    mode: &catears::audio::Mode,
+) -> bool {
+    loop {
+        if !state.read().await.speakers.paused {
+            return true;
+        }
+        if !play_silence(buffer, tx, state, status, side, mode, MODE_POLL_INTERVAL).await {
+            return false;
+        }
+    }
+}
+
+/// Returns the underrun counter of the speaker on `side`.
+fn underruns(
+    status: &'static catears::status::Status,
+    side: catears::audio::Side,
+) -> &'static catears::status::Underruns {
+    match side {
+        catears::audio::Side::Left => &status.underruns_left,
+        catears::audio::Side::Right => &status.underruns_right,
+    }
+}
+
+/// Auxiliary servos on the second MCPWM operator.
+type AuxServos = (
+    catears::servo::Servo<
+        esp_hal::mcpwm::operator::PwmPin<'static, esp_hal::peripherals::MCPWM0<'static>, 1, true>,
+    >,
+    catears::servo::Servo<
+        esp_hal::mcpwm::operator::PwmPin<'static, esp_hal::peripherals::MCPWM0<'static>, 1, false>,
+    >,
+);
 
 #[embassy_executor::task]
 async fn control_servos(
     state: &'static RwLock<CriticalSectionRawMutex, catears::state::State>,
+    status: &'static catears::status::Status,
+    gestures: &'static catears::servo::gestures::GestureChannel,
+    level: &'static catears::audio::dsp::AudioLevel,
     mut servo_left: catears::servo::Servo<
         esp_hal::mcpwm::operator::PwmPin<'static, esp_hal::peripherals::MCPWM0<'static>, 0, true>,
     >,
     mut servo_right: catears::servo::Servo<
         esp_hal::mcpwm::operator::PwmPin<'static, esp_hal::peripherals::MCPWM0<'static>, 0, false>,
     >,
+    mut aux_servos: Option<AuxServos>,
 ) -> ! {
+    use catears::servo::gestures::Target;
     use embassy_time::Instant;
-    use crate::state::ServoMode;
-    
-    let mut left_start = Instant::now();
-    let mut right_start = Instant::now();
-    
+
+    let mut left = ModeTracker::new(7919);
+    let mut right = ModeTracker::new(13337);
+    let mut aux0 = ModeTracker::new(2971);
+    let mut aux1 = ModeTracker::new(5483);
+    let mut left_gesture: Option<ActiveGesture> = None;
+    let mut right_gesture: Option<ActiveGesture> = None;
+
     loop {
-        let servos = state.read().await.servos;
-        
-        // Handle left servo
-        let left_position = match servos.left {
-            ServoMode::Static(pos) => {
-                left_start = Instant::now(); // Reset timer for mode changes
-                pos
-            },
-            ServoMode::Sweep { min, max, speed_ms } => {
-                let elapsed = left_start.elapsed().as_millis() as u32;
-                let cycle_time = speed_ms * 2; // Full cycle is min->max->min
-                let phase = (elapsed % cycle_time) as f32 / cycle_time as f32;
-                
-                if phase < 0.5 {
-                    // Sweeping from min to max
-                    let t = phase * 2.0;
-                    (min as f32 + (max - min) as f32 * t) as u8
-                } else {
-                    // Sweeping from max to min
-                    let t = (phase - 0.5) * 2.0;
-                    (max as f32 - (max - min) as f32 * t) as u8
-                }
-            },
-            ServoMode::Twitch { center, amplitude, interval_ms } => {
-                let elapsed = left_start.elapsed().as_millis() as u32;
-                if elapsed > interval_ms {
-                    left_start = Instant::now();
-                    // Simple random-like twitch using elapsed time as pseudo-random
-                    let offset = ((elapsed * 7919) % (amplitude as u32 * 2)) as i16 - amplitude as i16;
-                    (center as i16 + offset).clamp(0, 255) as u8
-                } else {
-                    center
+        status.heartbeats.stamp(Task::Servos);
+
+        if status.sleep.is_entering() {
+            // Let the ears go limp rather than straining to hold a position until the power goes.
+            status.servo_left.record("Left servo", servo_left.detach());
+            status
+                .servo_right
+                .record("Right servo", servo_right.detach());
+            if let Some((servo_aux0, servo_aux1)) = aux_servos.as_mut() {
+                status.servo_aux.record("Aux servo 0", servo_aux0.detach());
+                status.servo_aux.record("Aux servo 1", servo_aux1.detach());
+            }
+            Timer::after(embassy_time::Duration::from_millis(100)).await;
+            continue;
+        }
+
+        let started = Instant::now();
+        // A new gesture replaces whatever was in flight on the sides it targets.
+        while let Ok(request) = gestures.try_receive() {
+            let gesture = request.gesture;
+            debug!("Starting gesture {}", gesture.name());
+            match request.target {
+                Target::Left => left_gesture = ActiveGesture::start(gesture, false),
+                Target::Right => right_gesture = ActiveGesture::start(gesture, false),
+                Target::Both => {
+                    left_gesture = ActiveGesture::start(gesture, false);
+                    right_gesture = ActiveGesture::start(gesture, gesture.mirrored());
                 }
-            },
+            }
+        }
+
+        let (power, servos) = {
+            let state = state.read().await;
+            (state.power && !status.thermal.is_critical(), state.servos)
         };
-        
-        // Handle right servo  
-        let right_position = match servos.right {
+        let sound = level.get().rms;
+
+        let left_position = apply_gesture(&mut left_gesture, left.position(servos.left, sound));
+        let right_position = apply_gesture(&mut right_gesture, right.position(servos.right, sound));
+
+        // While powered off or shedding load the servos simply hold their last commanded position.
+        if !power || status.supply.is_active() {
+            status.timing.record(Task::Servos, started);
+            Timer::after(embassy_time::Duration::from_millis(10)).await;
+            continue;
+        }
+
+        // A failed update just skips this tick, the next one retries with a fresh position.
+        status
+            .servo_left
+            .record("Left servo", servo_left.set_rotation(left_position));
+        status
+            .servo_right
+            .record("Right servo", servo_right.set_rotation(right_position));
+        // Auxiliary servos without a mode are left wherever they are.
+        if let Some((servo_aux0, servo_aux1)) = aux_servos.as_mut() {
+            if let Some(mode) = servos.aux0 {
+                let position = aux0.position(mode, sound);
+                status
+                    .servo_aux
+                    .record("Aux servo 0", servo_aux0.set_rotation(position));
+            }
+            if let Some(mode) = servos.aux1 {
+                let position = aux1.position(mode, sound);
+                status
+                    .servo_aux
+                    .record("Aux servo 1", servo_aux1.set_rotation(position));
+            }
+        }
+        status.timing.record(Task::Servos, started);
+
+        Timer::after(embassy_time::Duration::from_millis(10)).await;
+    }
+}
+
+/// Resolves a servo mode into a position, tick by tick, for a single servo.
+struct ModeTracker {
+    /// Start of the current sweep or twitch interval.
+    start: embassy_time::Instant,
+    trigger: catears::audio::dsp::Trigger,
+    /// Multiplier that spreads twitch offsets, distinct per servo so that they do not twitch in lockstep.
+    twitch_seed: u32,
+}
+
+impl ModeTracker {
+    fn new(twitch_seed: u32) -> Self {
+        Self {
+            start: embassy_time::Instant::now(),
+            trigger: catears::audio::dsp::Trigger::new(),
+            twitch_seed,
+        }
+    }
+
+    /// Returns the position `mode` calls for now, given the current microphone level.
+    fn position(&mut self, mode: catears::state::ServoMode, sound: u8) -> u8 {
+        use catears::state::ServoMode;
+
+        match mode {
             ServoMode::Static(pos) => {
-                right_start = Instant::now(); // Reset timer for mode changes
+                self.start = embassy_time::Instant::now(); // Reset timer for mode changes
                 pos
-            },
+            }
             ServoMode::Sweep { min, max, speed_ms } => {
-                let elapsed = right_start.elapsed().as_millis() as u32;
-                let cycle_time = speed_ms * 2; // Full cycle is min->max->min
+                // Modes come from remote JSON and the command line, so a reversed range or a zero speed is possible.
+                let (min, max) = (min.min(max), min.max(max));
+                let elapsed = self.start.elapsed().as_millis() as u32;
+                let cycle_time = speed_ms.saturating_mul(2).max(1); // Full cycle is min->max->min
                 let phase = (elapsed % cycle_time) as f32 / cycle_time as f32;
-                
+
                 if phase < 0.5 {
                     // Sweeping from min to max
                     let t = phase * 2.0;
@@ -557,199 +3094,489 @@ async fn control_servos(
                     let t = (phase - 0.5) * 2.0;
                     (max as f32 - (max - min) as f32 * t) as u8
                 }
-            },
-            ServoMode::Twitch { center, amplitude, interval_ms } => {
-                let elapsed = right_start.elapsed().as_millis() as u32;
+            }
+            ServoMode::Twitch {
+                center,
+                amplitude,
+                interval_ms,
+            } => {
+                let elapsed = self.start.elapsed().as_millis() as u32;
                 if elapsed > interval_ms {
-                    right_start = Instant::now();
+                    self.start = embassy_time::Instant::now();
                     // Simple random-like twitch using elapsed time as pseudo-random
-                    let offset = ((elapsed * 13337) % (amplitude as u32 * 2)) as i16 - amplitude as i16;
+                    let span = (amplitude as u32 * 2).max(1);
+                    let offset =
+                        (elapsed.wrapping_mul(self.twitch_seed) % span) as i16 - amplitude as i16;
                     (center as i16 + offset).clamp(0, 255) as u8
                 } else {
                     center
                 }
-            },
-        };
+            }
+            ServoMode::Reactive {
+                rest,
+                perk,
+                threshold,
+                release,
+            } => {
+                if self.trigger.update(sound, threshold, release) {
+                    perk
+                } else {
+                    rest
+                }
+            }
+        }
+    }
+}
 
-        servo_left
-            .set_rotation(left_position)
-            .expect("unable to set servo_left rotation");
-        servo_right
-            .set_rotation(right_position)
-            .expect("unable to set servo_right rotation");
+/// A one-shot gesture currently playing on one servo.
+#[derive(Clone, Copy)]
+struct ActiveGesture {
+    gesture: catears::servo::gestures::Gesture,
+    start: embassy_time::Instant,
+    /// Whether the gesture's offsets are negated on this servo.
+    mirror: bool,
+}
 
-        Timer::after(embassy_time::Duration::from_millis(10)).await;
+impl ActiveGesture {
+    fn start(gesture: catears::servo::gestures::Gesture, mirror: bool) -> Option<Self> {
+        Some(Self {
+            gesture,
+            start: embassy_time::Instant::now(),
+            mirror,
+        })
     }
 }
-#[derive(Default)]
+
+/// Offsets a servo position by the active gesture, clearing the gesture once it has finished.
+fn apply_gesture(active: &mut Option<ActiveGesture>, position: u8) -> u8 {
+    let Some(ActiveGesture {
+        gesture,
+        start,
+        mirror,
+    }) = *active
+    else {
+        return position;
+    };
+    if let Some(offset) = gesture.offset_at(start.elapsed().as_millis()) {
+        let offset = if mirror {
+            offset.saturating_neg()
+        } else {
+            offset
+        };
+        position.saturating_add_signed(offset)
+    } else {
+        debug!("Gesture {} finished", gesture.name());
+        *active = None;
+        position
+    }
+}
+
 struct AnimationState {
-    left: PatternState,
-    right: PatternState,
+    left: catears::lights::render::PatternState,
+    right: catears::lights::render::PatternState,
+    /// Light modes of the left and right rings at the last frame.
+    modes: (catears::lights::Mode, catears::lights::Mode),
+    /// Crossfades of the left and right rings into their latest modes.
+    fades: (
+        catears::lights::render::Crossfade<LED_COUNT>,
+        catears::lights::render::Crossfade<LED_COUNT>,
+    ),
+    /// Colors the left and right rings showed at the last frame, underneath any flash.
+    shown: ([smart_leds::RGB8; LED_COUNT], [smart_leds::RGB8; LED_COUNT]),
+    /// Brightness dithers of the left and right rings.
+    dithers: (
+        catears::lights::render::Dither<LED_COUNT>,
+        catears::lights::render::Dither<LED_COUNT>,
+    ),
+}
+
+impl Default for AnimationState {
+    fn default() -> Self {
+        // Seeded apart, so that random animations like fire do not mirror each other across the ears.
+        Self {
+            left: catears::lights::render::PatternState::with_seed(0x9e37_79b9),
+            right: catears::lights::render::PatternState::with_seed(0x85eb_ca6b),
+            modes: Default::default(),
+            fades: Default::default(),
+            shown: Default::default(),
+            dithers: Default::default(),
+        }
+    }
+}
+
+impl AnimationState {
+    /// Starts the animation of a ring over when it switches to a new custom animation, which would otherwise pick up
+    /// at whatever frame the one before it had reached, or to a comet, which would otherwise trail whatever LED levels
+    /// the mode before it left.
+    ///
+    /// Any change of mode also starts a crossfade away from the mode the ring showed.
+    fn follow(&mut self, lights: &catears::state::Lights) {
+        for (state, shown, mode, fade, colors) in [
+            (
+                &mut self.left,
+                &mut self.modes.0,
+                &lights.left,
+                &mut self.fades.0,
+                self.shown.0,
+            ),
+            (
+                &mut self.right,
+                &mut self.modes.1,
+                &lights.right,
+                &mut self.fades.1,
+                self.shown.1,
+            ),
+        ] {
+            if shown == mode {
+                continue;
+            }
+            // A strobe cuts in at once, so that its first flash has the same hard edges as the rest.
+            if matches!(mode, catears::lights::Mode::Strobe(_)) {
+                *fade = catears::lights::render::Crossfade::new();
+            } else {
+                fade.start(*shown, *state, colors);
+            }
+            if matches!(
+                mode,
+                catears::lights::Mode::Animation(_)
+                    | catears::lights::Mode::Comet(_)
+                    | catears::lights::Mode::Strobe(_)
+            ) {
+                state.restart();
+            }
+            *shown = *mode;
+        }
+    }
+
+    /// Returns whether both rings show `lights` as they are, with no change of mode or crossfade left to show.
+    fn is_settled(&self, lights: &catears::state::Lights) -> bool {
+        self.modes == (lights.left, lights.right)
+            && !self.fades.0.is_fading()
+            && !self.fades.1.is_fading()
+    }
 }
 
+/// Time in milliseconds the LED task waits for a state change or a flash while the LEDs are dark and their output is
+/// suspended, before it wakes up anyway to stamp its heartbeat.
+///
+/// This is also how long it takes at most for the ambient light, the heat, or the supply, which are not part of the
+/// state, to let dark rings light up again.
+const LED_PARKED_HEARTBEAT_MS: u64 = 1_000;
+
+/// Whether the LEDs fade in over [`catears::lights::render::BrightnessRamp::DURATION_MS`] on boot, and so on waking
+/// from deep sleep. Turned off at build time by setting the `LED_NO_BOOT_RAMP` environment variable to anything, which
+/// saves the wait on the bench.
+const LED_BOOT_RAMP: bool = option_env!("LED_NO_BOOT_RAMP").is_none();
+
+/// Amount the brightness fades per frame while going to sleep.
+const SLEEP_FADE_STEP: u8 = 8;
+
+/// Number of LED frames whose output time is summarized in each debug log.
+const FRAME_TIME_LOG_FRAMES: u32 = 1000;
+
+/// Output time of the recent LED frames, logged at debug level every [`FRAME_TIME_LOG_FRAMES`] frames.
 #[derive(Default)]
-struct PatternState {
-    position: u8,
-    hue: u8,
-    pulse_phase: u16,
+struct FrameTimes {
+    frames: u32,
+    total_us: u64,
+    max_us: u64,
+}
+
+impl FrameTimes {
+    fn record(&mut self, elapsed_us: u64) {
+        self.frames += 1;
+        self.total_us += elapsed_us;
+        self.max_us = self.max_us.max(elapsed_us);
+        if self.frames >= FRAME_TIME_LOG_FRAMES {
+            debug!(
+                "LED frame output time: {} us average, {} us max",
+                self.total_us / u64::from(self.frames),
+                self.max_us
+            );
+            *self = Self::default();
+        }
+    }
 }
 
 #[embassy_executor::task]
 async fn control_leds(
     state: &'static RwLock<CriticalSectionRawMutex, catears::state::State>,
+    status: &'static catears::status::Status,
+    level: &'static catears::audio::dsp::AudioLevel,
+    flashes: &'static catears::lights::flashes::FlashChannel,
     mut left: SmartLedsAdapterAsync<
         rmt::ConstChannelAccess<rmt::Tx, 1>,
-        { esp_hal_smartled::buffer_size_async(12) },
+        { esp_hal_smartled::buffer_size_async(LED_COUNT) },
     >,
     mut right: SmartLedsAdapterAsync<
         rmt::ConstChannelAccess<rmt::Tx, 2>,
-        { esp_hal_smartled::buffer_size_async(12) },
+        { esp_hal_smartled::buffer_size_async(LED_COUNT) },
     >,
 ) -> ! {
+    use embassy_time::Instant;
+
     let mut animation_state = AnimationState::default();
+    let mut flash: Option<(smart_leds::RGB8, Instant)> = None;
+    // Scales the brightness down to nothing while going to sleep.
+    let mut sleep_fade = u8::MAX;
+    let mut frame_times = FrameTimes::default();
+    // Whether a blackout frame went out and the rings are left alone until something lights up again.
+    let mut parked = false;
+    // Start of the last rendered frame, so the animations follow the actual frame rate.
+    let mut last_frame: Option<Instant> = None;
+    // Whether both rings were set to mirror each other at the last frame, so that it is only warned about once.
+    let mut mirror_loop = false;
+    // Light playlist showing, and when it started, so that it starts over from its first entry whenever it changes.
+    let mut playlist_clock: Option<(catears::lights::Playlist, u32)> = None;
+    // Fades the rings in from nothing rather than lighting them up at full brightness.
+    let mut ramp = catears::lights::render::BrightnessRamp::new(if LED_BOOT_RAMP {
+        catears::lights::render::BrightnessRamp::DURATION_MS
+    } else {
+        0
+    });
+    ramp.restart(now_ms());
 
     loop {
-        let lights = state.read().await.lights;
-        let brightness_scale = lights.brightness;
-
-        // Process left LED ring
-        let left_colors =
-            generate_pattern(&lights.left, &mut animation_state.left, brightness_scale);
-        left.write(left_colors.into_iter())
-            .await
-            .expect("unable to write to left LED ring");
-
-        // Process right LED ring
-        let right_colors =
-            generate_pattern(&lights.right, &mut animation_state.right, brightness_scale);
-        right
-            .write(right_colors.into_iter())
-            .await
-            .expect("unable to write to right LED ring");
-
-        Timer::after(embassy_time::Duration::from_millis(10)).await;
-    }
-}
-
-fn generate_pattern(
-    mode: &catears::lights::Mode,
-    state: &mut PatternState,
-    brightness_scale: u8,
-) -> [smart_leds::RGB8; 12] {
-    let mut colors = [smart_leds::RGB8::new(0, 0, 0); 12];
+        status.heartbeats.stamp(Task::Leds);
+        let started = Instant::now();
+
+        // A new flash replaces whatever flash was showing.
+        while let Ok(request) = flashes.try_receive() {
+            let until = Instant::now()
+                + embassy_time::Duration::from_millis(u64::from(request.duration_ms));
+            flash = Some((request.color, until));
+        }
+        if flash.is_some_and(|(_, until)| Instant::now() >= until) {
+            flash = None;
+        }
 
-    match mode {
-        catears::lights::Mode::Off => {
-            // All LEDs off - already initialized to black
+        let (power, mut lights) = {
+            let state = state.read().await;
+            (state.power && !status.thermal.is_critical(), state.lights)
+        };
+        // A playlist takes over both rings while it has entries, and the usual crossfades carry it from entry to entry.
+        if lights.playlist.entries().is_empty() {
+            playlist_clock = None;
+        } else if playlist_clock.is_none_or(|(playlist, _)| playlist != lights.playlist) {
+            playlist_clock = Some((lights.playlist, now_ms()));
         }
-        catears::lights::Mode::Solid(color) => {
-            let scaled = scale_brightness(*color, brightness_scale);
-            colors.fill(scaled);
+        if let Some(preset) = playlist_clock
+            .and_then(|(playlist, started_ms)| playlist.at(now_ms().wrapping_sub(started_ms)))
+        {
+            let mode = preset.mode();
+            lights.left = mode;
+            lights.right = mode;
         }
-        catears::lights::Mode::Gradient(start, end) => {
-            for (i, color) in colors.iter_mut().enumerate() {
-                #[allow(clippy::cast_precision_loss)]
-                let t = i as f32 / 11.0;
-                let interpolated = interpolate_color(*start, *end, t);
-                *color = scale_brightness(interpolated, brightness_scale);
+        let looped = matches!(
+            (&lights.left, &lights.right),
+            (
+                catears::lights::Mode::MirrorOther,
+                catears::lights::Mode::MirrorOther
+            )
+        );
+        if looped {
+            if !mirror_loop {
+                warn!("Both light rings set to mirror each other, turning them off");
             }
+            lights.left = catears::lights::Mode::Off;
+            lights.right = catears::lights::Mode::Off;
         }
-        catears::lights::Mode::Chase(pattern) => {
-            // Update position based on speed (10ms per loop iteration)
-            state.position = state.position.wrapping_add(1);
-            let steps_per_rotation = (pattern.speed_ms / 10).max(1);
-            #[allow(clippy::cast_possible_truncation)]
-            let current_step = (state.position / steps_per_rotation as u8) % 12;
-
-            // Fill background
-            let bg = scale_brightness(pattern.background, brightness_scale);
-            colors.fill(bg);
-
-            // Draw chase pattern
-            for i in 0..pattern.length {
-                let pos = if pattern.clockwise {
-                    (current_step + i) % 12
-                } else {
-                    (12 + current_step - i) % 12
-                };
-                colors[pos as usize] = scale_brightness(pattern.color, brightness_scale);
-            }
+        mirror_loop = looped;
+        if status.sleep.is_entering() {
+            sleep_fade = sleep_fade.saturating_sub(SLEEP_FADE_STEP);
         }
-        catears::lights::Mode::Pulse(pattern) => {
-            // Update pulse phase
-            state.pulse_phase = state.pulse_phase.wrapping_add(10); // 10ms per iteration
-            let phase = state.pulse_phase % pattern.period_ms;
-            let t = f32::from(phase) / f32::from(pattern.period_ms);
-
-            // Calculate brightness using sine wave
-            let sine = libm::sinf(t * 2.0 * core::f32::consts::PI);
-            let normalized = f32::midpoint(sine, 1.0); // Map from [-1,1] to [0,1]
-            let brightness = f32::from(pattern.min_brightness)
-                + f32::from(pattern.max_brightness - pattern.min_brightness) * normalized;
+        // Automatic brightness only ever dims, so the manual brightness stays the ceiling.
+        let ambient_scale = if lights.auto_brightness {
+            status.ambient.scale()
+        } else {
+            u8::MAX
+        };
+        let brightness_scale = if power {
+            scale_level(
+                lights
+                    .brightness
+                    .min(status.thermal.brightness_cap())
+                    .min(status.supply.brightness_cap()),
+                scale_level(ambient_scale, sleep_fade),
+            )
+        } else {
+            0
+        };
 
-            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
-            let pulsed = scale_brightness(pattern.color, brightness as u8);
-            let final_color = scale_brightness(pulsed, brightness_scale);
-            colors.fill(final_color);
+        // WS2812s hold their last frame, so once both rings have been sent black there is nothing to refresh. Rings
+        // switched off only count as black once they have faded out. Only a flash or a state change can light them up
+        // again, so just wait for those without any RMT traffic.
+        let dark = flash.is_none()
+            && (brightness_scale == 0
+                || (animation_state.is_settled(&lights)
+                    && matches!(
+                        (&lights.left, &lights.right),
+                        (
+                            catears::lights::Mode::Off | catears::lights::Mode::MirrorOther,
+                            catears::lights::Mode::Off | catears::lights::Mode::MirrorOther
+                        )
+                    )));
+        // The ramp is left out of whether the rings are dark, so that they are not parked before they fade in.
+        let brightness_scale = scale_level(brightness_scale, ramp.level(now_ms()));
+        // A dithered frame is rendered at full brightness and only dimmed as it goes out, so that what would be
+        // rounded away is still there to carry over.
+        let (brightness_scale, output_scale) = if lights.dither {
+            (u8::MAX, brightness_scale)
+        } else {
+            (brightness_scale, u8::MAX)
+        };
+        if dark && parked {
+            status.timing.record(Task::Leds, started);
+            embassy_futures::select::select3(
+                flashes.ready_to_receive(),
+                catears::state::CHANGED.wait(),
+                Timer::after(embassy_time::Duration::from_millis(LED_PARKED_HEARTBEAT_MS)),
+            )
+            .await;
+            continue;
         }
-        catears::lights::Mode::Rainbow(pattern) => {
-            // Update hue based on speed
-            let hue_step = 255 / (pattern.speed_ms / 10).max(1);
-            #[allow(clippy::cast_possible_truncation)]
-            let hue_increment = hue_step as u8;
-            state.hue = state.hue.wrapping_add(hue_increment);
-
-            if pattern.spread {
-                // Rainbow spread across all LEDs
-                for (i, color) in colors.iter_mut().enumerate() {
-                    #[allow(clippy::cast_possible_truncation)]
-                    let hue = state.hue.wrapping_add((i * 21) as u8); // 21 = 255/12
-                    let hsv = Hsv {
-                        hue,
-                        sat: 255,
-                        val: pattern.brightness,
-                    };
-                    *color = scale_brightness(hsv2rgb(hsv), brightness_scale);
-                }
+        if parked {
+            debug!("Resuming LED output");
+            parked = false;
+            // Start the animations over rather than jumping into the middle of wherever they were left.
+            animation_state = AnimationState::default();
+            last_frame = None;
+        }
+
+        animation_state.follow(&lights);
+        let elapsed_ms = last_frame.map_or(0, |last| {
+            u32::try_from(started.duration_since(last).as_millis()).unwrap_or(u32::MAX)
+        });
+        last_frame = Some(started);
+        let levels = level.get();
+        let flash_color = flash.map(|(color, _)| scale_brightness(color, brightness_scale));
+
+        // Render both rings before writing either, still advancing the animations underneath a flash so they resume
+        // smoothly.
+        let now = now_ms();
+        let left_beat = beat_clock(catears::audio::Side::Left).get(now);
+        let right_beat = beat_clock(catears::audio::Side::Right).get(now);
+        let battery = status.battery.percent();
+        let left_own = render(
+            &lights.left,
+            &mut animation_state.left,
+            brightness_scale,
+            &levels,
+            left_beat,
+            battery,
+            elapsed_ms,
+        );
+        let right_own = render(
+            &lights.right,
+            &mut animation_state.right,
+            brightness_scale,
+            &levels,
+            right_beat,
+            battery,
+            elapsed_ms,
+        );
+        let blend_left = |to, fade: &mut catears::lights::render::Crossfade<LED_COUNT>| {
+            fade.blend(to, lights.transition_ms, elapsed_ms, |mode, state| {
+                render(
+                    mode,
+                    state,
+                    brightness_scale,
+                    &levels,
+                    left_beat,
+                    battery,
+                    elapsed_ms,
+                )
+            })
+        };
+        let blend_right = |to, fade: &mut catears::lights::render::Crossfade<LED_COUNT>| {
+            fade.blend(to, lights.transition_ms, elapsed_ms, |mode, state| {
+                render(
+                    mode,
+                    state,
+                    brightness_scale,
+                    &levels,
+                    right_beat,
+                    battery,
+                    elapsed_ms,
+                )
+            })
+        };
+        // A ring mirroring the other fades into the other ring's colors as they are shown, crossfade and all, so the
+        // other ring is blended first.
+        let (mut left_colors, mut right_colors) =
+            if matches!(lights.left, catears::lights::Mode::MirrorOther) {
+                let right_colors = blend_right(right_own, &mut animation_state.fades.1);
+                (
+                    blend_left(mirror(right_colors), &mut animation_state.fades.0),
+                    right_colors,
+                )
             } else {
-                // All LEDs same color
-                let hsv = Hsv {
-                    hue: state.hue,
-                    sat: 255,
-                    val: pattern.brightness,
+                let left_colors = blend_left(left_own, &mut animation_state.fades.0);
+                let right_to = if matches!(lights.right, catears::lights::Mode::MirrorOther) {
+                    mirror(left_colors)
+                } else {
+                    right_own
                 };
-                let color = scale_brightness(hsv2rgb(hsv), brightness_scale);
-                colors.fill(color);
-            }
+                (
+                    left_colors,
+                    blend_right(right_to, &mut animation_state.fades.1),
+                )
+            };
+        animation_state.shown = (left_colors, right_colors);
+        if let Some(color) = flash_color {
+            left_colors.fill(color);
+            right_colors.fill(color);
         }
-        catears::lights::Mode::Custom(pattern) => {
-            for (i, color) in colors.iter_mut().enumerate() {
-                *color = scale_brightness(pattern.leds[i], brightness_scale);
-            }
+        let (left_colors, right_colors) = if lights.dither {
+            (
+                animation_state.dithers.0.scale(left_colors, output_scale),
+                animation_state.dithers.1.scale(right_colors, output_scale),
+            )
+        } else {
+            (left_colors, right_colors)
+        };
+        // White balance goes last, once the colors are scaled to the brightness with or without dithering, so that it
+        // corrects flashes just the same.
+        let left_colors = left_colors.map(|color| lights.white_balance.apply(color));
+        let right_colors = right_colors.map(|color| lights.white_balance.apply(color));
+        let left_colors = orient(
+            left_colors,
+            lights.rotation_offset_left,
+            lights.reversed_left,
+        );
+        let right_colors = orient(
+            right_colors,
+            lights.rotation_offset_right,
+            lights.reversed_right,
+        );
+
+        // Both rings are written at once so that they stay in step. A failed write just drops that ring's frame, the
+        // next one is rendered from scratch anyway.
+        let write_start = Instant::now();
+        let (left_result, right_result) = embassy_futures::join::join(
+            left.write(left_colors.into_iter()),
+            right.write(right_colors.into_iter()),
+        )
+        .await;
+        // Only a blackout frame that made it out to both rings lets the task park, a failed one is sent again.
+        if dark && left_result.is_ok() && right_result.is_ok() {
+            debug!("LEDs dark, suspending output");
+            parked = true;
         }
+        status.led_left.record("Left LED ring", left_result);
+        status.led_right.record("Right LED ring", right_result);
+        frame_times.record(write_start.elapsed().as_micros());
+        status.timing.record(Task::Leds, started);
+
+        Timer::after(embassy_time::Duration::from_millis(u64::from(
+            catears::lights::render::FRAME_MS,
+        )))
+        .await;
     }
-
-    colors
 }
 
-fn scale_brightness(color: smart_leds::RGB8, scale: u8) -> smart_leds::RGB8 {
-    #[allow(clippy::cast_possible_truncation)]
-    let r = ((u16::from(color.r) * u16::from(scale)) / 255) as u8;
-    #[allow(clippy::cast_possible_truncation)]
-    let g = ((u16::from(color.g) * u16::from(scale)) / 255) as u8;
+fn scale_level(level: u8, scale: u8) -> u8 {
     #[allow(clippy::cast_possible_truncation)]
-    let b = ((u16::from(color.b) * u16::from(scale)) / 255) as u8;
-
-    smart_leds::RGB8::new(r, g, b)
-}
-
-fn interpolate_color(start: smart_leds::RGB8, end: smart_leds::RGB8, t: f32) -> smart_leds::RGB8 {
-    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
-    let r = (f32::from(start.r) + (f32::from(end.r) - f32::from(start.r)) * t) as u8;
-    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
-    let g = (f32::from(start.g) + (f32::from(end.g) - f32::from(start.g)) * t) as u8;
-    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
-    let b = (f32::from(start.b) + (f32::from(end.b) - f32::from(start.b)) * t) as u8;
-
-    smart_leds::RGB8::new(r, g, b)
+    let level = ((u16::from(level) * u16::from(scale)) / 255) as u8;
+    level
 }
@@ -0,0 +1,517 @@
+//! Head motion tracking from an I2C accelerometer.
+//!
+//! The firmware samples an [`Imu`] at a fixed rate and feeds the readings into a [`Tracker`], which turns them into a
+//! tilt estimate and one-shot [`Event`]s (a quick shake, tilting the head down). The latest readings are published into
+//! [`Readings`] in the runtime status, and events are turned into servo gestures and light flashes according to the
+//! configured [`Reaction`]s.
+
+use core::sync::atomic::{AtomicBool, AtomicI16, AtomicU32, Ordering};
+
+use embedded_hal_async::i2c::I2c;
+use serde::{Deserialize, Serialize};
+
+use crate::lights::flashes::Flash;
+use crate::servo::gestures::Gesture;
+
+/// Number of samples averaged when capturing a calibration offset.
+pub const CALIBRATION_SAMPLES: u32 = 16;
+
+/// Acceleration along each axis in milli-g.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, defmt::Format)]
+pub struct Acceleration {
+    /// Acceleration along the X axis, pointing forward out of the face.
+    pub x: i16,
+    /// Acceleration along the Y axis, pointing towards the left ear.
+    pub y: i16,
+    /// Acceleration along the Z axis, pointing up out of the top of the head.
+    pub z: i16,
+}
+
+impl Acceleration {
+    /// Reading of a level, motionless sensor.
+    pub const LEVEL: Self = Self::new(0, 0, 1000);
+
+    /// Creates a new acceleration.
+    #[must_use]
+    pub const fn new(x: i16, y: i16, z: i16) -> Self {
+        Self { x, y, z }
+    }
+
+    /// Returns the magnitude of the acceleration in milli-g.
+    #[must_use]
+    pub fn magnitude(&self) -> u16 {
+        let [x, y, z] = [self.x, self.y, self.z].map(|v| f32::from(v) * f32::from(v));
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        {
+            libm::sqrtf(x + y + z) as u16
+        }
+    }
+
+    /// Returns the forward (positive when looking down) and sideways (positive when leaning towards the right ear)
+    /// tilt in whole degrees.
+    #[must_use]
+    pub fn tilt(&self) -> (i16, i16) {
+        let (x, y, z) = (f32::from(self.x), f32::from(self.y), f32::from(self.z));
+        let pitch = libm::atan2f(-x, libm::sqrtf(y * y + z * z));
+        let roll = libm::atan2f(y, z);
+        #[allow(clippy::cast_possible_truncation)]
+        (
+            libm::roundf(pitch.to_degrees()) as i16,
+            libm::roundf(roll.to_degrees()) as i16,
+        )
+    }
+
+    fn offset_by(self, offset: Self) -> Self {
+        Self::new(
+            self.x.saturating_sub(offset.x),
+            self.y.saturating_sub(offset.y),
+            self.z.saturating_sub(offset.z),
+        )
+    }
+}
+
+/// Motion events recognized by a [`Tracker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Event {
+    /// A quick shake of the head.
+    Shake,
+    /// The head was tilted down past [`Config::tilt_down_deg`].
+    TiltDown,
+}
+
+/// What the ears do in response to a motion or touch event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reaction {
+    /// Gesture to play on both ears, if any.
+    pub gesture: Option<Gesture>,
+    /// Flash to show on both LED rings, if any.
+    pub flash: Option<Flash>,
+}
+
+impl Reaction {
+    /// A reaction that does nothing.
+    pub const NONE: Self = Self {
+        gesture: None,
+        flash: None,
+    };
+}
+
+/// Configuration for motion tracking and reactions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    /// Time in milliseconds between samples.
+    pub sample_interval_ms: u64,
+    /// Deviation in milli-g from the slowly tracked acceleration magnitude that counts as a shake.
+    pub shake_threshold_mg: u16,
+    /// Minimum time in milliseconds between two reported shakes.
+    pub shake_cooldown_ms: u64,
+    /// Forward tilt in degrees at or beyond which the head counts as tilted down.
+    pub tilt_down_deg: i16,
+    /// Forward tilt in degrees below which the head counts as raised again.
+    pub tilt_release_deg: i16,
+    /// Reaction to [`Event::Shake`].
+    pub shake: Reaction,
+    /// Reaction to [`Event::TiltDown`].
+    pub tilt_down: Reaction,
+}
+
+impl Config {
+    /// Default configuration: wiggle and flash on a shake, droop when looking down.
+    pub const DEFAULT: Self = Self {
+        sample_interval_ms: 20,
+        shake_threshold_mg: 600,
+        shake_cooldown_ms: 1500,
+        tilt_down_deg: 35,
+        tilt_release_deg: 20,
+        shake: Reaction {
+            gesture: Some(Gesture::Wiggle),
+            flash: Some(Flash::new(smart_leds::RGB8::new(255, 255, 255), 150)),
+        },
+        tilt_down: Reaction {
+            gesture: Some(Gesture::Droop),
+            flash: None,
+        },
+    };
+
+    /// Returns the reaction configured for `event`.
+    #[must_use]
+    pub const fn reaction(&self, event: Event) -> Reaction {
+        match event {
+            Event::Shake => self.shake,
+            Event::TiltDown => self.tilt_down,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Processed output of a single [`Tracker::update`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sample {
+    /// Calibrated acceleration.
+    pub acceleration: Acceleration,
+    /// Forward tilt in degrees, positive when looking down.
+    pub pitch: i16,
+    /// Sideways tilt in degrees, positive when leaning towards the right ear.
+    pub roll: i16,
+    /// Event recognized at this sample, if any.
+    pub event: Option<Event>,
+}
+
+/// Tilt and shake detector for a stream of accelerometer readings.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::motion::{Acceleration, Config, Event, Tracker};
+///
+/// let mut tracker = Tracker::new(Config::DEFAULT);
+///
+/// // Holding still produces no events.
+/// for t in 0..50 {
+///     let sample = tracker.update(Acceleration::LEVEL, t * 20);
+///     assert_eq!(sample.event, None);
+///     assert_eq!((sample.pitch, sample.roll), (0, 0));
+/// }
+///
+/// // A sudden jolt is a shake, but a second one within the cooldown is ignored.
+/// assert_eq!(tracker.update(Acceleration::new(1500, 0, 1000), 1000).event, Some(Event::Shake));
+/// assert_eq!(tracker.update(Acceleration::new(-1500, 0, 1000), 1020).event, None);
+///
+/// // Looking down is reported once, until the head is raised again.
+/// let down = Acceleration::new(-707, 0, 707);
+/// let sample = tracker.update(down, 5000);
+/// assert_eq!((sample.pitch, sample.event), (45, Some(Event::TiltDown)));
+/// assert_eq!(tracker.update(down, 5020).event, None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tracker {
+    config: Config,
+    offset: Acceleration,
+    calibration: Option<(i32, i32, i32, u32)>,
+    /// Slowly tracked acceleration magnitude in milli-g, scaled by 16.
+    baseline: Option<i32>,
+    last_shake_ms: Option<u64>,
+    tilted_down: bool,
+}
+
+impl Tracker {
+    /// Creates a new uncalibrated tracker.
+    #[must_use]
+    pub const fn new(config: Config) -> Self {
+        Self {
+            config,
+            offset: Acceleration::new(0, 0, 0),
+            calibration: None,
+            baseline: None,
+            last_shake_ms: None,
+            tilted_down: false,
+        }
+    }
+
+    /// Returns the configuration of the tracker.
+    #[must_use]
+    pub const fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Returns the offset subtracted from every raw reading.
+    #[must_use]
+    pub const fn offset(&self) -> Acceleration {
+        self.offset
+    }
+
+    /// Starts capturing a new zero offset over the next [`CALIBRATION_SAMPLES`] readings.
+    ///
+    /// The head should be held level and still while calibrating. Events are suppressed until the capture completes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use catears::motion::{Acceleration, Config, Tracker, CALIBRATION_SAMPLES};
+    ///
+    /// let mut tracker = Tracker::new(Config::DEFAULT);
+    /// tracker.calibrate();
+    /// for t in 0..u64::from(CALIBRATION_SAMPLES) {
+    ///     tracker.update(Acceleration::new(100, -50, 980), t * 20);
+    /// }
+    /// assert_eq!(tracker.offset(), Acceleration::new(100, -50, -20));
+    /// assert!(!tracker.is_calibrating());
+    /// ```
+    pub fn calibrate(&mut self) {
+        self.calibration = Some((0, 0, 0, 0));
+    }
+
+    /// Returns whether a calibration capture is in progress.
+    #[must_use]
+    pub const fn is_calibrating(&self) -> bool {
+        self.calibration.is_some()
+    }
+
+    /// Processes a raw reading taken at `now_ms`.
+    pub fn update(&mut self, raw: Acceleration, now_ms: u64) -> Sample {
+        if let Some((x, y, z, count)) = self.calibration.as_mut() {
+            *x += i32::from(raw.x);
+            *y += i32::from(raw.y);
+            *z += i32::from(raw.z);
+            *count += 1;
+            if *count >= CALIBRATION_SAMPLES {
+                let average =
+                    |sum: i32| clamp_i16(sum / i32::try_from(CALIBRATION_SAMPLES).unwrap_or(1));
+                let level = Acceleration::LEVEL;
+                self.offset = Acceleration::new(
+                    average(*x).saturating_sub(level.x),
+                    average(*y).saturating_sub(level.y),
+                    average(*z).saturating_sub(level.z),
+                );
+                self.calibration = None;
+                self.baseline = None;
+            }
+        }
+
+        let acceleration = raw.offset_by(self.offset);
+        let (pitch, roll) = acceleration.tilt();
+        let (jolt, shake) = self.detect_shake(acceleration, now_ms);
+        let event = if self.calibration.is_some() {
+            None
+        } else if shake.is_some() {
+            shake
+        } else if jolt < u32::from(self.config.shake_threshold_mg) {
+            // The tilt estimate assumes gravity is the only acceleration, so it is meaningless while the head is moving.
+            self.detect_tilt(pitch)
+        } else {
+            None
+        };
+
+        Sample {
+            acceleration,
+            pitch,
+            roll,
+            event,
+        }
+    }
+
+    /// Returns the deviation from the tracked magnitude in milli-g, along with the shake it caused, if any.
+    fn detect_shake(&mut self, acceleration: Acceleration, now_ms: u64) -> (u32, Option<Event>) {
+        let magnitude = i32::from(acceleration.magnitude());
+        // One-pole low-pass of the magnitude; what is left after subtracting it is the high-passed jolt.
+        let baseline = self.baseline.get_or_insert(magnitude << 4);
+        let jolt = (magnitude - (*baseline >> 4)).unsigned_abs();
+        *baseline += magnitude - (*baseline >> 4);
+
+        let cooled_down = self
+            .last_shake_ms
+            .is_none_or(|last| now_ms.saturating_sub(last) >= self.config.shake_cooldown_ms);
+        if jolt >= u32::from(self.config.shake_threshold_mg) && cooled_down {
+            self.last_shake_ms = Some(now_ms);
+            (jolt, Some(Event::Shake))
+        } else {
+            (jolt, None)
+        }
+    }
+
+    fn detect_tilt(&mut self, pitch: i16) -> Option<Event> {
+        if !self.tilted_down && pitch >= self.config.tilt_down_deg {
+            self.tilted_down = true;
+            Some(Event::TiltDown)
+        } else {
+            if self.tilted_down && pitch < self.config.tilt_release_deg {
+                self.tilted_down = false;
+            }
+            None
+        }
+    }
+}
+
+fn clamp_i16(value: i32) -> i16 {
+    i16::try_from(value.clamp(i32::from(i16::MIN), i32::from(i16::MAX))).unwrap_or_default()
+}
+
+/// Latest motion readings, published by the motion task for the rest of the firmware.
+pub struct Readings {
+    present: AtomicBool,
+    calibration_requested: AtomicBool,
+    pitch: AtomicI16,
+    roll: AtomicI16,
+    shakes: AtomicU32,
+}
+
+impl Readings {
+    /// Creates new readings for an absent sensor.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            present: AtomicBool::new(false),
+            calibration_requested: AtomicBool::new(false),
+            pitch: AtomicI16::new(0),
+            roll: AtomicI16::new(0),
+            shakes: AtomicU32::new(0),
+        }
+    }
+
+    /// Marks the sensor as detected.
+    pub fn set_present(&self) {
+        self.present.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether a sensor was detected at boot.
+    #[must_use]
+    pub fn is_present(&self) -> bool {
+        self.present.load(Ordering::Relaxed)
+    }
+
+    /// Publishes a processed sample.
+    pub fn publish(&self, sample: &Sample) {
+        self.pitch.store(sample.pitch, Ordering::Relaxed);
+        self.roll.store(sample.roll, Ordering::Relaxed);
+        if sample.event == Some(Event::Shake) {
+            self.shakes.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the latest forward and sideways tilt in degrees.
+    #[must_use]
+    pub fn tilt(&self) -> (i16, i16) {
+        (
+            self.pitch.load(Ordering::Relaxed),
+            self.roll.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Returns the number of shakes detected since boot.
+    #[must_use]
+    pub fn shakes(&self) -> u32 {
+        self.shakes.load(Ordering::Relaxed)
+    }
+
+    /// Asks the motion task to capture a new zero offset.
+    pub fn request_calibration(&self) {
+        self.calibration_requested.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether a calibration was requested since the last call, clearing the request.
+    pub fn take_calibration_request(&self) -> bool {
+        self.calibration_requested.swap(false, Ordering::Relaxed)
+    }
+}
+
+impl Default for Readings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Supported accelerometers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Sensor {
+    /// ST LIS3DH, in high resolution mode at 100 Hz and ±2 g.
+    Lis3dh,
+    /// `InvenSense` MPU-6050, accelerometer only at ±2 g.
+    Mpu6050,
+}
+
+/// Errors returned by the [`Imu`] driver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Error<E> {
+    /// No supported sensor answered on the bus.
+    NotFound,
+    /// The sensor was found but a bus transaction failed.
+    Bus(E),
+}
+
+impl<E> From<E> for Error<E> {
+    fn from(e: E) -> Self {
+        Self::Bus(e)
+    }
+}
+
+/// Accelerometer driver for the supported [`Sensor`]s.
+pub struct Imu<I> {
+    i2c: I,
+    sensor: Sensor,
+    address: u8,
+}
+
+impl<I: I2c> Imu<I> {
+    /// Looks for a supported sensor on the bus at any of its usual addresses and configures the first one found.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotFound`] if no supported sensor answers, or [`Error::Bus`] if configuring it fails.
+    pub async fn probe(mut i2c: I) -> Result<Self, Error<I::Error>> {
+        const CANDIDATES: [(Sensor, u8, u8, u8); 4] = [
+            // (sensor, address, WHO_AM_I register, expected value)
+            (Sensor::Lis3dh, 0x18, 0x0F, 0x33),
+            (Sensor::Lis3dh, 0x19, 0x0F, 0x33),
+            (Sensor::Mpu6050, 0x68, 0x75, 0x68),
+            (Sensor::Mpu6050, 0x69, 0x75, 0x68),
+        ];
+
+        for (sensor, address, register, expected) in CANDIDATES {
+            let mut id = [0u8];
+            // Absent devices simply don't acknowledge, so a failed read just moves on to the next candidate.
+            if i2c.write_read(address, &[register], &mut id).await.is_err() || id[0] != expected {
+                continue;
+            }
+
+            match sensor {
+                Sensor::Lis3dh => {
+                    // CTRL_REG1: 100 Hz, all axes enabled. CTRL_REG4: block data update, high resolution, ±2 g.
+                    i2c.write(address, &[0x20, 0x57]).await?;
+                    i2c.write(address, &[0x23, 0x88]).await?;
+                }
+                Sensor::Mpu6050 => {
+                    // PWR_MGMT_1: wake up. ACCEL_CONFIG: ±2 g.
+                    i2c.write(address, &[0x6B, 0x00]).await?;
+                    i2c.write(address, &[0x1C, 0x00]).await?;
+                }
+            }
+            return Ok(Self {
+                i2c,
+                sensor,
+                address,
+            });
+        }
+
+        Err(Error::NotFound)
+    }
+
+    /// Returns the detected sensor.
+    #[must_use]
+    pub const fn sensor(&self) -> Sensor {
+        self.sensor
+    }
+
+    /// Reads the current acceleration.
+    ///
+    /// # Errors
+    ///
+    /// Returns the bus error if the read fails.
+    pub async fn read(&mut self) -> Result<Acceleration, I::Error> {
+        let mut data = [0u8; 6];
+        match self.sensor {
+            Sensor::Lis3dh => {
+                // OUT_X_L with the auto-increment bit set; 12-bit left-justified little-endian values, 1 mg per digit.
+                self.i2c
+                    .write_read(self.address, &[0x28 | 0x80], &mut data)
+                    .await?;
+                let axis = |i: usize| i16::from_le_bytes([data[i], data[i + 1]]) >> 4;
+                Ok(Acceleration::new(axis(0), axis(2), axis(4)))
+            }
+            Sensor::Mpu6050 => {
+                // ACCEL_XOUT_H; 16-bit big-endian values, 16384 digits per g.
+                self.i2c
+                    .write_read(self.address, &[0x3B], &mut data)
+                    .await?;
+                let axis = |i: usize| {
+                    clamp_i16((i32::from(i16::from_be_bytes([data[i], data[i + 1]])) * 1000) >> 14)
+                };
+                Ok(Acceleration::new(axis(0), axis(2), axis(4)))
+            }
+        }
+    }
+}
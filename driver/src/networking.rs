@@ -1,11 +1,15 @@
 #![allow(clippy::doc_markdown)]
 
+use core::sync::atomic::{AtomicBool, Ordering};
+
 use defmt::{info, warn};
+use embassy_futures::select::{select, Either};
 use embassy_net::{DhcpConfig, Runner, StackResources};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, signal::Signal};
 use embassy_time::{Duration, Timer};
 use esp_hal::peripherals::WIFI;
 use esp_wifi::{
-    wifi::{WifiController, WifiDevice},
+    wifi::{WifiController, WifiDevice, WifiEvent},
     EspWifiController, EspWifiRngSource, EspWifiTimerSource, InitializationError,
 };
 use static_cell::StaticCell;
@@ -24,6 +28,15 @@ static RADIO_CONTROLLER: StaticCell<EspWifiController> = StaticCell::new();
 /// TCP/UDP networking operations.
 static NETWORKING_STACK_RESOURCES: StaticCell<StackResources<8>> = StaticCell::new();
 
+/// Whether the network task is running and can be asked to disconnect.
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Signalled to ask the network task to disconnect from the access point and stop the radio.
+static DISCONNECT_REQUEST: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Signalled by the network task once the radio is stopped.
+static DISCONNECTED: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
 /// Configuration parameters for WiFi networking setup.
 ///
 /// This struct contains all the necessary configuration parameters to establish a WiFi connection and configure the
@@ -60,15 +73,17 @@ pub struct Config {
     pub dhcp_hostname: heapless::String<32>,
 }
 
-/// Initializes the WiFi networking stack and connects to the configured access point.
+/// Initializes the WiFi networking stack and starts connecting to the configured access point.
 ///
 /// This function performs the complete WiFi initialization sequence including:
 /// - Initializing the radio controller
 /// - Configuring and starting the WiFi client
-/// - Connecting to the specified access point with retry logic
 /// - Setting up the networking stack with DHCP configuration
-/// - Spawning the network runner task
-/// - Waiting for link and configuration to be established
+/// - Spawning the network task, which connects to the access point in the background and reconnects whenever the
+///   connection drops
+///
+/// It does not wait for the connection, so that the rest of the firmware can come up while the access point is out of
+/// reach. Use [`embassy_net::Stack::wait_config_up`] to wait for the network to become usable.
 ///
 /// # Parameters
 ///
@@ -80,7 +95,7 @@ pub struct Config {
 ///
 /// # Returns
 ///
-/// Returns a configured and connected `embassy_net::Stack` on success.
+/// Returns a configured `embassy_net::Stack` on success.
 ///
 /// # Errors
 ///
@@ -107,65 +122,88 @@ pub async fn init(
         let (mut controller, interfaces) = esp_wifi::wifi::new(radio_controller, wifi)?;
         controller.set_configuration(&esp_wifi::wifi::Configuration::Client(config.client))?;
         controller.start_async().await?;
-        loop {
-            match controller.connect_async().await {
-                Ok(()) => {
-                    info!("WiFi connected!");
-                    break;
-                }
-                Err(e) => {
-                    warn!("Failed to connect to WiFi: {:?}", e);
-                    Timer::after(Duration::from_millis(5000)).await;
-                }
-            }
-        }
-
         (controller, interfaces.sta)
     };
 
-    let stack = {
-        let mut dhcp_config = DhcpConfig::default();
-        info!(
-            "Setting hostname for DHCP configuration: {}",
-            config.dhcp_hostname
-        );
-        dhcp_config.hostname = Some(config.dhcp_hostname);
-
-        let config = embassy_net::Config::dhcpv4(dhcp_config);
-        let (stack, runner) = embassy_net::new(
-            wifi_interface,
-            config,
-            NETWORKING_STACK_RESOURCES.init(StackResources::new()),
-            // Note that this is bad randomness since we're casting the u32 to a u64, but we don't really care.
-            seed,
-        );
-
-        spawner
-            .spawn(net_task(wifi_controller, runner))
-            .expect("Failed to spawn stack runner");
-
-        stack.wait_link_up().await;
-        stack.wait_config_up().await;
-
-        if let Some(config) = stack.config_v4() {
-            info!("Stack initialized with IP: {}", config.address.address());
-        } else {
-            warn!("Stack initialized, but could not find IP address.");
-        }
-        stack
-    };
+    let mut dhcp_config = DhcpConfig::default();
+    info!(
+        "Setting hostname for DHCP configuration: {}",
+        config.dhcp_hostname
+    );
+    dhcp_config.hostname = Some(config.dhcp_hostname);
+
+    let config = embassy_net::Config::dhcpv4(dhcp_config);
+    let (stack, runner) = embassy_net::new(
+        wifi_interface,
+        config,
+        NETWORKING_STACK_RESOURCES.init(StackResources::new()),
+        // Note that this is bad randomness since we're casting the u32 to a u64, but we don't really care.
+        seed,
+    );
+
+    spawner
+        .spawn(net_task(wifi_controller, runner))
+        .expect("Failed to spawn stack runner");
 
     Ok(stack)
 }
 
+/// Disconnects from the access point and stops the radio, e.g. before going to sleep.
+///
+/// The networking stack is unusable afterwards. Returns immediately if networking was never initialized.
+pub async fn disconnect() {
+    if !RUNNING.load(Ordering::Relaxed) {
+        return;
+    }
+    DISCONNECT_REQUEST.signal(());
+    DISCONNECTED.wait().await;
+}
+
 #[embassy_executor::task]
 async fn net_task(
-    wifi_controller: WifiController<'static>,
+    mut wifi_controller: WifiController<'static>,
     mut runner: Runner<'static, WifiDevice<'static>>,
 ) -> ! {
     // We need to hold on to the wifi_controller to keep the WiFi device alive. If we don't do this, we get weird
     // memory issues...
-    let _ = wifi_controller;
     info!("Starting networking stack runner...");
-    runner.run().await
+    RUNNING.store(true, Ordering::Relaxed);
+    let connection = async {
+        select(
+            stay_connected(&mut wifi_controller),
+            DISCONNECT_REQUEST.wait(),
+        )
+        .await;
+        info!("Disconnecting from WiFi...");
+        if let Err(e) = wifi_controller.disconnect_async().await {
+            warn!("Failed to disconnect from WiFi: {:?}", e);
+        }
+        if let Err(e) = wifi_controller.stop_async().await {
+            warn!("Failed to stop WiFi: {:?}", e);
+        }
+    };
+    match select(runner.run(), connection).await {
+        Either::First(never) => never,
+        Either::Second(()) => {
+            DISCONNECTED.signal(());
+            core::future::pending().await
+        }
+    }
+}
+
+/// Connects to the access point, retrying until it succeeds, and reconnects whenever the connection drops.
+async fn stay_connected(controller: &mut WifiController<'static>) -> ! {
+    loop {
+        match controller.connect_async().await {
+            Ok(()) => {
+                info!("WiFi connected!");
+                controller.wait_for_event(WifiEvent::StaDisconnected).await;
+                warn!("WiFi disconnected, reconnecting...");
+            }
+            Err(e) => {
+                warn!("Failed to connect to WiFi: {:?}", e);
+                Timer::after(Duration::from_millis(5000)).await;
+            }
+        }
+    }
 }
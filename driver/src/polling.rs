@@ -0,0 +1,91 @@
+//! Adaptive polling of the remote state.
+//!
+//! The remote state changes a handful of times an hour, usually in bursts of edits. The update task therefore polls
+//! quickly for a while after every change, then backs off towards a slow interval while nothing changes, following a
+//! [`Schedule`] configured by [`crate::state::PollingConfig`].
+
+use crate::state::PollingConfig;
+
+/// Longest allowed poll interval in milliseconds, well inside the update task's watchdog timeout.
+pub const MAX_INTERVAL_MS: u32 = 60_000;
+
+/// Decides how long to wait before the next poll.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::polling::Schedule;
+/// use catears::state::PollingConfig;
+///
+/// let config = PollingConfig {
+///     fast_ms: 500,
+///     slow_ms: 2000,
+///     fast_window_s: 10,
+///     decay_pct: 200,
+/// };
+/// let mut schedule = Schedule::new();
+///
+/// // Polls fast for the window after a change.
+/// assert_eq!(schedule.changed(&config, 0), 500);
+/// assert_eq!(schedule.unchanged(&config, 9_000), 500);
+///
+/// // Then backs off until it reaches the slow interval.
+/// assert_eq!(schedule.unchanged(&config, 10_000), 1000);
+/// assert_eq!(schedule.unchanged(&config, 11_000), 2000);
+/// assert_eq!(schedule.unchanged(&config, 13_000), 2000);
+///
+/// // And snaps back to fast on the next change.
+/// assert_eq!(schedule.changed(&config, 15_000), 500);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Schedule {
+    interval_ms: u32,
+    fast_until_ms: u64,
+}
+
+impl Schedule {
+    /// Creates a new schedule that starts out at the fast interval and backs off right away.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            interval_ms: 0,
+            fast_until_ms: 0,
+        }
+    }
+
+    /// Returns the current poll interval in milliseconds.
+    #[must_use]
+    pub const fn interval_ms(&self) -> u32 {
+        self.interval_ms
+    }
+
+    /// Records that a poll at `now_ms` observed a change, and returns the interval until the next poll.
+    pub fn changed(&mut self, config: &PollingConfig, now_ms: u64) -> u32 {
+        self.fast_until_ms = now_ms + u64::from(config.fast_window_s) * 1000;
+        self.interval_ms = Self::clamp(config, config.fast_ms);
+        self.interval_ms
+    }
+
+    /// Records that a poll at `now_ms` observed no change, and returns the interval until the next poll.
+    pub fn unchanged(&mut self, config: &PollingConfig, now_ms: u64) -> u32 {
+        let interval_ms = if now_ms < self.fast_until_ms {
+            config.fast_ms
+        } else {
+            let decayed = u64::from(self.interval_ms) * u64::from(config.decay_pct) / 100;
+            u32::try_from(decayed).unwrap_or(u32::MAX)
+        };
+        self.interval_ms = Self::clamp(config, interval_ms);
+        self.interval_ms
+    }
+
+    fn clamp(config: &PollingConfig, interval_ms: u32) -> u32 {
+        let slow_ms = config.slow_ms.min(MAX_INTERVAL_MS);
+        interval_ms.clamp(config.fast_ms.min(slow_ms), slow_ms)
+    }
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
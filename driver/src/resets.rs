@@ -0,0 +1,233 @@
+//! Evidence of unexpected reboots.
+//!
+//! At boot the firmware classifies why the chip reset into a [`Cause`], and carries a [`Ledger`] across resets in RTC
+//! memory that counts the reboots since power on, counts brownouts in a row, and remembers how long the previous session
+//! had been up. Both end up in [`Report`] in the runtime status, where `system info` shows them.
+
+use core::sync::atomic::{AtomicU32, AtomicU8, Ordering};
+
+/// Size in 32-bit words of an encoded ledger.
+pub const LEDGER_WORDS: usize = 5;
+
+/// Marks a valid ledger, and changes whenever the ledger layout does.
+const LEDGER_MAGIC: u32 = 0xCA7E_B008;
+
+/// Why the chip last reset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Cause {
+    /// Power was applied, so there is no previous session to speak of.
+    PowerOn,
+    /// The firmware or a debugger reset the chip on purpose.
+    Software,
+    /// Woken from deep sleep.
+    DeepSleep,
+    /// The firmware panicked and reset the chip.
+    Panic,
+    /// A watchdog reset the chip because the firmware hung.
+    Watchdog,
+    /// The supply voltage dropped too low.
+    Brownout,
+    /// Anything else, like a clock or power glitch.
+    Other,
+}
+
+impl Cause {
+    /// Returns the human-readable name of the cause.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::PowerOn => "power on",
+            Self::Software => "software",
+            Self::DeepSleep => "deep sleep",
+            Self::Panic => "panic",
+            Self::Watchdog => "watchdog",
+            Self::Brownout => "brownout",
+            Self::Other => "other",
+        }
+    }
+
+    /// Returns whether the reset was unexpected and worth looking into.
+    #[must_use]
+    pub const fn is_unexpected(self) -> bool {
+        matches!(
+            self,
+            Self::Panic | Self::Watchdog | Self::Brownout | Self::Other
+        )
+    }
+
+    pub(crate) const fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Self::Software,
+            2 => Self::DeepSleep,
+            3 => Self::Panic,
+            4 => Self::Watchdog,
+            5 => Self::Brownout,
+            6 => Self::Other,
+            _ => Self::PowerOn,
+        }
+    }
+
+    pub(crate) const fn to_u8(self) -> u8 {
+        match self {
+            Self::PowerOn => 0,
+            Self::Software => 1,
+            Self::DeepSleep => 2,
+            Self::Panic => 3,
+            Self::Watchdog => 4,
+            Self::Brownout => 5,
+            Self::Other => 6,
+        }
+    }
+}
+
+/// Reboot bookkeeping kept in RTC memory across resets.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::resets::{Cause, Ledger, LEDGER_WORDS};
+///
+/// let ledger = Ledger {
+///     reboots: 3,
+///     brownouts: 1,
+///     uptime_s: 3600,
+/// };
+/// assert_eq!(Ledger::decode(&ledger.encode()), Some(ledger));
+///
+/// // Brownouts are counted until the chip resets for any other reason.
+/// let ledger = Ledger::next(Some(ledger), Cause::Brownout);
+/// assert_eq!((ledger.reboots, ledger.brownouts), (4, 2));
+/// let ledger = Ledger::next(Some(ledger), Cause::Software);
+/// assert_eq!((ledger.reboots, ledger.brownouts), (5, 0));
+///
+/// // Garbage left in memory after a cold boot is rejected.
+/// let mut words = ledger.encode();
+/// words[2] ^= 1;
+/// assert_eq!(Ledger::decode(&words), None);
+/// assert_eq!(Ledger::decode(&[0u32; LEDGER_WORDS]), None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Ledger {
+    /// Number of resets since power on.
+    pub reboots: u32,
+    /// Number of brownout resets in a row.
+    pub brownouts: u32,
+    /// Time in seconds the session had been up when the ledger was last saved.
+    pub uptime_s: u32,
+}
+
+impl Ledger {
+    /// Serializes the ledger.
+    #[must_use]
+    pub fn encode(&self) -> [u32; LEDGER_WORDS] {
+        [
+            LEDGER_MAGIC,
+            self.reboots,
+            self.brownouts,
+            self.uptime_s,
+            Self::checksum(self.reboots, self.brownouts, self.uptime_s),
+        ]
+    }
+
+    /// Deserializes a ledger encoded with [`Ledger::encode`], or returns `None` if the words do not hold one.
+    #[must_use]
+    pub fn decode(words: &[u32; LEDGER_WORDS]) -> Option<Self> {
+        let [magic, reboots, brownouts, uptime_s, checksum] = *words;
+        (magic == LEDGER_MAGIC && checksum == Self::checksum(reboots, brownouts, uptime_s))
+            .then_some(Self {
+                reboots,
+                brownouts,
+                uptime_s,
+            })
+    }
+
+    /// Returns the ledger of a new session after a reset with the given cause, following the ledger that survived the
+    /// reset, if any.
+    #[must_use]
+    pub const fn next(previous: Option<Self>, cause: Cause) -> Self {
+        let (reboots, brownouts) = match (previous, cause) {
+            (_, Cause::PowerOn) | (None, _) => (0, 0),
+            (Some(previous), Cause::Brownout) => (
+                previous.reboots.saturating_add(1),
+                previous.brownouts.saturating_add(1),
+            ),
+            (Some(previous), _) => (previous.reboots.saturating_add(1), 0),
+        };
+        Self {
+            reboots,
+            brownouts,
+            uptime_s: 0,
+        }
+    }
+
+    fn checksum(reboots: u32, brownouts: u32, uptime_s: u32) -> u32 {
+        let mut bytes = [0u8; 12];
+        bytes[..4].copy_from_slice(&reboots.to_le_bytes());
+        bytes[4..8].copy_from_slice(&brownouts.to_le_bytes());
+        bytes[8..].copy_from_slice(&uptime_s.to_le_bytes());
+        crate::sleep::checksum(&bytes)
+    }
+}
+
+/// Why the device last reset and what came before, published at boot.
+pub struct Report {
+    cause: AtomicU8,
+    reboots: AtomicU32,
+    brownouts: AtomicU32,
+    previous_uptime_s: AtomicU32,
+}
+
+impl Report {
+    /// Creates a new report of a power on.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            cause: AtomicU8::new(Cause::PowerOn.to_u8()),
+            reboots: AtomicU32::new(0),
+            brownouts: AtomicU32::new(0),
+            previous_uptime_s: AtomicU32::new(0),
+        }
+    }
+
+    /// Publishes why the device reset, the ledger of the previous session if one survived, and the ledger of this
+    /// session.
+    pub fn publish(&self, cause: Cause, previous: Option<Ledger>, current: Ledger) {
+        self.cause.store(cause.to_u8(), Ordering::Relaxed);
+        self.reboots.store(current.reboots, Ordering::Relaxed);
+        self.brownouts.store(current.brownouts, Ordering::Relaxed);
+        self.previous_uptime_s.store(
+            previous.map_or(0, |previous| previous.uptime_s),
+            Ordering::Relaxed,
+        );
+    }
+
+    /// Returns why the device last reset.
+    #[must_use]
+    pub fn cause(&self) -> Cause {
+        Cause::from_u8(self.cause.load(Ordering::Relaxed))
+    }
+
+    /// Returns the number of resets since power on.
+    #[must_use]
+    pub fn reboots(&self) -> u32 {
+        self.reboots.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of brownout resets in a row.
+    #[must_use]
+    pub fn brownouts(&self) -> u32 {
+        self.brownouts.load(Ordering::Relaxed)
+    }
+
+    /// Returns how long in seconds the previous session had been up, or zero after a power on.
+    #[must_use]
+    pub fn previous_uptime_s(&self) -> u32 {
+        self.previous_uptime_s.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for Report {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -213,4 +213,185 @@ where
         self.pwm
             .set_duty_cycle(u16::try_from(desired_duty).expect("desired duty too large"))
     }
+
+    /// Stops sending control pulses, letting the servo go limp instead of holding its position.
+    ///
+    /// The next call to [`Servo::set_rotation`] attaches the servo again.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the PWM duty cycle cannot be set.
+    pub fn detach(&mut self) -> Result<(), P::Error> {
+        self.pwm.set_duty_cycle_fully_off()
+    }
+}
+
+/// Predefined one-shot ear gestures.
+///
+/// A gesture is a short sequence of keyframes expressed as offsets from whatever position the configured servo mode
+/// would otherwise command. Gestures are played once through the gesture channel and the servo then returns to its
+/// configured mode.
+pub mod gestures {
+    use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel};
+    use serde::{Deserialize, Serialize};
+
+    /// Channel used to request one-shot gestures from the servo control task.
+    pub type GestureChannel = Channel<CriticalSectionRawMutex, GestureRequest, 4>;
+
+    /// A single point in a gesture.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Keyframe {
+        /// Offset from the resting position to reach at the end of this keyframe.
+        pub offset: i8,
+        /// Time in milliseconds to move linearly from the previous offset to this one.
+        pub duration_ms: u16,
+    }
+
+    impl Keyframe {
+        /// Creates a new keyframe.
+        #[must_use]
+        pub const fn new(offset: i8, duration_ms: u16) -> Self {
+            Self {
+                offset,
+                duration_ms,
+            }
+        }
+    }
+
+    /// Which ears a gesture should be played on.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Target {
+        /// Left ear only.
+        Left,
+        /// Right ear only.
+        Right,
+        /// Both ears simultaneously.
+        Both,
+    }
+
+    /// A request to play a gesture, sent through the [`GestureChannel`].
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct GestureRequest {
+        /// Ears to play the gesture on.
+        pub target: Target,
+        /// Gesture to play.
+        pub gesture: Gesture,
+    }
+
+    const PERK: &[Keyframe] = &[
+        Keyframe::new(60, 150),
+        Keyframe::new(60, 600),
+        Keyframe::new(0, 300),
+    ];
+    const FLATTEN: &[Keyframe] = &[
+        Keyframe::new(-70, 200),
+        Keyframe::new(-70, 800),
+        Keyframe::new(0, 400),
+    ];
+    const WIGGLE: &[Keyframe] = &[
+        Keyframe::new(25, 80),
+        Keyframe::new(-25, 160),
+        Keyframe::new(25, 160),
+        Keyframe::new(-25, 160),
+        Keyframe::new(0, 80),
+    ];
+    const DROOP: &[Keyframe] = &[
+        Keyframe::new(-40, 600),
+        Keyframe::new(-40, 1200),
+        Keyframe::new(0, 600),
+    ];
+    const CURIOUS: &[Keyframe] = &[
+        Keyframe::new(50, 150),
+        Keyframe::new(50, 900),
+        Keyframe::new(0, 300),
+    ];
+
+    /// Named ear gestures.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum Gesture {
+        /// Ears snap up and hold briefly.
+        Perk,
+        /// Ears press back flat.
+        Flatten,
+        /// Quick back-and-forth wiggle.
+        Wiggle,
+        /// Ears slowly sag and recover.
+        Droop,
+        /// One ear up, one ear down, like a head tilt.
+        Curious,
+    }
+
+    impl Gesture {
+        /// All available gestures, in display order.
+        pub const ALL: [Self; 5] = [
+            Self::Perk,
+            Self::Flatten,
+            Self::Wiggle,
+            Self::Droop,
+            Self::Curious,
+        ];
+
+        /// Returns the lowercase name of the gesture.
+        #[must_use]
+        pub const fn name(self) -> &'static str {
+            match self {
+                Self::Perk => "perk",
+                Self::Flatten => "flatten",
+                Self::Wiggle => "wiggle",
+                Self::Droop => "droop",
+                Self::Curious => "curious",
+            }
+        }
+
+        /// Looks up a gesture by its (case-insensitive) name.
+        #[must_use]
+        pub fn from_name(name: &str) -> Option<Self> {
+            Self::ALL
+                .into_iter()
+                .find(|gesture| gesture.name().eq_ignore_ascii_case(name))
+        }
+
+        /// Returns the keyframes making up the gesture.
+        #[must_use]
+        pub const fn keyframes(self) -> &'static [Keyframe] {
+            match self {
+                Self::Perk => PERK,
+                Self::Flatten => FLATTEN,
+                Self::Wiggle => WIGGLE,
+                Self::Droop => DROOP,
+                Self::Curious => CURIOUS,
+            }
+        }
+
+        /// Whether the right ear's offsets are negated when the gesture is played on both ears.
+        ///
+        /// Symmetric gestures (perk, flatten, droop) are mirrored so both ears move the same way on mirror-mounted
+        /// servos, while asymmetric ones (wiggle, curious) are not.
+        #[must_use]
+        pub const fn mirrored(self) -> bool {
+            matches!(self, Self::Perk | Self::Flatten | Self::Droop)
+        }
+
+        /// Returns the offset from the resting position at `elapsed_ms` into the gesture, or `None` once the gesture
+        /// has finished.
+        #[must_use]
+        pub fn offset_at(self, elapsed_ms: u64) -> Option<i8> {
+            let mut start_ms = 0u64;
+            let mut from = 0i8;
+            for keyframe in self.keyframes() {
+                let end_ms = start_ms + u64::from(keyframe.duration_ms);
+                if elapsed_ms < end_ms {
+                    #[allow(clippy::cast_precision_loss)]
+                    let t = (elapsed_ms - start_ms) as f32 / f32::from(keyframe.duration_ms);
+                    let delta = f32::from(keyframe.offset) - f32::from(from);
+                    #[allow(clippy::cast_possible_truncation)]
+                    let offset = (f32::from(from) + delta * t) as i8;
+                    return Some(offset);
+                }
+                start_ms = end_ms;
+                from = keyframe.offset;
+            }
+            None
+        }
+    }
 }
@@ -0,0 +1,350 @@
+//! Deep sleep support.
+//!
+//! Going to sleep is coordinated through [`Sleep`] in the runtime status: the command line (or the idle timer) requests
+//! it, the firmware's sleep manager then marks the device as entering sleep so that the other tasks fade the lights out,
+//! silence the speakers, and detach the servos, stashes the state with [`encode`], and finally powers down. On the next
+//! boot the firmware records why it woke up and restores the stash with [`decode`].
+//!
+//! The same stash also carries the state across soft resets: the firmware refreshes it with [`encode_trimmed`] whenever
+//! the state changes, and restores it after a software, panic, or watchdog reset, so the ears pick up where they left
+//! off instead of showing the defaults until the remote state comes back.
+
+use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+use crate::state::State;
+
+/// Size in 32-bit words of the stash kept in RTC memory across deep sleep.
+pub const STASH_WORDS: usize = 512;
+
+/// Marks a valid stash, and changes whenever the layout of the stash itself does.
+const STASH_MAGIC: u32 = 0xCA7E_A502;
+
+/// Version of the serialized [`State`] layout in the stash.
+///
+/// Bump it whenever a change to [`State`] or anything in it changes how it serializes, so that firmware restoring a
+/// stash written by an older build before an update starts from the defaults instead of misreading it.
+pub const STASH_VERSION: u32 = 1;
+
+/// Number of header words (magic, version, length, checksum) before the stashed bytes.
+const STASH_HEADER_WORDS: usize = 4;
+
+/// Why the device last booted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum WakeCause {
+    /// A regular boot, not a wake from deep sleep.
+    PowerOn,
+    /// Woken from deep sleep by the button.
+    Button,
+    /// Woken from deep sleep by the wake timer.
+    Timer,
+    /// Woken from deep sleep by something else.
+    Other,
+}
+
+impl WakeCause {
+    /// Returns the human-readable name of the cause.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::PowerOn => "power on",
+            Self::Button => "button",
+            Self::Timer => "timer",
+            Self::Other => "other",
+        }
+    }
+
+    /// Returns whether the device woke from deep sleep rather than booting normally.
+    #[must_use]
+    pub const fn from_deep_sleep(self) -> bool {
+        !matches!(self, Self::PowerOn)
+    }
+
+    const fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Self::Button,
+            2 => Self::Timer,
+            3 => Self::Other,
+            _ => Self::PowerOn,
+        }
+    }
+
+    const fn to_u8(self) -> u8 {
+        match self {
+            Self::PowerOn => 0,
+            Self::Button => 1,
+            Self::Timer => 2,
+            Self::Other => 3,
+        }
+    }
+}
+
+/// Deep sleep coordination between the command line, the sleep manager, and the output tasks.
+pub struct Sleep {
+    requested: AtomicBool,
+    entering: AtomicBool,
+    wake_button: AtomicBool,
+    wake_cause: AtomicU8,
+}
+
+impl Sleep {
+    /// Creates a new awake state after a regular boot, with no wake button.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            requested: AtomicBool::new(false),
+            entering: AtomicBool::new(false),
+            wake_button: AtomicBool::new(false),
+            wake_cause: AtomicU8::new(WakeCause::PowerOn.to_u8()),
+        }
+    }
+
+    /// Asks the sleep manager to put the device to sleep.
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether sleep was requested since the last call, clearing the request.
+    pub fn take_request(&self) -> bool {
+        self.requested.swap(false, Ordering::Relaxed)
+    }
+
+    /// Marks the device as going to sleep. There is no way back, the next thing that happens is a deep sleep.
+    pub fn begin(&self) {
+        self.entering.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether the device is going to sleep, in which case outputs should wind down.
+    #[must_use]
+    pub fn is_entering(&self) -> bool {
+        self.entering.load(Ordering::Relaxed)
+    }
+
+    /// Records whether a button is fitted that can wake the device.
+    pub fn set_wake_button(&self, fitted: bool) {
+        self.wake_button.store(fitted, Ordering::Relaxed);
+    }
+
+    /// Returns whether the device could wake up again if it went to sleep with the given wake timer (zero for none).
+    #[must_use]
+    pub fn can_wake(&self, wake_after_s: u32) -> bool {
+        wake_after_s > 0 || self.wake_button.load(Ordering::Relaxed)
+    }
+
+    /// Records why the device booted.
+    pub fn set_wake_cause(&self, cause: WakeCause) {
+        self.wake_cause.store(cause.to_u8(), Ordering::Relaxed);
+    }
+
+    /// Returns why the device booted.
+    #[must_use]
+    pub fn wake_cause(&self) -> WakeCause {
+        WakeCause::from_u8(self.wake_cause.load(Ordering::Relaxed))
+    }
+}
+
+impl Default for Sleep {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Tracks how long the state has gone unchanged.
+///
+/// Any change to the state, whether from the command line, the button, or the remote, counts as activity.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::sleep::IdleTimer;
+/// use catears::state::State;
+///
+/// let mut state = State::default();
+/// state.sleep.idle_timeout_s = 60;
+///
+/// let mut idle = IdleTimer::new();
+/// assert!(!idle.update(&state, 0));
+/// assert!(!idle.update(&state, 59_999));
+///
+/// // Touching the state restarts the timer.
+/// state.lights.brightness = 10;
+/// assert!(!idle.update(&state, 60_000));
+/// assert!(idle.update(&state, 120_000));
+///
+/// // A zero timeout never expires.
+/// state.sleep.idle_timeout_s = 0;
+/// assert!(!idle.update(&state, 1_000_000));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct IdleTimer {
+    last: Option<(State, u64)>,
+}
+
+impl IdleTimer {
+    /// Creates a new idle timer that starts counting at its first update.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { last: None }
+    }
+
+    /// Observes the state at `now_ms` and returns whether it has been unchanged for longer than its idle timeout.
+    pub fn update(&mut self, state: &State, now_ms: u64) -> bool {
+        let since_ms = match self.last {
+            Some((last, since_ms)) if last == *state => since_ms,
+            _ => {
+                self.last = Some((*state, now_ms));
+                now_ms
+            }
+        };
+        let timeout_ms = u64::from(state.sleep.idle_timeout_s) * 1000;
+        timeout_ms > 0 && now_ms.saturating_sub(since_ms) >= timeout_ms
+    }
+}
+
+/// Serializes `state` into a stash, returning whether it fit.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::sleep::{decode, encode, STASH_VERSION, STASH_WORDS};
+/// use catears::state::State;
+///
+/// let mut state = State::default();
+/// state.lights.brightness = 42;
+///
+/// let mut stash = [0u32; STASH_WORDS];
+/// assert!(encode(&state, &mut stash));
+/// assert_eq!(decode(&stash), Some(state));
+///
+/// // Corrupted or uninitialized memory is rejected.
+/// let mut corrupted = stash;
+/// corrupted[4] ^= 1;
+/// assert_eq!(decode(&corrupted), None);
+/// assert_eq!(decode(&[0u32; STASH_WORDS]), None);
+///
+/// // So is a stash written by a firmware with another state layout, even with a valid checksum.
+/// let mut outdated = stash;
+/// outdated[1] = STASH_VERSION - 1;
+/// assert_eq!(decode(&outdated), None);
+/// ```
+#[must_use]
+pub fn encode(state: &State, stash: &mut [u32; STASH_WORDS]) -> bool {
+    let mut bytes = [0u8; (STASH_WORDS - STASH_HEADER_WORDS) * 4];
+    let Ok(len) = serde_json_core::to_slice(state, &mut bytes) else {
+        return false;
+    };
+
+    stash.fill(0);
+    stash[0] = STASH_MAGIC;
+    stash[1] = STASH_VERSION;
+    stash[2] = u32::try_from(len).unwrap_or(u32::MAX);
+    stash[3] = checksum(&bytes[..len]);
+    for (word, chunk) in stash[STASH_HEADER_WORDS..]
+        .iter_mut()
+        .zip(bytes.chunks_exact(4))
+    {
+        *word = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+    true
+}
+
+/// Serializes `state` into a stash like [`encode`], but if it does not fit, stashes it with the speakers playing chiptunes
+/// silenced instead of not at all, and failing that, with light animations cut down to their first frame. Returns
+/// whether anything had to be dropped, or `None` if even that did not fit.
+///
+/// A chiptune is by far the largest part of a state, and the part least missed when it is lost. A light animation comes
+/// next.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::{ChiptuneSequence, Mode, Note, Side};
+/// use catears::lights::{Animation, AnimationFrame, LedPattern, Mode as LightMode, LED_COUNT};
+/// use catears::sleep::{decode, encode, encode_trimmed, STASH_WORDS};
+/// use smart_leds::RGB8;
+/// use catears::state::State;
+///
+/// let mut state = State::default();
+/// let mut stash = [0u32; STASH_WORDS];
+/// assert_eq!(encode_trimmed(&state, &mut stash), Some(false));
+/// assert_eq!(decode(&stash), Some(state));
+///
+/// // A full chiptune does not fit, so it is left out, while a tone on the other side stays.
+/// state.lights.brightness = 42;
+/// state.speakers.left = Mode::Chiptune(ChiptuneSequence::from_notes(&[Note::new(440.0, 100); 64]));
+/// state.speakers.right = Mode::Tone(Note::new(440.0, 100));
+/// assert!(!encode(&state, &mut stash));
+/// assert_eq!(encode_trimmed(&state, &mut stash), Some(true));
+/// let restored = decode(&stash).unwrap();
+/// assert_eq!(restored.lights.brightness, 42);
+/// assert_eq!(restored.speakers.mode(Side::Left), Mode::Silent);
+/// assert_eq!(restored.speakers.right, state.speakers.right);
+///
+/// // Long light animations on both rings do not fit either, so they are cut down to their first frames.
+/// let frame = AnimationFrame::new([RGB8::new(255, 100, 0); LED_COUNT], 100);
+/// state.lights.left = LightMode::Animation((0..8).fold(Animation::new(), |animation, _| animation.with_frame(frame)));
+/// state.lights.right = state.lights.left;
+/// assert_eq!(encode_trimmed(&state, &mut stash), Some(true));
+/// let restored = decode(&stash).unwrap();
+/// assert_eq!(restored.lights.left, LightMode::Custom(LedPattern { leds: frame.leds, looping: false }));
+/// ```
+#[must_use]
+pub fn encode_trimmed(state: &State, stash: &mut [u32; STASH_WORDS]) -> Option<bool> {
+    if encode(state, stash) {
+        return Some(false);
+    }
+    let mut trimmed = *state;
+    let mut dropped = false;
+    for side in crate::audio::Side::ALL {
+        let mode = trimmed.speakers.mode_mut(side);
+        if matches!(mode, crate::audio::Mode::Chiptune(_)) {
+            *mode = crate::audio::Mode::Silent;
+            dropped = true;
+        }
+    }
+    if dropped && encode(&trimmed, stash) {
+        return Some(true);
+    }
+    for ring in [&mut trimmed.lights.left, &mut trimmed.lights.right] {
+        if let crate::lights::Mode::Animation(animation) = *ring {
+            *ring = animation
+                .frames()
+                .first()
+                .map_or(crate::lights::Mode::Off, |frame| {
+                    crate::lights::Mode::Custom(crate::lights::LedPattern {
+                        leds: frame.leds,
+                        looping: false,
+                    })
+                });
+            dropped = true;
+        }
+    }
+    (dropped && encode(&trimmed, stash)).then_some(true)
+}
+
+/// Deserializes a state stashed with [`encode`], or returns `None` if the stash is empty, corrupted, or of another
+/// [`STASH_VERSION`].
+#[must_use]
+pub fn decode(stash: &[u32; STASH_WORDS]) -> Option<State> {
+    let mut bytes = [0u8; (STASH_WORDS - STASH_HEADER_WORDS) * 4];
+    let len = usize::try_from(stash[2]).ok()?;
+    if stash[0] != STASH_MAGIC || stash[1] != STASH_VERSION || len > bytes.len() {
+        return None;
+    }
+
+    for (chunk, word) in bytes.chunks_exact_mut(4).zip(&stash[STASH_HEADER_WORDS..]) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    if checksum(&bytes[..len]) != stash[3] {
+        return None;
+    }
+    serde_json_core::from_slice(&bytes[..len])
+        .ok()
+        .map(|(state, _)| state)
+}
+
+/// FNV-1a hash of `bytes`.
+pub(crate) fn checksum(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811C_9DC5, |hash, &byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    })
+}
@@ -0,0 +1,192 @@
+//! Staged startup reporting.
+//!
+//! The firmware starts up in a fixed sequence of [`Stage`]s. Each stage records its [`Outcome`] and how long it took
+//! into the [`BootReport`] in the runtime status, so that a device that came up degraded can say why over the command
+//! line and with a flash of its lights, without a debugger attached.
+
+use core::sync::atomic::{AtomicU32, AtomicU8, Ordering};
+
+/// A named step of the startup sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Stage {
+    /// Heap allocator setup.
+    Heap,
+    /// Embassy time driver setup.
+    Embassy,
+    /// LED ring setup.
+    Leds,
+    /// Servo PWM setup.
+    Servos,
+    /// Speaker (and microphone) I2S setup.
+    I2s,
+    /// USB serial command line setup.
+    Cli,
+    /// Network stack and access point connection setup.
+    Networking,
+    /// Restoring the state from before the last reset.
+    StateLoad,
+    /// I2C bus setup for the accelerometer.
+    Imu,
+    /// Chip temperature sensor setup.
+    Thermal,
+}
+
+impl Stage {
+    /// Every stage, in startup order.
+    pub const ALL: [Self; 10] = [
+        Self::Heap,
+        Self::Embassy,
+        Self::Leds,
+        Self::Servos,
+        Self::I2s,
+        Self::Cli,
+        Self::Networking,
+        Self::StateLoad,
+        Self::Imu,
+        Self::Thermal,
+    ];
+
+    /// Returns the human-readable name of the stage.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Heap => "heap",
+            Self::Embassy => "embassy",
+            Self::Leds => "LEDs",
+            Self::Servos => "servos",
+            Self::I2s => "I2S",
+            Self::Cli => "CLI",
+            Self::Networking => "networking",
+            Self::StateLoad => "state load",
+            Self::Imu => "IMU",
+            Self::Thermal => "thermal",
+        }
+    }
+
+    const fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Result of a boot stage, ordered from best to worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, defmt::Format)]
+pub enum Outcome {
+    /// The stage has not finished yet.
+    Pending,
+    /// The stage's subsystem is not built into this firmware.
+    Skipped,
+    /// The stage succeeded.
+    Ok,
+    /// The stage failed and its subsystem is disabled or running in a reduced mode.
+    Degraded,
+}
+
+impl Outcome {
+    /// Returns the human-readable name of the outcome.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Skipped => "skipped",
+            Self::Ok => "ok",
+            Self::Degraded => "degraded",
+        }
+    }
+
+    const fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Self::Ok,
+            2 => Self::Degraded,
+            3 => Self::Skipped,
+            _ => Self::Pending,
+        }
+    }
+
+    const fn to_u8(self) -> u8 {
+        match self {
+            Self::Pending => 0,
+            Self::Ok => 1,
+            Self::Degraded => 2,
+            Self::Skipped => 3,
+        }
+    }
+}
+
+/// Outcome and duration of every boot stage.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::startup::{BootReport, Outcome, Stage};
+///
+/// let report = BootReport::new();
+/// assert_eq!(report.outcome(Stage::Heap), Outcome::Pending);
+/// assert_eq!(report.overall(), Outcome::Pending);
+///
+/// for stage in Stage::ALL {
+///     report.record(stage, Outcome::Ok, 1);
+/// }
+/// assert_eq!(report.overall(), Outcome::Ok);
+///
+/// // A stage left out of the build does not hold up the boot.
+/// report.record(Stage::Imu, Outcome::Skipped, 0);
+/// assert_eq!(report.overall(), Outcome::Ok);
+///
+/// // A single degraded stage degrades the whole boot.
+/// report.record(Stage::Cli, Outcome::Degraded, 1000);
+/// assert_eq!(report.overall(), Outcome::Degraded);
+/// assert_eq!(report.duration_ms(Stage::Cli), 1000);
+/// ```
+pub struct BootReport {
+    outcomes: [AtomicU8; Stage::ALL.len()],
+    durations_ms: [AtomicU32; Stage::ALL.len()],
+}
+
+impl BootReport {
+    /// Creates a new report with every stage pending.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            outcomes: [const { AtomicU8::new(Outcome::Pending.to_u8()) }; Stage::ALL.len()],
+            durations_ms: [const { AtomicU32::new(0) }; Stage::ALL.len()],
+        }
+    }
+
+    /// Records the outcome of a stage and how long it took.
+    pub fn record(&self, stage: Stage, outcome: Outcome, duration_ms: u32) {
+        self.outcomes[stage.index()].store(outcome.to_u8(), Ordering::Relaxed);
+        self.durations_ms[stage.index()].store(duration_ms, Ordering::Relaxed);
+    }
+
+    /// Returns the outcome of a stage.
+    #[must_use]
+    pub fn outcome(&self, stage: Stage) -> Outcome {
+        Outcome::from_u8(self.outcomes[stage.index()].load(Ordering::Relaxed))
+    }
+
+    /// Returns how long a stage took in milliseconds.
+    #[must_use]
+    pub fn duration_ms(&self, stage: Stage) -> u32 {
+        self.durations_ms[stage.index()].load(Ordering::Relaxed)
+    }
+
+    /// Returns the outcome of the boot as a whole: degraded if any stage was, otherwise pending until every stage has
+    /// finished or been skipped.
+    #[must_use]
+    pub fn overall(&self) -> Outcome {
+        let outcomes = Stage::ALL.map(|stage| self.outcome(stage));
+        if outcomes.contains(&Outcome::Degraded) {
+            Outcome::Degraded
+        } else if outcomes.contains(&Outcome::Pending) {
+            Outcome::Pending
+        } else {
+            Outcome::Ok
+        }
+    }
+}
+
+impl Default for BootReport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
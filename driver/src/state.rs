@@ -3,22 +3,58 @@
 //! This module defines the data structures used to represent and control the various hardware components of the
 //! catears device, including servo motors for ear movement, RGB LED lights, and speakers for audio playback.
 
-use crate::audio::Mode as AudioMode;
+use crate::audio::{Event as AudioEvent, Mode as AudioMode, Side};
+use crate::lights::flashes::Flash;
 use crate::lights::Mode as LightMode;
+use crate::motion::Reaction;
+use crate::servo::gestures::Gesture;
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, signal::Signal};
 use serde::{Deserialize, Serialize};
 
+/// Signalled whenever a task changes the state, so that the LED task, parked while the rings are dark, wakes up to
+/// look at it instead of polling.
+///
+/// A signal wakes only one waiter, and the LED task is the only one.
+pub static CHANGED: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
 /// Complete state representation of all controllable hardware components.
 ///
 /// This struct encapsulates the current state of all hardware peripherals that can be controlled, providing a single
 /// source of truth for the device's configuration at any given moment.
-#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+///
+/// It is also stashed across resets, so a change to how it serializes needs a bump of
+/// [`crate::sleep::STASH_VERSION`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct State {
+    /// Global power switch.
+    ///
+    /// While off, the lights are dark, the speakers are silent, and the servos hold their last position, without
+    /// losing the configuration of any of them. Defaults to on when absent.
+    #[serde(default = "default_power")]
+    pub power: bool,
     /// Servo motor positions for ear movement control.
     pub servos: Servos,
     /// RGB LED light configuration for visual feedback.
     pub lights: Lights,
     /// Speaker configuration for audio output.
     pub speakers: Speakers,
+    /// Deep sleep configuration. Defaults to never sleeping on its own when absent.
+    #[serde(default)]
+    pub sleep: SleepConfig,
+    /// Whether the on-board status LED shows connectivity and errors. Turn it off for stealth. Defaults to on when
+    /// absent.
+    #[serde(default = "default_status_led")]
+    pub status_led: bool,
+    /// Remote state polling configuration. Defaults to [`PollingConfig::default_const`] when absent.
+    #[serde(default)]
+    pub polling: PollingConfig,
+    /// Petting detection configuration. Defaults to [`TouchConfig::default_const`] when absent.
+    #[serde(default)]
+    pub touch: TouchConfig,
+    /// Whether the command line on the UART accepts commands. Only takes effect on builds with the `uart` feature.
+    /// Defaults to on when absent.
+    #[serde(default = "default_uart_cli")]
+    pub uart_cli: bool,
 }
 
 impl State {
@@ -33,11 +69,197 @@ impl State {
     #[must_use]
     pub const fn default_const() -> Self {
         Self {
+            power: true,
             servos: Servos::default_const(),
             lights: Lights::default_const(),
             speakers: Speakers::default_const(),
+            sleep: SleepConfig::default_const(),
+            status_led: true,
+            polling: PollingConfig::default_const(),
+            touch: TouchConfig::default_const(),
+            uart_cli: true,
+        }
+    }
+
+    /// Returns the audio mode that should actually be playing on `side`, taking the power switch into account.
+    ///
+    /// A sound event plays over the speaker's own mode until the speaker has played it, which is when `played_event`,
+    /// the id of the last event it played, catches up.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use catears::audio::{Event, Mode, Note, Side, Sound};
+    /// use catears::state::State;
+    ///
+    /// let mut state = State::default_const();
+    /// state.speakers.event = Some(Event::new(1, Sound::Tone(Note::new(2000.0, 80))));
+    /// assert_eq!(state.audio_mode(Side::Left, 0), Mode::Tone(Note::new(2000.0, 80)));
+    /// assert_eq!(state.audio_mode(Side::Left, 1), Mode::Silent);
+    /// ```
+    #[must_use]
+    pub fn audio_mode(&self, side: Side, played_event: u32) -> AudioMode {
+        if !self.power {
+            return AudioMode::Silent;
+        }
+        match self.pending_event(played_event) {
+            Some(event) => event.mode(),
+            None => self.speakers.mode(side),
+        }
+    }
+
+    /// Returns the sound event still to play on a speaker that last played the event `played_event`, if any.
+    #[must_use]
+    pub fn pending_event(&self, played_event: u32) -> Option<AudioEvent> {
+        self.speakers
+            .event
+            .filter(|event| self.power && event.id != played_event)
+    }
+
+    /// Toggles the global power switch.
+    pub fn toggle_power(&mut self) {
+        self.power = !self.power;
+    }
+
+    /// Switches both LED rings to the light preset following the one currently shown on the left ring, clearing any
+    /// light playlist so that it does not take the rings straight back.
+    pub fn next_light_preset(&mut self) {
+        self.lights.playlist = crate::lights::Playlist::new();
+        let mode = crate::lights::patterns::next(&self.lights.left);
+        self.lights.left = mode;
+        self.lights.right = mode;
+    }
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self::default_const()
+    }
+}
+
+const fn default_power() -> bool {
+    true
+}
+
+const fn default_status_led() -> bool {
+    true
+}
+
+const fn default_uart_cli() -> bool {
+    true
+}
+
+/// Deep sleep configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct SleepConfig {
+    /// Time in seconds without any change to the state after which the device goes to sleep, or zero to never sleep
+    /// on its own.
+    #[serde(default)]
+    pub idle_timeout_s: u32,
+    /// Time in seconds after which a sleeping device wakes up again, or zero to only wake on the button.
+    #[serde(default)]
+    pub wake_after_s: u32,
+}
+
+impl SleepConfig {
+    /// Creates sleep configuration with compile-time constant default values.
+    ///
+    /// By default the device never goes to sleep on its own and only the button wakes it up.
+    #[must_use]
+    pub const fn default_const() -> Self {
+        Self {
+            idle_timeout_s: 0,
+            wake_after_s: 0,
+        }
+    }
+}
+
+/// Remote state polling configuration.
+///
+/// After every change the remote is polled at the fast interval for the fast window, after which the interval grows by
+/// the decay factor with every poll that sees no change, up to the slow interval.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PollingConfig {
+    /// Poll interval in milliseconds right after a change.
+    pub fast_ms: u32,
+    /// Longest poll interval in milliseconds, capped at [`crate::polling::MAX_INTERVAL_MS`].
+    pub slow_ms: u32,
+    /// Time in seconds to keep polling at the fast interval after a change.
+    pub fast_window_s: u32,
+    /// Percentage the interval grows to with every poll that sees no change, for example 150 for half again as long.
+    pub decay_pct: u16,
+}
+
+impl PollingConfig {
+    /// Creates polling configuration with compile-time constant default values.
+    ///
+    /// By default the remote is polled every 500 ms for a minute after a change, backing off to every 30 s.
+    #[must_use]
+    pub const fn default_const() -> Self {
+        Self {
+            fast_ms: 500,
+            slow_ms: 30_000,
+            fast_window_s: 60,
+            decay_pct: 150,
+        }
+    }
+}
+
+impl Default for PollingConfig {
+    fn default() -> Self {
+        Self::default_const()
+    }
+}
+
+/// Petting detection configuration.
+///
+/// Only takes effect on builds with the `touch` feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TouchConfig {
+    /// Change from the untouched reading in percent that counts as a touch. Lower values are more sensitive.
+    pub threshold_pct: u8,
+    /// Reaction to a quick tap on either ear.
+    pub tap: Reaction,
+    /// Reaction to stroking either ear.
+    pub stroke: Reaction,
+}
+
+impl TouchConfig {
+    /// Creates petting detection configuration with compile-time constant default values.
+    ///
+    /// By default a tap perks the ears up with a pink blink, and a stroke wiggles them with a longer pink glow.
+    #[must_use]
+    pub const fn default_const() -> Self {
+        const PINK: smart_leds::RGB8 = smart_leds::RGB8::new(255, 105, 180);
+        Self {
+            threshold_pct: 3,
+            tap: Reaction {
+                gesture: Some(Gesture::Perk),
+                flash: Some(Flash::new(PINK, 200)),
+            },
+            stroke: Reaction {
+                gesture: Some(Gesture::Wiggle),
+                flash: Some(Flash::new(PINK, 800)),
+            },
         }
     }
+
+    /// Returns the reaction configured for `event`.
+    #[must_use]
+    pub const fn reaction(&self, event: crate::touch::Event) -> Reaction {
+        match event {
+            crate::touch::Event::Tap => self.tap,
+            crate::touch::Event::Stroke => self.stroke,
+        }
+    }
+}
+
+impl Default for TouchConfig {
+    fn default() -> Self {
+        Self::default_const()
+    }
 }
 
 /// Servo operation mode for each ear.
@@ -63,6 +285,18 @@ pub enum ServoMode {
         /// Average time between twitches in milliseconds.
         interval_ms: u32,
     },
+    /// Reactive mode - servo perks up while the microphone picks up loud sound.
+    Reactive {
+        /// Position while it is quiet (0-255).
+        rest: u8,
+        /// Position while it is loud (0-255).
+        perk: u8,
+        /// Microphone level (0-255) at or above which the servo perks up.
+        threshold: u8,
+        /// Microphone level (0-255) below which the servo returns to rest. Keeping this below `threshold` stops the
+        /// ear from flapping when the level hovers around the threshold.
+        release: u8,
+    },
 }
 
 impl Default for ServoMode {
@@ -73,20 +307,27 @@ impl Default for ServoMode {
 
 /// Servo motor control state for ear positioning.
 ///
-/// Controls the position and movement patterns of left and right servo motors that actuate the cat ear movements.
+/// Controls the position and movement patterns of left and right servo motors that actuate the cat ear movements, and of
+/// the two auxiliary servos on builds with the `aux-servos` feature.
 #[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
 pub struct Servos {
     /// Left ear servo mode.
     pub left: ServoMode,
     /// Right ear servo mode.
     pub right: ServoMode,
+    /// First auxiliary servo mode, or `None` to leave it where it is.
+    #[serde(default)]
+    pub aux0: Option<ServoMode>,
+    /// Second auxiliary servo mode, or `None` to leave it where it is.
+    #[serde(default)]
+    pub aux1: Option<ServoMode>,
 }
 
 impl Servos {
     /// Creates servo configuration with compile-time constant default values.
     ///
     /// Both servos are initialized to their center position (125), which typically represents ears in a neutral,
-    /// upright position.
+    /// upright position. The auxiliary servos are left alone.
     ///
     /// # Returns
     ///
@@ -96,6 +337,8 @@ impl Servos {
         Self {
             left: ServoMode::Static(125),
             right: ServoMode::Static(125),
+            aux0: None,
+            aux1: None,
         }
     }
 }
@@ -104,6 +347,7 @@ impl Servos {
 ///
 /// Controls the LED rings in the left and right cat ears. Each ring contains 12 individually addressable RGB LEDs.
 #[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct Lights {
     /// Left ear LED ring configuration.
     pub left: LightMode,
@@ -111,9 +355,43 @@ pub struct Lights {
     pub right: LightMode,
     /// Global brightness multiplier (0-255).
     pub brightness: u8,
+    /// Whether the ambient light sensor scales the brightness down in dim surroundings, with [`Lights::brightness`] as
+    /// the ceiling. Only takes effect on builds with the `ambient` feature. Defaults to off when absent.
+    #[serde(default)]
+    pub auto_brightness: bool,
+    /// Time in milliseconds a ring takes to crossfade into a new mode, zero to switch at once. Defaults to
+    /// [`Lights::TRANSITION_MS`] when absent.
+    #[serde(default = "default_transition_ms")]
+    pub transition_ms: u16,
+    /// Index of the LED at the top of the left ring, where the patterns start. Defaults to 0 when absent.
+    #[serde(default)]
+    pub rotation_offset_left: u8,
+    /// Index of the LED at the top of the right ring, where the patterns start. Defaults to 0 when absent.
+    #[serde(default)]
+    pub rotation_offset_right: u8,
+    /// Whether the left ring is wired counter-clockwise. Defaults to off when absent.
+    #[serde(default)]
+    pub reversed_left: bool,
+    /// Whether the right ring is wired counter-clockwise. Defaults to off when absent.
+    #[serde(default)]
+    pub reversed_right: bool,
+    /// White balance correction of both rings. Defaults to [`crate::lights::ColorCorrection::NEUTRAL`] when absent.
+    #[serde(default)]
+    pub white_balance: crate::lights::ColorCorrection,
+    /// Whether to dither the brightness over time, for smooth fades at low brightness. Turn it off to keep cameras from
+    /// catching the flicker. Defaults to on when absent.
+    #[serde(default = "default_dither")]
+    pub dither: bool,
+    /// Presets shown on both rings in turn, in place of their own modes while it has entries. Defaults to empty when
+    /// absent.
+    #[serde(default)]
+    pub playlist: crate::lights::Playlist,
 }
 
 impl Lights {
+    /// Default crossfade time, quick enough to keep up with the changes and slow enough not to snap.
+    pub const TRANSITION_MS: u16 = 300;
+
     /// Creates light configuration with compile-time constant default values.
     ///
     /// Both LED rings are initialized to off state with full brightness capability.
@@ -133,20 +411,74 @@ impl Lights {
                 250,
             )),
             brightness: 255,
+            auto_brightness: false,
+            transition_ms: Self::TRANSITION_MS,
+            rotation_offset_left: 0,
+            rotation_offset_right: 0,
+            reversed_left: false,
+            reversed_right: false,
+            white_balance: crate::lights::ColorCorrection::NEUTRAL,
+            dither: true,
+            playlist: crate::lights::Playlist::new(),
         }
     }
 }
 
+const fn default_transition_ms() -> u16 {
+    Lights::TRANSITION_MS
+}
+
+const fn default_dither() -> bool {
+    true
+}
+
 /// Speaker control state for audio output.
 ///
 /// Manages the audio playback state for the speakers, supporting both simple tone generation and playback of
-/// predefined chiptune melodies.
+/// predefined chiptune melodies. Each speaker plays a mode of its own, at a shared master volume.
+///
+/// A state from before the speakers were split, with a single `mode`, still parses and plays that mode on both sides.
+///
+/// Muting or pausing the speakers silences them without changing their modes, so whatever they were playing carries on
+/// once they are unmuted or resumed.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::audio::{Mode, Note, Side};
+/// use catears::state::Speakers;
+///
+/// let json = r#"{"left":{"Tone":{"frequency":440.0,"duration_ms":100,"volume":null}},"right":"Silent","volume":9}"#;
+/// let (speakers, _) = serde_json_core::from_str::<Speakers>(json).unwrap();
+/// assert_eq!(speakers.mode(Side::Left), Mode::Tone(Note::new(440.0, 100)));
+/// assert_eq!(speakers.mode(Side::Right), Mode::Silent);
+///
+/// let old = r#"{"mode":{"Tone":{"frequency":440.0,"duration_ms":100,"volume":null}},"volume":9}"#;
+/// let (speakers, _) = serde_json_core::from_str::<Speakers>(old).unwrap();
+/// assert_eq!(speakers.left, Mode::Tone(Note::new(440.0, 100)));
+/// assert_eq!(speakers.right, speakers.left);
+/// assert_eq!(speakers.event, None);
+/// assert!(!speakers.muted && !speakers.paused);
+/// assert_eq!(speakers.balance, 0);
+/// ```
 #[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(from = "SpeakersRepr")]
 pub struct Speakers {
-    /// Current audio mode determining what sound is being played.
-    pub mode: AudioMode,
+    /// Audio mode of the left speaker.
+    pub left: AudioMode,
+    /// Audio mode of the right speaker.
+    pub right: AudioMode,
     /// Master volume level (0-255) that scales all audio output.
     pub volume: u8,
+    /// Latest sound event, which both speakers play once over their own modes, see [`AudioEvent`].
+    pub event: Option<AudioEvent>,
+    /// Whether the speakers are muted, playing on in silence.
+    pub muted: bool,
+    /// Whether the speakers are paused, holding their place in whatever they play.
+    pub paused: bool,
+    /// Balance between the speakers, from -127 for only the left one through 0 for both equally to 127 for only the
+    /// right one, see [`crate::audio::synth::balanced`].
+    pub balance: i8,
 }
 
 impl Speakers {
@@ -160,8 +492,64 @@ impl Speakers {
     #[must_use]
     pub const fn default_const() -> Self {
         Self {
-            mode: AudioMode::Silent,
+            left: AudioMode::Silent,
+            right: AudioMode::Silent,
             volume: 128,
+            event: None,
+            muted: false,
+            paused: false,
+            balance: 0,
+        }
+    }
+
+    /// Returns the audio mode of the speaker on `side`.
+    #[must_use]
+    pub const fn mode(&self, side: Side) -> AudioMode {
+        match side {
+            Side::Left => self.left,
+            Side::Right => self.right,
+        }
+    }
+
+    /// Returns the audio mode of the speaker on `side` for changing.
+    pub fn mode_mut(&mut self, side: Side) -> &mut AudioMode {
+        match side {
+            Side::Left => &mut self.left,
+            Side::Right => &mut self.right,
+        }
+    }
+}
+
+/// Speaker state as it may arrive, either split by side or with the single mode of older states.
+#[derive(Deserialize)]
+struct SpeakersRepr {
+    #[serde(default)]
+    mode: Option<AudioMode>,
+    #[serde(default)]
+    left: Option<AudioMode>,
+    #[serde(default)]
+    right: Option<AudioMode>,
+    volume: u8,
+    #[serde(default)]
+    event: Option<AudioEvent>,
+    #[serde(default)]
+    muted: bool,
+    #[serde(default)]
+    paused: bool,
+    #[serde(default)]
+    balance: i8,
+}
+
+impl From<SpeakersRepr> for Speakers {
+    fn from(repr: SpeakersRepr) -> Self {
+        Self {
+            left: repr.left.or(repr.mode).unwrap_or_default(),
+            right: repr.right.or(repr.mode).unwrap_or_default(),
+            volume: repr.volume,
+            event: repr.event,
+            muted: repr.muted,
+            paused: repr.paused,
+            balance: repr.balance,
         }
     }
 }
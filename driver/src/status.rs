@@ -0,0 +1,351 @@
+//! Runtime status reporting.
+//!
+//! Unlike [`crate::state::State`], which describes what the device *should* be doing and is written by the command line
+//! and the remote, the status is written only by the firmware tasks themselves and describes what the device is
+//! *actually* doing. Everything in here is lock-free so that tasks can update it from hot loops without contending with
+//! the state lock.
+
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+
+use defmt::{debug, info, warn};
+
+/// Number of consecutive failures after which a peripheral is reported as degraded.
+pub const DEGRADED_THRESHOLD: u32 = 10;
+
+/// Read-only runtime status of the device, shared between all tasks.
+pub struct Status {
+    /// Outcome of every startup stage.
+    pub boot: crate::startup::BootReport,
+    /// The last crash, kept until cleared.
+    pub last_crash: crate::crash::Report,
+    /// Why the device last reset, and how many times it has since power on.
+    pub resets: crate::resets::Report,
+    /// Health of the left ear LED ring.
+    pub led_left: Health,
+    /// Health of the right ear LED ring.
+    pub led_right: Health,
+    /// Health of the left ear servo.
+    pub servo_left: Health,
+    /// Health of the right ear servo.
+    pub servo_right: Health,
+    /// Health of the auxiliary servos, on builds with the `aux-servos` feature.
+    pub servo_aux: Health,
+    /// Health of the left speaker I2S output.
+    pub speaker_left: Health,
+    /// Health of the right speaker I2S output.
+    pub speaker_right: Health,
+    /// Times the left speaker ran dry.
+    pub underruns_left: Underruns,
+    /// Times the right speaker ran dry.
+    pub underruns_right: Underruns,
+    /// Level of the latest buffer played on the left speaker.
+    pub output_left: crate::audio::dsp::AudioLevel,
+    /// Level of the latest buffer played on the right speaker.
+    pub output_right: crate::audio::dsp::AudioLevel,
+    /// Health of the microphone I2S input.
+    pub microphone: Health,
+    /// Health of the accelerometer I2C bus.
+    pub imu: Health,
+    /// Health of the remote state fetch.
+    pub remote: Health,
+    /// Latest head motion readings.
+    pub motion: crate::motion::Readings,
+    /// Latest touch pad readings.
+    pub touch: crate::touch::Readings,
+    /// Latest ambient light reading and the brightness scale it calls for.
+    pub ambient: crate::ambient::Readings,
+    /// Latest battery charge, if anything measures it.
+    pub battery: crate::battery::Readings,
+    /// Latest heap usage.
+    pub memory: Memory,
+    /// Deep sleep coordination and the reason for the last boot.
+    pub sleep: crate::sleep::Sleep,
+    /// Latest chip temperature and thermal throttling level.
+    pub thermal: crate::thermal::Readings,
+    /// Load shedding after brownouts.
+    pub supply: crate::supply::Shedding,
+    /// Recent log frames, for reading the logs without a debugger.
+    pub logs: crate::logging::LogSink,
+    /// Liveness stamps of the long-running tasks, checked by the watchdog feeder.
+    pub heartbeats: crate::watchdog::Heartbeats,
+    /// Iteration timing of the long-running tasks.
+    pub timing: crate::timing::Timings,
+}
+
+impl Status {
+    /// Creates a new status with every peripheral healthy.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            boot: crate::startup::BootReport::new(),
+            last_crash: crate::crash::Report::new(),
+            resets: crate::resets::Report::new(),
+            led_left: Health::new(),
+            led_right: Health::new(),
+            servo_left: Health::new(),
+            servo_right: Health::new(),
+            servo_aux: Health::new(),
+            speaker_left: Health::new(),
+            speaker_right: Health::new(),
+            underruns_left: Underruns::new(),
+            underruns_right: Underruns::new(),
+            output_left: crate::audio::dsp::AudioLevel::new(),
+            output_right: crate::audio::dsp::AudioLevel::new(),
+            microphone: Health::new(),
+            imu: Health::new(),
+            remote: Health::new(),
+            motion: crate::motion::Readings::new(),
+            touch: crate::touch::Readings::new(),
+            ambient: crate::ambient::Readings::new(),
+            battery: crate::battery::Readings::new(),
+            memory: Memory::new(),
+            sleep: crate::sleep::Sleep::new(),
+            thermal: crate::thermal::Readings::new(),
+            supply: crate::supply::Shedding::new(),
+            logs: crate::logging::LogSink::new(),
+            heartbeats: crate::watchdog::Heartbeats::new(),
+            timing: crate::timing::Timings::new(),
+        }
+    }
+}
+
+impl Default for Status {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Outcome of recording a peripheral operation in a [`Health`] tracker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    /// Nothing noteworthy changed.
+    Unchanged,
+    /// The peripheral just crossed [`DEGRADED_THRESHOLD`] consecutive failures.
+    Degraded,
+    /// The peripheral succeeded after having been degraded.
+    Recovered,
+}
+
+/// Failure counters for a single peripheral.
+pub struct Health {
+    consecutive_failures: AtomicU32,
+    total_failures: AtomicU32,
+    degraded: AtomicBool,
+}
+
+impl Health {
+    /// Creates a new healthy tracker.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            consecutive_failures: AtomicU32::new(0),
+            total_failures: AtomicU32::new(0),
+            degraded: AtomicBool::new(false),
+        }
+    }
+
+    /// Records a successful operation, resetting the consecutive failure count.
+    pub fn record_success(&self) -> Transition {
+        self.consecutive_failures.store(0, Ordering::Relaxed);
+        if self.degraded.swap(false, Ordering::Relaxed) {
+            Transition::Recovered
+        } else {
+            Transition::Unchanged
+        }
+    }
+
+    /// Records a failed operation.
+    pub fn record_failure(&self) -> Transition {
+        self.total_failures.fetch_add(1, Ordering::Relaxed);
+        let consecutive = self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if consecutive >= DEGRADED_THRESHOLD && !self.degraded.swap(true, Ordering::Relaxed) {
+            Transition::Degraded
+        } else {
+            Transition::Unchanged
+        }
+    }
+
+    /// Records the result of an operation on the named peripheral, logging failures and health transitions.
+    ///
+    /// Failures are logged at warn level until the peripheral is degraded, after which they are only logged at debug
+    /// level so that a dead peripheral does not flood the log.
+    ///
+    /// # Returns
+    ///
+    /// The successful value, or `None` if the operation failed.
+    pub fn record<T, E: defmt::Format>(&self, name: &str, result: Result<T, E>) -> Option<T> {
+        match result {
+            Ok(value) => {
+                if self.record_success() == Transition::Recovered {
+                    info!("{} recovered", name);
+                }
+                Some(value)
+            }
+            Err(e) => {
+                if self.is_degraded() {
+                    debug!("{} failed: {:?}", name, e);
+                } else {
+                    warn!("{} failed: {:?}", name, e);
+                }
+                if self.record_failure() == Transition::Degraded {
+                    warn!(
+                        "{} failed {} times in a row, marking as degraded",
+                        name, DEGRADED_THRESHOLD
+                    );
+                }
+                None
+            }
+        }
+    }
+
+    /// Returns the number of failures since the last success.
+    #[must_use]
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of failures since boot.
+    #[must_use]
+    pub fn total_failures(&self) -> u32 {
+        self.total_failures.load(Ordering::Relaxed)
+    }
+
+    /// Returns whether the peripheral has failed [`DEGRADED_THRESHOLD`] or more times in a row.
+    #[must_use]
+    pub fn is_degraded(&self) -> bool {
+        self.degraded.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Heap usage, sampled periodically by the firmware.
+///
+/// Only the heap is tracked: embassy tasks do not have stacks of their own (their futures live in the executor's task
+/// arena and they all run on the main stack), so there is no per-task stack high-water mark to report.
+pub struct Memory {
+    size: AtomicUsize,
+    used: AtomicUsize,
+    peak: AtomicUsize,
+    low: AtomicBool,
+}
+
+impl Memory {
+    /// Creates a new tracker with no samples.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            size: AtomicUsize::new(0),
+            used: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+            low: AtomicBool::new(false),
+        }
+    }
+
+    /// Records a heap sample, logging once when free memory drops below `low_threshold` bytes and again once it has
+    /// recovered.
+    ///
+    /// `peak` is the allocator's own high-water mark, which also catches short-lived spikes between samples.
+    pub fn record(&self, size: usize, used: usize, peak: usize, low_threshold: usize) {
+        self.size.store(size, Ordering::Relaxed);
+        self.used.store(used, Ordering::Relaxed);
+        self.peak.fetch_max(peak.max(used), Ordering::Relaxed);
+
+        let free = size.saturating_sub(used);
+        let low = free < low_threshold;
+        if self.low.swap(low, Ordering::Relaxed) != low {
+            if low {
+                warn!("Free heap low: {} of {} bytes free", free, size);
+            } else {
+                info!("Free heap recovered: {} of {} bytes free", free, size);
+            }
+        }
+    }
+
+    /// Returns the total heap size in bytes.
+    #[must_use]
+    pub fn size(&self) -> usize {
+        self.size.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of heap bytes in use.
+    #[must_use]
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of free heap bytes.
+    #[must_use]
+    pub fn free(&self) -> usize {
+        self.size().saturating_sub(self.used())
+    }
+
+    /// Returns the highest number of heap bytes in use since boot.
+    #[must_use]
+    pub fn peak(&self) -> usize {
+        self.peak.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Times a speaker ran dry, with its DMA idle because the next buffer was not ready in time.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::status::Underruns;
+///
+/// let underruns = Underruns::new();
+/// assert_eq!(underruns.record(300), 1);
+/// assert_eq!(underruns.record(120), 2);
+/// assert_eq!((underruns.count(), underruns.longest_us()), (2, 300));
+/// ```
+pub struct Underruns {
+    count: AtomicU32,
+    longest_us: AtomicU32,
+}
+
+impl Underruns {
+    /// Creates a new counter with no underruns.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            count: AtomicU32::new(0),
+            longest_us: AtomicU32::new(0),
+        }
+    }
+
+    /// Records an underrun that left the speaker dry for `us` microseconds, returning how many there have been since
+    /// boot.
+    pub fn record(&self, us: u32) -> u32 {
+        self.longest_us.fetch_max(us, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed).saturating_add(1)
+    }
+
+    /// Returns how many underruns there have been since boot.
+    #[must_use]
+    pub fn count(&self) -> u32 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Returns the longest time in microseconds the speaker ran dry for.
+    #[must_use]
+    pub fn longest_us(&self) -> u32 {
+        self.longest_us.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for Underruns {
+    fn default() -> Self {
+        Self::new()
+    }
+}
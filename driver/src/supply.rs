@@ -0,0 +1,126 @@
+//! Load shedding to keep the supply from browning out.
+//!
+//! Full-white LEDs with both servos moving can pull the 3.3 V rail low enough for the chip's brownout detector to reset
+//! it. There is no pin left to measure the rail with, so the firmware relies on that reset itself as the signal: after a
+//! brownout it boots into shedding load, with the LED brightness capped hard and the servos holding still, and only
+//! lifts that once the hold time has passed. Every brownout in a row doubles the hold time, so a supply that keeps
+//! sagging is not brought back to full load again and again.
+
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+/// Load shedding configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    /// Highest LED brightness (0-255) allowed while shedding load.
+    pub shed_brightness: u8,
+    /// Time in seconds to shed load after a single brownout.
+    pub hold_s: u32,
+    /// Longest time in seconds to shed load after repeated brownouts.
+    pub max_hold_s: u32,
+}
+
+impl Config {
+    /// Default configuration, shedding load for a minute after a brownout and up to a quarter of an hour.
+    pub const DEFAULT: Self = Self {
+        shed_brightness: 32,
+        hold_s: 60,
+        max_hold_s: 900,
+    };
+
+    /// Returns how long in seconds to shed load after the given number of brownouts in a row.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use catears::supply::Config;
+    ///
+    /// let config = Config::DEFAULT;
+    /// assert_eq!(config.hold_s(0), 0);
+    /// assert_eq!(config.hold_s(1), 60);
+    /// assert_eq!(config.hold_s(2), 120);
+    /// assert_eq!(config.hold_s(3), 240);
+    /// assert_eq!(config.hold_s(10), 900);
+    /// ```
+    #[must_use]
+    pub const fn hold_s(&self, brownouts: u32) -> u32 {
+        if brownouts == 0 {
+            return 0;
+        }
+        let doublings = if brownouts - 1 < 16 {
+            brownouts - 1
+        } else {
+            16
+        };
+        let hold_s = self.hold_s.saturating_mul(1 << doublings);
+        if hold_s < self.max_hold_s {
+            hold_s
+        } else {
+            self.max_hold_s
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Whether load is being shed, published for the output tasks.
+pub struct Shedding {
+    active: AtomicBool,
+    brightness_cap: AtomicU32,
+    events: AtomicU32,
+}
+
+impl Shedding {
+    /// Creates a new state without shedding load.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            active: AtomicBool::new(false),
+            brightness_cap: AtomicU32::new(u8::MAX as u32),
+            events: AtomicU32::new(0),
+        }
+    }
+
+    /// Starts shedding load, capping the LED brightness at `brightness_cap`.
+    pub fn start(&self, brightness_cap: u8) {
+        self.brightness_cap
+            .store(u32::from(brightness_cap), Ordering::Relaxed);
+        if !self.active.swap(true, Ordering::Relaxed) {
+            self.events.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Stops shedding load.
+    pub fn stop(&self) {
+        self.active.store(false, Ordering::Relaxed);
+        self.brightness_cap
+            .store(u32::from(u8::MAX), Ordering::Relaxed);
+    }
+
+    /// Returns whether load is being shed, in which case the servos should hold still.
+    #[must_use]
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    /// Returns the highest LED brightness currently allowed.
+    #[must_use]
+    pub fn brightness_cap(&self) -> u8 {
+        u8::try_from(self.brightness_cap.load(Ordering::Relaxed)).unwrap_or(u8::MAX)
+    }
+
+    /// Returns the number of times load shedding started since boot.
+    #[must_use]
+    pub fn events(&self) -> u32 {
+        self.events.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for Shedding {
+    fn default() -> Self {
+        Self::new()
+    }
+}
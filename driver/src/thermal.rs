@@ -0,0 +1,225 @@
+//! Chip temperature monitoring and thermal throttling.
+//!
+//! The thermal task periodically reads the ESP32-S3's on-die temperature sensor and feeds it through a [`Governor`],
+//! which decides how hard to throttle the outputs. The result is published into [`Readings`] in the runtime status:
+//! while throttled the LED brightness is capped, and while critical the outputs are forced off as if the power switch
+//! was off, without touching the switch itself.
+
+use core::sync::atomic::{AtomicBool, AtomicI16, AtomicU8, Ordering};
+
+/// How hard the outputs are throttled, from least to most.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, defmt::Format)]
+pub enum Level {
+    /// Running normally.
+    Normal,
+    /// Warm, LED brightness is capped.
+    Throttled,
+    /// Too hot, outputs are forced off.
+    Critical,
+}
+
+impl Level {
+    /// Returns the human-readable name of the level.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Normal => "normal",
+            Self::Throttled => "throttled",
+            Self::Critical => "critical",
+        }
+    }
+
+    const fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Self::Throttled,
+            2 => Self::Critical,
+            _ => Self::Normal,
+        }
+    }
+
+    const fn to_u8(self) -> u8 {
+        match self {
+            Self::Normal => 0,
+            Self::Throttled => 1,
+            Self::Critical => 2,
+        }
+    }
+}
+
+/// Thermal monitoring configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    /// Time in milliseconds between temperature readings.
+    pub sample_interval_ms: u64,
+    /// Temperature in degrees Celsius at or above which the LED brightness is capped.
+    pub throttle_c: i16,
+    /// Highest LED brightness (0-255) allowed while throttled.
+    pub throttle_brightness: u8,
+    /// Temperature in degrees Celsius at or above which the outputs are forced off.
+    pub critical_c: i16,
+    /// Degrees Celsius the temperature has to fall below a threshold before its response is lifted again.
+    pub hysteresis_c: i16,
+}
+
+impl Config {
+    /// Default configuration, well below the chip's 125 °C limit but warm enough to notice on a head.
+    pub const DEFAULT: Self = Self {
+        sample_interval_ms: 2000,
+        throttle_c: 70,
+        throttle_brightness: 64,
+        critical_c: 85,
+        hysteresis_c: 5,
+    };
+
+    /// Returns the level a temperature falls into, ignoring hysteresis.
+    const fn classify(&self, celsius: i16) -> Level {
+        if celsius >= self.critical_c {
+            Level::Critical
+        } else if celsius >= self.throttle_c {
+            Level::Throttled
+        } else {
+            Level::Normal
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Decides the thermal response from successive temperature readings.
+///
+/// A level is entered as soon as the temperature reaches its threshold, and only left once the temperature has fallen
+/// [`Config::hysteresis_c`] below it again.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::thermal::{Config, Governor, Level};
+///
+/// let mut governor = Governor::new(Config::DEFAULT);
+/// assert_eq!(governor.update(50), Level::Normal);
+/// assert_eq!(governor.update(72), Level::Throttled);
+/// assert_eq!(governor.brightness_cap(), Config::DEFAULT.throttle_brightness);
+/// assert_eq!(governor.update(90), Level::Critical);
+///
+/// // Cooling just below the critical threshold is not enough to recover.
+/// assert_eq!(governor.update(82), Level::Critical);
+/// assert_eq!(governor.update(79), Level::Throttled);
+/// assert_eq!(governor.update(66), Level::Throttled);
+/// assert_eq!(governor.update(64), Level::Normal);
+/// assert_eq!(governor.brightness_cap(), u8::MAX);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Governor {
+    config: Config,
+    level: Level,
+}
+
+impl Governor {
+    /// Creates a new governor starting at [`Level::Normal`].
+    #[must_use]
+    pub const fn new(config: Config) -> Self {
+        Self {
+            config,
+            level: Level::Normal,
+        }
+    }
+
+    /// Returns the configuration.
+    #[must_use]
+    pub const fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Returns the current level.
+    #[must_use]
+    pub const fn level(&self) -> Level {
+        self.level
+    }
+
+    /// Returns the highest LED brightness allowed at the current level.
+    #[must_use]
+    pub const fn brightness_cap(&self) -> u8 {
+        match self.level {
+            Level::Normal => u8::MAX,
+            Level::Throttled => self.config.throttle_brightness,
+            Level::Critical => 0,
+        }
+    }
+
+    /// Updates the governor with a new temperature reading in degrees Celsius and returns the resulting level.
+    pub fn update(&mut self, celsius: i16) -> Level {
+        let rising = self.config.classify(celsius);
+        let falling = self
+            .config
+            .classify(celsius.saturating_add(self.config.hysteresis_c));
+        self.level = rising.max(self.level.min(falling));
+        self.level
+    }
+}
+
+/// Latest thermal readings, published by the thermal task for the rest of the firmware.
+pub struct Readings {
+    present: AtomicBool,
+    celsius: AtomicI16,
+    level: AtomicU8,
+    brightness_cap: AtomicU8,
+}
+
+impl Readings {
+    /// Creates new readings for an absent sensor, which never throttles.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            present: AtomicBool::new(false),
+            celsius: AtomicI16::new(0),
+            level: AtomicU8::new(Level::Normal.to_u8()),
+            brightness_cap: AtomicU8::new(u8::MAX),
+        }
+    }
+
+    /// Publishes a temperature reading in degrees Celsius along with the governor's response to it.
+    pub fn publish(&self, celsius: i16, governor: &Governor) {
+        self.celsius.store(celsius, Ordering::Relaxed);
+        self.level
+            .store(governor.level().to_u8(), Ordering::Relaxed);
+        self.brightness_cap
+            .store(governor.brightness_cap(), Ordering::Relaxed);
+        self.present.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns the latest temperature in degrees Celsius, or `None` before the first reading.
+    #[must_use]
+    pub fn celsius(&self) -> Option<i16> {
+        self.present
+            .load(Ordering::Relaxed)
+            .then(|| self.celsius.load(Ordering::Relaxed))
+    }
+
+    /// Returns the current thermal level.
+    #[must_use]
+    pub fn level(&self) -> Level {
+        Level::from_u8(self.level.load(Ordering::Relaxed))
+    }
+
+    /// Returns whether the outputs are forced off because the chip is too hot.
+    #[must_use]
+    pub fn is_critical(&self) -> bool {
+        self.level() == Level::Critical
+    }
+
+    /// Returns the highest LED brightness currently allowed.
+    #[must_use]
+    pub fn brightness_cap(&self) -> u8 {
+        self.brightness_cap.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for Readings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
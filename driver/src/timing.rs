@@ -0,0 +1,130 @@
+//! Per-task iteration timing.
+//!
+//! Every long-running task measures how long each iteration of its loop takes, not counting the time it deliberately
+//! sleeps, and records it into [`Timings`] in the runtime status. Each task keeps its last iteration time, the longest
+//! one since the last reset, and how many iterations ran over the task's [`budget_us`]. Recording costs a timer read
+//! and a few relaxed atomic operations, so it can stay on in every build.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use crate::watchdog::Task;
+
+/// Returns the longest an iteration of `task` should take in microseconds, beyond which it counts as an overrun.
+///
+/// The LED and servo loops render a frame every 10 ms and the motion and touch loops sample every 20 ms, so an
+/// iteration longer than that drops frames or samples. A speaker write plays a buffer of about 46 ms of audio, so one
+/// that takes much longer has stalled. The command lines should answer a keystroke without a noticeable delay, and a
+/// poll of the remote state, handshake included, should stay well inside its timeout.
+#[must_use]
+pub const fn budget_us(task: Task) -> u32 {
+    match task {
+        Task::Leds | Task::Servos => 10_000,
+        Task::Motion | Task::Touch => 20_000,
+        Task::Cli | Task::UartCli | Task::SpeakerLeft | Task::SpeakerRight => 50_000,
+        Task::UpdateState => 10_000_000,
+    }
+}
+
+/// Timing of a single task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Stats {
+    /// Duration of the last iteration in microseconds.
+    pub last_us: u32,
+    /// Duration of the longest iteration since the last reset in microseconds.
+    pub max_us: u32,
+    /// Number of iterations since the last reset that took longer than the task's budget.
+    pub overruns: u32,
+}
+
+/// Iteration timing of every task.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::timing::Timings;
+/// use catears::watchdog::Task;
+///
+/// let timings = Timings::new();
+/// timings.record_us(Task::Leds, 2_000);
+/// timings.record_us(Task::Leds, 14_000);
+/// timings.record_us(Task::Leds, 3_000);
+/// timings.record_us(Task::Servos, 1_000);
+///
+/// let leds = timings.get(Task::Leds);
+/// assert_eq!((leds.last_us, leds.max_us, leds.overruns), (3_000, 14_000, 1));
+/// assert_eq!(timings.worst().map(|(task, _)| task), Some(Task::Leds));
+///
+/// timings.reset();
+/// assert_eq!(timings.get(Task::Leds).max_us, 0);
+/// assert_eq!(timings.worst(), None);
+/// ```
+pub struct Timings {
+    last_us: [AtomicU32; Task::ALL.len()],
+    max_us: [AtomicU32; Task::ALL.len()],
+    overruns: [AtomicU32; Task::ALL.len()],
+}
+
+impl Timings {
+    /// Creates new timings with nothing recorded.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            last_us: [const { AtomicU32::new(0) }; Task::ALL.len()],
+            max_us: [const { AtomicU32::new(0) }; Task::ALL.len()],
+            overruns: [const { AtomicU32::new(0) }; Task::ALL.len()],
+        }
+    }
+
+    /// Records an iteration of `task` that started at `start`.
+    pub fn record(&self, task: Task, start: embassy_time::Instant) {
+        self.record_us(
+            task,
+            u32::try_from(start.elapsed().as_micros()).unwrap_or(u32::MAX),
+        );
+    }
+
+    /// Records an iteration of `task` that took `elapsed_us` microseconds.
+    pub fn record_us(&self, task: Task, elapsed_us: u32) {
+        let index = task.index();
+        self.last_us[index].store(elapsed_us, Ordering::Relaxed);
+        self.max_us[index].fetch_max(elapsed_us, Ordering::Relaxed);
+        if elapsed_us > budget_us(task) {
+            self.overruns[index].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the timing of `task`.
+    #[must_use]
+    pub fn get(&self, task: Task) -> Stats {
+        let index = task.index();
+        Stats {
+            last_us: self.last_us[index].load(Ordering::Relaxed),
+            max_us: self.max_us[index].load(Ordering::Relaxed),
+            overruns: self.overruns[index].load(Ordering::Relaxed),
+        }
+    }
+
+    /// Returns the task with the most overruns since the last reset, if any task overran at all.
+    #[must_use]
+    pub fn worst(&self) -> Option<(Task, Stats)> {
+        Task::ALL
+            .into_iter()
+            .map(|task| (task, self.get(task)))
+            .filter(|(_, stats)| stats.overruns > 0)
+            .max_by_key(|(_, stats)| (stats.overruns, stats.max_us))
+    }
+
+    /// Clears the maxima and overrun counts.
+    pub fn reset(&self) {
+        for (max_us, overruns) in self.max_us.iter().zip(&self.overruns) {
+            max_us.store(0, Ordering::Relaxed);
+            overruns.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Default for Timings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,323 @@
+//! Petting detection from capacitive touch pads.
+//!
+//! A strip of copper tape inside each ear is wired to a touch pin. The firmware samples the raw touch readings at a
+//! fixed rate and feeds them into a [`Tracker`] per pad, which follows the slowly drifting untouched baseline, debounces
+//! touches and releases, and turns them into one-shot [`Event`]s: a quick tap, or a longer stroke. The latest readings
+//! are published into [`Readings`] in the runtime status, and events are turned into servo gestures and light flashes
+//! according to the reactions in [`crate::state::TouchConfig`].
+
+use core::sync::atomic::{AtomicBool, AtomicU16, AtomicU32, Ordering};
+
+/// Number of samples averaged into the initial baseline.
+pub const CALIBRATION_SAMPLES: u32 = 16;
+
+/// Touch events recognized by a [`Tracker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Event {
+    /// A touch released before it became a stroke.
+    Tap,
+    /// A touch held for at least [`Config::stroke_ms`], reported while it is still going on.
+    Stroke,
+}
+
+/// Configuration for touch tracking.
+///
+/// The touch threshold itself lives in [`crate::state::TouchConfig`], so that it can be tuned at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    /// Time in milliseconds between samples.
+    pub sample_interval_ms: u64,
+    /// Number of samples in a row that have to agree before a touch or a release counts.
+    pub debounce_samples: u8,
+    /// Time in milliseconds a touch has to last to count as a stroke.
+    pub stroke_ms: u64,
+    /// Time in milliseconds after which a touch that never releases is taken for drift, and becomes the new baseline.
+    pub stuck_ms: u64,
+    /// How slowly the baseline follows the untouched readings, as a power of two in samples.
+    pub baseline_shift: u8,
+}
+
+impl Config {
+    /// Default configuration, sampling at 50 Hz and following drift over about a second.
+    pub const DEFAULT: Self = Self {
+        sample_interval_ms: 20,
+        debounce_samples: 3,
+        stroke_ms: 600,
+        stuck_ms: 10_000,
+        baseline_shift: 6,
+    };
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Processed output of a single [`Tracker::update`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sample {
+    /// Raw reading.
+    pub raw: u16,
+    /// Untouched baseline the reading is compared against.
+    pub baseline: u16,
+    /// Whether the pad is touched, after debouncing.
+    pub touched: bool,
+    /// Event recognized at this sample, if any.
+    pub event: Option<Event>,
+}
+
+/// Touch detector for a stream of raw readings from a single pad.
+///
+/// A reading counts as touched once it differs from the baseline by at least the threshold, and as released once it is
+/// back within two thirds of the threshold, so a reading right at the edge does not flicker.
+///
+/// # Examples
+///
+/// ```rust
+/// use catears::touch::{Config, Event, Tracker, CALIBRATION_SAMPLES};
+///
+/// let mut tracker = Tracker::new(Config::DEFAULT);
+/// let mut t = 0;
+/// let mut feed = |tracker: &mut Tracker, raw: u16, samples: u64| {
+///     let mut events = [None; 2];
+///     for _ in 0..samples {
+///         if let Some(event) = tracker.update(raw, 5, t).event {
+///             events[usize::from(events[0].is_some())] = Some(event);
+///         }
+///         t += 20;
+///     }
+///     events
+/// };
+///
+/// // The baseline is captured from the first readings.
+/// feed(&mut tracker, 10_000, u64::from(CALIBRATION_SAMPLES));
+/// assert_eq!(tracker.baseline(), Some(10_000));
+///
+/// // A single spike is debounced away, a short touch is a tap once released.
+/// assert_eq!(feed(&mut tracker, 11_000, 1), [None, None]);
+/// assert_eq!(feed(&mut tracker, 10_000, 5), [None, None]);
+/// assert_eq!(feed(&mut tracker, 11_000, 10), [None, None]);
+/// assert_eq!(feed(&mut tracker, 10_000, 5), [Some(Event::Tap), None]);
+///
+/// // A long touch is a stroke, and releasing it reports nothing more.
+/// assert_eq!(feed(&mut tracker, 11_000, 50), [Some(Event::Stroke), None]);
+/// assert_eq!(feed(&mut tracker, 10_000, 5), [None, None]);
+///
+/// // Slow drift moves the baseline along without ever counting as a touch.
+/// for step in 1..=100 {
+///     assert_eq!(feed(&mut tracker, 10_000 + step * 10, 10), [None, None]);
+/// }
+/// assert!(tracker.baseline().is_some_and(|baseline| baseline > 10_900));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tracker {
+    config: Config,
+    calibration: Option<(u32, u32)>,
+    /// Untouched baseline, scaled by 2 to the power of the baseline shift.
+    baseline: u32,
+    touched: bool,
+    /// Number of samples in a row that disagreed with the debounced state.
+    pending: u8,
+    touch_start_ms: u64,
+    stroked: bool,
+}
+
+impl Tracker {
+    /// Creates a new tracker that captures its baseline from the first readings.
+    #[must_use]
+    pub const fn new(config: Config) -> Self {
+        Self {
+            config,
+            calibration: Some((0, 0)),
+            baseline: 0,
+            touched: false,
+            pending: 0,
+            touch_start_ms: 0,
+            stroked: false,
+        }
+    }
+
+    /// Returns the configuration of the tracker.
+    #[must_use]
+    pub const fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Returns the untouched baseline, or `None` while it is still being captured.
+    #[must_use]
+    pub fn baseline(&self) -> Option<u16> {
+        if self.calibration.is_some() {
+            None
+        } else {
+            Some(u16::try_from(self.baseline >> self.config.baseline_shift).unwrap_or(u16::MAX))
+        }
+    }
+
+    /// Starts capturing a new baseline over the next [`CALIBRATION_SAMPLES`] readings.
+    ///
+    /// The pads should not be touched while calibrating. Events are suppressed until the capture completes.
+    pub fn calibrate(&mut self) {
+        self.calibration = Some((0, 0));
+        self.touched = false;
+        self.pending = 0;
+    }
+
+    /// Processes a raw reading taken at `now_ms`, counting a change of at least `threshold_pct` percent from the
+    /// baseline as a touch.
+    pub fn update(&mut self, raw: u16, threshold_pct: u8, now_ms: u64) -> Sample {
+        if let Some((sum, count)) = self.calibration.as_mut() {
+            *sum += u32::from(raw);
+            *count += 1;
+            if *count >= CALIBRATION_SAMPLES {
+                self.baseline = (*sum / CALIBRATION_SAMPLES) << self.config.baseline_shift;
+                self.calibration = None;
+            }
+            return Sample {
+                raw,
+                baseline: raw,
+                touched: false,
+                event: None,
+            };
+        }
+
+        let baseline = self.baseline >> self.config.baseline_shift;
+        let deviation = u32::from(raw).abs_diff(baseline);
+        let threshold = (baseline * u32::from(threshold_pct) / 100).max(1);
+        let above = if self.touched {
+            deviation * 3 >= threshold * 2
+        } else {
+            deviation >= threshold
+        };
+
+        let mut event = None;
+        if above == self.touched {
+            self.pending = 0;
+        } else {
+            self.pending += 1;
+            if self.pending >= self.config.debounce_samples {
+                self.pending = 0;
+                self.touched = above;
+                if self.touched {
+                    self.touch_start_ms = now_ms;
+                    self.stroked = false;
+                } else if !self.stroked {
+                    event = Some(Event::Tap);
+                }
+            }
+        }
+
+        if self.touched {
+            let held_ms = now_ms.saturating_sub(self.touch_start_ms);
+            if !self.stroked && held_ms >= self.config.stroke_ms {
+                self.stroked = true;
+                event = Some(Event::Stroke);
+            }
+            if held_ms >= self.config.stuck_ms {
+                // Nobody pets an ear for this long, the baseline has jumped instead.
+                self.baseline = u32::from(raw) << self.config.baseline_shift;
+                self.touched = false;
+            }
+        } else if self.pending == 0 {
+            // Follow drift from humidity and temperature, but never the first samples of a touch.
+            self.baseline =
+                self.baseline - (self.baseline >> self.config.baseline_shift) + u32::from(raw);
+        }
+
+        Sample {
+            raw,
+            baseline: u16::try_from(baseline).unwrap_or(u16::MAX),
+            touched: self.touched,
+            event,
+        }
+    }
+}
+
+/// Latest touch readings of both pads, published by the touch task for the rest of the firmware.
+pub struct Readings {
+    present: AtomicBool,
+    calibration_requested: AtomicBool,
+    raw: [AtomicU16; 2],
+    baseline: [AtomicU16; 2],
+    taps: AtomicU32,
+    strokes: AtomicU32,
+}
+
+impl Readings {
+    /// Creates new readings for absent pads.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            present: AtomicBool::new(false),
+            calibration_requested: AtomicBool::new(false),
+            raw: [AtomicU16::new(0), AtomicU16::new(0)],
+            baseline: [AtomicU16::new(0), AtomicU16::new(0)],
+            taps: AtomicU32::new(0),
+            strokes: AtomicU32::new(0),
+        }
+    }
+
+    /// Marks the pads as set up.
+    pub fn set_present(&self) {
+        self.present.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether the pads were set up at boot.
+    #[must_use]
+    pub fn is_present(&self) -> bool {
+        self.present.load(Ordering::Relaxed)
+    }
+
+    /// Publishes a processed sample of the pad at `index`, zero for the left ear and one for the right.
+    pub fn publish(&self, index: usize, sample: &Sample) {
+        let Some((raw, baseline)) = self.raw.get(index).zip(self.baseline.get(index)) else {
+            return;
+        };
+        raw.store(sample.raw, Ordering::Relaxed);
+        baseline.store(sample.baseline, Ordering::Relaxed);
+        match sample.event {
+            Some(Event::Tap) => self.taps.fetch_add(1, Ordering::Relaxed),
+            Some(Event::Stroke) => self.strokes.fetch_add(1, Ordering::Relaxed),
+            None => 0,
+        };
+    }
+
+    /// Returns the latest raw reading and baseline of the pad at `index`.
+    #[must_use]
+    pub fn pad(&self, index: usize) -> (u16, u16) {
+        self.raw
+            .get(index)
+            .zip(self.baseline.get(index))
+            .map_or((0, 0), |(raw, baseline)| {
+                (
+                    raw.load(Ordering::Relaxed),
+                    baseline.load(Ordering::Relaxed),
+                )
+            })
+    }
+
+    /// Returns the number of taps and strokes detected since boot.
+    #[must_use]
+    pub fn counts(&self) -> (u32, u32) {
+        (
+            self.taps.load(Ordering::Relaxed),
+            self.strokes.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Asks the touch task to capture new baselines.
+    pub fn request_calibration(&self) {
+        self.calibration_requested.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether a calibration was requested since the last call, clearing the request.
+    pub fn take_calibration_request(&self) -> bool {
+        self.calibration_requested.swap(false, Ordering::Relaxed)
+    }
+}
+
+impl Default for Readings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
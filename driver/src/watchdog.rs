@@ -0,0 +1,142 @@
+//! Task liveness supervision.
+//!
+//! Every long-running task periodically stamps its heartbeat, and a feeder task in the firmware only pets the hardware
+//! watchdog while every started task has checked in recently. If a task deadlocks (a stuck lock, a wedged DMA await),
+//! the feeder stops petting and the watchdog resets the chip instead of leaving the ears silently frozen.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+/// Long-running tasks supervised by the watchdog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Task {
+    /// LED ring animation task.
+    Leds,
+    /// Servo control task.
+    Servos,
+    /// Playback task of the left speaker.
+    SpeakerLeft,
+    /// Playback task of the right speaker.
+    SpeakerRight,
+    /// Remote state polling task.
+    UpdateState,
+    /// Command line handler task.
+    Cli,
+    /// Command line handler task on the UART.
+    UartCli,
+    /// Accelerometer sampling task.
+    Motion,
+    /// Touch pad sampling task.
+    Touch,
+}
+
+impl Task {
+    /// All supervised tasks.
+    pub const ALL: [Self; 9] = [
+        Self::Leds,
+        Self::Servos,
+        Self::SpeakerLeft,
+        Self::SpeakerRight,
+        Self::UpdateState,
+        Self::Cli,
+        Self::UartCli,
+        Self::Motion,
+        Self::Touch,
+    ];
+
+    /// Returns the human-readable name of the task.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Leds => "LEDs",
+            Self::Servos => "servos",
+            Self::SpeakerLeft => "left speaker",
+            Self::SpeakerRight => "right speaker",
+            Self::UpdateState => "update state",
+            Self::Cli => "command line",
+            Self::UartCli => "UART command line",
+            Self::Motion => "motion",
+            Self::Touch => "touch",
+        }
+    }
+
+    /// Returns how long the task may go without stamping its heartbeat before it is considered stuck.
+    ///
+    /// The thresholds are generous multiples of each task's longest legitimate wait: the LED and servo loops tick every
+    /// 10 ms, though the LED loop waits up to a second while the LEDs are dark, the motion and touch loops tick every
+    /// 20 ms, the speaker tasks wait at most one DMA buffer or mode poll between stamps, the command lines wake up at
+    /// least once a second, and the remote state poll may sit through DNS, TLS handshakes, and network backoff.
+    #[must_use]
+    pub const fn timeout_ms(self) -> u32 {
+        match self {
+            Self::Servos | Self::Motion | Self::Touch => 2_000,
+            Self::Leds | Self::SpeakerLeft | Self::SpeakerRight | Self::Cli | Self::UartCli => {
+                5_000
+            }
+            Self::UpdateState => 120_000,
+        }
+    }
+
+    /// Returns the playback task of the speaker on `side`.
+    ///
+    /// Each side stamps a heartbeat of its own, so that a wedged side is caught even while the other keeps playing.
+    #[must_use]
+    pub const fn speaker(side: crate::audio::Side) -> Self {
+        match side {
+            crate::audio::Side::Left => Self::SpeakerLeft,
+            crate::audio::Side::Right => Self::SpeakerRight,
+        }
+    }
+
+    pub(crate) const fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Per-task heartbeat stamps.
+pub struct Heartbeats {
+    /// Milliseconds since boot of each task's last stamp, or zero if the task has never stamped.
+    stamps: [AtomicU32; Task::ALL.len()],
+}
+
+impl Heartbeats {
+    /// Creates a new set of heartbeats with no task started.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            stamps: [const { AtomicU32::new(0) }; Task::ALL.len()],
+        }
+    }
+
+    /// Records that `task` is alive.
+    pub fn stamp(&self, task: Task) {
+        self.stamps[task.index()].store(now_ms().max(1), Ordering::Relaxed);
+    }
+
+    /// Returns the first started task that has not stamped its heartbeat within its timeout, along with how long ago it
+    /// last did.
+    ///
+    /// Tasks that have never stamped (e.g. the command line when no serial port is attached) are not supervised.
+    #[must_use]
+    pub fn stale_task(&self) -> Option<(Task, u32)> {
+        let now = now_ms();
+        Task::ALL.into_iter().find_map(|task| {
+            let stamp = self.stamps[task.index()].load(Ordering::Relaxed);
+            let silent_for = now.wrapping_sub(stamp);
+            (stamp != 0 && silent_for > task.timeout_ms()).then_some((task, silent_for))
+        })
+    }
+}
+
+impl Default for Heartbeats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Milliseconds since boot, wrapping after about 49 days.
+fn now_ms() -> u32 {
+    #[allow(clippy::cast_possible_truncation)]
+    {
+        embassy_time::Instant::now().as_millis() as u32
+    }
+}